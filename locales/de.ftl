@@ -0,0 +1,58 @@
+# Deutsche Meldungen
+
+## Agent notifications
+
+agent-updated-title = Agent aktualisiert
+agent-updated = Der Agent wurde auf Version { $version } aktualisiert
+
+updates-resumed-title = Richtlinien-Updates funktionieren wieder
+updates-resumed = Die Suche nach Richtlinien-Updates funktioniert wieder, nachdem sie seit { $since } fehlgeschlagen ist
+
+updates-failing-title = Richtlinien-Updates schlagen fehl
+updates-failing = Die letzten { $failures } Suchen nach Richtlinien-Updates sind fehlgeschlagen; der Agent versucht es weiter, aber seltener. Letzter Fehler: { $error }
+
+approval-needed-title = Richtlinienänderung wartet auf Freigabe
+approval-needed =
+    Die neue Richtlinie würde Folgendes entfernen oder ändern:
+      { $changes }
+
+    Mit 'family-policy approve' wird sie angewendet.
+
+policy-quarantined-title = Richtlinie unter Quarantäne
+policy-quarantined = Die neue Richtlinie konnte { $attempts }-mal hintereinander nicht angewendet werden und wird erst nach einer Änderung erneut versucht. Die letzte funktionierende Richtlinie bleibt in Kraft. Letzter Fehler: { $error }
+
+schedule-changed-title = Geplante Richtlinien geändert
+schedule-expired-title = Richtlinien abgelaufen
+schedule-started = Jetzt in Kraft: { $policies }
+schedule-stopped = Nicht mehr in Kraft: { $policies }
+
+policies-restored-title = Richtlinien wiederhergestellt
+policies-restored = { $count } Richtlinienwerte wurden geändert oder entfernt, während der Agent gestoppt war, und wurden erneut angewendet
+
+unmanaged-browser-title = Nicht verwalteter Browser gefunden
+unmanaged-browser-blocked = { $browser } ist unter { $path } installiert. Browser-Richtlinien gelten dafür nicht, daher wurde sein Start blockiert.
+unmanaged-browser = { $browser } ist unter { $path } installiert. Browser-Richtlinien gelten dafür nicht; deinstalliere ihn, damit sie wirksam bleiben.
+
+## Status window
+
+ui-status-title = Family Policy – Status
+ui-loading = Status wird geladen...
+ui-load-failed = Status konnte nicht geladen werden: { $error }
+ui-retry = Erneut versuchen
+ui-no-policies = Auf diesem System sind derzeit keine Richtlinien angewendet.
+ui-current-status = Aktueller Status
+ui-active = Aktiv
+ui-inactive = Inaktiv
+ui-last-updated = Zuletzt aktualisiert
+ui-never = Nie
+ui-total-extensions = Erweiterungen insgesamt
+ui-privacy-settings = Datenschutzeinstellungen
+ui-browser-extensions = { $count } Erweiterungen
+ui-browser-privacy-settings = { $count } Datenschutzeinstellungen
+ui-browser-not-covered = { $browser } ist installiert, aber von keinem Richtlinieneintrag erfasst
+ui-configuration-id = Konfigurations-ID:
+ui-refresh = Aktualisieren
+ui-apply-now = Richtlinie jetzt anwenden
+ui-applying = Wird angewendet...
+ui-running-as-admin = Läuft als Administrator
+ui-apply-failed = Die Richtlinie konnte nicht angewendet werden: { $error }
@@ -0,0 +1,61 @@
+# English messages (the fallback for every other language)
+#
+# Syntax: `id = text`, `{ $name }` for values filled in by the program,
+# indented lines continue the text on a new line. See src-tauri/src/i18n.rs.
+
+## Agent notifications
+
+agent-updated-title = Agent updated
+agent-updated = The agent was updated to version { $version }
+
+updates-resumed-title = Policy updates resumed
+updates-resumed = Checking for policy updates works again after failing since { $since }
+
+updates-failing-title = Policy updates failing
+updates-failing = The last { $failures } checks for policy updates failed; the agent will keep retrying less often. Last error: { $error }
+
+approval-needed-title = Policy change waiting for approval
+approval-needed =
+    The new policy would remove or change:
+      { $changes }
+
+    Run 'family-policy approve' to apply it.
+
+policy-quarantined-title = Policy quarantined
+policy-quarantined = The new policy failed to apply { $attempts } times in a row and won't be retried until it changes. The last good policy stays in force. Last error: { $error }
+
+schedule-changed-title = Scheduled policies changed
+schedule-expired-title = Policies expired
+schedule-started = Now enforced: { $policies }
+schedule-stopped = No longer enforced: { $policies }
+
+policies-restored-title = Policies restored
+policies-restored = { $count } policy values were changed or removed while the agent was stopped and have been re-applied
+
+unmanaged-browser-title = Unmanaged browser found
+unmanaged-browser-blocked = { $browser } is installed at { $path }. Browser policies don't apply to it, so it has been blocked from starting.
+unmanaged-browser = { $browser } is installed at { $path }. Browser policies don't apply to it; uninstall it to keep them enforced.
+
+## Status window
+
+ui-status-title = Family Policy Status
+ui-loading = Loading status...
+ui-load-failed = Failed to load status: { $error }
+ui-retry = Retry
+ui-no-policies = No policies are currently applied on this system.
+ui-current-status = Current Status
+ui-active = Active
+ui-inactive = Inactive
+ui-last-updated = Last Updated
+ui-never = Never
+ui-total-extensions = Total Extensions
+ui-privacy-settings = Privacy Settings
+ui-browser-extensions = { $count } extensions
+ui-browser-privacy-settings = { $count } privacy settings
+ui-browser-not-covered = { $browser } is installed but not covered by any policy entry
+ui-configuration-id = Configuration ID:
+ui-refresh = Refresh
+ui-apply-now = Apply Policy Now
+ui-applying = Applying...
+ui-running-as-admin = Running as Administrator
+ui-apply-failed = Applying the policy failed: { $error }
@@ -0,0 +1,16 @@
+# Family Policy Agent - RPM %post
+# Generated by `family-policy package --target rpm`.
+# $1 is 1 on install, 2 or more on upgrade.
+
+mkdir -p /etc/family-policy /var/lib/browser-extension-policy
+systemctl daemon-reload || :
+
+if [ "$1" -eq 1 ]; then
+    systemctl enable family-policy-agent.service || :
+    # Started by `family-policy provision` once configured
+    if [ -f /etc/family-policy/agent.conf ]; then
+        systemctl start family-policy-agent.service || :
+    fi
+else
+    systemctl try-restart family-policy-agent.service || :
+fi
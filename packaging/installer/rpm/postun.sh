@@ -0,0 +1,4 @@
+# Family Policy Agent - RPM %postun
+# Generated by `family-policy package --target rpm`.
+
+systemctl daemon-reload || :
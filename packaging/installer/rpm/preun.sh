@@ -0,0 +1,11 @@
+# Family Policy Agent - RPM %preun
+# Generated by `family-policy package --target rpm`.
+# $1 is 0 when the package is being removed, not upgraded.
+#
+# Browser policies the agent applied stay in place; remove them first with
+# `sudo family-policy uninstall` if they should go too.
+
+if [ "$1" -eq 0 ]; then
+    systemctl stop family-policy-agent.service || :
+    systemctl disable family-policy-agent.service || :
+fi
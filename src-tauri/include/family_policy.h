@@ -0,0 +1,53 @@
+/*
+ * Family Policy - C interface to the browser policy engine
+ *
+ * Link against the family_policy_lib static or shared library built from
+ * src-tauri (crate-type staticlib/cdylib). See src/ffi.rs for details.
+ *
+ * Strings passed in must be NUL-terminated UTF-8. Every non-NULL char*
+ * returned by this library must be released with fp_string_free().
+ */
+
+#ifndef FAMILY_POLICY_H
+#define FAMILY_POLICY_H
+
+#include <stdbool.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * Apply policies from a YAML document.
+ * Returns an ApplyResult as JSON, or NULL on error (see fp_last_error).
+ * Requires administrator privileges unless dry_run is true.
+ */
+char *fp_apply_policy_yaml(const char *yaml, bool dry_run);
+
+/*
+ * Remove all policies created by this tool.
+ * Returns a RemovalResult as JSON, or NULL on error (see fp_last_error).
+ * Requires administrator privileges unless dry_run is true.
+ */
+char *fp_remove_policies(bool dry_run);
+
+/*
+ * Check whether the YAML document is the currently applied policy.
+ * Returns 1 if applied, 0 if not, -1 on error (see fp_last_error).
+ */
+int fp_verify(const char *yaml);
+
+/*
+ * Last error message for the calling thread, or NULL if the last call
+ * succeeded. The returned string must be released with fp_string_free().
+ */
+char *fp_last_error(void);
+
+/* Release a string returned by this library. NULL is ignored. */
+void fp_string_free(char *ptr);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* FAMILY_POLICY_H */
@@ -1,7 +1,11 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use crate::browser::current_platform;
+use crate::config::HostContext;
+use crate::inbox::NotificationLevel;
 
 /// Agent configuration
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
@@ -11,6 +15,12 @@ pub struct AgentConfig {
     pub logging: LoggingConfig,
     #[serde(default)]
     pub security: SecurityConfig,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    #[serde(default)]
+    pub update: UpdateConfig,
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
 }
 
 /// GitHub repository settings
@@ -21,6 +31,9 @@ pub struct GitHubConfig {
 
     /// For private repositories (optional)
     /// Create at: https://github.com/settings/tokens
+    ///
+    /// May be a `keychain:<name>` reference to a token stored with
+    /// `family-policy secret set <name>`.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub access_token: Option<String>,
 }
@@ -42,6 +55,54 @@ pub struct AgentSettings {
 
     #[serde(default = "default_max_retries")]
     pub max_retries: u32,
+
+    /// After this many failed checks in a row, poll less often and send a
+    /// single alert instead of one per failure
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+
+    /// Longest the poll interval is widened to while checks keep failing
+    /// (seconds)
+    #[serde(default = "default_max_poll_interval")]
+    pub max_poll_interval: u64,
+
+    /// Stop retrying a policy that failed to apply this many times in a row
+    /// until the repo content changes (0 = retry forever)
+    #[serde(default = "default_quarantine_after")]
+    pub quarantine_after: u32,
+
+    /// Hold policy changes that remove or relax anything until they are
+    /// approved with `family-policy approve`; changes that only add are
+    /// applied straight away
+    #[serde(default)]
+    pub confirm_before_apply: bool,
+
+    /// Look for unmanaged browsers less often, for slow machines
+    ///
+    /// Policies are still checked and verified as usual.
+    #[serde(default)]
+    pub low_power: bool,
+
+    /// Proxy to fetch the policy through, e.g. `http://proxy.lan:3128`
+    ///
+    /// Without it, the standard `HTTPS_PROXY`/`ALL_PROXY`/`NO_PROXY`
+    /// environment variables are honored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_proxy: Option<String>,
+
+    /// Extra CA certificates (PEM files) to trust, for proxies that
+    /// intercept TLS
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ca_certificates: Vec<PathBuf>,
+
+    /// Tags for this machine, matched by `match: { tags: [...] }` in policies
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
+    /// Language for notifications, e.g. `de` or `pt-BR` (default: the
+    /// system locale)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
 }
 
 /// Logging configuration
@@ -50,8 +111,27 @@ pub struct LoggingConfig {
     #[serde(default = "default_log_level")]
     pub level: String,
 
+    /// Also log to this file, rotated according to `rotation`
     #[serde(skip_serializing_if = "Option::is_none")]
     pub file: Option<PathBuf>,
+
+    /// How often to start a new log file
+    #[serde(default)]
+    pub rotation: LogRotation,
+
+    /// Number of rotated log files to keep (0 = keep all)
+    #[serde(default = "default_max_log_files")]
+    pub max_files: usize,
+}
+
+/// When the log file is rotated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Hourly,
+    #[default]
+    Daily,
+    Never,
 }
 
 /// Security configuration (for advanced users)
@@ -63,6 +143,94 @@ pub struct SecurityConfig {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub trusted_key: Option<String>,
+
+    /// Local accounts that may apply, remove and configure through the
+    /// agent's helper socket without elevating the desktop app (Linux and
+    /// macOS); the helper only runs if this is set
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub helper_users: Vec<String>,
+
+    /// Removing all policies through the helper needs requests from two
+    /// different accounts within ten minutes of each other
+    #[serde(default)]
+    pub two_person_removal: bool,
+}
+
+/// Commands run around policy application
+///
+/// Each hook is a shell command line. The event is described to the command
+/// through `FAMILY_POLICY_*` environment variables.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HooksConfig {
+    /// Run before a new policy is applied; a non-zero exit aborts the apply
+    ///
+    /// Runs again before each retry of a failed apply.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_apply: Option<String>,
+
+    /// Run after each policy apply attempt, whether or not it succeeded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_apply: Option<String>,
+
+    /// Kill a hook that runs longer than this (seconds)
+    #[serde(default = "default_hook_timeout")]
+    pub timeout: u64,
+}
+
+/// Updating the agent itself from GitHub Releases
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UpdateConfig {
+    /// Let the agent install new releases by itself
+    #[serde(default)]
+    pub automatic: bool,
+
+    /// GitHub repository (`owner/name`) releases are published in
+    #[serde(default = "default_update_repository")]
+    pub repository: String,
+
+    /// minisign public key the release binaries are signed with
+    ///
+    /// Required to install any update, automatic or with `self-update`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+
+    /// How often the agent checks for a new release (seconds)
+    #[serde(default = "default_update_check_interval")]
+    pub check_interval: u64,
+}
+
+/// Services agent notifications are pushed to, e.g. the parent's phone
+///
+/// Tokens may be `keychain:<name>` references like `github.access_token`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NotificationsConfig {
+    /// ntfy topic URL, e.g. `https://ntfy.sh/my-family-alerts`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ntfy_url: Option<String>,
+
+    /// Access token for a protected ntfy topic
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ntfy_token: Option<String>,
+
+    /// Pushover application token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pushover_token: Option<String>,
+
+    /// Pushover user or group key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pushover_user: Option<String>,
+
+    /// Telegram bot token from @BotFather
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub telegram_bot_token: Option<String>,
+
+    /// Telegram chat the bot posts to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub telegram_chat_id: Option<String>,
+
+    /// Least severe notification that is pushed
+    #[serde(default = "default_push_level")]
+    pub min_level: NotificationLevel,
 }
 
 // Default values
@@ -82,10 +250,42 @@ fn default_max_retries() -> u32 {
     3
 }
 
+fn default_failure_threshold() -> u32 {
+    3
+}
+
+fn default_max_poll_interval() -> u64 {
+    6 * 60 * 60 // 6 hours
+}
+
+fn default_quarantine_after() -> u32 {
+    5
+}
+
 fn default_log_level() -> String {
     "info".to_string()
 }
 
+fn default_max_log_files() -> usize {
+    7
+}
+
+fn default_update_repository() -> String {
+    "emosenkis/family-policy".to_string()
+}
+
+fn default_update_check_interval() -> u64 {
+    24 * 60 * 60 // daily
+}
+
+fn default_hook_timeout() -> u64 {
+    30
+}
+
+fn default_push_level() -> NotificationLevel {
+    NotificationLevel::Warning
+}
+
 impl Default for AgentSettings {
     fn default() -> Self {
         Self {
@@ -93,6 +293,15 @@ impl Default for AgentSettings {
             poll_jitter: default_jitter(),
             retry_interval: default_retry_interval(),
             max_retries: default_max_retries(),
+            failure_threshold: default_failure_threshold(),
+            max_poll_interval: default_max_poll_interval(),
+            quarantine_after: default_quarantine_after(),
+            confirm_before_apply: false,
+            low_power: false,
+            http_proxy: None,
+            ca_certificates: Vec::new(),
+            tags: Vec::new(),
+            locale: None,
         }
     }
 }
@@ -102,23 +311,130 @@ impl Default for LoggingConfig {
         Self {
             level: default_log_level(),
             file: None,
+            rotation: LogRotation::default(),
+            max_files: default_max_log_files(),
         }
     }
 }
 
-impl AgentConfig {
-    /// Load configuration from file
-    pub fn load(path: &PathBuf) -> Result<Self> {
-        let content = fs::read_to_string(path)
-            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            pre_apply: None,
+            post_apply: None,
+            timeout: default_hook_timeout(),
+        }
+    }
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        Self {
+            automatic: false,
+            repository: default_update_repository(),
+            public_key: None,
+            check_interval: default_update_check_interval(),
+        }
+    }
+}
 
-        let config: AgentConfig = toml::from_str(&content)
-            .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            ntfy_url: None,
+            ntfy_token: None,
+            pushover_token: None,
+            pushover_user: None,
+            telegram_bot_token: None,
+            telegram_chat_id: None,
+            min_level: default_push_level(),
+        }
+    }
+}
 
-        // Validate config
-        config.validate()?;
+/// Directory next to the agent config file holding `*.toml` fragments
+/// merged over it, e.g. `/etc/family-policy/family-policy.d/`
+pub const DROP_IN_DIR: &str = "family-policy.d";
+
+/// Prefix of the environment variables that override agent settings
+///
+/// `FAMILY_POLICY_AGENT_POLL_INTERVAL=600` sets `agent.poll_interval`; the
+/// common settings also have the short names in `ENV_ALIASES`.
+pub const ENV_PREFIX: &str = "FAMILY_POLICY_";
+
+/// Short environment variable names (after `ENV_PREFIX`) for common settings
+pub const ENV_ALIASES: &[(&str, &str)] = &[
+    ("POLICY_URL", "github.policy_url"),
+    ("ACCESS_TOKEN", "github.access_token"),
+    ("POLL_INTERVAL", "agent.poll_interval"),
+    ("POLL_JITTER", "agent.poll_jitter"),
+    ("HTTP_PROXY", "agent.http_proxy"),
+    ("CONFIRM_BEFORE_APPLY", "agent.confirm_before_apply"),
+    ("LOW_POWER", "agent.low_power"),
+    ("TAGS", "agent.tags"),
+    ("LOG_LEVEL", "logging.level"),
+    ("LOG_FILE", "logging.file"),
+];
+
+/// Top-level sections of the agent configuration, which settings are
+/// addressed by (`section.key`)
+const SECTIONS: &[&str] = &["github", "agent", "logging", "security", "hooks", "update", "notifications"];
+
+/// Where a layer of the agent configuration came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    Defaults,
+    File(PathBuf),
+    /// Environment variable name
+    Environment(String),
+    /// `section.key` set on the command line
+    Flag(String),
+}
 
-        Ok(config)
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSource::Defaults => write!(f, "built-in defaults"),
+            ConfigSource::File(path) => write!(f, "{}", path.display()),
+            ConfigSource::Environment(name) => write!(f, "environment: {}", name),
+            ConfigSource::Flag(key) => write!(f, "command line: {}", key),
+        }
+    }
+}
+
+/// Agent configuration merged from every layer
+#[derive(Debug, Clone)]
+pub struct LayeredConfig {
+    pub config: AgentConfig,
+    /// Layers that were used, lowest precedence first
+    pub sources: Vec<ConfigSource>,
+}
+
+impl AgentConfig {
+    /// Load configuration from file, with its drop-ins and environment
+    /// overrides (see `load_layered`)
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(Self::load_layered(path, &[])?.config)
+    }
+
+    /// Load and validate configuration from every layer
+    ///
+    /// Later layers win: built-in defaults, `path`, the `*.toml` files in
+    /// `family-policy.d/` next to it (in name order), `FAMILY_POLICY_*`
+    /// environment variables, then `overrides` (`section.key=value` from
+    /// command-line flags).
+    pub fn load_layered(path: &Path, overrides: &[String]) -> Result<LayeredConfig> {
+        let layered = merge_layers(path, std::env::vars(), overrides)?;
+        layered.config.validate()?;
+        Ok(layered)
+    }
+
+    /// Build a configuration from `settings` (sections as in agent.conf),
+    /// with built-in defaults for anything it leaves out
+    pub fn from_settings(settings: toml::Table) -> Result<Self> {
+        let mut merged = toml::Table::try_from(AgentConfig::default()).context("Failed to serialize defaults")?;
+        merge_table(&mut merged, settings);
+        merged.try_into().context("Invalid agent configuration")
     }
 
     /// Save configuration to file
@@ -171,10 +487,212 @@ impl AgentConfig {
             );
         }
 
+        if self.agent.failure_threshold == 0 {
+            anyhow::bail!("Failure threshold must be at least 1");
+        }
+
+        if self.agent.max_poll_interval < self.agent.poll_interval {
+            anyhow::bail!(
+                "Maximum poll interval ({}) must be at least the poll interval ({})",
+                self.agent.max_poll_interval,
+                self.agent.poll_interval
+            );
+        }
+
+        // Validate proxy URL
+        if let Some(proxy) = &self.agent.http_proxy {
+            let proxy_url = url::Url::parse(proxy)
+                .with_context(|| format!("Invalid HTTP proxy URL: {}", proxy))?;
+            if !matches!(proxy_url.scheme(), "http" | "https") {
+                anyhow::bail!("HTTP proxy URL must use http or https (got: {})", proxy);
+            }
+        }
+
+        if self.update.automatic && self.update.public_key.is_none() {
+            anyhow::bail!("Automatic updates require update.public_key to verify releases");
+        }
+
+        // Validate hook timeout
+        if self.hooks.timeout == 0 {
+            anyhow::bail!("Hook timeout must be at least 1 second");
+        }
+
+        let notifications = &self.notifications;
+        if let Some(ntfy_url) = &notifications.ntfy_url {
+            let url = url::Url::parse(ntfy_url)
+                .with_context(|| format!("Invalid ntfy URL: {}", ntfy_url))?;
+            if url.scheme() != "https" {
+                anyhow::bail!("ntfy URL must use HTTPS (got: {})", url.scheme());
+            }
+        }
+        if notifications.pushover_token.is_some() != notifications.pushover_user.is_some() {
+            anyhow::bail!("Pushover needs both notifications.pushover_token and notifications.pushover_user");
+        }
+        if notifications.telegram_bot_token.is_some() != notifications.telegram_chat_id.is_some() {
+            anyhow::bail!("Telegram needs both notifications.telegram_bot_token and notifications.telegram_chat_id");
+        }
+
         Ok(())
     }
 }
 
+/// Merge the configuration layers without validating the result
+fn merge_layers(
+    path: &Path,
+    env: impl Iterator<Item = (String, String)>,
+    overrides: &[String],
+) -> Result<LayeredConfig> {
+    let mut merged = toml::Table::try_from(AgentConfig::default()).context("Failed to serialize defaults")?;
+    let mut sources = vec![ConfigSource::Defaults];
+
+    let mut files = vec![path.to_path_buf()];
+    files.extend(drop_in_files(path)?);
+    for file in files {
+        let content = fs::read_to_string(&file)
+            .with_context(|| format!("Failed to read config file: {}", file.display()))?;
+        let table: toml::Table = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config file: {}", file.display()))?;
+        merge_table(&mut merged, table);
+        sources.push(ConfigSource::File(file));
+    }
+
+    let mut env: Vec<(String, String)> = env.collect();
+    env.sort();
+    for (name, value) in env {
+        let Some(key) = name.strip_prefix(ENV_PREFIX).and_then(env_setting) else {
+            continue;
+        };
+        set_setting(&mut merged, &key, &value).with_context(|| format!("Invalid {}", name))?;
+        sources.push(ConfigSource::Environment(name));
+    }
+
+    for assignment in overrides {
+        let (key, value) = assignment
+            .split_once('=')
+            .with_context(|| format!("Expected section.key=value, got: {}", assignment))?;
+        set_setting(&mut merged, key.trim(), value.trim())?;
+        sources.push(ConfigSource::Flag(key.trim().to_string()));
+    }
+
+    let config: AgentConfig = merged
+        .try_into()
+        .with_context(|| format!("Invalid agent configuration in {}", path.display()))?;
+    Ok(LayeredConfig { config, sources })
+}
+
+/// The `section.key` an environment variable (without `ENV_PREFIX`) sets
+///
+/// Hook variables (`FAMILY_POLICY_EVENT`, ...) share the prefix and set
+/// nothing.
+fn env_setting(name: &str) -> Option<String> {
+    if let Some((_, key)) = ENV_ALIASES.iter().find(|(alias, _)| *alias == name) {
+        return Some(key.to_string());
+    }
+    let name = name.to_ascii_lowercase();
+    let section = SECTIONS.iter().find(|s| name.starts_with(&format!("{}_", s)))?;
+    Some(format!("{}.{}", section, &name[section.len() + 1..]))
+}
+
+/// The drop-in files for the config file at `path`, in the order they apply
+pub fn drop_in_files(path: &Path) -> Result<Vec<PathBuf>> {
+    let dir = path.parent().unwrap_or(Path::new(".")).join(DROP_IN_DIR);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Merge `overlay` into `base`, replacing everything but nested tables
+fn merge_table(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(table)) => merge_table(existing, table),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Set `section.key` to `raw`
+///
+/// `raw` is read as a TOML value (`600`, `true`, `["a", "b"]`), falling
+/// back to a plain string, including where the setting only takes a string
+/// (so `12345` can still be a chat ID).
+fn set_setting(table: &mut toml::Table, key: &str, raw: &str) -> Result<()> {
+    let parsed = toml::from_str::<toml::Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut t| t.remove("value"));
+    let Some(value) = parsed.filter(|v| !v.is_str()) else {
+        return insert_setting(table, key, toml::Value::String(raw.to_string()));
+    };
+
+    let mut typed = table.clone();
+    insert_setting(&mut typed, key, value)?;
+    let mut as_string = table.clone();
+    insert_setting(&mut as_string, key, toml::Value::String(raw.to_string()))?;
+    let deserializes = |t: &toml::Table| t.clone().try_into::<AgentConfig>().is_ok();
+    *table = if !deserializes(&typed) && deserializes(&as_string) { as_string } else { typed };
+    Ok(())
+}
+
+fn insert_setting(table: &mut toml::Table, key: &str, value: toml::Value) -> Result<()> {
+    let (section, name) = key
+        .split_once('.')
+        .filter(|(section, name)| SECTIONS.contains(section) && !name.is_empty() && !name.contains('.'))
+        .with_context(|| format!("Unknown setting: {} (expected section.key, e.g. agent.poll_interval)", key))?;
+
+    table
+        .entry(section)
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+        .as_table_mut()
+        .with_context(|| format!("{} is not a section", section))?
+        .insert(name.to_string(), value);
+    Ok(())
+}
+
+/// This machine, for evaluating `match` blocks in policies
+///
+/// Tags come from the agent configuration if there is one, so machines
+/// managed locally without an agent have none.
+pub fn current_host() -> HostContext {
+    let tags = get_agent_config_path()
+        .ok()
+        .filter(|path| path.exists())
+        .and_then(|path| merge_layers(&path, std::env::vars(), &[]).ok())
+        .map(|layered| layered.config.agent.tags)
+        .unwrap_or_default();
+
+    HostContext {
+        hostname: hostname(),
+        os: current_platform(),
+        tags,
+    }
+}
+
+#[cfg(unix)]
+fn hostname() -> String {
+    let mut buf = [0u8; 256];
+    if unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } != 0 {
+        return String::new();
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+#[cfg(windows)]
+fn hostname() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_default()
+}
+
 /// Get the platform-specific agent config file path
 pub fn get_agent_config_path() -> Result<PathBuf> {
     #[cfg(target_os = "linux")]
@@ -219,6 +737,9 @@ mod tests {
             agent: AgentSettings::default(),
             logging: LoggingConfig::default(),
             security: SecurityConfig::default(),
+            hooks: HooksConfig::default(),
+            update: UpdateConfig::default(),
+            notifications: NotificationsConfig::default(),
         };
 
         assert!(config.validate().is_err());
@@ -235,6 +756,9 @@ mod tests {
             agent: AgentSettings::default(),
             logging: LoggingConfig::default(),
             security: SecurityConfig::default(),
+            hooks: HooksConfig::default(),
+            update: UpdateConfig::default(),
+            notifications: NotificationsConfig::default(),
         };
 
         assert!(config.validate().is_ok());
@@ -254,6 +778,9 @@ mod tests {
             },
             logging: LoggingConfig::default(),
             security: SecurityConfig::default(),
+            hooks: HooksConfig::default(),
+            update: UpdateConfig::default(),
+            notifications: NotificationsConfig::default(),
         };
 
         assert!(config.validate().is_err());
@@ -266,6 +793,171 @@ mod tests {
         assert_eq!(settings.poll_jitter, 60);
         assert_eq!(settings.retry_interval, 60);
         assert_eq!(settings.max_retries, 3);
+        assert_eq!(settings.failure_threshold, 3);
+        assert_eq!(settings.max_poll_interval, 21600);
+        assert_eq!(settings.quarantine_after, 5);
+        assert!(!settings.low_power);
+        assert!(settings.http_proxy.is_none());
+        assert!(settings.ca_certificates.is_empty());
+    }
+
+    #[test]
+    fn automatic_updates_require_public_key() {
+        let mut config = AgentConfig {
+            github: GitHubConfig {
+                policy_url: "https://raw.githubusercontent.com/user/repo/main/policy.yaml"
+                    .to_string(),
+                access_token: None,
+            },
+            ..Default::default()
+        };
+
+        config.update.automatic = true;
+        assert!(config.validate().is_err());
+
+        config.update.public_key = Some("RWQBI0VniavN7wOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4".to_string());
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn agent_config_validates_proxy_url() {
+        let mut config = AgentConfig {
+            github: GitHubConfig {
+                policy_url: "https://raw.githubusercontent.com/user/repo/main/policy.yaml"
+                    .to_string(),
+                access_token: None,
+            },
+            ..Default::default()
+        };
+
+        config.agent.http_proxy = Some("http://proxy.lan:3128".to_string());
+        assert!(config.validate().is_ok());
+
+        config.agent.http_proxy = Some("proxy.lan:3128".to_string());
+        assert!(config.validate().is_err());
+
+        config.agent.http_proxy = Some("not a url".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn agent_config_validates_notifications() {
+        let mut config = AgentConfig {
+            github: GitHubConfig {
+                policy_url: "https://raw.githubusercontent.com/user/repo/main/policy.yaml"
+                    .to_string(),
+                access_token: None,
+            },
+            ..Default::default()
+        };
+
+        config.notifications.ntfy_url = Some("https://ntfy.sh/family-alerts".to_string());
+        assert!(config.validate().is_ok());
+
+        config.notifications.ntfy_url = Some("http://ntfy.sh/family-alerts".to_string());
+        assert!(config.validate().is_err());
+        config.notifications.ntfy_url = None;
+
+        config.notifications.pushover_token = Some("app-token".to_string());
+        assert!(config.validate().is_err());
+        config.notifications.pushover_user = Some("user-key".to_string());
+        assert!(config.validate().is_ok());
+
+        config.notifications.telegram_chat_id = Some("12345".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn hooks_config_defaults_when_section_missing() {
+        let config: AgentConfig = toml::from_str(
+            r#"
+            [github]
+            policy_url = "https://raw.githubusercontent.com/user/repo/main/policy.yaml"
+
+            [agent]
+
+            [logging]
+            "#,
+        )
+        .unwrap();
+
+        assert!(config.hooks.pre_apply.is_none());
+        assert!(config.hooks.post_apply.is_none());
+        assert_eq!(config.hooks.timeout, 30);
+    }
+
+    #[test]
+    fn layers_merge_in_precedence_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("agent.conf");
+        fs::write(
+            &path,
+            r#"
+            [github]
+            policy_url = "https://raw.githubusercontent.com/user/repo/main/policy.yaml"
+
+            [agent]
+            poll_interval = 600
+            poll_jitter = 10
+            "#,
+        )
+        .unwrap();
+        fs::create_dir(dir.path().join(DROP_IN_DIR)).unwrap();
+        fs::write(dir.path().join(DROP_IN_DIR).join("20-poll.toml"), "[agent]\npoll_interval = 900\n").unwrap();
+        fs::write(
+            dir.path().join(DROP_IN_DIR).join("10-tags.toml"),
+            "[agent]\npoll_interval = 1200\ntags = [\"kids\"]\n",
+        )
+        .unwrap();
+        let env = [
+            ("FAMILY_POLICY_AGENT_POLL_JITTER".to_string(), "30".to_string()),
+            ("FAMILY_POLICY_NOTIFICATIONS_TELEGRAM_CHAT_ID".to_string(), "12345".to_string()),
+            ("FAMILY_POLICY_EVENT".to_string(), "pre_apply".to_string()),
+        ];
+        let overrides = ["agent.poll_jitter=5".to_string()];
+
+        let layered = merge_layers(&path, env.into_iter(), &overrides).unwrap();
+
+        let config = layered.config;
+        assert_eq!(config.agent.poll_interval, 900);
+        assert_eq!(config.agent.poll_jitter, 5);
+        assert_eq!(config.agent.tags, vec!["kids".to_string()]);
+        assert_eq!(config.agent.retry_interval, 60);
+        assert_eq!(config.notifications.telegram_chat_id.as_deref(), Some("12345"));
+        assert_eq!(
+            layered.sources,
+            vec![
+                ConfigSource::Defaults,
+                ConfigSource::File(path.clone()),
+                ConfigSource::File(dir.path().join(DROP_IN_DIR).join("10-tags.toml")),
+                ConfigSource::File(dir.path().join(DROP_IN_DIR).join("20-poll.toml")),
+                ConfigSource::Environment("FAMILY_POLICY_AGENT_POLL_JITTER".to_string()),
+                ConfigSource::Environment("FAMILY_POLICY_NOTIFICATIONS_TELEGRAM_CHAT_ID".to_string()),
+                ConfigSource::Flag("agent.poll_jitter".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn env_variables_map_to_settings() {
+        assert_eq!(env_setting("POLL_INTERVAL").as_deref(), Some("agent.poll_interval"));
+        assert_eq!(env_setting("POLICY_URL").as_deref(), Some("github.policy_url"));
+        assert_eq!(env_setting("AGENT_MAX_RETRIES").as_deref(), Some("agent.max_retries"));
+        assert_eq!(env_setting("NOTIFICATIONS_NTFY_URL").as_deref(), Some("notifications.ntfy_url"));
+        assert_eq!(env_setting("EVENT"), None);
+        assert_eq!(env_setting("CONFIG_HASH"), None);
+    }
+
+    #[test]
+    fn set_setting_rejects_unknown_sections() {
+        let mut table = toml::Table::new();
+        assert!(set_setting(&mut table, "poll_interval", "600").is_err());
+        assert!(set_setting(&mut table, "agents.poll_interval", "600").is_err());
+
+        set_setting(&mut table, "agent.tags", r#"["kids", "laptop"]"#).unwrap();
+        set_setting(&mut table, "github.policy_url", "https://example.com/policy.yaml").unwrap();
+        assert_eq!(table["agent"]["tags"].as_array().unwrap().len(), 2);
+        assert_eq!(table["github"]["policy_url"].as_str(), Some("https://example.com/policy.yaml"));
     }
 
     #[test]
@@ -273,5 +965,7 @@ mod tests {
         let logging = LoggingConfig::default();
         assert_eq!(logging.level, "info");
         assert!(logging.file.is_none());
+        assert_eq!(logging.rotation, LogRotation::Daily);
+        assert_eq!(logging.max_files, 7);
     }
 }
@@ -1,16 +1,51 @@
 use anyhow::{Context, Result};
-use sha2::{Digest, Sha256};
+use chrono::Utc;
+use std::sync::Arc;
 use std::time::Duration;
-use tokio::time::sleep;
+use tokio::sync::{watch, Mutex};
+use tokio::time::{sleep, Instant};
 
-use super::{AgentConfig, GitHubPoller, PolicyFetchResult, PollingScheduler, State};
+#[cfg(unix)]
+use super::helper;
+use super::hooks::{self, HookEvent};
+use super::push::Pusher;
+use super::shutdown::Shutdown;
+use super::unmanaged;
+use super::update::{self, Updater};
+use super::{current_host, AgentConfig, GitHubPoller, PolicyFetchResult, PollingScheduler, State};
+use crate::browser::current_platform;
 use crate::config;
+use crate::core::diff::{generate_diff, generate_diff_with_conflicts, ExtensionDiff, PolicyDiff};
+use crate::i18n::{self, tr};
+use crate::inbox::{self, NotificationLevel};
+use crate::incidents::{self, IncidentKind};
 use crate::policy;
-use crate::state::{AppliedPolicies, load_state, save_state};
+use crate::state::{
+    AppliedPolicies, ChangeDetection, applied_config_hash, compute_config_hash, compute_content_hash,
+    load_policy_cache, load_state, save_policy_cache, save_state,
+};
 
-/// Run the agent daemon in a loop
+/// In low-power mode, look for unmanaged browsers only every this many checks
+const LOW_POWER_UNMANAGED_CHECK_EVERY: u32 = 6;
+
+/// Run the agent daemon in a loop until a shutdown signal arrives
 pub async fn run_agent_daemon(config: AgentConfig) -> Result<()> {
+    let shutdown = Shutdown::on_signals()?;
+    // Only a Windows service can be paused
+    let (_pause, paused) = watch::channel(false);
+    run_agent_daemon_with(config, shutdown, paused).await
+}
+
+/// Run the agent daemon in a loop until `shutdown` is requested
+///
+/// While `paused` is true, no checks are started.
+pub(super) async fn run_agent_daemon_with(
+    config: AgentConfig,
+    mut shutdown: Shutdown,
+    mut paused: watch::Receiver<bool>,
+) -> Result<()> {
     tracing::info!("Starting agent daemon");
+    i18n::init(config.agent.locale.as_deref());
     tracing::info!("Policy URL: {}", config.github.policy_url);
     tracing::info!(
         "Poll interval: {} seconds (±{} seconds jitter)",
@@ -18,29 +53,234 @@ pub async fn run_agent_daemon(config: AgentConfig) -> Result<()> {
         config.agent.poll_jitter
     );
 
-    let scheduler = PollingScheduler::new(config.agent.poll_interval, config.agent.poll_jitter);
+    let scheduler = PollingScheduler::new(config.agent.poll_interval, config.agent.poll_jitter)
+        .with_backoff(config.agent.failure_threshold, config.agent.max_poll_interval);
+
+    // Set up before the startup checks so what they find is pushed too
+    let mut pusher = match Pusher::new(&config.notifications, &config.agent) {
+        Ok(pusher) => pusher,
+        Err(e) => {
+            tracing::warn!("Notifications won't be pushed: {:#}", e);
+            None
+        }
+    };
+
+    // A leftover marker means the previous run never shut down cleanly
+    match incidents::agent_started() {
+        Ok(Some(started)) => incidents::record(
+            IncidentKind::UnexpectedStop,
+            &format!(
+                "The agent started at {} was killed, crashed or lost power",
+                started.format("%Y-%m-%d %H:%M:%S %Z")
+            ),
+        ),
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Failed to check for an unexpected stop: {:#}", e),
+    }
+
+    // Restore anything removed while the agent wasn't running before the
+    // first network poll. This runs in low-power mode too: it's what undoes
+    // tampering while the agent was off.
+    if let Err(e) = verify_and_restore_cached_policy(&config).await {
+        tracing::warn!("Startup policy verification failed: {:#}", e);
+    }
+    if let Some(pusher) = &mut pusher {
+        pusher.push_new().await;
+    }
+
+    // Held while applying, so helper requests from the UI wait their turn
+    let applying = Arc::new(Mutex::new(()));
+    let helper = start_helper(&config, &applying);
+
+    let mut next_update_check = Instant::now();
+    let mut updated_to = None;
+    let mut checks: u32 = 0;
 
     loop {
-        // Check and apply policy
-        match check_and_apply_with_retry(&config).await {
+        if *paused.borrow() {
+            tracing::info!("Agent paused");
+            tokio::select! {
+                _ = wait_while_paused(&mut paused) => tracing::info!("Agent resumed"),
+                _ = shutdown.requested() => break,
+            }
+        }
+
+        // Check and apply policy. This is never interrupted mid-apply, so the
+        // state file always matches what was written.
+        let result = {
+            let _applying = applying.lock().await;
+            check_and_apply_with_retry(&config, &mut shutdown).await
+        };
+        match &result {
             Ok(applied) => {
-                if applied {
+                if *applied {
                     tracing::info!("Policy updated and applied successfully");
                 } else {
                     tracing::debug!("Policy unchanged");
                 }
             }
             Err(e) => {
-                tracing::error!("Failed to check/apply policy: {:#}", e);
                 // Continue running even if this check failed
+                tracing::error!("Failed to check/apply policy: {:#}", e);
+            }
+        }
+        let failures = record_poll_result(&result, config.agent.failure_threshold);
+
+        // Scanning for browsers is the periodic work low-power mode throttles
+        let scan_due = !config.agent.low_power || checks.is_multiple_of(LOW_POWER_UNMANAGED_CHECK_EVERY);
+        if scan_due && let Err(e) = unmanaged::check_unmanaged_browsers() {
+            tracing::warn!("Failed to check for unmanaged browsers: {:#}", e);
+        }
+        checks = checks.wrapping_add(1);
+        if let Some(pusher) = &mut pusher {
+            pusher.push_new().await;
+        }
+
+        if shutdown.is_requested() {
+            break;
+        }
+
+        if config.update.automatic && Instant::now() >= next_update_check {
+            next_update_check = Instant::now() + Duration::from_secs(config.update.check_interval);
+            match update_agent(&config).await {
+                Ok(Some(version)) => {
+                    updated_to = Some(version);
+                    break;
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Agent update failed: {:#}", e),
             }
         }
 
-        // Sleep until next check
-        let next_check = scheduler.next_poll_time();
-        tracing::debug!("Next check at: {}", next_check.format("%Y-%m-%d %H:%M:%S %Z"));
-        scheduler.sleep_until_next_poll().await;
+        // Sleep until next check (or until asked to stop), less often while
+        // checks keep failing
+        let next_poll = scheduler.schedule_poll_after_failures(failures);
+        tracing::debug!(
+            "Next check at: {}",
+            next_poll.display_time().format("%Y-%m-%d %H:%M:%S %Z")
+        );
+        tokio::select! {
+            _ = next_poll.wait() => {}
+            _ = shutdown.requested() => break,
+        }
+    }
+
+    if let Some(helper) = helper {
+        helper.abort();
     }
+    if let Err(e) = incidents::agent_stopped() {
+        tracing::warn!("{:#}", e);
+    }
+
+    tracing::info!("Agent daemon stopped");
+
+    // Service managers restart the agent when it exits with an error, which
+    // starts the new binary
+    if let Some(version) = updated_to {
+        anyhow::bail!("Agent updated to {}, exiting to restart", version);
+    }
+    Ok(())
+}
+
+/// Start serving the UI's privileged requests if any accounts may use them
+#[cfg(unix)]
+fn start_helper(config: &AgentConfig, applying: &Arc<Mutex<()>>) -> Option<tokio::task::JoinHandle<()>> {
+    if config.security.helper_users.is_empty() {
+        return None;
+    }
+    let helper = helper::serve(config.clone(), applying.clone());
+    Some(tokio::spawn(async move {
+        if let Err(e) = helper.await {
+            tracing::warn!("Helper stopped: {:#}", e);
+        }
+    }))
+}
+
+#[cfg(not(unix))]
+fn start_helper(config: &AgentConfig, _applying: &Arc<Mutex<()>>) -> Option<tokio::task::JoinHandle<()>> {
+    if !config.security.helper_users.is_empty() {
+        tracing::warn!("security.helper_users is ignored: the helper isn't available on this platform");
+    }
+    None
+}
+
+/// Install a newer agent release if there is one, returning its version
+async fn update_agent(config: &AgentConfig) -> Result<Option<semver::Version>> {
+    let updater = Updater::new(config.update.clone(), &config.agent)?;
+    let Some(release) = updater.check().await? else {
+        return Ok(None);
+    };
+
+    tracing::info!("Updating agent from {} to {}", update::current_version(), release.version);
+    let binary = updater.download(&release).await?;
+    update::install(&binary)?;
+
+    inbox::notify(
+        NotificationLevel::Info,
+        &tr("agent-updated-title", &[]),
+        &tr("agent-updated", &[("version", &release.version)]),
+    );
+    Ok(Some(release.version))
+}
+
+/// Wait until the daemon is no longer paused
+async fn wait_while_paused(paused: &mut watch::Receiver<bool>) {
+    // An error means nothing can resume it; wait for shutdown instead
+    if paused.wait_for(|paused| !*paused).await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Count consecutive failed checks in the state file, alerting once when they
+/// reach `threshold` and again when checks recover
+///
+/// Returns the number of consecutive failures, for widening the poll interval.
+fn record_poll_result(result: &Result<bool>, threshold: u32) -> u32 {
+    let mut state = match load_state() {
+        Ok(state) => state.unwrap_or_else(State::new_agent),
+        Err(e) => {
+            tracing::warn!("Failed to record check result: {:#}", e);
+            return u32::from(result.is_err());
+        }
+    };
+
+    match result {
+        Ok(_) => {
+            let Some(ended) = state.record_poll_success() else {
+                return 0;
+            };
+            tracing::info!("Policy checks succeeded again after {} failures", ended.failures);
+            if ended.alerted {
+                inbox::notify(
+                    NotificationLevel::Info,
+                    &tr("updates-resumed-title", &[]),
+                    &tr("updates-resumed", &[("since", &ended.since.format("%Y-%m-%d %H:%M"))]),
+                );
+            }
+        }
+        Err(e) => {
+            let error = format!("{:#}", e);
+            if state.record_poll_failure(&error, threshold) {
+                tracing::warn!(
+                    "{} policy checks failed in a row, polling less often",
+                    state.consecutive_poll_failures()
+                );
+                inbox::notify(
+                    NotificationLevel::Error,
+                    &tr("updates-failing-title", &[]),
+                    &tr(
+                        "updates-failing",
+                        &[("failures", &state.consecutive_poll_failures()), ("error", &error)],
+                    ),
+                );
+            }
+        }
+    }
+
+    if let Err(e) = save_state(&state) {
+        tracing::warn!("Failed to record check result: {:#}", e);
+    }
+    state.consecutive_poll_failures()
 }
 
 /// Check for policy updates and apply if changed (single execution)
@@ -48,8 +288,36 @@ pub async fn check_and_apply_once(config: &AgentConfig, dry_run: bool) -> Result
     check_and_apply_policy(config, dry_run).await
 }
 
+/// Download the remote policy and compare it with what is applied, without
+/// changing anything
+///
+/// The stored ETag is ignored so the preview always reflects the policy on
+/// the server. Returns `None` if it is the policy that is already applied.
+pub async fn preview_remote_policy(config: &AgentConfig) -> Result<Option<PolicyDiff>> {
+    let state = load_state()?;
+    let poller = GitHubPoller::new(config.github.clone(), &config.agent)?;
+
+    let content = match poller.fetch_policy(None, None).await? {
+        PolicyFetchResult::Updated { content, .. } => content,
+        PolicyFetchResult::NotModified => {
+            anyhow::bail!("Server answered 304 Not Modified without a validator")
+        }
+    };
+    let content = poller.resolve_includes(&content).await?.unwrap_or(content);
+
+    let (policy_config, new_hash) = parse_policy(&content)?;
+    if state.as_ref().is_some_and(|s| s.config_hash == new_hash) {
+        return Ok(None);
+    }
+
+    Ok(Some(generate_diff_with_conflicts(&policy_config, state.as_ref())?))
+}
+
 /// Check and apply policy with retry logic
-async fn check_and_apply_with_retry(config: &AgentConfig) -> Result<bool> {
+///
+/// Every attempt is a full check, so a policy that fails to apply runs the
+/// hooks again on each retry.
+pub(super) async fn check_and_apply_with_retry(config: &AgentConfig, shutdown: &mut Shutdown) -> Result<bool> {
     let max_retries = config.agent.max_retries;
     let mut retries = 0;
 
@@ -68,7 +336,10 @@ async fn check_and_apply_with_retry(config: &AgentConfig) -> Result<bool> {
                 );
                 tracing::info!("Retrying in {} seconds...", backoff.as_secs());
 
-                sleep(backoff).await;
+                tokio::select! {
+                    _ = sleep(backoff) => {}
+                    _ = shutdown.requested() => return Err(e),
+                }
             }
             Err(e) => {
                 tracing::error!("Failed to check/apply policy after {} retries", retries);
@@ -84,16 +355,26 @@ async fn check_and_apply_policy(config: &AgentConfig, dry_run: bool) -> Result<b
     let mut state = load_state()?.unwrap_or_else(|| State::new_agent());
 
     // 2. Create GitHub poller
-    let poller = GitHubPoller::new(config.github.clone())?;
+    let poller = GitHubPoller::new(config.github.clone(), &config.agent)?;
 
-    // 3. Fetch policy with ETag
+    // 3. Fetch policy with ETag (or Last-Modified if the server sent no ETag)
     let result = poller
-        .fetch_policy(state.etag.as_deref())
+        .fetch_policy(state.etag.as_deref(), state.last_modified.as_deref())
         .await?;
 
     // 4. Handle result
     match result {
         PolicyFetchResult::NotModified => {
+            state.change_detection = Some(if state.etag.is_some() {
+                ChangeDetection::Etag
+            } else {
+                ChangeDetection::LastModified
+            });
+
+            if reapply_if_schedule_changed(config, &mut state, dry_run).await? {
+                return Ok(true);
+            }
+
             // No change, just update check time (skip if dry-run)
             if !dry_run {
                 state.update_checked();
@@ -101,35 +382,99 @@ async fn check_and_apply_policy(config: &AgentConfig, dry_run: bool) -> Result<b
             }
             Ok(false)
         }
-        PolicyFetchResult::Updated { content, etag } => {
+        PolicyFetchResult::Updated { content, etag, last_modified } => {
+            // Included files have no ETag of their own, so a policy with
+            // includes is downloaded in full on every poll and compared by hash
+            let (content, etag, last_modified) = match poller.resolve_includes(&content).await? {
+                Some(merged) => (merged, None, None),
+                None => (content, etag, last_modified),
+            };
+
+            // Servers without validators send the same file every time; skip
+            // parsing it if it is byte-for-byte what was applied last
+            let content_hash = compute_content_hash(&content);
+            if state.content_hash.as_deref() == Some(content_hash.as_str()) {
+                tracing::debug!("Content downloaded but identical to the applied policy");
+                state.change_detection = Some(ChangeDetection::ContentHash);
+                if !dry_run {
+                    state.update_validators(etag, last_modified, content_hash);
+                    save_state(&state).context("Failed to save state")?;
+                }
+                return reapply_if_schedule_changed(config, &mut state, dry_run).await;
+            }
+
             // Content changed, check if policy actually changed
-            let new_hash = compute_yaml_hash(&content);
+            let (policy_config, new_hash) = parse_policy(&content)?;
 
             if state.config_hash == new_hash {
-                // Same content (hash collision or ETag issue), just update ETag
+                // Same policy (file reformatted or reordered, or ETag changed),
+                // just update ETag
                 tracing::debug!("Content downloaded but hash unchanged");
+                state.change_detection = Some(ChangeDetection::ConfigHash);
+                if !dry_run {
+                    // A held change that was reverted in the repo
+                    state.pending_approval = None;
+                    state.update_validators(etag, last_modified, content_hash);
+                    save_state(&state).context("Failed to save state")?;
+                    // Agents upgraded from a version without the cache get one here
+                    if let Err(e) = save_policy_cache(&content) {
+                        tracing::warn!("Failed to cache applied policy: {:#}", e);
+                    }
+                }
+                return reapply_if_schedule_changed(config, &mut state, dry_run).await;
+            }
+
+            if state.is_quarantined(&new_hash) {
+                tracing::warn!(
+                    "Policy {} is quarantined after repeated apply failures, keeping the last good policy",
+                    &new_hash[..16]
+                );
+                // Storing the ETag means it is only fetched again once it changes
                 if !dry_run {
                     state.update_etag(etag);
+                    state.last_modified = last_modified;
                     save_state(&state).context("Failed to save state")?;
                 }
                 return Ok(false);
             }
 
-            // Policy changed, apply it
             tracing::info!("New policy detected (hash: {})", &new_hash[..16]);
 
-            // Parse policy
-            let policy_config = config::Config::from_yaml_str(&content)
-                .context("Failed to parse policy YAML")?;
+            if config.agent.confirm_before_apply && !state.is_approved(&new_hash) {
+                let removals = removals(&generate_diff(&policy_config, Some(&state)), &state);
+                if !removals.is_empty() {
+                    hold_for_approval(&mut state, &new_hash, removals, dry_run)?;
+                    return Ok(false);
+                }
+            }
+
+            // Policy changed, apply it. Each retry of a failed check runs
+            // the hooks again.
+            let apply_result =
+                apply_with_hooks(config, &policy_config, &state, &new_hash, dry_run).await?;
 
-            // Apply policies using existing logic
-            let applied_policies = apply_policy_config(&policy_config, dry_run)
-                .context("Failed to apply policies")?;
+            let applied_policies = match apply_result {
+                Ok(applied_policies) => applied_policies,
+                Err(e) => {
+                    if !dry_run {
+                        record_apply_failure(&mut state, &new_hash, &e, config.agent.quarantine_after);
+                    }
+                    return Err(e);
+                }
+            };
 
             // Update state (skip if dry-run)
             if !dry_run {
+                state.failed_apply = None;
+                state.pending_approval = None;
                 state.update_applied(new_hash, etag, applied_policies);
+                state.last_modified = last_modified;
+                state.content_hash = Some(content_hash);
+                state.change_detection = Some(ChangeDetection::Changed);
                 save_state(&state).context("Failed to save state")?;
+                if let Err(e) = save_policy_cache(&content) {
+                    tracing::warn!("Failed to cache applied policy: {:#}", e);
+                }
                 tracing::info!("Policy applied successfully");
             } else {
                 tracing::info!("Policy would be applied (dry-run)");
@@ -139,70 +484,513 @@ async fn check_and_apply_policy(config: &AgentConfig, dry_run: bool) -> Result<b
     }
 }
 
-/// Apply policy configuration using policy module
-fn apply_policy_config(config: &config::Config, dry_run: bool) -> Result<AppliedPolicies> {
-    // Use the centralized policy application logic
-    // Note: Agent maintains its own state, so we don't use core::apply here
-    policy::apply_policies(config, None, dry_run)
-        .context("Failed to apply policies")
+/// Apply `policy_config` with the configured hooks around it
+///
+/// A failing pre_apply hook vetoes the apply and is the outer error; the
+/// inner result is the apply's own. post_apply runs after every attempt, and
+/// a failure there is only logged since the policy is already in force.
+async fn apply_with_hooks(
+    config: &AgentConfig,
+    policy_config: &config::Config,
+    state: &State,
+    hash: &str,
+    dry_run: bool,
+) -> Result<Result<AppliedPolicies>> {
+    // Describe the change to hook scripts
+    let hook_env = [
+        ("FAMILY_POLICY_CONFIG_HASH", hash.to_string()),
+        ("FAMILY_POLICY_PREVIOUS_HASH", state.config_hash.clone()),
+    ];
+    let hook_timeout = Duration::from_secs(config.hooks.timeout);
+
+    if let Some(command) = &config.hooks.pre_apply {
+        if dry_run {
+            tracing::info!("Would run pre_apply hook (dry-run): {}", command);
+        } else {
+            hooks::run_hook(command, HookEvent::PreApply, &hook_env, hook_timeout)
+                .await
+                .context("pre_apply hook failed, policy not applied")?;
+        }
+    }
+
+    // Apply policies using existing logic
+    let apply_result = apply_policy_config(policy_config, state, dry_run).context("Failed to apply policies");
+
+    if let Some(command) = &config.hooks.post_apply {
+        let mut env = hook_env.to_vec();
+        match &apply_result {
+            Ok(_) => env.push(("FAMILY_POLICY_RESULT", "success".to_string())),
+            Err(e) => {
+                env.push(("FAMILY_POLICY_RESULT", "failure".to_string()));
+                env.push(("FAMILY_POLICY_ERROR", format!("{:#}", e)));
+            }
+        }
+
+        if dry_run {
+            tracing::info!("Would run post_apply hook (dry-run): {}", command);
+        } else if let Err(e) = hooks::run_hook(command, HookEvent::PostApply, &env, hook_timeout).await {
+            // The policy is already applied; don't fail the check over the hook
+            tracing::warn!("{:#}", e);
+        }
+    }
+
+    Ok(apply_result)
+}
+
+/// What applying `diff` would remove from, or change in, the applied policy
+///
+/// Browsers missing from the new policy have no diff at all, so they are
+/// looked up in the state.
+fn removals(diff: &PolicyDiff, state: &State) -> Vec<String> {
+    let applied = &state.applied_policies;
+    let browsers = [
+        ("Chrome", &diff.chrome, applied.chrome.is_some()),
+        ("Firefox", &diff.firefox, applied.firefox.is_some()),
+        ("Edge", &diff.edge, applied.edge.is_some()),
+        ("Chromium", &diff.chromium, applied.chromium.is_some()),
+        ("Vivaldi", &diff.vivaldi, applied.vivaldi.is_some()),
+        ("Opera", &diff.opera, applied.opera.is_some()),
+        ("Safari", &diff.safari, applied.safari.is_some()),
+    ];
+
+    let mut removals = Vec::new();
+    for (browser, diff, was_applied) in browsers {
+        let Some(diff) = diff else {
+            if was_applied {
+                removals.push(format!("{}: all policies", browser));
+            }
+            continue;
+        };
+
+        for extension in &diff.extensions {
+            if let ExtensionDiff::Removed { id, name, .. } = extension {
+                removals.push(format!("{}: extension {}", browser, name.as_deref().unwrap_or(id)));
+            }
+        }
+        // Setting something new only tightens; changing or dropping an
+        // applied setting may relax it
+        for setting in diff.privacy_settings.iter().filter(|s| s.old_value.is_some()) {
+            removals.push(format!(
+                "{}: {} {} -> {}",
+                browser,
+                setting.setting_name,
+                setting.old_value.as_deref().unwrap_or("none"),
+                setting.new_value.as_deref().unwrap_or("none")
+            ));
+        }
+    }
+    removals
 }
 
-/// Compute SHA-256 hash of YAML content
-fn compute_yaml_hash(content: &str) -> String {
-    let mut hasher = Sha256::new();
-    hasher.update(content.as_bytes());
-    let result = hasher.finalize();
+/// Keep the current policy and ask the administrator to approve the new one
+///
+/// The ETag isn't stored, so the policy is fetched again on every poll and
+/// applied by the first check after it is approved.
+fn hold_for_approval(state: &mut State, hash: &str, removals: Vec<String>, dry_run: bool) -> Result<()> {
+    tracing::warn!(
+        "Policy {} removes or changes applied policies, waiting for approval: {}",
+        &hash[..16],
+        removals.join(", ")
+    );
+    if dry_run {
+        return Ok(());
+    }
 
-    format!("sha256:{}", hex::encode(&result))
+    let message = tr("approval-needed", &[("changes", &removals.join("\n  "))]);
+    if state.hold_for_approval(hash, removals) {
+        inbox::notify(NotificationLevel::Warning, &tr("approval-needed-title", &[]), &message);
+    }
+    state.update_checked();
+    save_state(state).context("Failed to save state")
 }
 
-// Helper module for hex encoding
-mod hex {
-    pub fn encode(bytes: &[u8]) -> String {
-        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+/// Count a failed apply and quarantine the policy if it keeps failing
+///
+/// A quarantined policy isn't retried until the repo content changes. The
+/// failed attempt may have left browsers half-configured, so the last good
+/// policy is re-applied from the cache.
+fn record_apply_failure(state: &mut State, hash: &str, error: &anyhow::Error, quarantine_after: u32) {
+    let error = format!("{:#}", error);
+    let quarantined = state.record_apply_failure(hash, &error, quarantine_after);
+
+    if quarantined {
+        tracing::error!(
+            "Policy {} failed to apply {} times in a row, quarantining it",
+            &hash[..16],
+            quarantine_after
+        );
+        if let Err(e) = restore_cached_policy(state) {
+            tracing::warn!("Failed to re-apply the last good policy: {:#}", e);
+        }
+    }
+
+    if let Err(e) = save_state(state) {
+        tracing::warn!("Failed to record apply failure: {:#}", e);
+    }
+
+    if quarantined {
+        inbox::notify(
+            NotificationLevel::Error,
+            &tr("policy-quarantined-title", &[]),
+            &tr("policy-quarantined", &[("attempts", &quarantine_after), ("error", &error)]),
+        );
     }
 }
 
-// Extension trait to add from_yaml_str to Config
-impl config::Config {
-    /// Parse config from YAML string
-    pub fn from_yaml_str(content: &str) -> Result<Self> {
-        let config: config::Config = serde_yaml::from_str(content)
-            .context("Failed to parse YAML")?;
+/// Re-apply the cached (last successfully applied) policy
+fn restore_cached_policy(state: &mut State) -> Result<()> {
+    let Some(content) = load_policy_cache()? else {
+        return Ok(());
+    };
+
+    let (policy_config, hash) = parse_policy(&content).context("Invalid cached policy")?;
+    if hash != state.config_hash {
+        anyhow::bail!("Cached policy doesn't match applied state");
+    }
+
+    state.applied_policies = apply_policy_config(&policy_config, state, false)
+        .context("Failed to re-apply cached policy")?;
+    Ok(())
+}
+
+/// Re-apply the cached policy if any of its entries expired, or started or
+/// stopped on schedule, since it was applied
+///
+/// The policy file doesn't change when that happens, so the hash check alone
+/// would leave the old entries in force indefinitely. Like a new policy, the
+/// re-apply runs the hooks, and with `confirm_before_apply` one that relaxes
+/// the applied policy waits for approval.
+async fn reapply_if_schedule_changed(config: &AgentConfig, state: &mut State, dry_run: bool) -> Result<bool> {
+    let Some(content) = load_policy_cache()? else {
+        return Ok(false);
+    };
+
+    let (policy_config, hash) = parse_policy(&content).context("Invalid cached policy")?;
+    if hash != state.config_hash {
+        return Ok(false);
+    }
+
+    let now = Utc::now();
+    let names = |policies: Vec<&crate::config::PolicyEntry>| -> Vec<String> {
+        policies.iter().map(|p| p.name.clone()).collect()
+    };
+    let (started, stopped) = policy_config.schedule_changes_since(state.last_updated, now);
+    let (started, mut stopped) = (names(started), names(stopped));
+    stopped.extend(names(policy_config.expired_since(state.last_updated, now)));
 
-        // Validate the config
-        config::validate_config(&config)?;
+    if started.is_empty() && stopped.is_empty() {
+        return Ok(false);
+    }
 
-        Ok(config)
+    let mut changes = Vec::new();
+    if !started.is_empty() {
+        changes.push(tr("schedule-started", &[("policies", &started.join(", "))]));
+    }
+    if !stopped.is_empty() {
+        changes.push(tr("schedule-stopped", &[("policies", &stopped.join(", "))]));
+    }
+    let summary = changes.join("; ");
+    tracing::info!("Scheduled policy changes: {}", summary);
+
+    // The file hash doesn't change, so the entries now in force identify
+    // what is approved
+    let in_force_hash = compute_config_hash(&policy_config.effective(now))?;
+    if config.agent.confirm_before_apply && !stopped.is_empty() && !state.is_approved(&in_force_hash) {
+        let removals = removals(&generate_diff(&policy_config, Some(state)), state);
+        if !removals.is_empty() {
+            hold_for_approval(state, &in_force_hash, removals, dry_run)?;
+            return Ok(false);
+        }
     }
+
+    if dry_run {
+        tracing::info!("Policy would be re-applied with the entries now in force (dry-run)");
+        return Ok(true);
+    }
+
+    let config_hash = state.config_hash.clone();
+    let applied_policies = apply_with_hooks(config, &policy_config, state, &config_hash, false)
+        .await?
+        .context("Failed to re-apply policy after a scheduled change")?;
+
+    let etag = state.etag.clone();
+    state.pending_approval = None;
+    state.update_applied(config_hash, etag, applied_policies);
+    save_state(state).context("Failed to save state")?;
+
+    let title = if started.is_empty() { "schedule-expired-title" } else { "schedule-changed-title" };
+    inbox::notify(NotificationLevel::Info, &tr(title, &[]), &summary);
+    Ok(true)
+}
+
+/// Verify the applied policies against the cached policy and re-apply on drift
+///
+/// Runs at daemon startup, so policies wiped while the machine was off (or
+/// the agent stopped) come back without waiting for the network. Restoring
+/// runs the hooks like any apply; it only puts back the policy that was
+/// applied, so there is nothing to hold for approval.
+pub(super) async fn verify_and_restore_cached_policy(config: &AgentConfig) -> Result<()> {
+    let state = load_state()?;
+
+    let Some(content) = load_policy_cache()? else {
+        tracing::debug!("No cached policy, skipping startup verification");
+        return Ok(());
+    };
+
+    // The cache is only written alongside the state, so one without the
+    // other means the state file was deleted
+    let Some(mut state) = state else {
+        incidents::record(
+            IncidentKind::StateMismatch,
+            "The state file was deleted; the cached policy is re-applied",
+        );
+        return bootstrap_from_cache(config, &content).await;
+    };
+
+    // Older agents didn't record the content hash
+    if state
+        .content_hash
+        .as_ref()
+        .is_some_and(|expected| *expected != compute_content_hash(&content))
+    {
+        tracing::warn!("Cached policy was modified, skipping startup verification");
+        incidents::record(
+            IncidentKind::StateMismatch,
+            "The cached policy doesn't match the one the agent downloaded; it is replaced on \
+             the next poll",
+        );
+        return Ok(());
+    }
+
+    let (policy_config, hash) = parse_policy(&content).context("Invalid cached policy")?;
+    if hash != state.config_hash {
+        tracing::warn!("Cached policy doesn't match applied state, skipping startup verification");
+        incidents::record(
+            IncidentKind::StateMismatch,
+            "The state file doesn't match the last policy the agent applied (expected after \
+             a local 'apply' or 'remove')",
+        );
+        return Ok(());
+    }
+
+    let plan = policy::evaluate_policy(&policy_config.effective(Utc::now()), current_platform())?;
+    let drift = policy::verify_plan(&plan)?;
+
+    if drift.is_empty() {
+        tracing::info!("Applied policies verified ({} values)", plan.writes.len());
+        return Ok(());
+    }
+
+    for d in &drift {
+        tracing::warn!("Policy drift: {}", d);
+    }
+    let mut detail: Vec<String> = drift.iter().take(3).map(|d| d.to_string()).collect();
+    if drift.len() > detail.len() {
+        detail.push(format!("and {} more", drift.len() - detail.len()));
+    }
+    incidents::record(
+        IncidentKind::PolicyDrift,
+        &format!("{} policy values changed while the agent was stopped: {}", drift.len(), detail.join("; ")),
+    );
+    tracing::info!("Re-applying cached policy ({} values drifted)", drift.len());
+
+    let config_hash = state.config_hash.clone();
+    let applied_policies = apply_with_hooks(config, &policy_config, &state, &config_hash, false)
+        .await?
+        .context("Failed to re-apply cached policy")?;
+
+    let etag = state.etag.clone();
+    state.update_applied(config_hash, etag, applied_policies);
+    save_state(&state).context("Failed to save state")?;
+
+    tracing::info!("Cached policy re-applied");
+    inbox::notify(
+        NotificationLevel::Warning,
+        &tr("policies-restored-title", &[]),
+        &tr("policies-restored", &[("count", &drift.len())]),
+    );
+    Ok(())
+}
+
+/// Apply the cached policy when there is no state file to compare it with
+///
+/// Without this, a machine whose state was lost wouldn't be enforced again
+/// until the agent can reach the network.
+async fn bootstrap_from_cache(config: &AgentConfig, content: &str) -> Result<()> {
+    let (policy_config, hash) = parse_policy(content).context("Invalid cached policy")?;
+    tracing::info!("Applying cached policy (hash: {})", &hash[..16]);
+
+    let mut state = State::new_agent();
+    let applied_policies = apply_with_hooks(config, &policy_config, &state, &hash, false)
+        .await?
+        .context("Failed to apply cached policy")?;
+
+    // No ETag, so the next poll downloads the policy and compares contents
+    state.update_applied(hash, None, applied_policies);
+    state.content_hash = Some(compute_content_hash(content));
+    save_state(&state).context("Failed to save state")?;
+
+    tracing::info!("Cached policy applied");
+    Ok(())
+}
+
+#[cfg(test)]
+type TestApplier = Box<dyn Fn(&config::Config) -> Result<AppliedPolicies>>;
+
+#[cfg(test)]
+thread_local! {
+    static TEST_APPLIER: std::cell::RefCell<Option<TestApplier>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Apply policies with `applier` instead of the platform writers on the
+/// current thread until the returned guard is dropped
+#[cfg(test)]
+pub(super) fn use_test_applier(
+    applier: impl Fn(&config::Config) -> Result<AppliedPolicies> + 'static,
+) -> TestApplierGuard {
+    TEST_APPLIER.with(|a| *a.borrow_mut() = Some(Box::new(applier)));
+    TestApplierGuard
+}
+
+#[cfg(test)]
+pub(super) struct TestApplierGuard;
+
+#[cfg(test)]
+impl Drop for TestApplierGuard {
+    fn drop(&mut self) {
+        TEST_APPLIER.with(|a| *a.borrow_mut() = None);
+    }
+}
+
+/// Apply policy configuration using policy module
+fn apply_policy_config(config: &config::Config, state: &State, dry_run: bool) -> Result<AppliedPolicies> {
+    #[cfg(test)]
+    if let Some(result) = TEST_APPLIER.with(|a| a.borrow().as_ref().map(|apply| apply(config))) {
+        return result;
+    }
+
+    // Use the centralized policy application logic
+    // Note: Agent maintains its own state, so we don't use core::apply here
+    policy::apply_policies(config, Some(state), dry_run)
+        .context("Failed to apply policies")
+}
+
+/// Parse policy YAML and compute the hash recorded in the state file
+///
+/// Entries whose `match` block doesn't match this machine are dropped. The
+/// hash is of the normalized config, so reformatting the file or reordering
+/// policies in the repo doesn't trigger a re-apply.
+fn parse_policy(content: &str) -> Result<(config::Config, String)> {
+    let host = current_host();
+    let policy_config = config::Config::from_yaml_str(content).context("Failed to parse policy YAML")?;
+    let hash = applied_config_hash(&policy_config, &host)?;
+    Ok((policy_config.for_host(&host), hash))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const POLICY: &str = r#"
+policies:
+  - name: Privacy
+    browsers: [chrome]
+    disable_private_mode: true
+  - name: Extensions
+    browsers: [chrome]
+    extensions:
+      - name: uBO Lite
+        id: ddkjiahejlhfcafbddmgiahcphecmpfh
+"#;
+
+    #[test]
+    fn parse_policy_hash_has_correct_format() {
+        let (_, hash) = parse_policy(POLICY).unwrap();
+        assert!(hash.starts_with("sha256:"));
+        assert_eq!(hash.len(), 71); // "sha256:" (7) + 64 hex chars
+    }
+
     #[test]
-    fn compute_yaml_hash_is_deterministic() {
-        let yaml = "chrome:\n  extensions:\n    - id: test123";
-        let hash1 = compute_yaml_hash(yaml);
-        let hash2 = compute_yaml_hash(yaml);
-        assert_eq!(hash1, hash2);
+    fn parse_policy_hash_ignores_reordering_and_formatting() {
+        let reordered = r#"
+# Reordered, with comments
+policies:
+  - name: Extensions
+    browsers: [chrome]
+    extensions:
+      - name: uBO Lite
+        id: ddkjiahejlhfcafbddmgiahcphecmpfh
+  - name: Privacy
+    browsers: [chrome]
+    disable_private_mode: true
+"#;
+        assert_eq!(parse_policy(POLICY).unwrap().1, parse_policy(reordered).unwrap().1);
+    }
+
+    #[test]
+    fn parse_policy_hash_different_for_different_policy() {
+        let changed = POLICY.replace("disable_private_mode: true", "disable_private_mode: false");
+        assert_ne!(parse_policy(POLICY).unwrap().1, parse_policy(&changed).unwrap().1);
+    }
+
+    /// Assert that editing `POLICY` into `changed` changes its hash
+    fn assert_hash_changes(changed: &str) {
+        let (original, changed_hash) = (parse_policy(POLICY).unwrap().1, parse_policy(changed).unwrap().1);
+        assert_ne!(original, changed_hash, "{}", changed);
+    }
+
+    /// `POLICY` with `lines` added to its Privacy entry
+    fn with_privacy_lines(lines: &str) -> String {
+        let privacy = "    disable_private_mode: true\n";
+        POLICY.replace(privacy, &format!("{}{}", privacy, lines))
     }
 
     #[test]
-    fn compute_yaml_hash_different_for_different_content() {
-        let yaml1 = "chrome:\n  extensions:\n    - id: test123";
-        let yaml2 = "chrome:\n  extensions:\n    - id: test456";
-        let hash1 = compute_yaml_hash(yaml1);
-        let hash2 = compute_yaml_hash(yaml2);
-        assert_ne!(hash1, hash2);
+    fn parse_policy_hash_changes_with_enabled() {
+        assert_hash_changes(&with_privacy_lines("    enabled: false\n"));
     }
 
     #[test]
-    fn compute_yaml_hash_has_correct_format() {
-        let yaml = "chrome:\n  extensions:\n    - id: test123";
-        let hash = compute_yaml_hash(yaml);
-        assert!(hash.starts_with("sha256:"));
-        assert_eq!(hash.len(), 71); // "sha256:" (7) + 64 hex chars
+    fn parse_policy_hash_changes_with_schedule() {
+        assert_hash_changes(&with_privacy_lines("    active_from: 2025-06-01T08:00:00Z\n"));
+        assert_hash_changes(&with_privacy_lines("    active_until: 2025-06-01T20:00:00Z\n"));
+    }
+
+    #[test]
+    fn parse_policy_hash_changes_with_expires_at() {
+        assert_hash_changes(&with_privacy_lines("    expires_at: 2025-06-06T18:00:00Z\n"));
+    }
+
+    #[test]
+    fn parse_policy_hash_changes_with_top_level_settings() {
+        assert_hash_changes(&format!("unmanaged_browsers: warn\n{}", POLICY));
+        assert_hash_changes(&format!("conflict_policy: refuse\n{}", POLICY));
+    }
+
+    #[test]
+    fn parse_policy_hash_changes_with_other_browsers() {
+        for browser in ["chromium", "vivaldi", "opera", "safari"] {
+            let browsers = format!("browsers: [chrome, {}]", browser);
+            assert_hash_changes(&POLICY.replacen("browsers: [chrome]", &browsers, 1));
+        }
+    }
+
+    #[test]
+    fn parse_policy_hash_changes_with_blocked_urls() {
+        let safari = POLICY.replacen("browsers: [chrome]", "browsers: [chrome, safari]", 1);
+        let blocked = safari.replace(
+            "    disable_private_mode: true\n",
+            "    disable_private_mode: true\n    blocked_urls: [reddit.com]\n",
+        );
+        assert_ne!(parse_policy(&safari).unwrap().1, parse_policy(&blocked).unwrap().1);
+        assert_ne!(
+            parse_policy(&blocked).unwrap().1,
+            parse_policy(&blocked.replace("reddit.com", "tiktok.com")).unwrap().1
+        );
+    }
+
+    #[test]
+    fn parse_policy_rejects_invalid_yaml() {
+        assert!(parse_policy("policies: [").is_err());
     }
 }
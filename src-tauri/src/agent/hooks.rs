@@ -0,0 +1,195 @@
+//! Admin-configured hook commands run around policy application
+//!
+//! Hooks are run through the platform shell with a timeout. Details of the
+//! event are passed in FAMILY_POLICY_* environment variables so scripts don't
+//! need to parse arguments. Every run is recorded in the incident log with
+//! its exit status and output, since hooks run as root.
+
+use anyhow::{Context, Result};
+use std::time::Duration;
+use tokio::process::Command;
+
+use crate::incidents::{self, IncidentKind};
+
+/// Characters of a hook's output kept in the incident log
+const MAX_RECORDED_OUTPUT: usize = 1000;
+
+/// Lifecycle events that can trigger a hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    PreApply,
+    PostApply,
+}
+
+impl HookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookEvent::PreApply => "pre_apply",
+            HookEvent::PostApply => "post_apply",
+        }
+    }
+}
+
+/// Run a hook command and wait for it to finish
+///
+/// Fails if the command cannot be started, exits non-zero, or runs longer
+/// than `timeout` (in which case it is killed).
+pub async fn run_hook(
+    command: &str,
+    event: HookEvent,
+    env: &[(&str, String)],
+    timeout: Duration,
+) -> Result<()> {
+    tracing::info!("Running {} hook: {}", event.as_str(), command);
+
+    let mut cmd = shell_command(command);
+    cmd.env("FAMILY_POLICY_EVENT", event.as_str())
+        .envs(env.iter().map(|(k, v)| (*k, v.as_str())))
+        .kill_on_drop(true);
+
+    let output = tokio::time::timeout(timeout, cmd.output())
+        .await
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "{} hook timed out after {} seconds",
+                event.as_str(),
+                timeout.as_secs()
+            )
+        })
+        .and_then(|output| output.with_context(|| format!("Failed to run {} hook", event.as_str())));
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => {
+            incidents::record(IncidentKind::HookRun, &format!("{}: {:#}", command, e));
+            return Err(e);
+        }
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !stdout.trim().is_empty() {
+        tracing::info!("{} hook output: {}", event.as_str(), stdout.trim());
+    }
+    if !stderr.trim().is_empty() {
+        tracing::warn!("{} hook stderr: {}", event.as_str(), stderr.trim());
+    }
+
+    let mut detail = format!("{} hook {} exited with {}", event.as_str(), command, output.status);
+    let recorded = recorded_output(&stdout, &stderr);
+    if !recorded.is_empty() {
+        detail.push_str(": ");
+        detail.push_str(&recorded);
+    }
+    incidents::record(IncidentKind::HookRun, &detail);
+
+    if !output.status.success() {
+        anyhow::bail!("{} hook exited with {}", event.as_str(), output.status);
+    }
+
+    tracing::info!("{} hook completed", event.as_str());
+    Ok(())
+}
+
+/// A hook's output for the incident log, cut to `MAX_RECORDED_OUTPUT`
+/// characters
+fn recorded_output(stdout: &str, stderr: &str) -> String {
+    let output: Vec<&str> = [stdout.trim(), stderr.trim()]
+        .into_iter()
+        .filter(|s| !s.is_empty())
+        .collect();
+    let output = output.join("\n");
+    if output.chars().count() <= MAX_RECORDED_OUTPUT {
+        return output;
+    }
+    let mut cut: String = output.chars().take(MAX_RECORDED_OUTPUT).collect();
+    cut.push('…');
+    cut
+}
+
+#[cfg(unix)]
+fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("/bin/sh");
+    cmd.arg("-c").arg(command);
+    cmd
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("cmd.exe");
+    cmd.arg("/C").arg(command);
+    cmd
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// Keep the incident log of hook runs in a temp directory
+    fn test_state_dir() -> (tempfile::TempDir, crate::state::TestStateDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let guard = crate::state::use_test_state_dir(dir.path());
+        (dir, guard)
+    }
+
+    #[tokio::test]
+    async fn hook_success() {
+        let _state = test_state_dir();
+        let result = run_hook("true", HookEvent::PreApply, &[], Duration::from_secs(5)).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn hook_runs_are_recorded_with_status_and_output() {
+        let _state = test_state_dir();
+        run_hook("echo done", HookEvent::PostApply, &[], Duration::from_secs(5)).await.unwrap();
+        run_hook("echo oops >&2; exit 3", HookEvent::PreApply, &[], Duration::from_secs(5))
+            .await
+            .unwrap_err();
+
+        let log = incidents::load_incidents().unwrap();
+        let details: Vec<&str> = log.incidents.iter().map(|i| i.detail.as_str()).collect();
+        assert!(log.incidents.iter().all(|i| i.kind == IncidentKind::HookRun));
+        assert_eq!(
+            details,
+            [
+                "post_apply hook echo done exited with exit status: 0: done",
+                "pre_apply hook echo oops >&2; exit 3 exited with exit status: 3: oops",
+            ]
+        );
+    }
+
+    #[test]
+    fn long_output_is_cut() {
+        let output = recorded_output(&"x".repeat(MAX_RECORDED_OUTPUT + 10), "");
+        assert_eq!(output.chars().count(), MAX_RECORDED_OUTPUT + 1);
+        assert!(output.ends_with('…'));
+    }
+
+    #[tokio::test]
+    async fn hook_non_zero_exit_fails() {
+        let _state = test_state_dir();
+        let result = run_hook("exit 3", HookEvent::PreApply, &[], Duration::from_secs(5)).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn hook_receives_event_env() {
+        let _state = test_state_dir();
+        let env = [("FAMILY_POLICY_RESULT", "success".to_string())];
+        let result = run_hook(
+            r#"test "$FAMILY_POLICY_EVENT" = post_apply && test "$FAMILY_POLICY_RESULT" = success"#,
+            HookEvent::PostApply,
+            &env,
+            Duration::from_secs(5),
+        )
+        .await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn hook_timeout_fails() {
+        let _state = test_state_dir();
+        let result = run_hook("sleep 5", HookEvent::PreApply, &[], Duration::from_millis(100)).await;
+        assert!(result.unwrap_err().to_string().contains("timed out"));
+    }
+}
@@ -0,0 +1,276 @@
+//! Agent logging
+//!
+//! The daemon always logs to stdout, which the service manager captures.
+//! Under systemd, events are sent to the journal directly instead so they
+//! keep their priority. On Windows, warnings and errors also go to the
+//! Application event log. `logging.file` adds a rotated log file on top.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{fmt, prelude::*, EnvFilter, Layer, Registry};
+
+use super::config::{LogRotation, LoggingConfig};
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Initialize logging for the daemon
+///
+/// The returned guard flushes the log file when dropped, so it must be kept
+/// alive until the daemon exits.
+pub fn init_agent_logging(config: &LoggingConfig, verbose: bool) -> Result<Option<WorkerGuard>> {
+    let level = if verbose { "debug" } else { config.level.as_str() };
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(level))
+        .with_context(|| format!("Invalid log level: {}", level))?;
+
+    let mut layers: Vec<BoxedLayer> = vec![console_layer()];
+
+    #[cfg(target_os = "windows")]
+    match event_log::EventLogLayer::new() {
+        Ok(layer) => layers.push(Box::new(layer)),
+        Err(e) => eprintln!("Event Log unavailable: {:#}", e),
+    }
+
+    let guard = match &config.file {
+        Some(path) => {
+            let appender = file_appender(config, path)?;
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            layers.push(fmt::layer().with_ansi(false).with_writer(writer).boxed());
+            Some(guard)
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(layers)
+        .with(filter)
+        .try_init()
+        .context("Failed to initialize logging")?;
+
+    Ok(guard)
+}
+
+/// stdout, or the journal when running as a systemd service
+fn console_layer() -> BoxedLayer {
+    #[cfg(target_os = "linux")]
+    if std::env::var_os("JOURNAL_STREAM").is_some() {
+        match tracing_journald::layer() {
+            Ok(layer) => {
+                return layer
+                    .with_syslog_identifier("family-policy-agent".to_string())
+                    .boxed();
+            }
+            Err(e) => eprintln!("journald unavailable, logging to stdout: {}", e),
+        }
+    }
+
+    fmt::layer().boxed()
+}
+
+fn file_appender(config: &LoggingConfig, path: &Path) -> Result<RollingFileAppender> {
+    let (dir, prefix, suffix) = split_log_path(path)?;
+
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create log directory: {}", dir.display()))?;
+
+    let rotation = match config.rotation {
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Daily => Rotation::DAILY,
+        LogRotation::Never => Rotation::NEVER,
+    };
+
+    let mut builder = RollingFileAppender::builder()
+        .rotation(rotation)
+        .filename_prefix(prefix);
+    if let Some(suffix) = suffix {
+        builder = builder.filename_suffix(suffix);
+    }
+    if config.max_files > 0 {
+        builder = builder.max_log_files(config.max_files);
+    }
+
+    builder
+        .build(&dir)
+        .with_context(|| format!("Failed to open log file: {}", path.display()))
+}
+
+/// Split `/var/log/family-policy/agent.log` into the directory, file prefix
+/// (`agent`) and suffix (`log`) the rolling appender names its files with
+fn split_log_path(path: &Path) -> Result<(PathBuf, String, Option<String>)> {
+    let dir = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."))
+        .to_path_buf();
+    let prefix = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .with_context(|| format!("Invalid log file path: {}", path.display()))?
+        .to_string();
+    let suffix = path.extension().and_then(|s| s.to_str()).map(String::from);
+
+    Ok((dir, prefix, suffix))
+}
+
+/// The log file currently being written for the configured `path`
+///
+/// Rotated files are named `<prefix>.<date>.<suffix>`, so this is the most
+/// recently modified file matching the configured name.
+pub fn current_log_file(path: &Path) -> Result<Option<PathBuf>> {
+    let latest = log_files(path)?
+        .into_iter()
+        .filter_map(|file| {
+            let modified = std::fs::metadata(&file).ok()?.modified().ok()?;
+            Some((modified, file))
+        })
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, file)| file);
+
+    Ok(latest)
+}
+
+/// All log files written for the configured `path`, including rotated ones
+pub fn log_files(path: &Path) -> Result<Vec<PathBuf>> {
+    let (dir, prefix, suffix) = split_log_path(path)?;
+
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("Failed to read log directory: {}", dir.display()));
+        }
+    };
+
+    let files = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with(&prefix)
+                && suffix.as_deref().is_none_or(|suffix| name.ends_with(suffix))
+        })
+        .map(|entry| entry.path())
+        .collect();
+
+    Ok(files)
+}
+
+#[cfg(target_os = "windows")]
+mod event_log {
+    use anyhow::Result;
+    use std::fmt::Write;
+    use tracing::field::{Field, Visit};
+    use tracing::{Event, Level, Subscriber};
+    use tracing_subscriber::layer::Context;
+    use tracing_subscriber::Layer;
+    use windows_sys::Win32::System::EventLog::{
+        RegisterEventSourceW, ReportEventW, EVENTLOG_ERROR_TYPE, EVENTLOG_WARNING_TYPE,
+    };
+
+    const SOURCE: &str = "FamilyPolicy";
+
+    /// Writes warnings and errors to the Application event log
+    pub struct EventLogLayer {
+        // Event source handle; kept as an integer so the layer is Send + Sync
+        handle: usize,
+    }
+
+    impl EventLogLayer {
+        pub fn new() -> Result<Self> {
+            let source = to_wide(SOURCE);
+            let handle = unsafe { RegisterEventSourceW(std::ptr::null(), source.as_ptr()) };
+            if handle.is_null() {
+                anyhow::bail!(
+                    "Failed to register event source: {}",
+                    std::io::Error::last_os_error()
+                );
+            }
+            Ok(Self { handle: handle as usize })
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for EventLogLayer {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            let event_type = match *event.metadata().level() {
+                Level::ERROR => EVENTLOG_ERROR_TYPE,
+                Level::WARN => EVENTLOG_WARNING_TYPE,
+                _ => return,
+            };
+
+            let mut visitor = MessageVisitor::default();
+            event.record(&mut visitor);
+            let message = to_wide(&visitor.0);
+            let strings = [message.as_ptr()];
+
+            unsafe {
+                ReportEventW(
+                    self.handle as _,
+                    event_type,
+                    0,
+                    0,
+                    std::ptr::null_mut(),
+                    1,
+                    0,
+                    strings.as_ptr(),
+                    std::ptr::null(),
+                );
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct MessageVisitor(String);
+
+    impl Visit for MessageVisitor {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "message" {
+                let _ = write!(self.0, "{:?}", value);
+            } else {
+                let _ = write!(self.0, " {}={:?}", field.name(), value);
+            }
+        }
+    }
+
+    fn to_wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(std::iter::once(0)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_log_path_uses_stem_and_extension() {
+        let (dir, prefix, suffix) =
+            split_log_path(Path::new("/var/log/family-policy/agent.log")).unwrap();
+        assert_eq!(dir, PathBuf::from("/var/log/family-policy"));
+        assert_eq!(prefix, "agent");
+        assert_eq!(suffix.as_deref(), Some("log"));
+
+        let (dir, prefix, suffix) = split_log_path(Path::new("agent")).unwrap();
+        assert_eq!(dir, PathBuf::from("."));
+        assert_eq!(prefix, "agent");
+        assert_eq!(suffix, None);
+    }
+
+    #[test]
+    fn current_log_file_picks_newest_rotated_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let old = temp_dir.path().join("agent.2026-10-14.log");
+        let new = temp_dir.path().join("agent.2026-10-15.log");
+        std::fs::write(&old, "old").unwrap();
+        std::fs::write(temp_dir.path().join("other.log"), "unrelated").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        std::fs::write(&new, "new").unwrap();
+
+        let configured = temp_dir.path().join("agent.log");
+        assert_eq!(current_log_file(&configured).unwrap(), Some(new));
+
+        let missing = temp_dir.path().join("missing/agent.log");
+        assert_eq!(current_log_file(&missing).unwrap(), None);
+    }
+}
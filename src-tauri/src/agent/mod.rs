@@ -6,12 +6,25 @@
 
 pub mod config;
 mod daemon;
+pub mod helper;
+mod hooks;
+mod logging;
 mod poller;
+#[cfg(test)]
+mod polling_tests;
+mod push;
 mod scheduler;
+pub mod secrets;
+#[cfg(target_os = "windows")]
+pub mod service;
+mod shutdown;
 mod state;
+mod unmanaged;
+pub mod update;
 
-pub use config::{AgentConfig, get_agent_config_path};
-pub use daemon::{run_agent_daemon, check_and_apply_once};
+pub use config::{AgentConfig, HooksConfig, current_host, get_agent_config_path};
+pub use daemon::{run_agent_daemon, check_and_apply_once, preview_remote_policy};
+pub use logging::{current_log_file, init_agent_logging, log_files};
 pub use poller::{GitHubPoller, PolicyFetchResult};
 pub use scheduler::PollingScheduler;
 pub use state::State; // Re-export unified State type
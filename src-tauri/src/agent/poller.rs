@@ -1,8 +1,12 @@
 use anyhow::{Context, Result};
-use reqwest::{Client, StatusCode};
+use reqwest::{Certificate, Client, RequestBuilder, StatusCode};
+use std::collections::HashMap;
+use std::path::Path;
 use std::time::Duration;
 
-use super::config::GitHubConfig;
+use super::config::{AgentSettings, GitHubConfig};
+use super::secrets;
+use crate::config::{self, Config};
 
 /// Result of fetching policy from GitHub
 #[derive(Debug)]
@@ -13,59 +17,73 @@ pub enum PolicyFetchResult {
     Updated {
         content: String,
         etag: Option<String>,
+        last_modified: Option<String>,
     },
 }
 
-/// GitHub poller with ETag support
+/// GitHub poller with ETag and Last-Modified support
 pub struct GitHubPoller {
     client: Client,
     config: GitHubConfig,
+    /// Access token with any `keychain:` reference resolved
+    access_token: Option<String>,
 }
 
 impl GitHubPoller {
     /// Create a new GitHub poller
-    pub fn new(config: GitHubConfig) -> Result<Self> {
+    ///
+    /// `settings` supplies the proxy and extra CA certificates to use.
+    pub fn new(config: GitHubConfig, settings: &AgentSettings) -> Result<Self> {
         // Validate HTTPS
         let url = url::Url::parse(&config.policy_url)
             .context("Invalid policy URL")?;
 
-        if url.scheme() != "https" {
+        // Tests serve policies from a plain HTTP server on the loopback address
+        let test_server = cfg!(test) && url.host_str() == Some("127.0.0.1");
+
+        if url.scheme() != "https" && !test_server {
             anyhow::bail!("Policy URL must use HTTPS for security (got: {})", url.scheme());
         }
 
-        // Build HTTP client with rustls (HTTPS only)
-        let client = Client::builder()
-            .user_agent(format!("family-policy-agent/{}", env!("CARGO_PKG_VERSION")))
-            .timeout(Duration::from_secs(30))
-            .https_only(true) // Enforce HTTPS
-            .build()
-            .context("Failed to create HTTP client")?;
+        let client = http_client(settings, !test_server)?;
 
-        Ok(Self { client, config })
+        let access_token = config
+            .access_token
+            .as_deref()
+            .map(secrets::resolve)
+            .transpose()
+            .context("Failed to resolve GitHub access token")?;
+
+        Ok(Self { client, config, access_token })
     }
 
     /// Fetch policy from GitHub with ETag support
     ///
     /// # Arguments
     /// * `etag` - Optional ETag from previous request for conditional GET
+    /// * `last_modified` - Last-Modified from previous request, used when
+    ///   there is no ETag
     ///
     /// # Returns
     /// * `PolicyFetchResult::NotModified` if content unchanged (304)
-    /// * `PolicyFetchResult::Updated` with new content and ETag if changed
-    pub async fn fetch_policy(&self, etag: Option<&str>) -> Result<PolicyFetchResult> {
+    /// * `PolicyFetchResult::Updated` with new content and validators if changed
+    pub async fn fetch_policy(
+        &self,
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+    ) -> Result<PolicyFetchResult> {
         tracing::debug!("Fetching policy from: {}", self.config.policy_url);
 
-        let mut request = self.client.get(&self.config.policy_url);
-
-        // Add authentication if configured
-        if let Some(token) = &self.config.access_token {
-            request = request.header("Authorization", format!("token {}", token));
-        }
+        let mut request = self.get(&self.config.policy_url)?;
 
         // Add ETag for conditional request (saves bandwidth)
         if let Some(etag) = etag {
             tracing::debug!("Using ETag for conditional request: {}", etag);
             request = request.header("If-None-Match", etag);
+        } else if let Some(last_modified) = last_modified {
+            // Servers without ETags may still support date-based validation
+            tracing::debug!("Using Last-Modified for conditional request: {}", last_modified);
+            request = request.header("If-Modified-Since", last_modified);
         }
 
         // Send request
@@ -90,6 +108,12 @@ impl GitHubPoller {
                     tracing::debug!("New ETag: {}", etag);
                 }
 
+                let last_modified = response
+                    .headers()
+                    .get("last-modified")
+                    .and_then(|v| v.to_str().ok())
+                    .map(String::from);
+
                 let content = response.text().await
                     .context("Failed to read response body")?;
 
@@ -98,6 +122,7 @@ impl GitHubPoller {
                 Ok(PolicyFetchResult::Updated {
                     content,
                     etag: new_etag,
+                    last_modified,
                 })
             }
             StatusCode::NOT_FOUND => {
@@ -118,6 +143,107 @@ impl GitHubPoller {
             }
         }
     }
+
+    /// Merge the files the policy includes, fetched relative to the policy URL
+    ///
+    /// Returns the merged policy as YAML, or `None` if it includes nothing.
+    pub async fn resolve_includes(&self, content: &str) -> Result<Option<String>> {
+        let root: Config = serde_yaml::from_str(content).context("Failed to parse policy YAML")?;
+        if root.include.is_empty() {
+            return Ok(None);
+        }
+
+        // Download everything first; merging checks for cycles
+        let mut files = HashMap::from([(self.config.policy_url.clone(), content.to_string())]);
+        let mut pending: Vec<String> = root
+            .include
+            .iter()
+            .map(|include| config::include_location(&self.config.policy_url, include))
+            .collect::<Result<_>>()?;
+
+        while let Some(url) = pending.pop() {
+            if files.contains_key(&url) {
+                continue;
+            }
+
+            tracing::debug!("Fetching included policy: {}", url);
+            let response = self.get(&url)?.send().await
+                .with_context(|| format!("Failed to fetch included policy: {}", url))?;
+            if !response.status().is_success() {
+                anyhow::bail!("Included policy returned {}: {}", response.status(), url);
+            }
+            let text = response.text().await
+                .with_context(|| format!("Failed to read included policy: {}", url))?;
+
+            let included: Config = serde_yaml::from_str(&text)
+                .with_context(|| format!("Failed to parse included policy: {}", url))?;
+            for include in &included.include {
+                pending.push(config::include_location(&url, include)?);
+            }
+            files.insert(url, text);
+        }
+
+        let merged = config::merge_includes(&self.config.policy_url, &mut |url| {
+            files.get(url).cloned().with_context(|| format!("Included policy not downloaded: {}", url))
+        })?;
+        let yaml = serde_yaml::to_string(&merged).context("Failed to serialize merged policy")?;
+        Ok(Some(yaml))
+    }
+
+    /// GET request for `url`, authenticated if it's on the policy's host
+    ///
+    /// The token is never sent to other hosts a policy includes files from.
+    fn get(&self, url: &str) -> Result<RequestBuilder> {
+        let mut request = self.client.get(url);
+
+        let same_host = url::Url::parse(url)?.host_str()
+            == url::Url::parse(&self.config.policy_url)?.host_str();
+        if let Some(token) = self.access_token.as_ref().filter(|_| same_host) {
+            request = request.header("Authorization", format!("token {}", token));
+        }
+
+        Ok(request)
+    }
+}
+
+/// Build the HTTP client used for everything the agent downloads
+///
+/// `settings` supplies the proxy and extra CA certificates to use.
+pub(super) fn http_client(settings: &AgentSettings, https_only: bool) -> Result<Client> {
+    // Build HTTP client with rustls (HTTPS only)
+    let mut builder = Client::builder()
+        .user_agent(format!("family-policy-agent/{}", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(30))
+        .https_only(https_only); // Enforce HTTPS
+
+    // Without an explicit proxy, reqwest uses the *_PROXY environment variables
+    if let Some(proxy_url) = &settings.http_proxy {
+        tracing::debug!("Using HTTP proxy: {}", proxy_url);
+        let proxy = reqwest::Proxy::all(proxy_url)
+            .with_context(|| format!("Invalid HTTP proxy URL: {}", proxy_url))?
+            .no_proxy(reqwest::NoProxy::from_env());
+        builder = builder.proxy(proxy);
+    }
+
+    for path in &settings.ca_certificates {
+        for certificate in load_ca_certificates(path)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    builder.build().context("Failed to create HTTP client")
+}
+
+/// Read the certificates in a PEM file
+fn load_ca_certificates(path: &Path) -> Result<Vec<Certificate>> {
+    let pem = std::fs::read(path)
+        .with_context(|| format!("Failed to read CA certificate file: {}", path.display()))?;
+    let certificates = Certificate::from_pem_bundle(&pem)
+        .with_context(|| format!("Invalid CA certificate file: {}", path.display()))?;
+    if certificates.is_empty() {
+        anyhow::bail!("No certificates found in {}", path.display());
+    }
+    Ok(certificates)
 }
 
 #[cfg(test)]
@@ -131,7 +257,7 @@ mod tests {
             access_token: None,
         };
 
-        assert!(GitHubPoller::new(config).is_err());
+        assert!(GitHubPoller::new(config, &AgentSettings::default()).is_err());
     }
 
     #[test]
@@ -141,7 +267,7 @@ mod tests {
             access_token: None,
         };
 
-        assert!(GitHubPoller::new(config).is_ok());
+        assert!(GitHubPoller::new(config, &AgentSettings::default()).is_ok());
     }
 
     #[test]
@@ -151,6 +277,39 @@ mod tests {
             access_token: None,
         };
 
-        assert!(GitHubPoller::new(config).is_err());
+        assert!(GitHubPoller::new(config, &AgentSettings::default()).is_err());
+    }
+
+    #[test]
+    fn github_poller_uses_proxy_settings() {
+        let config = GitHubConfig {
+            policy_url: "https://raw.githubusercontent.com/user/repo/main/policy.yaml".to_string(),
+            access_token: None,
+        };
+        let settings = AgentSettings {
+            http_proxy: Some("http://proxy.lan:3128".to_string()),
+            ..Default::default()
+        };
+
+        assert!(GitHubPoller::new(config, &settings).is_ok());
+    }
+
+    #[test]
+    fn github_poller_rejects_bad_ca_certificates() {
+        let config = GitHubConfig {
+            policy_url: "https://raw.githubusercontent.com/user/repo/main/policy.yaml".to_string(),
+            access_token: None,
+        };
+        let temp_dir = tempfile::tempdir().unwrap();
+        let not_pem = temp_dir.path().join("ca.pem");
+        std::fs::write(&not_pem, "not a certificate").unwrap();
+
+        for path in [not_pem, temp_dir.path().join("missing.pem")] {
+            let settings = AgentSettings {
+                ca_certificates: vec![path],
+                ..Default::default()
+            };
+            assert!(GitHubPoller::new(config.clone(), &settings).is_err());
+        }
     }
 }
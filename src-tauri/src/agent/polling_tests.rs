@@ -0,0 +1,630 @@
+//! End-to-end tests of the polling loop against a local mock policy server
+//!
+//! Each test serves policy YAML from a wiremock server and drives the daemon's
+//! check/apply functions with the state file in a temp directory and a
+//! recording applier in place of the platform writers, so nothing touches
+//! the network or the real browser policies.
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::watch;
+use wiremock::matchers::{header, header_exists, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use super::config::{AgentSettings, GitHubConfig};
+use super::daemon::{
+    check_and_apply_with_retry, run_agent_daemon_with, use_test_applier,
+    verify_and_restore_cached_policy, TestApplierGuard,
+};
+use super::shutdown::Shutdown;
+use super::{check_and_apply_once, preview_remote_policy, AgentConfig};
+use crate::state::{self, AppliedPolicies, ChangeDetection, TestStateDir};
+
+const POLICY: &str = r#"
+policies:
+  - name: Privacy
+    browsers: [chrome]
+    disable_private_mode: true
+"#;
+
+/// Same policy as `POLICY`, so its hash is the same
+const POLICY_REFORMATTED: &str = r#"
+# Reformatted in the repo
+policies:
+  - browsers: [chrome]
+    name: Privacy
+    disable_private_mode: true
+"#;
+
+const POLICY_CHANGED: &str = r#"
+policies:
+  - name: Privacy
+    browsers: [chrome]
+    disable_private_mode: true
+    disable_guest_mode: true
+"#;
+
+/// `POLICY` plus an entry that has since expired
+const POLICY_WITH_EXPIRED: &str = r#"
+policies:
+  - name: Privacy
+    browsers: [chrome]
+    disable_private_mode: true
+  - name: Exam week
+    browsers: [chrome]
+    disable_guest_mode: true
+    expires_at: 2020-01-01T00:00:00Z
+"#;
+
+/// Test environment: temp state directory and an applier counting applies
+struct Agent {
+    config: AgentConfig,
+    applies: Rc<Cell<u32>>,
+    _state_dir: TempDir,
+    _state: TestStateDir,
+    _applier: TestApplierGuard,
+}
+
+impl Agent {
+    fn new(server: &MockServer) -> Self {
+        let state_dir = tempfile::tempdir().unwrap();
+        let state = state::use_test_state_dir(state_dir.path());
+
+        let applies = Rc::new(Cell::new(0));
+        let counter = applies.clone();
+        let applier = use_test_applier(move |_| {
+            counter.set(counter.get() + 1);
+            Ok(AppliedPolicies::default())
+        });
+
+        let config = AgentConfig {
+            github: GitHubConfig {
+                policy_url: format!("{}/policy.yaml", server.uri()),
+                access_token: None,
+            },
+            agent: AgentSettings {
+                retry_interval: 0,
+                max_retries: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        Self {
+            config,
+            applies,
+            _state_dir: state_dir,
+            _state: state,
+            _applier: applier,
+        }
+    }
+
+    fn state(&self) -> state::State {
+        state::load_state().unwrap().expect("state file written")
+    }
+}
+
+fn policy_response(body: &str, etag: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200)
+        .insert_header("ETag", etag)
+        .set_body_string(body)
+}
+
+#[tokio::test]
+async fn first_poll_applies_policy_and_records_etag() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/policy.yaml"))
+        .respond_with(policy_response(POLICY, "\"v1\""))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let agent = Agent::new(&server);
+
+    assert!(check_and_apply_once(&agent.config, false).await.unwrap());
+
+    assert_eq!(agent.applies.get(), 1);
+    let state = agent.state();
+    assert_eq!(state.etag.as_deref(), Some("\"v1\""));
+    assert!(state.config_hash.starts_with("sha256:"));
+    assert_eq!(state::load_policy_cache().unwrap().as_deref(), Some(POLICY));
+}
+
+#[tokio::test]
+async fn not_modified_skips_apply() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(header("If-None-Match", "\"v1\""))
+        .respond_with(ResponseTemplate::new(304))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(policy_response(POLICY, "\"v1\""))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let agent = Agent::new(&server);
+
+    assert!(check_and_apply_once(&agent.config, false).await.unwrap());
+    let applied = agent.state();
+
+    assert!(!check_and_apply_once(&agent.config, false).await.unwrap());
+
+    assert_eq!(agent.applies.get(), 1);
+    let state = agent.state();
+    assert_eq!(state.config_hash, applied.config_hash);
+    assert!(state.last_checked.is_some());
+    assert_eq!(state.change_detection, Some(ChangeDetection::Etag));
+}
+
+#[tokio::test]
+async fn falls_back_to_last_modified_without_etag() {
+    const LAST_MODIFIED: &str = "Wed, 14 Oct 2026 08:00:00 GMT";
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        // `header` would split the date at its comma
+        .and(header_exists("If-Modified-Since"))
+        .respond_with(ResponseTemplate::new(304))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("Last-Modified", LAST_MODIFIED)
+                .set_body_string(POLICY),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    let agent = Agent::new(&server);
+
+    assert!(check_and_apply_once(&agent.config, false).await.unwrap());
+    assert_eq!(agent.state().last_modified.as_deref(), Some(LAST_MODIFIED));
+
+    assert!(!check_and_apply_once(&agent.config, false).await.unwrap());
+
+    assert_eq!(agent.applies.get(), 1);
+    assert_eq!(agent.state().change_detection, Some(ChangeDetection::LastModified));
+}
+
+#[tokio::test]
+async fn identical_content_skips_parsing() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(POLICY))
+        .expect(2)
+        .mount(&server)
+        .await;
+    let agent = Agent::new(&server);
+
+    assert!(check_and_apply_once(&agent.config, false).await.unwrap());
+    assert_eq!(agent.state().change_detection, Some(ChangeDetection::Changed));
+
+    assert!(!check_and_apply_once(&agent.config, false).await.unwrap());
+
+    assert_eq!(agent.applies.get(), 1);
+    let state = agent.state();
+    assert_eq!(state.change_detection, Some(ChangeDetection::ContentHash));
+    assert_eq!(state.content_hash, Some(state::compute_content_hash(POLICY)));
+}
+
+#[tokio::test]
+async fn unchanged_hash_only_updates_etag() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(policy_response(POLICY, "\"v1\""))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(policy_response(POLICY_REFORMATTED, "\"v2\""))
+        .mount(&server)
+        .await;
+    let agent = Agent::new(&server);
+
+    assert!(check_and_apply_once(&agent.config, false).await.unwrap());
+    let applied = agent.state();
+
+    assert!(!check_and_apply_once(&agent.config, false).await.unwrap());
+
+    assert_eq!(agent.applies.get(), 1);
+    let state = agent.state();
+    assert_eq!(state.etag.as_deref(), Some("\"v2\""));
+    assert_eq!(state.config_hash, applied.config_hash);
+    assert_eq!(state.change_detection, Some(ChangeDetection::ConfigHash));
+}
+
+#[tokio::test]
+async fn changed_policy_is_applied() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(policy_response(POLICY, "\"v1\""))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(policy_response(POLICY_CHANGED, "\"v2\""))
+        .mount(&server)
+        .await;
+    let agent = Agent::new(&server);
+
+    assert!(check_and_apply_once(&agent.config, false).await.unwrap());
+    let applied = agent.state();
+
+    assert!(check_and_apply_once(&agent.config, false).await.unwrap());
+
+    assert_eq!(agent.applies.get(), 2);
+    let state = agent.state();
+    assert_eq!(state.etag.as_deref(), Some("\"v2\""));
+    assert_ne!(state.config_hash, applied.config_hash);
+}
+
+#[tokio::test]
+async fn safari_only_change_is_applied() {
+    let safari = format!("{}  - name: Safari\n    browsers: [safari]\n    blocked_urls: [reddit.com]\n", POLICY);
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(policy_response(&safari, "\"v1\""))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(policy_response(&safari.replace("reddit.com", "tiktok.com"), "\"v2\""))
+        .mount(&server)
+        .await;
+    let agent = Agent::new(&server);
+
+    assert!(check_and_apply_once(&agent.config, false).await.unwrap());
+    assert!(check_and_apply_once(&agent.config, false).await.unwrap());
+
+    assert_eq!(agent.applies.get(), 2);
+}
+
+#[tokio::test]
+async fn dry_run_leaves_state_untouched() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(policy_response(POLICY, "\"v1\""))
+        .mount(&server)
+        .await;
+    let agent = Agent::new(&server);
+
+    assert!(check_and_apply_once(&agent.config, true).await.unwrap());
+
+    assert!(state::load_state().unwrap().is_none());
+    assert!(state::load_policy_cache().unwrap().is_none());
+}
+
+#[tokio::test]
+async fn preview_ignores_etag_and_shows_changes() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(policy_response(POLICY, "\"v1\""))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(policy_response(POLICY_CHANGED, "\"v2\""))
+        .mount(&server)
+        .await;
+    let agent = Agent::new(&server);
+    check_and_apply_once(&agent.config, false).await.unwrap();
+    let before = agent.state();
+
+    let diff = preview_remote_policy(&agent.config).await.unwrap().expect("policy changed");
+
+    assert!(diff.summary.total_additions + diff.summary.total_changes > 0);
+    assert_eq!(agent.applies.get(), 1);
+    assert_eq!(agent.state().etag, before.etag);
+}
+
+#[tokio::test]
+async fn preview_of_applied_policy_is_empty() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(policy_response(POLICY, "\"v1\""))
+        .mount(&server)
+        .await;
+    let agent = Agent::new(&server);
+    check_and_apply_once(&agent.config, false).await.unwrap();
+
+    assert!(preview_remote_policy(&agent.config).await.unwrap().is_none());
+}
+
+/// Record the Chrome privacy settings a policy sets, like the real applier
+fn record_chrome_settings(config: &crate::config::Config) -> anyhow::Result<AppliedPolicies> {
+    let mut chrome = state::BrowserState::new();
+    for policy in &config.policies {
+        chrome.disable_incognito = chrome.disable_incognito.or(policy.disable_private_mode);
+        chrome.disable_guest_mode = chrome.disable_guest_mode.or(policy.disable_guest_mode);
+    }
+    Ok(AppliedPolicies {
+        chrome: Some(chrome),
+        ..Default::default()
+    })
+}
+
+#[tokio::test]
+async fn confirm_before_apply_holds_relaxed_policy_until_approved() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(policy_response(POLICY_CHANGED, "\"v1\""))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(policy_response(POLICY, "\"v2\""))
+        .mount(&server)
+        .await;
+    let mut agent = Agent::new(&server);
+    agent.config.agent.confirm_before_apply = true;
+    let _applier = use_test_applier(record_chrome_settings);
+
+    // Nothing applied yet, so nothing is removed
+    assert!(check_and_apply_once(&agent.config, false).await.unwrap());
+    let applied_hash = agent.state().config_hash;
+
+    // Dropping disable_guest_mode relaxes the policy
+    assert!(!check_and_apply_once(&agent.config, false).await.unwrap());
+    assert!(!check_and_apply_once(&agent.config, false).await.unwrap());
+    let state = agent.state();
+    assert_eq!(state.config_hash, applied_hash);
+    let pending = state.pending_approval.expect("change held");
+    assert_eq!(pending.removals, ["Chrome: Disable Guest Mode true -> none"]);
+    assert_eq!(crate::inbox::load_inbox().unwrap().notifications.len(), 1);
+
+    let mut state = agent.state();
+    state.approve_pending();
+    state::save_state(&state).unwrap();
+
+    assert!(check_and_apply_once(&agent.config, false).await.unwrap());
+    let state = agent.state();
+    assert_ne!(state.config_hash, applied_hash);
+    assert!(state.pending_approval.is_none());
+}
+
+#[tokio::test]
+async fn confirm_before_apply_holds_expired_entries_until_approved() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(policy_response(POLICY_WITH_EXPIRED, "\"v1\""))
+        .mount(&server)
+        .await;
+    let mut agent = Agent::new(&server);
+    agent.config.agent.confirm_before_apply = true;
+    // Applies every entry, as if the policy was applied before "Exam week"
+    // expired
+    let _applier = use_test_applier(record_chrome_settings);
+    assert!(check_and_apply_once(&agent.config, false).await.unwrap());
+    let mut state = agent.state();
+    state.last_updated = "2019-01-01T00:00:00Z".parse().unwrap();
+    state::save_state(&state).unwrap();
+
+    assert!(!check_and_apply_once(&agent.config, false).await.unwrap());
+    let pending = agent.state().pending_approval.expect("expiry held");
+    assert_eq!(pending.removals, ["Chrome: Disable Guest Mode true -> none"]);
+
+    let mut state = agent.state();
+    state.approve_pending();
+    state::save_state(&state).unwrap();
+
+    assert!(check_and_apply_once(&agent.config, false).await.unwrap());
+    assert!(agent.state().pending_approval.is_none());
+}
+
+#[tokio::test]
+async fn confirm_before_apply_applies_additions() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(policy_response(POLICY, "\"v1\""))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(policy_response(POLICY_CHANGED, "\"v2\""))
+        .mount(&server)
+        .await;
+    let mut agent = Agent::new(&server);
+    agent.config.agent.confirm_before_apply = true;
+    let _applier = use_test_applier(record_chrome_settings);
+
+    assert!(check_and_apply_once(&agent.config, false).await.unwrap());
+    assert!(check_and_apply_once(&agent.config, false).await.unwrap());
+    assert!(agent.state().pending_approval.is_none());
+}
+
+#[tokio::test]
+async fn sends_access_token() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(header("Authorization", "token ghp_test"))
+        .respond_with(policy_response(POLICY, "\"v1\""))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let mut agent = Agent::new(&server);
+    agent.config.github.access_token = Some("ghp_test".to_string());
+
+    assert!(check_and_apply_once(&agent.config, false).await.unwrap());
+}
+
+#[tokio::test]
+async fn retries_server_errors() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(policy_response(POLICY, "\"v1\""))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let agent = Agent::new(&server);
+    let (_tx, mut shutdown) = Shutdown::channel();
+
+    assert!(check_and_apply_with_retry(&agent.config, &mut shutdown).await.unwrap());
+    assert_eq!(agent.applies.get(), 1);
+}
+
+#[tokio::test]
+async fn gives_up_after_max_retries() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(3) // first attempt + max_retries
+        .mount(&server)
+        .await;
+    let agent = Agent::new(&server);
+    let (_tx, mut shutdown) = Shutdown::channel();
+
+    assert!(check_and_apply_with_retry(&agent.config, &mut shutdown).await.is_err());
+    assert_eq!(agent.applies.get(), 0);
+}
+
+#[tokio::test]
+async fn failed_apply_is_recorded() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(policy_response(POLICY, "\"v1\""))
+        .mount(&server)
+        .await;
+    let agent = Agent::new(&server);
+    let _failing = use_test_applier(|_| anyhow::bail!("registry is read-only"));
+
+    assert!(check_and_apply_once(&agent.config, false).await.is_err());
+
+    let state = agent.state();
+    let failed = state.failed_apply.expect("failure recorded");
+    assert_eq!(failed.failures, 1);
+    assert!(failed.last_error.contains("registry is read-only"));
+    // Not applied, so the next poll fetches it again
+    assert_eq!(state.etag, None);
+}
+
+#[tokio::test]
+async fn includes_are_fetched_relative_to_policy_url() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/policy.yaml"))
+        .respond_with(policy_response("include: [shared/base.yaml]\npolicies: []\n", "\"v1\""))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/shared/base.yaml"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(POLICY))
+        .expect(2)
+        .mount(&server)
+        .await;
+    let agent = Agent::new(&server);
+
+    assert!(check_and_apply_once(&agent.config, false).await.unwrap());
+    let state = agent.state();
+    // No ETag, so the includes are checked on every poll
+    assert_eq!(state.etag, None);
+    let cached = state::load_policy_cache().unwrap().unwrap();
+    assert!(cached.contains("name: Privacy"));
+
+    // Unchanged includes aren't applied again
+    assert!(!check_and_apply_once(&agent.config, false).await.unwrap());
+    assert_eq!(agent.applies.get(), 1);
+}
+
+#[tokio::test]
+async fn cached_policy_is_applied_without_state_or_network() {
+    let server = MockServer::start().await;
+    let agent = Agent::new(&server);
+    state::save_policy_cache(POLICY).unwrap();
+
+    verify_and_restore_cached_policy(&agent.config).await.unwrap();
+
+    assert_eq!(agent.applies.get(), 1);
+    let state = agent.state();
+    assert!(state.config_hash.starts_with("sha256:"));
+    assert_eq!(state.content_hash, Some(state::compute_content_hash(POLICY)));
+
+    // Once the network is back, the same policy isn't applied again
+    Mock::given(method("GET"))
+        .respond_with(policy_response(POLICY, "\"v1\""))
+        .mount(&server)
+        .await;
+    assert!(!check_and_apply_once(&agent.config, false).await.unwrap());
+    assert_eq!(agent.applies.get(), 1);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn cached_policy_is_restored_through_the_hooks() {
+    use crate::incidents::{self, IncidentKind};
+
+    let server = MockServer::start().await;
+    let mut agent = Agent::new(&server);
+    state::save_policy_cache(POLICY).unwrap();
+
+    agent.config.hooks.pre_apply = Some("exit 1".to_string());
+    assert!(verify_and_restore_cached_policy(&agent.config).await.is_err());
+    assert_eq!(agent.applies.get(), 0);
+
+    agent.config.hooks.pre_apply = Some("true".to_string());
+    agent.config.hooks.post_apply = Some(r#"test "$FAMILY_POLICY_RESULT" = success"#.to_string());
+    verify_and_restore_cached_policy(&agent.config).await.unwrap();
+    assert_eq!(agent.applies.get(), 1);
+
+    let runs: Vec<String> = incidents::load_incidents()
+        .unwrap()
+        .incidents
+        .into_iter()
+        .filter(|i| i.kind == IncidentKind::HookRun)
+        .map(|i| i.detail)
+        .collect();
+    assert_eq!(runs.len(), 3);
+    assert_eq!(runs[0], "pre_apply hook exit 1 exited with exit status: 1");
+    assert!(runs[2].starts_with("post_apply hook test"));
+    assert!(runs[2].ends_with("exited with exit status: 0"));
+}
+
+#[tokio::test]
+async fn modified_cache_is_not_applied() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(policy_response(POLICY, "\"v1\""))
+        .mount(&server)
+        .await;
+    let agent = Agent::new(&server);
+    assert!(check_and_apply_once(&agent.config, false).await.unwrap());
+
+    state::save_policy_cache("policies: []\n").unwrap();
+    verify_and_restore_cached_policy(&agent.config).await.unwrap();
+
+    assert_eq!(agent.applies.get(), 1);
+}
+
+#[tokio::test]
+async fn paused_daemon_does_not_poll() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(policy_response(POLICY, "\"v1\""))
+        .expect(0)
+        .mount(&server)
+        .await;
+    let agent = Agent::new(&server);
+    let (shutdown_tx, shutdown) = Shutdown::channel();
+    let (_pause_tx, paused) = watch::channel(true);
+
+    let (result, _) = tokio::join!(
+        run_agent_daemon_with(agent.config.clone(), shutdown, paused),
+        async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            shutdown_tx.send(true).unwrap();
+        }
+    );
+
+    result.unwrap();
+    assert_eq!(agent.applies.get(), 0);
+}
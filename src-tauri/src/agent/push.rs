@@ -0,0 +1,272 @@
+//! Pushing agent notifications to the parent's phone
+//!
+//! After each check, notifications added to the inbox since the last push
+//! are sent to every service configured under `[notifications]` (ntfy,
+//! Pushover, Telegram), along with tampering incidents that don't raise a
+//! notification of their own. Only events from after the agent started are
+//! pushed, so a restart doesn't resend old ones. A failed push is logged and
+//! not retried.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+
+use super::config::{AgentSettings, NotificationsConfig};
+use super::current_host;
+use super::poller::http_client;
+use super::secrets;
+use crate::inbox::{self, NotificationLevel};
+use crate::incidents::{self, IncidentKind};
+
+/// A service notifications are pushed to
+#[derive(Debug)]
+enum Service {
+    Ntfy { url: String, token: Option<String> },
+    Pushover { token: String, user: String },
+    Telegram { bot_token: String, chat_id: String },
+}
+
+impl Service {
+    fn name(&self) -> &'static str {
+        match self {
+            Service::Ntfy { .. } => "ntfy",
+            Service::Pushover { .. } => "Pushover",
+            Service::Telegram { .. } => "Telegram",
+        }
+    }
+}
+
+/// Something to tell the parent about
+#[derive(Debug)]
+struct Event {
+    level: NotificationLevel,
+    title: String,
+    message: String,
+}
+
+/// Pushes new inbox notifications and incidents to the configured services
+pub struct Pusher {
+    client: Client,
+    services: Vec<Service>,
+    min_level: NotificationLevel,
+    hostname: String,
+    next_notification_id: u64,
+    incidents_since: DateTime<Utc>,
+    pushover_api: String,
+    telegram_api: String,
+}
+
+impl Pusher {
+    /// Create a pusher starting from the current inbox, or `None` if no
+    /// service is configured
+    pub fn new(config: &NotificationsConfig, settings: &AgentSettings) -> Result<Option<Self>> {
+        let mut services = Vec::new();
+        if let Some(url) = &config.ntfy_url {
+            services.push(Service::Ntfy {
+                url: url.clone(),
+                token: config.ntfy_token.as_deref().map(secrets::resolve).transpose()?,
+            });
+        }
+        if let (Some(token), Some(user)) = (&config.pushover_token, &config.pushover_user) {
+            services.push(Service::Pushover {
+                token: secrets::resolve(token)?,
+                user: user.clone(),
+            });
+        }
+        if let (Some(bot_token), Some(chat_id)) = (&config.telegram_bot_token, &config.telegram_chat_id) {
+            services.push(Service::Telegram {
+                bot_token: secrets::resolve(bot_token)?,
+                chat_id: chat_id.clone(),
+            });
+        }
+        if services.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            // Tests push to a plain HTTP server
+            client: http_client(settings, !cfg!(test))?,
+            services,
+            min_level: config.min_level,
+            hostname: current_host().hostname,
+            next_notification_id: inbox::load_inbox()?.next_id,
+            incidents_since: Utc::now(),
+            pushover_api: "https://api.pushover.net".to_string(),
+            telegram_api: "https://api.telegram.org".to_string(),
+        }))
+    }
+
+    /// Push everything recorded since the last call
+    pub async fn push_new(&mut self) {
+        let events = match self.new_events() {
+            Ok(events) => events,
+            Err(e) => {
+                tracing::warn!("Failed to read events to push: {:#}", e);
+                return;
+            }
+        };
+
+        for event in &events {
+            for service in &self.services {
+                if let Err(e) = self.send(service, event).await {
+                    tracing::warn!("Failed to push '{}' to {}: {:#}", event.title, service.name(), e);
+                }
+            }
+        }
+    }
+
+    /// Collect new events and move past them
+    fn new_events(&mut self) -> Result<Vec<Event>> {
+        let mut events = Vec::new();
+
+        let inbox = inbox::load_inbox()?;
+        for notification in &inbox.notifications {
+            if notification.id >= self.next_notification_id && notification.level >= self.min_level {
+                events.push(Event {
+                    level: notification.level,
+                    title: notification.title.clone(),
+                    message: notification.message.clone(),
+                });
+            }
+        }
+        self.next_notification_id = inbox.next_id;
+
+        // Drift and unmanaged browsers are already in the inbox
+        let log = incidents::load_incidents()?;
+        let since = self.incidents_since;
+        for incident in log.incidents.iter().filter(|i| i.timestamp > since) {
+            if matches!(incident.kind, IncidentKind::UnexpectedStop | IncidentKind::StateMismatch) {
+                events.push(Event {
+                    level: NotificationLevel::Warning,
+                    title: format!("Tamper attempt detected: {}", incident.kind.description().to_lowercase()),
+                    message: incident.detail.clone(),
+                });
+            }
+            self.incidents_since = self.incidents_since.max(incident.timestamp);
+        }
+
+        Ok(events)
+    }
+
+    async fn send(&self, service: &Service, event: &Event) -> Result<()> {
+        let title = format!("{}: {}", self.hostname, event.title);
+        let urgent = event.level == NotificationLevel::Error;
+
+        let request = match service {
+            Service::Ntfy { url, token } => {
+                let mut request = self
+                    .client
+                    .post(url)
+                    .header("Title", title)
+                    .header("Priority", if urgent { "high" } else { "default" })
+                    .header("Tags", event.level.as_str())
+                    .body(event.message.clone());
+                if let Some(token) = token {
+                    request = request.bearer_auth(token);
+                }
+                request
+            }
+            Service::Pushover { token, user } => self
+                .client
+                .post(format!("{}/1/messages.json", self.pushover_api))
+                .form(&[
+                    ("token", token.as_str()),
+                    ("user", user.as_str()),
+                    ("title", title.as_str()),
+                    ("message", event.message.as_str()),
+                    ("priority", if urgent { "1" } else { "0" }),
+                ]),
+            Service::Telegram { bot_token, chat_id } => self
+                .client
+                .post(format!("{}/bot{}/sendMessage", self.telegram_api, bot_token))
+                .form(&[
+                    ("chat_id", chat_id.as_str()),
+                    ("text", format!("{}\n{}", title, event.message).as_str()),
+                ]),
+        };
+
+        request
+            .send()
+            .await
+            .context("Request failed")?
+            .error_for_status()
+            .context("Service rejected the notification")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_string_contains, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn pusher(config: NotificationsConfig) -> Pusher {
+        let mut pusher = Pusher::new(&config, &AgentSettings::default()).unwrap().unwrap();
+        pusher.hostname = "kids-pc".to_string();
+        pusher
+    }
+
+    #[test]
+    fn nothing_configured() {
+        let config = NotificationsConfig::default();
+        assert!(Pusher::new(&config, &AgentSettings::default()).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn pushes_new_notifications_to_ntfy() {
+        let dir = tempfile::tempdir().unwrap();
+        let _state = crate::state::use_test_state_dir(dir.path());
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/family"))
+            .and(header("Title", "kids-pc: Policies restored"))
+            .and(header("Authorization", "Bearer tk_secret"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        // From before the agent started
+        inbox::notify(NotificationLevel::Error, "Policy updates failing", "old");
+
+        let mut pusher = pusher(NotificationsConfig {
+            ntfy_url: Some(format!("{}/family", server.uri())),
+            ntfy_token: Some("tk_secret".to_string()),
+            ..Default::default()
+        });
+        inbox::notify(NotificationLevel::Info, "Agent updated", "below min_level");
+        inbox::notify(NotificationLevel::Warning, "Policies restored", "3 values re-applied");
+
+        pusher.push_new().await;
+        // Already pushed
+        pusher.push_new().await;
+    }
+
+    #[tokio::test]
+    async fn pushes_tampering_incidents_to_telegram() {
+        let dir = tempfile::tempdir().unwrap();
+        let _state = crate::state::use_test_state_dir(dir.path());
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/bot123:abc/sendMessage"))
+            .and(body_string_contains("Tamper+attempt+detected"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut pusher = pusher(NotificationsConfig {
+            telegram_bot_token: Some("123:abc".to_string()),
+            telegram_chat_id: Some("42".to_string()),
+            ..Default::default()
+        });
+        pusher.telegram_api = server.uri();
+
+        incidents::record(IncidentKind::UnexpectedStop, "killed");
+        // Reported through its own inbox notification instead
+        incidents::record(IncidentKind::PolicyDrift, "values removed");
+
+        pusher.push_new().await;
+    }
+}
@@ -1,12 +1,19 @@
 use chrono::{DateTime, Utc};
 use rand::Rng;
 use std::time::Duration;
-use tokio::time::sleep;
+use tokio::time::{sleep_until, Instant};
+
+/// Wall-clock drift beyond this during a wait is reported as a clock jump
+const CLOCK_JUMP_THRESHOLD_SECS: i64 = 30;
 
 /// Polling scheduler with jitter to prevent thundering herd
 pub struct PollingScheduler {
     base_interval: Duration,
     jitter_range: Duration,
+    /// Consecutive failures after which the interval starts widening
+    backoff_threshold: u32,
+    /// Cap on the widened interval
+    max_interval: Duration,
 }
 
 impl PollingScheduler {
@@ -19,17 +26,42 @@ impl PollingScheduler {
         Self {
             base_interval: Duration::from_secs(interval_secs),
             jitter_range: Duration::from_secs(jitter_secs),
+            backoff_threshold: u32::MAX,
+            max_interval: Duration::from_secs(interval_secs),
         }
     }
 
+    /// Widen the interval while polls keep failing
+    ///
+    /// From `threshold` consecutive failures on, the interval doubles with
+    /// each further failure, up to `max_interval_secs`.
+    pub fn with_backoff(mut self, threshold: u32, max_interval_secs: u64) -> Self {
+        self.backoff_threshold = threshold;
+        self.max_interval = Duration::from_secs(max_interval_secs).max(self.base_interval);
+        self
+    }
+
     /// Sleep until next poll time with jitter
     pub async fn sleep_until_next_poll(&self) {
-        let sleep_duration = self.calculate_next_interval();
-        tracing::debug!(
-            "Sleeping for {} seconds until next poll",
-            sleep_duration.as_secs()
-        );
-        sleep(sleep_duration).await;
+        self.schedule_next_poll().wait().await;
+    }
+
+    /// Pick the next poll interval (with jitter)
+    ///
+    /// The wait runs on the monotonic clock, so wall-clock changes (NTP
+    /// corrections, someone changing the system time) can't move it.
+    pub fn schedule_next_poll(&self) -> ScheduledPoll {
+        self.schedule_poll_after_failures(0)
+    }
+
+    /// Pick the next poll interval after `failures` consecutive failed polls
+    pub fn schedule_poll_after_failures(&self, failures: u32) -> ScheduledPoll {
+        let interval = self.backoff_interval(failures) + self.random_jitter();
+        ScheduledPoll {
+            interval,
+            deadline: Instant::now() + interval,
+            wall_start: Utc::now(),
+        }
     }
 
     /// Calculate the next poll time (current time + interval + jitter)
@@ -44,6 +76,18 @@ impl PollingScheduler {
         self.base_interval + jitter
     }
 
+    /// Base interval widened for `failures` consecutive failed polls
+    fn backoff_interval(&self, failures: u32) -> Duration {
+        if failures < self.backoff_threshold {
+            return self.base_interval;
+        }
+
+        let doublings = (failures - self.backoff_threshold + 1).min(16);
+        self.base_interval
+            .saturating_mul(1 << doublings)
+            .min(self.max_interval)
+    }
+
     /// Generate random jitter in range [0, jitter_range]
     fn random_jitter(&self) -> Duration {
         let jitter_secs = rand::thread_rng().gen_range(0..=self.jitter_range.as_secs());
@@ -51,6 +95,54 @@ impl PollingScheduler {
     }
 }
 
+/// A poll scheduled on the monotonic clock
+pub struct ScheduledPoll {
+    interval: Duration,
+    deadline: Instant,
+    wall_start: DateTime<Utc>,
+}
+
+impl ScheduledPoll {
+    /// Expected wall-clock time of the poll (for display only)
+    pub fn display_time(&self) -> DateTime<Utc> {
+        self.wall_start + chrono::Duration::from_std(self.interval).unwrap_or_default()
+    }
+
+    /// Wait for the poll, logging if the wall clock jumped in the meantime
+    pub async fn wait(&self) {
+        tracing::debug!(
+            "Sleeping for {} seconds until next poll",
+            self.interval.as_secs()
+        );
+        let started = Instant::now();
+        sleep_until(self.deadline).await;
+
+        let monotonic_elapsed = started.elapsed();
+        let wall_elapsed = Utc::now() - self.wall_start;
+        if let Some(jump) = detect_clock_jump(monotonic_elapsed, wall_elapsed) {
+            tracing::warn!(
+                "System clock jumped by {:+} seconds while waiting to poll \
+                 (clock change or suspend); polling schedule is unaffected",
+                jump.num_seconds()
+            );
+        }
+    }
+}
+
+/// Compare elapsed wall-clock time against monotonic time
+///
+/// Returns how far the wall clock moved beyond the monotonic clock, if that
+/// exceeds `CLOCK_JUMP_THRESHOLD_SECS` in either direction.
+fn detect_clock_jump(
+    monotonic_elapsed: Duration,
+    wall_elapsed: chrono::Duration,
+) -> Option<chrono::Duration> {
+    let monotonic = chrono::Duration::from_std(monotonic_elapsed).ok()?;
+    let drift = wall_elapsed - monotonic;
+
+    (drift.num_seconds().abs() > CLOCK_JUMP_THRESHOLD_SECS).then_some(drift)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,6 +186,65 @@ mod tests {
         }
     }
 
+    #[test]
+    fn backoff_widens_interval_up_to_cap() {
+        let scheduler = PollingScheduler::new(300, 0).with_backoff(3, 3600);
+
+        assert_eq!(scheduler.backoff_interval(0), Duration::from_secs(300));
+        assert_eq!(scheduler.backoff_interval(2), Duration::from_secs(300));
+        assert_eq!(scheduler.backoff_interval(3), Duration::from_secs(600));
+        assert_eq!(scheduler.backoff_interval(4), Duration::from_secs(1200));
+        assert_eq!(scheduler.backoff_interval(5), Duration::from_secs(2400));
+        assert_eq!(scheduler.backoff_interval(6), Duration::from_secs(3600));
+        assert_eq!(scheduler.backoff_interval(1000), Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn no_backoff_by_default() {
+        let scheduler = PollingScheduler::new(300, 0);
+        assert_eq!(scheduler.backoff_interval(100), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn scheduled_poll_display_time_matches_interval() {
+        let scheduler = PollingScheduler::new(300, 0);
+        let poll = scheduler.schedule_next_poll();
+
+        assert_eq!(
+            poll.display_time() - poll.wall_start,
+            chrono::Duration::seconds(300)
+        );
+    }
+
+    #[test]
+    fn detect_clock_jump_ignores_small_drift() {
+        let jump = detect_clock_jump(Duration::from_secs(300), chrono::Duration::seconds(305));
+        assert!(jump.is_none());
+    }
+
+    #[test]
+    fn detect_clock_jump_forward() {
+        let jump = detect_clock_jump(Duration::from_secs(300), chrono::Duration::seconds(3900));
+        assert_eq!(jump, Some(chrono::Duration::seconds(3600)));
+    }
+
+    #[test]
+    fn detect_clock_jump_backward() {
+        let jump = detect_clock_jump(Duration::from_secs(300), chrono::Duration::seconds(-3300));
+        assert_eq!(jump, Some(chrono::Duration::seconds(-3600)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn scheduled_poll_waits_on_monotonic_clock() {
+        let scheduler = PollingScheduler::new(300, 0);
+        let poll = scheduler.schedule_next_poll();
+        let started = Instant::now();
+
+        poll.wait().await;
+
+        assert!(started.elapsed() >= Duration::from_secs(300));
+    }
+
     #[test]
     fn random_jitter_produces_different_values() {
         let scheduler = PollingScheduler::new(300, 60);
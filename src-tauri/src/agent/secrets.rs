@@ -0,0 +1,104 @@
+//! Secrets stored in the OS credential store
+//!
+//! Instead of a plaintext token, a config value can hold a reference like
+//! `keychain:github-token`, which is looked up in Windows Credential Manager,
+//! the macOS Keychain or the Secret Service (libsecret) when it is used.
+//! Secrets are written with `family-policy secret set <name>`.
+
+use anyhow::{Context, Result};
+use keyring::Entry;
+
+/// Service name secrets are stored under
+const SERVICE: &str = "family-policy";
+
+/// Prefix marking a config value as a reference to a stored secret
+pub const REFERENCE_PREFIX: &str = "keychain:";
+
+/// Store a secret, replacing any existing value
+pub fn set_secret(name: &str, value: &str) -> Result<()> {
+    entry(name)?
+        .set_password(value)
+        .with_context(|| format!("Failed to store secret '{}'", name))
+}
+
+/// Look up a stored secret
+pub fn get_secret(name: &str) -> Result<String> {
+    match entry(name)?.get_password() {
+        Ok(value) => Ok(value),
+        Err(keyring::Error::NoEntry) => anyhow::bail!(
+            "Secret '{}' not found; store it with 'family-policy secret set {}'",
+            name,
+            name
+        ),
+        Err(e) => Err(e).with_context(|| format!("Failed to read secret '{}'", name)),
+    }
+}
+
+/// Delete a stored secret
+///
+/// Returns false if there was nothing to delete.
+pub fn delete_secret(name: &str) -> Result<bool> {
+    match entry(name)?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(e).with_context(|| format!("Failed to delete secret '{}'", name)),
+    }
+}
+
+/// Resolve a config value, looking it up in the credential store if it is a
+/// `keychain:<name>` reference
+///
+/// Any other value is returned unchanged.
+pub fn resolve(value: &str) -> Result<String> {
+    match secret_name(value)? {
+        Some(name) => get_secret(name),
+        None => Ok(value.to_string()),
+    }
+}
+
+/// The secret name in a `keychain:<name>` reference
+pub fn secret_name(value: &str) -> Result<Option<&str>> {
+    let Some(name) = value.strip_prefix(REFERENCE_PREFIX) else {
+        return Ok(None);
+    };
+    validate_name(name)?;
+    Ok(Some(name))
+}
+
+/// Secret names are limited to lowercase letters, digits and dashes
+pub fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !valid {
+        anyhow::bail!(
+            "Invalid secret name '{}': use lowercase letters, digits and dashes",
+            name
+        );
+    }
+    Ok(())
+}
+
+fn entry(name: &str) -> Result<Entry> {
+    validate_name(name)?;
+    Entry::new(SERVICE, name).context("Failed to open the OS credential store")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_values_are_not_references() {
+        assert_eq!(secret_name("ghp_abc123").unwrap(), None);
+        assert_eq!(resolve("ghp_abc123").unwrap(), "ghp_abc123");
+    }
+
+    #[test]
+    fn parses_references() {
+        assert_eq!(secret_name("keychain:github-token").unwrap(), Some("github-token"));
+        assert!(secret_name("keychain:").is_err());
+        assert!(secret_name("keychain:GitHub Token").is_err());
+    }
+}
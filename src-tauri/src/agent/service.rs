@@ -0,0 +1,139 @@
+//! Windows service entry point
+//!
+//! The Service Control Manager starts `family-policy run-service`, which hands
+//! the process to the service dispatcher. Stop and shutdown requests end the
+//! daemon loop the same way Ctrl+C does in the foreground, so an in-flight
+//! apply finishes first; pause holds off polling until the service is
+//! continued.
+
+use anyhow::{Context, Result};
+use std::ffi::OsString;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::sync::watch;
+use windows_service::service::{
+    ServiceControl, ServiceControlAccept, ServiceExitCode, ServiceState, ServiceStatus,
+    ServiceType,
+};
+use windows_service::service_control_handler::{
+    self, ServiceControlHandlerResult, ServiceStatusHandle,
+};
+use windows_service::{define_windows_service, service_dispatcher};
+
+use super::shutdown::Shutdown;
+
+/// Name the service is registered under
+pub const SERVICE_NAME: &str = "FamilyPolicyAgent";
+
+/// How long the SCM should wait for startup (startup verification may apply
+/// the cached policy)
+const START_WAIT_HINT: Duration = Duration::from_secs(60);
+
+/// How long the SCM should wait for the in-flight check to finish on stop
+const STOP_WAIT_HINT: Duration = Duration::from_secs(60);
+
+/// Exit code reported when the agent fails (service-specific)
+const EXIT_FAILURE: u32 = 1;
+
+static STATUS_HANDLE: OnceLock<ServiceStatusHandle> = OnceLock::new();
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// Run as a Windows service, returning once the service has stopped
+///
+/// Fails straight away if the process wasn't started by the Service Control
+/// Manager.
+pub fn run(verbose: bool) -> Result<()> {
+    VERBOSE.store(verbose, Ordering::Relaxed);
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main).context(
+        "Failed to connect to the Service Control Manager; use 'family-policy start' instead",
+    )
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    match run_service() {
+        Ok(()) => report(ServiceState::Stopped, ServiceExitCode::NO_ERROR, Duration::ZERO),
+        Err(e) => {
+            tracing::error!("Agent service failed: {:#}", e);
+            report(
+                ServiceState::Stopped,
+                ServiceExitCode::ServiceSpecific(EXIT_FAILURE),
+                Duration::ZERO,
+            );
+        }
+    }
+}
+
+fn run_service() -> Result<()> {
+    let (shutdown_tx, shutdown) = Shutdown::channel();
+    let (pause_tx, paused) = watch::channel(false);
+
+    let event_handler = move |control| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            report(ServiceState::StopPending, ServiceExitCode::NO_ERROR, STOP_WAIT_HINT);
+            tracing::info!("Service stop requested, shutting down");
+            let _ = shutdown_tx.send(true);
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Pause => {
+            let _ = pause_tx.send(true);
+            report(ServiceState::Paused, ServiceExitCode::NO_ERROR, Duration::ZERO);
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Continue => {
+            let _ = pause_tx.send(false);
+            report(ServiceState::Running, ServiceExitCode::NO_ERROR, Duration::ZERO);
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    };
+
+    let status_handle = service_control_handler::register(SERVICE_NAME, event_handler)
+        .context("Failed to register service control handler")?;
+    let _ = STATUS_HANDLE.set(status_handle);
+    report(ServiceState::StartPending, ServiceExitCode::NO_ERROR, START_WAIT_HINT);
+
+    let config_path = super::get_agent_config_path()?;
+    let config = super::AgentConfig::load(&config_path)
+        .context("Failed to load agent configuration. Run 'family-policy setup' first.")?;
+
+    // Keep the guard alive so the log file is flushed on exit
+    let _log_guard = super::init_agent_logging(&config.logging, VERBOSE.load(Ordering::Relaxed))?;
+
+    let runtime = tokio::runtime::Runtime::new().context("Failed to start async runtime")?;
+    report(ServiceState::Running, ServiceExitCode::NO_ERROR, Duration::ZERO);
+    runtime.block_on(super::daemon::run_agent_daemon_with(config, shutdown, paused))
+}
+
+/// Tell the SCM what state the service is in
+fn report(state: ServiceState, exit_code: ServiceExitCode, wait_hint: Duration) {
+    let Some(status_handle) = STATUS_HANDLE.get() else {
+        return;
+    };
+
+    let controls_accepted = match state {
+        ServiceState::Running | ServiceState::Paused => {
+            ServiceControlAccept::STOP
+                | ServiceControlAccept::SHUTDOWN
+                | ServiceControlAccept::PAUSE_CONTINUE
+        }
+        _ => ServiceControlAccept::empty(),
+    };
+
+    let status = ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state: state,
+        controls_accepted,
+        exit_code,
+        checkpoint: 0,
+        wait_hint,
+        process_id: None,
+    };
+
+    if let Err(e) = status_handle.set_service_status(status) {
+        tracing::warn!("Failed to report service status: {}", e);
+    }
+}
@@ -0,0 +1,121 @@
+// Shutdown signal handling for the agent daemon
+//
+// A background task listens for SIGTERM/SIGINT (Ctrl+C, console close and
+// system shutdown on Windows) and flips a watch channel. The daemon checks it
+// between iterations, so an in-flight apply always finishes and its state is
+// saved before the process exits.
+
+use anyhow::{Context, Result};
+use std::future::Future;
+use tokio::sync::watch;
+
+/// Handle the daemon uses to observe a shutdown request
+#[derive(Clone)]
+pub struct Shutdown {
+    rx: watch::Receiver<bool>,
+}
+
+impl Shutdown {
+    /// Start listening for OS shutdown signals
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn on_signals() -> Result<Self> {
+        let signal = wait_for_signal()?;
+        let (tx, shutdown) = Self::channel();
+
+        tokio::spawn(async move {
+            let name = signal.await;
+            tracing::info!("Received {}, shutting down", name);
+            let _ = tx.send(true);
+        });
+
+        Ok(shutdown)
+    }
+
+    /// Create a shutdown handle triggered by the returned sender
+    pub fn channel() -> (watch::Sender<bool>, Self) {
+        let (tx, rx) = watch::channel(false);
+        (tx, Self { rx })
+    }
+
+    /// Whether shutdown has been requested
+    pub fn is_requested(&self) -> bool {
+        *self.rx.borrow()
+    }
+
+    /// Wait until shutdown is requested
+    pub async fn requested(&mut self) {
+        // An error means the sender is gone and no request can ever arrive
+        if self.rx.wait_for(|requested| *requested).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+#[cfg(unix)]
+fn wait_for_signal() -> Result<impl Future<Output = &'static str>> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate =
+        signal(SignalKind::terminate()).context("Failed to install SIGTERM handler")?;
+    let mut interrupt =
+        signal(SignalKind::interrupt()).context("Failed to install SIGINT handler")?;
+
+    Ok(async move {
+        tokio::select! {
+            _ = terminate.recv() => "SIGTERM",
+            _ = interrupt.recv() => "SIGINT",
+        }
+    })
+}
+
+#[cfg(windows)]
+fn wait_for_signal() -> Result<impl Future<Output = &'static str>> {
+    use tokio::signal::windows;
+
+    let mut ctrl_c = windows::ctrl_c().context("Failed to install Ctrl+C handler")?;
+    let mut ctrl_close = windows::ctrl_close().context("Failed to install close handler")?;
+    let mut ctrl_shutdown =
+        windows::ctrl_shutdown().context("Failed to install shutdown handler")?;
+
+    Ok(async move {
+        tokio::select! {
+            _ = ctrl_c.recv() => "Ctrl+C",
+            _ = ctrl_close.recv() => "console close",
+            _ = ctrl_shutdown.recv() => "system shutdown",
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn shutdown_not_requested_initially() {
+        let (_tx, shutdown) = Shutdown::channel();
+        assert!(!shutdown.is_requested());
+    }
+
+    #[tokio::test]
+    async fn shutdown_requested_wakes_waiter() {
+        let (tx, mut shutdown) = Shutdown::channel();
+        tx.send(true).unwrap();
+
+        tokio::time::timeout(Duration::from_secs(1), shutdown.requested())
+            .await
+            .expect("waiter should wake once shutdown is requested");
+        assert!(shutdown.is_requested());
+    }
+
+    #[tokio::test]
+    async fn dropped_sender_never_requests_shutdown() {
+        let (tx, mut shutdown) = Shutdown::channel();
+        drop(tx);
+
+        let waited = tokio::time::timeout(Duration::from_millis(50), shutdown.requested()).await;
+        assert!(waited.is_err());
+        assert!(!shutdown.is_requested());
+    }
+}
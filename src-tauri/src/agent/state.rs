@@ -12,7 +12,7 @@ mod tests {
     #[test]
     fn agent_state_new_creates_valid_state() {
         let state = State::new_agent();
-        assert_eq!(state.version, "1.0");
+        assert_eq!(state.version, "1.1");
         assert!(!state.machine_id.is_empty());
         assert!(state.last_checked.is_none());
         assert!(state.etag.is_none());
@@ -41,6 +41,12 @@ mod tests {
             chrome: Some(BrowserState::new()),
             firefox: None,
             edge: None,
+            chromium: None,
+            vivaldi: None,
+            opera: None,
+            safari: None,
+            unverified: Vec::new(),
+            conflicts: Vec::new(),
         };
 
         let before = Utc::now();
@@ -67,4 +73,79 @@ mod tests {
         assert_eq!(state.etag, Some("W/\"new\"".to_string()));
         assert!(state.last_checked.is_some());
     }
+
+    #[test]
+    fn agent_state_quarantines_after_consecutive_failures() {
+        let mut state = State::new_agent();
+
+        assert!(!state.record_apply_failure("sha256:bad", "boom", 3));
+        assert!(!state.record_apply_failure("sha256:bad", "boom", 3));
+        assert!(!state.is_quarantined("sha256:bad"));
+
+        assert!(state.record_apply_failure("sha256:bad", "boom again", 3));
+        assert!(state.is_quarantined("sha256:bad"));
+        assert!(!state.is_quarantined("sha256:other"));
+
+        // Only reported once
+        assert!(!state.record_apply_failure("sha256:bad", "boom", 3));
+        assert_eq!(state.failed_apply.as_ref().unwrap().failures, 4);
+    }
+
+    #[test]
+    fn agent_state_failure_count_resets_for_new_policy() {
+        let mut state = State::new_agent();
+        state.record_apply_failure("sha256:first", "boom", 2);
+        state.record_apply_failure("sha256:second", "boom", 2);
+
+        let failed = state.failed_apply.as_ref().unwrap();
+        assert_eq!(failed.config_hash, "sha256:second");
+        assert_eq!(failed.failures, 1);
+        assert!(!failed.quarantined);
+
+        // 0 disables quarantine
+        for _ in 0..10 {
+            assert!(!state.record_apply_failure("sha256:third", "boom", 0));
+        }
+        assert!(!state.is_quarantined("sha256:third"));
+    }
+
+    #[test]
+    fn agent_state_holds_policy_until_approved() {
+        let mut state = State::new_agent();
+        assert!(state.approve_pending().is_none());
+
+        assert!(state.hold_for_approval("sha256:new", vec!["Chrome: extension X".to_string()]));
+        assert!(!state.hold_for_approval("sha256:new", vec![]));
+        assert!(!state.is_approved("sha256:new"));
+
+        state.approve_pending();
+        assert!(state.is_approved("sha256:new"));
+        assert!(!state.is_approved("sha256:other"));
+
+        // A different change needs approving again
+        assert!(state.hold_for_approval("sha256:other", vec![]));
+        assert!(!state.is_approved("sha256:other"));
+    }
+
+    #[test]
+    fn agent_state_alerts_once_when_poll_failures_reach_threshold() {
+        let mut state = State::new_agent();
+
+        assert!(!state.record_poll_failure("timeout", 3));
+        assert!(!state.record_poll_failure("timeout", 3));
+        assert!(state.record_poll_failure("404", 3));
+        assert!(!state.record_poll_failure("404", 3));
+
+        let failures = state.poll_failures.as_ref().unwrap();
+        assert_eq!(failures.failures, 4);
+        assert_eq!(failures.last_error, "404");
+        assert_eq!(state.consecutive_poll_failures(), 4);
+
+        let ended = state.record_poll_success().unwrap();
+        assert!(ended.alerted);
+        assert_eq!(state.consecutive_poll_failures(), 0);
+
+        // A new run of failures alerts again
+        assert!(state.record_poll_failure("timeout", 1));
+    }
 }
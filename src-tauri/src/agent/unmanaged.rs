@@ -0,0 +1,249 @@
+// Reporting and blocking browsers the policy can't manage
+//
+// Runs after every check. Each unmanaged browser is reported once, as an
+// incident and an inbox warning, when it is first found, and again if it is
+// removed and comes back. With `block` the known executables are blocked on
+// Windows whether or not they are installed yet, so installing one later
+// doesn't get around it. Other platforms have no system-wide way to stop a
+// program from starting, so there `block` only warns. Opera and Vivaldi
+// are left alone while a policy entry manages them.
+
+use anyhow::Result;
+use std::path::PathBuf;
+
+use crate::browser::{
+    current_platform, find_unmanaged_browsers, user_home_dirs, Browser, Platform, UnmanagedBrowser,
+};
+use crate::config::{Config, UnmanagedBrowserAction};
+use crate::i18n::tr;
+use crate::inbox::{self, NotificationLevel};
+use crate::incidents::{self, IncidentKind};
+use crate::state::{load_policy_cache, load_state, save_state, State};
+
+/// Look for unmanaged browsers and act on them as the applied policy says
+pub(super) fn check_unmanaged_browsers() -> Result<()> {
+    let Some(mut state) = load_state()? else {
+        return Ok(());
+    };
+    let (action, managed) = match load_policy_cache()? {
+        Some(content) => {
+            let config = Config::from_yaml_str(&content)?;
+            (config.unmanaged_browsers, config.browsers())
+        }
+        None => (None, Vec::new()),
+    };
+
+    let found = match action {
+        Some(_) => find_unmanaged_browsers(&user_home_dirs()),
+        None => Vec::new(),
+    };
+
+    if enforce(action, &managed, &found, &mut state, current_platform(), &mut set_blocked) {
+        save_state(&state)?;
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn set_blocked(executable: &str, blocked: bool) -> Result<()> {
+    if blocked {
+        crate::platform::windows::block_executable(executable)
+    } else {
+        crate::platform::windows::unblock_executable(executable)
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn set_blocked(executable: &str, _blocked: bool) -> Result<()> {
+    anyhow::bail!("Can't block {} on this platform", executable)
+}
+
+/// Report newly found browsers and block or unblock executables, returning
+/// whether `state` changed
+///
+/// Browsers in `managed` (those the policy lists) are neither reported nor
+/// blocked. `set_blocked(executable, blocked)` blocks or unblocks an
+/// executable.
+fn enforce(
+    action: Option<UnmanagedBrowserAction>,
+    managed: &[Browser],
+    found: &[(UnmanagedBrowser, PathBuf)],
+    state: &mut State,
+    platform: Platform,
+    set_blocked: &mut dyn FnMut(&str, bool) -> Result<()>,
+) -> bool {
+    let mut changed = false;
+    let is_unmanaged = |browser: &UnmanagedBrowser| !browser.managed_as().is_some_and(|b| managed.contains(&b));
+    let found: Vec<&(UnmanagedBrowser, PathBuf)> = found.iter().filter(|(b, _)| is_unmanaged(b)).collect();
+
+    let blocking = action == Some(UnmanagedBrowserAction::Block) && platform == Platform::Windows;
+    let wanted: Vec<String> = if blocking {
+        UnmanagedBrowser::ALL
+            .iter()
+            .filter(|b| is_unmanaged(b))
+            .flat_map(|b| b.windows_executables())
+            .map(|exe| exe.to_string())
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    for executable in &wanted {
+        if state.blocked_executables.contains(executable) {
+            continue;
+        }
+        match set_blocked(executable, true) {
+            Ok(()) => {
+                tracing::info!("Blocked {} from starting", executable);
+                state.blocked_executables.push(executable.clone());
+                changed = true;
+            }
+            Err(e) => tracing::warn!("Failed to block {}: {:#}", executable, e),
+        }
+    }
+
+    let blocked = std::mem::take(&mut state.blocked_executables);
+    for executable in blocked {
+        if wanted.contains(&executable) {
+            state.blocked_executables.push(executable);
+            continue;
+        }
+        match set_blocked(&executable, false) {
+            Ok(()) => {
+                tracing::info!("Unblocked {}", executable);
+                changed = true;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to unblock {}: {:#}", executable, e);
+                state.blocked_executables.push(executable);
+            }
+        }
+    }
+
+    for (browser, path) in &found {
+        if !state.unmanaged_browsers.contains(browser) {
+            report(*browser, path, state);
+        }
+    }
+
+    let found: Vec<UnmanagedBrowser> = found.iter().map(|(b, _)| *b).collect();
+    if state.unmanaged_browsers != found {
+        state.unmanaged_browsers = found;
+        changed = true;
+    }
+
+    changed
+}
+
+fn report(browser: UnmanagedBrowser, path: &std::path::Path, state: &State) {
+    let blocked = browser
+        .windows_executables()
+        .iter()
+        .any(|exe| state.blocked_executables.iter().any(|b| b == exe));
+
+    incidents::record(
+        IncidentKind::UnmanagedBrowser,
+        &format!("{} found at {}", browser.name(), path.display()),
+    );
+    let message = if blocked { "unmanaged-browser-blocked" } else { "unmanaged-browser" };
+    inbox::notify(
+        NotificationLevel::Warning,
+        &tr("unmanaged-browser-title", &[]),
+        &tr(message, &[("browser", &browser.name()), ("path", &path.display())]),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opera() -> Vec<(UnmanagedBrowser, PathBuf)> {
+        vec![(UnmanagedBrowser::Opera, PathBuf::from("/usr/bin/opera"))]
+    }
+
+    #[test]
+    fn reports_each_browser_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let _state = crate::state::use_test_state_dir(dir.path());
+        let mut state = State::new_agent();
+        let mut never = |_: &str, _: bool| -> Result<()> { panic!("nothing to block") };
+
+        let warn = Some(UnmanagedBrowserAction::Warn);
+        assert!(enforce(warn, &[], &opera(), &mut state, Platform::Linux, &mut never));
+        assert!(!enforce(warn, &[], &opera(), &mut state, Platform::Linux, &mut never));
+
+        let inbox = inbox::load_inbox().unwrap();
+        assert_eq!(inbox.notifications.len(), 1);
+        let incidents = incidents::load_incidents().unwrap();
+        assert_eq!(incidents.incidents.len(), 1);
+        assert_eq!(incidents.incidents[0].kind, IncidentKind::UnmanagedBrowser);
+
+        // Removed, then installed again
+        assert!(enforce(warn, &[], &[], &mut state, Platform::Linux, &mut never));
+        assert!(enforce(warn, &[], &opera(), &mut state, Platform::Linux, &mut never));
+        assert_eq!(inbox::load_inbox().unwrap().notifications.len(), 2);
+    }
+
+    #[test]
+    fn blocks_on_windows_until_no_longer_configured() {
+        let dir = tempfile::tempdir().unwrap();
+        let _state = crate::state::use_test_state_dir(dir.path());
+        let mut state = State::new_agent();
+        let mut calls = Vec::new();
+        let mut record = |exe: &str, blocked: bool| -> Result<()> {
+            calls.push((exe.to_string(), blocked));
+            Ok(())
+        };
+
+        let block = Some(UnmanagedBrowserAction::Block);
+        assert!(enforce(block, &[], &[], &mut state, Platform::Windows, &mut record));
+        assert_eq!(state.blocked_executables, ["opera.exe", "vivaldi.exe"]);
+
+        // Nothing more to do while the policy is unchanged
+        assert!(!enforce(block, &[], &[], &mut state, Platform::Windows, &mut record));
+
+        assert!(enforce(None, &[], &[], &mut state, Platform::Windows, &mut record));
+        assert!(state.blocked_executables.is_empty());
+
+        assert_eq!(
+            calls,
+            [
+                ("opera.exe".to_string(), true),
+                ("vivaldi.exe".to_string(), true),
+                ("opera.exe".to_string(), false),
+                ("vivaldi.exe".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn leaves_browsers_the_policy_manages_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let _state = crate::state::use_test_state_dir(dir.path());
+        let mut state = State::new_agent();
+        let mut calls = Vec::new();
+        let mut record = |exe: &str, blocked: bool| -> Result<()> {
+            calls.push((exe.to_string(), blocked));
+            Ok(())
+        };
+
+        let block = Some(UnmanagedBrowserAction::Block);
+        assert!(enforce(block, &[Browser::Opera], &opera(), &mut state, Platform::Windows, &mut record));
+        assert_eq!(state.blocked_executables, ["vivaldi.exe"]);
+        assert!(state.unmanaged_browsers.is_empty());
+        assert!(inbox::load_inbox().unwrap().notifications.is_empty());
+    }
+
+    #[test]
+    fn block_only_warns_off_windows() {
+        let dir = tempfile::tempdir().unwrap();
+        let _state = crate::state::use_test_state_dir(dir.path());
+        let mut state = State::new_agent();
+        let mut never = |_: &str, _: bool| -> Result<()> { panic!("can't block on macOS") };
+
+        let block = Some(UnmanagedBrowserAction::Block);
+        assert!(enforce(block, &[], &opera(), &mut state, Platform::MacOS, &mut never));
+        assert_eq!(state.unmanaged_browsers, [UnmanagedBrowser::Opera]);
+        assert!(state.blocked_executables.is_empty());
+    }
+}
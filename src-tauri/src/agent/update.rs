@@ -0,0 +1,339 @@
+//! Updating the agent binary from GitHub Releases
+//!
+//! Each release publishes a plain binary per platform
+//! (`family-policy-<os>-<arch>[.exe]`), a `SHA256SUMS` file and a minisign
+//! signature of each binary (`<binary>.minisig`). A download is only
+//! installed if its checksum matches and its signature verifies against the
+//! configured public key; without a key nothing is installed, since the
+//! checksum comes from the same release as the binary. The running executable is swapped with a rename,
+//! so an interrupted update leaves the old binary in place.
+
+use anyhow::{Context, Result};
+use minisign_verify::{PublicKey, Signature};
+use reqwest::Client;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+use super::config::{AgentSettings, UpdateConfig};
+use super::poller::http_client;
+
+/// Asset listing the SHA-256 checksum of every binary in a release
+const CHECKSUMS_ASSET: &str = "SHA256SUMS";
+
+/// Binaries are larger than policy files; allow for slow connections
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Deserialize)]
+struct GitHubRelease {
+    tag_name: String,
+    #[serde(default)]
+    assets: Vec<GitHubAsset>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GitHubAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// A published release
+#[derive(Debug)]
+pub struct Release {
+    pub version: semver::Version,
+    assets: Vec<GitHubAsset>,
+}
+
+/// Checks for and downloads agent releases
+pub struct Updater {
+    client: Client,
+    config: UpdateConfig,
+    api_base: String,
+}
+
+impl Updater {
+    /// Create an updater using the agent's proxy and CA settings
+    pub fn new(config: UpdateConfig, settings: &AgentSettings) -> Result<Self> {
+        // Tests serve releases from a plain HTTP server
+        let client = http_client(settings, !cfg!(test))?;
+        Ok(Self {
+            client,
+            config,
+            api_base: "https://api.github.com".to_string(),
+        })
+    }
+
+    /// Look up the latest release, returning it if it is newer than the
+    /// running agent
+    pub async fn check(&self) -> Result<Option<Release>> {
+        let url = format!("{}/repos/{}/releases/latest", self.api_base, self.config.repository);
+        tracing::debug!("Checking for agent updates: {}", url);
+
+        let response = self
+            .client
+            .get(&url)
+            .header("Accept", "application/vnd.github+json")
+            .send()
+            .await
+            .context("Failed to connect to GitHub")?
+            .error_for_status()
+            .with_context(|| format!("Failed to look up the latest release of {}", self.config.repository))?;
+        let body = response.text().await.context("Failed to read release information")?;
+
+        let release: GitHubRelease =
+            serde_json::from_str(&body).context("Invalid release information from GitHub")?;
+        let version = parse_version(&release.tag_name)?;
+
+        if version <= current_version() {
+            tracing::debug!("Agent is up to date ({})", current_version());
+            return Ok(None);
+        }
+
+        Ok(Some(Release {
+            version,
+            assets: release.assets,
+        }))
+    }
+
+    /// Download this platform's binary from `release` and verify it
+    ///
+    /// Fails without a configured public key.
+    pub async fn download(&self, release: &Release) -> Result<Vec<u8>> {
+        let public_key = self
+            .config
+            .public_key
+            .as_deref()
+            .context("No update.public_key configured, so the release's signature can't be verified")?;
+        let name = binary_asset_name();
+        let binary = self.download_asset(release, &name).await?;
+
+        let sums = self.download_asset(release, CHECKSUMS_ASSET).await?;
+        let sums = String::from_utf8(sums).context("Invalid checksum file")?;
+        let expected = expected_checksum(&sums, &name)
+            .with_context(|| format!("{} has no checksum for {}", CHECKSUMS_ASSET, name))?;
+        verify_checksum(&binary, expected)?;
+
+        let signature = self.download_asset(release, &format!("{}.minisig", name)).await?;
+        let signature = String::from_utf8(signature).context("Invalid signature file")?;
+        verify_signature(&binary, &signature, public_key)?;
+
+        Ok(binary)
+    }
+
+    async fn download_asset(&self, release: &Release, name: &str) -> Result<Vec<u8>> {
+        let asset = release
+            .assets
+            .iter()
+            .find(|a| a.name == name)
+            .with_context(|| format!("Release {} has no {}", release.version, name))?;
+
+        tracing::debug!("Downloading {}", asset.browser_download_url);
+        let response = self
+            .client
+            .get(&asset.browser_download_url)
+            .timeout(DOWNLOAD_TIMEOUT)
+            .send()
+            .await
+            .with_context(|| format!("Failed to download {}", name))?
+            .error_for_status()
+            .with_context(|| format!("Failed to download {}", name))?;
+
+        let bytes = response
+            .bytes()
+            .await
+            .with_context(|| format!("Failed to download {}", name))?;
+        Ok(bytes.to_vec())
+    }
+}
+
+/// Version of the running agent
+pub fn current_version() -> semver::Version {
+    semver::Version::parse(env!("CARGO_PKG_VERSION")).expect("package version is valid semver")
+}
+
+fn parse_version(tag: &str) -> Result<semver::Version> {
+    semver::Version::parse(tag.trim_start_matches('v'))
+        .with_context(|| format!("Release tag '{}' is not a version", tag))
+}
+
+/// Name of the release asset for this platform, e.g. `family-policy-linux-x86_64`
+pub fn binary_asset_name() -> String {
+    format!(
+        "family-policy-{}-{}{}",
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        std::env::consts::EXE_SUFFIX
+    )
+}
+
+/// Find the checksum for `name` in `sha256sum` output
+fn expected_checksum<'a>(sums: &'a str, name: &str) -> Option<&'a str> {
+    sums.lines().find_map(|line| {
+        let (hash, file) = line.split_once(char::is_whitespace)?;
+        // Binary mode marks the file name with '*'
+        let file = file.trim_start().trim_start_matches('*');
+        (file == name).then_some(hash)
+    })
+}
+
+fn verify_checksum(data: &[u8], expected: &str) -> Result<()> {
+    let actual = format!("{:x}", Sha256::digest(data));
+    if !actual.eq_ignore_ascii_case(expected) {
+        anyhow::bail!("Checksum mismatch: expected {}, got {}", expected, actual);
+    }
+    Ok(())
+}
+
+fn verify_signature(data: &[u8], signature: &str, public_key: &str) -> Result<()> {
+    let public_key = PublicKey::from_base64(public_key.trim()).context("Invalid update public key")?;
+    let signature = Signature::decode(signature).context("Invalid release signature")?;
+    public_key
+        .verify(data, &signature, false)
+        .context("Release signature doesn't match the update public key")
+}
+
+/// Replace the running executable with `binary`
+///
+/// The new binary is written next to the current one and renamed over it,
+/// so the running process keeps using the old file until it restarts.
+pub fn install(binary: &[u8]) -> Result<()> {
+    let current = std::env::current_exe().context("Failed to find the running executable")?;
+    let staged = current.with_file_name(format!(".family-policy-update{}", std::env::consts::EXE_SUFFIX));
+
+    std::fs::write(&staged, binary)
+        .with_context(|| format!("Failed to write {}", staged.display()))?;
+    let result = self_replace::self_replace(&staged)
+        .with_context(|| format!("Failed to replace {}", current.display()));
+    let _ = std::fs::remove_file(&staged);
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::path;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const BINARY: &[u8] = b"family-policy test binary\n";
+    const BINARY_SHA256: &str = "f922c8322d08f3661a1faa8d6c8424df04d4613c6fd4d1f8cefc460b1765c6a9";
+    const PUBLIC_KEY: &str = "RWQBI0VniavN7wOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4";
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQBI0VniavN714oLyy/YNyA2f+rxfXU6K8PMw36MHKYo+PBSGAMNayAErN0AdGt55E8+5o7nSVmzoq4+GhHZcAM1bat3VMqTw8=
+trusted comment: timestamp:1760000000\tfile:family-policy-linux-x86_64
+ZuA+RJD3gz4hdJeieDgnHNypBY7Rk67V3uzEic394AZ+gAgc7+D0jceqm5fopEOjuizH2majrqLnsjF/1Y7UAA==
+";
+
+    #[test]
+    fn finds_checksums_in_sha256sum_output() {
+        let sums = format!(
+            "0000  family-policy-windows-x86_64.exe\n{} *{}\n",
+            BINARY_SHA256,
+            binary_asset_name()
+        );
+        assert_eq!(expected_checksum(&sums, &binary_asset_name()), Some(BINARY_SHA256));
+        assert_eq!(expected_checksum(&sums, "family-policy-plan9-mips"), None);
+    }
+
+    #[test]
+    fn verifies_checksum() {
+        assert!(verify_checksum(BINARY, BINARY_SHA256).is_ok());
+        assert!(verify_checksum(b"tampered", BINARY_SHA256).is_err());
+    }
+
+    #[test]
+    fn verifies_minisign_signature() {
+        assert!(verify_signature(BINARY, SIGNATURE, PUBLIC_KEY).is_ok());
+        assert!(verify_signature(b"tampered", SIGNATURE, PUBLIC_KEY).is_err());
+    }
+
+    #[test]
+    fn parses_release_tags() {
+        assert_eq!(parse_version("v1.2.3").unwrap(), semver::Version::new(1, 2, 3));
+        assert!(parse_version("nightly").is_err());
+    }
+
+    async fn release_server(tag: &str, checksum: &str) -> MockServer {
+        let server = MockServer::start().await;
+        let name = binary_asset_name();
+        let asset = |file: &str| {
+            serde_json::json!({
+                "name": file,
+                "browser_download_url": format!("{}/download/{}", server.uri(), file),
+            })
+        };
+        let release = serde_json::json!({
+            "tag_name": tag,
+            "assets": [
+                asset(&name),
+                asset(CHECKSUMS_ASSET),
+                asset(&format!("{}.minisig", name)),
+            ],
+        });
+
+        Mock::given(path("/repos/emosenkis/family-policy/releases/latest"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(release))
+            .mount(&server)
+            .await;
+        Mock::given(path(format!("/download/{}", name)))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(BINARY))
+            .mount(&server)
+            .await;
+        Mock::given(path(format!("/download/{}", CHECKSUMS_ASSET)))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!("{}  {}\n", checksum, name)))
+            .mount(&server)
+            .await;
+        Mock::given(path(format!("/download/{}.minisig", name)))
+            .respond_with(ResponseTemplate::new(200).set_body_string(SIGNATURE))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn updater(server: &MockServer) -> Updater {
+        let config = UpdateConfig {
+            public_key: Some(PUBLIC_KEY.to_string()),
+            ..Default::default()
+        };
+        let mut updater = Updater::new(config, &AgentSettings::default()).unwrap();
+        updater.api_base = server.uri();
+        updater
+    }
+
+    #[tokio::test]
+    async fn downloads_and_verifies_newer_release() {
+        let server = release_server("v999.0.0", BINARY_SHA256).await;
+        let updater = updater(&server);
+
+        let release = updater.check().await.unwrap().expect("newer release");
+        assert_eq!(release.version, semver::Version::new(999, 0, 0));
+        assert_eq!(updater.download(&release).await.unwrap(), BINARY);
+    }
+
+    #[tokio::test]
+    async fn ignores_release_that_is_not_newer() {
+        let server = release_server(&format!("v{}", current_version()), BINARY_SHA256).await;
+        assert!(updater(&server).check().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn rejects_checksum_mismatch() {
+        let server = release_server("v999.0.0", &"0".repeat(64)).await;
+        let updater = updater(&server);
+
+        let release = updater.check().await.unwrap().unwrap();
+        let error = updater.download(&release).await.unwrap_err();
+        assert!(format!("{:#}", error).contains("Checksum mismatch"));
+    }
+
+    #[tokio::test]
+    async fn refuses_to_download_without_public_key() {
+        let server = release_server("v999.0.0", BINARY_SHA256).await;
+        let mut updater = updater(&server);
+        updater.config.public_key = None;
+
+        let release = updater.check().await.unwrap().unwrap();
+        let error = updater.download(&release).await.unwrap_err();
+        assert!(format!("{:#}", error).contains("update.public_key"));
+    }
+}
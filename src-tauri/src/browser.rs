@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
 /// Supported browsers
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
@@ -7,15 +8,79 @@ pub enum Browser {
     Chrome,
     Firefox,
     Edge,
+    Chromium,
+    Vivaldi,
+    Opera,
+    /// macOS only
+    Safari,
 }
 
 impl Browser {
+    /// Every browser policies can be written for
+    pub const ALL: [Browser; 7] = [
+        Browser::Chrome,
+        Browser::Firefox,
+        Browser::Edge,
+        Browser::Chromium,
+        Browser::Vivaldi,
+        Browser::Opera,
+        Browser::Safari,
+    ];
+
     /// Get lowercase string representation
     pub fn as_str(&self) -> &'static str {
         match self {
             Browser::Chrome => "chrome",
             Browser::Firefox => "firefox",
             Browser::Edge => "edge",
+            Browser::Chromium => "chromium",
+            Browser::Vivaldi => "vivaldi",
+            Browser::Opera => "opera",
+            Browser::Safari => "safari",
+        }
+    }
+
+    /// Browser name for messages
+    pub fn name(&self) -> &'static str {
+        match self {
+            Browser::Chrome => "Chrome",
+            Browser::Firefox => "Firefox",
+            Browser::Edge => "Edge",
+            Browser::Chromium => "Chromium",
+            Browser::Vivaldi => "Vivaldi",
+            Browser::Opera => "Opera",
+            Browser::Safari => "Safari",
+        }
+    }
+
+    /// Whether the browser reads Chromium's policies (everything but Firefox
+    /// and Safari)
+    pub fn is_chromium_based(&self) -> bool {
+        !matches!(self, Browser::Firefox | Browser::Safari)
+    }
+
+    /// Whether extensions can be force-installed (not in Safari)
+    pub fn supports_extensions(&self) -> bool {
+        !matches!(self, Browser::Safari)
+    }
+}
+
+impl std::str::FromStr for Browser {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "chrome" => Ok(Browser::Chrome),
+            "firefox" => Ok(Browser::Firefox),
+            "edge" => Ok(Browser::Edge),
+            "chromium" => Ok(Browser::Chromium),
+            "vivaldi" => Ok(Browser::Vivaldi),
+            "opera" => Ok(Browser::Opera),
+            "safari" => Ok(Browser::Safari),
+            _ => Err(format!(
+                "unknown browser '{}' (expected chrome, firefox, edge, chromium, vivaldi, opera or safari)",
+                s
+            )),
         }
     }
 }
@@ -24,7 +89,8 @@ impl Browser {
 ///
 /// Note: On each platform, only the current platform variant is constructed,
 /// but all variants are needed for match expressions in policy modules.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
 #[allow(dead_code)] // Not all variants constructed on every platform
 pub enum Platform {
     Windows,
@@ -60,23 +126,37 @@ pub fn current_platform() -> Platform {
         Platform::Linux
     }
 
-    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    #[cfg(target_arch = "wasm32")]
+    {
+        // Only the validator is built for wasm32 and it never writes policies
+        unreachable!("No policy platform on wasm32")
+    }
+
+    #[cfg(not(any(
+        target_os = "windows",
+        target_os = "macos",
+        target_os = "linux",
+        target_arch = "wasm32"
+    )))]
     {
         compile_error!("Unsupported operating system");
     }
 }
 
-#[cfg(test)]
-mod test_helpers {
-    use super::*;
-    use std::path::PathBuf;
-
 /// Check if a browser is available on the system
 pub fn is_browser_available(browser: Browser) -> bool {
     match browser {
         Browser::Chrome => is_chrome_available(),
         Browser::Firefox => is_firefox_available(),
         Browser::Edge => is_edge_available(),
+        Browser::Chromium => is_chromium_available(),
+        // Also looked for as unmanaged browsers, which they are unless a
+        // policy lists them
+        Browser::Vivaldi => UnmanagedBrowser::Vivaldi.system_paths().iter().any(|p| p.exists()),
+        Browser::Opera => UnmanagedBrowser::Opera.system_paths().iter().any(|p| p.exists()),
+        Browser::Safari => {
+            current_platform() == Platform::MacOS && std::path::Path::new("/Applications/Safari.app").exists()
+        }
     }
 }
 
@@ -95,6 +175,11 @@ fn is_edge_available() -> bool {
     get_edge_paths().iter().any(|p| p.exists())
 }
 
+/// Check if Chromium is installed
+fn is_chromium_available() -> bool {
+    get_chromium_paths().iter().any(|p| p.exists())
+}
+
 /// Get possible Chrome installation paths
 pub fn get_chrome_paths() -> Vec<PathBuf> {
     #[cfg(target_os = "windows")]
@@ -117,8 +202,6 @@ pub fn get_chrome_paths() -> Vec<PathBuf> {
         vec![
             PathBuf::from("/usr/bin/google-chrome"),
             PathBuf::from("/usr/bin/google-chrome-stable"),
-            PathBuf::from("/usr/bin/chromium"),
-            PathBuf::from("/usr/bin/chromium-browser"),
         ]
     }
 
@@ -135,14 +218,18 @@ pub fn get_firefox_paths() -> Vec<PathBuf> {
         vec![
             PathBuf::from(r"C:\Program Files\Mozilla Firefox\firefox.exe"),
             PathBuf::from(r"C:\Program Files (x86)\Mozilla Firefox\firefox.exe"),
+            PathBuf::from(r"C:\Program Files\Firefox Developer Edition\firefox.exe"),
+            PathBuf::from(r"C:\Program Files\Firefox Nightly\firefox.exe"),
         ]
     }
 
     #[cfg(target_os = "macos")]
     {
-        vec![PathBuf::from(
-            "/Applications/Firefox.app/Contents/MacOS/firefox",
-        )]
+        vec![
+            PathBuf::from("/Applications/Firefox.app/Contents/MacOS/firefox"),
+            PathBuf::from("/Applications/Firefox Developer Edition.app/Contents/MacOS/firefox"),
+            PathBuf::from("/Applications/Firefox Nightly.app/Contents/MacOS/firefox"),
+        ]
     }
 
     #[cfg(target_os = "linux")]
@@ -150,6 +237,8 @@ pub fn get_firefox_paths() -> Vec<PathBuf> {
         vec![
             PathBuf::from("/usr/bin/firefox"),
             PathBuf::from("/usr/bin/firefox-esr"),
+            PathBuf::from("/usr/bin/firefox-developer-edition"),
+            PathBuf::from("/opt/firefox/firefox"),
         ]
     }
 
@@ -189,12 +278,207 @@ pub fn get_edge_paths() -> Vec<PathBuf> {
         vec![]
     }
 }
-} // end of test_helpers module
+
+/// Get possible Chromium installation paths
+pub fn get_chromium_paths() -> Vec<PathBuf> {
+    #[cfg(target_os = "windows")]
+    {
+        vec![
+            PathBuf::from(r"C:\Program Files\Chromium\Application\chrome.exe"),
+            PathBuf::from(r"C:\Program Files (x86)\Chromium\Application\chrome.exe"),
+        ]
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        vec![PathBuf::from("/Applications/Chromium.app/Contents/MacOS/Chromium")]
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        vec![
+            PathBuf::from("/usr/bin/chromium"),
+            PathBuf::from("/usr/bin/chromium-browser"),
+            PathBuf::from("/snap/bin/chromium"),
+        ]
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    {
+        vec![]
+    }
+}
+
+/// Browsers that ignore the policies this tool writes
+///
+/// Installing one is an easy way around the managed browsers, so the agent
+/// can look for them (see `unmanaged_browsers` in the policy). Opera and
+/// Vivaldi can be managed too; they only count as unmanaged when no policy
+/// lists them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnmanagedBrowser {
+    Opera,
+    Vivaldi,
+    TorBrowser,
+}
+
+impl UnmanagedBrowser {
+    pub const ALL: [UnmanagedBrowser; 3] = [
+        UnmanagedBrowser::Opera,
+        UnmanagedBrowser::Vivaldi,
+        UnmanagedBrowser::TorBrowser,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            UnmanagedBrowser::Opera => "Opera",
+            UnmanagedBrowser::Vivaldi => "Vivaldi",
+            UnmanagedBrowser::TorBrowser => "Tor Browser",
+        }
+    }
+
+    /// The managed browser this is when a policy lists it
+    pub fn managed_as(&self) -> Option<Browser> {
+        match self {
+            UnmanagedBrowser::Opera => Some(Browser::Opera),
+            UnmanagedBrowser::Vivaldi => Some(Browser::Vivaldi),
+            UnmanagedBrowser::TorBrowser => None,
+        }
+    }
+
+    /// Executable names that can be blocked from starting on Windows
+    ///
+    /// Tor Browser runs as firefox.exe, so blocking it would block Firefox.
+    pub fn windows_executables(&self) -> &'static [&'static str] {
+        match self {
+            UnmanagedBrowser::Opera => &["opera.exe"],
+            UnmanagedBrowser::Vivaldi => &["vivaldi.exe"],
+            UnmanagedBrowser::TorBrowser => &[],
+        }
+    }
+
+    /// System-wide installation paths
+    pub fn system_paths(&self) -> Vec<PathBuf> {
+        #[cfg(target_os = "windows")]
+        let paths: &[&str] = match self {
+            UnmanagedBrowser::Opera => &[r"C:\Program Files\Opera\opera.exe"],
+            UnmanagedBrowser::Vivaldi => &[r"C:\Program Files\Vivaldi\Application\vivaldi.exe"],
+            UnmanagedBrowser::TorBrowser => &[],
+        };
+
+        #[cfg(target_os = "macos")]
+        let paths: &[&str] = match self {
+            UnmanagedBrowser::Opera => &["/Applications/Opera.app", "/Applications/Opera GX.app"],
+            UnmanagedBrowser::Vivaldi => &["/Applications/Vivaldi.app"],
+            UnmanagedBrowser::TorBrowser => &["/Applications/Tor Browser.app"],
+        };
+
+        #[cfg(target_os = "linux")]
+        let paths: &[&str] = match self {
+            UnmanagedBrowser::Opera => &["/usr/bin/opera", "/snap/bin/opera"],
+            UnmanagedBrowser::Vivaldi => &["/usr/bin/vivaldi", "/usr/bin/vivaldi-stable", "/opt/vivaldi"],
+            UnmanagedBrowser::TorBrowser => &["/usr/bin/torbrowser-launcher"],
+        };
+
+        #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+        let paths: &[&str] = &[];
+
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    /// Installation paths relative to a user's home directory, for browsers
+    /// that install (or unpack) without admin rights
+    pub fn user_paths(&self) -> &'static [&'static str] {
+        #[cfg(target_os = "windows")]
+        let paths: &[&str] = match self {
+            UnmanagedBrowser::Opera => &[r"AppData\Local\Programs\Opera\opera.exe"],
+            UnmanagedBrowser::Vivaldi => &[r"AppData\Local\Vivaldi\Application\vivaldi.exe"],
+            UnmanagedBrowser::TorBrowser => &[r"Desktop\Tor Browser\Browser\firefox.exe"],
+        };
+
+        #[cfg(target_os = "macos")]
+        let paths: &[&str] = match self {
+            UnmanagedBrowser::Opera => &["Applications/Opera.app"],
+            UnmanagedBrowser::Vivaldi => &["Applications/Vivaldi.app"],
+            UnmanagedBrowser::TorBrowser => &["Applications/Tor Browser.app"],
+        };
+
+        #[cfg(target_os = "linux")]
+        let paths: &[&str] = match self {
+            UnmanagedBrowser::Opera => &[],
+            UnmanagedBrowser::Vivaldi => &[],
+            UnmanagedBrowser::TorBrowser => &[
+                "tor-browser",
+                "Desktop/tor-browser",
+                ".local/share/torbrowser/tbb",
+            ],
+        };
+
+        #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+        let paths: &[&str] = &[];
+
+        paths
+    }
+}
+
+/// Home directories of the machine's users
+pub fn user_home_dirs() -> Vec<PathBuf> {
+    let root = match current_platform() {
+        Platform::Windows => r"C:\Users",
+        Platform::MacOS => "/Users",
+        Platform::Linux => "/home",
+    };
+
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut homes: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    homes.sort();
+    homes
+}
+
+/// Unmanaged browsers installed system-wide or in one of `homes`, with the
+/// first path each was found at
+pub fn find_unmanaged_browsers(homes: &[PathBuf]) -> Vec<(UnmanagedBrowser, PathBuf)> {
+    UnmanagedBrowser::ALL
+        .into_iter()
+        .filter_map(|browser| {
+            let user_paths = homes
+                .iter()
+                .flat_map(|home| browser.user_paths().iter().map(move |p| home.join(p)));
+            browser
+                .system_paths()
+                .into_iter()
+                .chain(user_paths)
+                .find(|path| path.exists())
+                .map(|path| (browser, path))
+        })
+        .collect()
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use super::test_helpers::*;
+
+    #[test]
+    fn finds_unmanaged_browsers_in_home_directories() {
+        let home = tempfile::tempdir().unwrap();
+        let homes = vec![home.path().to_path_buf()];
+        assert!(find_unmanaged_browsers(&homes)
+            .iter()
+            .all(|(_, path)| !path.starts_with(home.path())));
+
+        let tor = home.path().join(UnmanagedBrowser::TorBrowser.user_paths()[0]);
+        std::fs::create_dir_all(&tor).unwrap();
+
+        let found = find_unmanaged_browsers(&homes);
+        assert!(found.contains(&(UnmanagedBrowser::TorBrowser, tor)));
+    }
 
     #[test]
     fn test_browser_clone() {
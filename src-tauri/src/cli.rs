@@ -1,6 +1,10 @@
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
+use crate::browser::Browser;
+use crate::config::PrivacySetting;
+use crate::installer::PackageTarget;
+
 /// Browser Extension Policy Manager
 ///
 /// Manages browser extension force-install policies and privacy controls
@@ -46,20 +50,152 @@ pub enum Commands {
     InstallService,
     /// Uninstall agent system service
     UninstallService,
+    /// Write the service registration and install scripts for building a
+    /// platform package
+    Package {
+        /// Package format
+        #[arg(long, value_enum)]
+        target: PackageTarget,
+
+        /// Directory to write the files to (default: dist/<target>)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Overwrite existing files
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Set up the agent in one step from a provisioning file: write its
+    /// configuration and secrets, apply the policy and install the service
+    Provision {
+        /// Provisioning file (YAML)
+        #[arg(long, value_name = "FILE")]
+        from: PathBuf,
+
+        /// Replace an existing agent configuration
+        #[arg(short, long)]
+        force: bool,
+    },
     /// Start agent daemon (background)
     Start {
         /// Run in foreground (don't daemonize)
         #[arg(long)]
         no_daemon: bool,
+
+        /// Override an agent setting for this run, e.g.
+        /// `--set agent.poll_interval=60` (with --no-daemon)
+        #[arg(long = "set", value_name = "KEY=VALUE", requires = "no_daemon")]
+        set: Vec<String>,
     },
+    /// Run as a Windows service (started by the Service Control Manager)
+    #[command(hide = true)]
+    RunService,
     /// Stop agent daemon
     Stop,
     /// Check for policy updates now (don't wait for next poll)
     CheckNow,
+    /// Apply a policy change held for approval (confirm_before_apply)
+    Approve,
     /// Show agent status
     Status,
     /// Show currently applied configuration
     ShowConfig,
+    /// Show the agent's log (requires admin)
+    Logs {
+        /// Number of lines to show
+        #[arg(long, default_value_t = 50)]
+        lines: usize,
+
+        /// Keep printing new log lines as they are written
+        #[arg(short = 'f', long)]
+        tail: bool,
+    },
+    /// Remove a single applied extension or privacy setting
+    ///
+    /// Useful for troubleshooting; the item comes back the next time the
+    /// policy is applied.
+    Remove {
+        /// Extension ID to remove
+        #[arg(long, conflicts_with = "setting", required_unless_present = "setting")]
+        extension: Option<String>,
+
+        /// Privacy setting to remove: private-mode (or incognito, inprivate,
+        /// private-browsing), guest-mode, history-deletion, sync or signin
+        #[arg(long)]
+        setting: Option<PrivacySetting>,
+
+        /// Only remove it from this browser (chrome, firefox or edge)
+        #[arg(long)]
+        browser: Option<Browser>,
+    },
+    /// Manage secrets stored in the OS credential store (requires admin)
+    Secret {
+        #[command(subcommand)]
+        command: SecretCommands,
+    },
+    /// Show recent agent notifications (failures, restored policies)
+    Inbox {
+        /// Only show unread notifications
+        #[arg(long)]
+        unread: bool,
+
+        /// Mark all notifications as read (requires admin)
+        #[arg(long, conflicts_with = "clear")]
+        mark_read: bool,
+
+        /// Remove all notifications (requires admin)
+        #[arg(long)]
+        clear: bool,
+    },
+    /// Summarize events suggesting the policies were bypassed (agent killed,
+    /// state file replaced, policy values removed)
+    Incidents {
+        /// How many days back to look
+        #[arg(long, default_value_t = 30)]
+        days: i64,
+    },
+    /// Show every policy value found where the browsers read machine
+    /// policies (registry, managed preferences, policy files), including
+    /// values set by something else such as Group Policy or MDM
+    Inspect,
+    /// Write an HTML report of applied policies, incidents and agent
+    /// notifications, e.g. for a weekly family review
+    Report {
+        /// File to write the report to
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// How many days back to cover
+        #[arg(long, default_value_t = 7)]
+        days: i64,
+
+        /// Cover the last week (same as --days 7)
+        #[arg(long, conflicts_with = "days")]
+        week: bool,
+    },
+    /// Check that the agent is installed, configured and enforcing policy
+    Doctor {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Remove the agent service, all policies, configuration and state
+    /// (requires admin)
+    Uninstall {
+        /// Also delete the inbox, incident log and agent log
+        #[arg(long)]
+        purge_history: bool,
+
+        /// Don't ask for confirmation
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+    /// Update the agent to the latest release (requires admin)
+    SelfUpdate {
+        /// Only check whether a newer release exists
+        #[arg(long)]
+        check: bool,
+    },
     /// Launch User UI (no admin required)
     UserUi {
         /// Run in system tray mode
@@ -74,6 +210,14 @@ pub enum Commands {
     AdminUi,
 }
 
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum OutputFormat {
+    /// Human-readable table
+    Text,
+    /// Machine-readable JSON
+    Json,
+}
+
 #[derive(Subcommand, Debug)]
 pub enum ConfigCommands {
     /// Initialize a new configuration file with examples
@@ -85,5 +229,45 @@ pub enum ConfigCommands {
         /// Overwrite existing file if it exists
         #[arg(short, long)]
         force: bool,
+
+        /// Start from a preset instead of the documented example:
+        /// young-child, teen or homework-mode
+        #[arg(long)]
+        preset: Option<String>,
+    },
+    /// Check a configuration file (the global --config) for errors
+    Validate {
+        /// Also look up every extension ID in the Chrome Web Store, Edge
+        /// Add-ons or addons.mozilla.org
+        #[arg(long)]
+        online: bool,
+    },
+    /// Show the agent configuration
+    Show {
+        /// Show the result of merging the config file, its family-policy.d/
+        /// drop-ins, FAMILY_POLICY_* environment variables and --set flags
+        #[arg(long)]
+        effective: bool,
+
+        /// Override a setting, e.g. `--set agent.poll_interval=600`
+        /// (with --effective)
+        #[arg(long = "set", value_name = "KEY=VALUE")]
+        set: Vec<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SecretCommands {
+    /// Store a secret, read from stdin (e.g. `secret set github-token`)
+    ///
+    /// Reference it in the agent configuration as "keychain:<name>".
+    Set {
+        /// Secret name
+        name: String,
+    },
+    /// Delete a stored secret
+    Delete {
+        /// Secret name
+        name: String,
     },
 }
@@ -1,6 +1,8 @@
 use anyhow::{Context, Result};
 
 use crate::agent;
+use crate::browser::{is_browser_available, Browser};
+use crate::core;
 use crate::platform;
 use crate::state;
 
@@ -132,13 +134,15 @@ pub fn install_service(verbose: bool) -> Result<()> {
         let bin_path = current_exe.to_string_lossy().to_string();
 
         // Service configuration
-        let service_name = "FamilyPolicyAgent";
+        let service_name = agent::service::SERVICE_NAME;
         let display_name = "Family Policy Agent";
         let description = "Browser Extension Policy Management - Automatically manages browser policies via GitHub polling";
 
         // Create service with sc.exe
-        // binPath must include the full command with arguments
-        let bin_path_with_args = format!("\"{}\" start --no-daemon", bin_path);
+        // binPath must include the full command with arguments. run-service
+        // speaks the service control protocol; a plain console process would
+        // be killed by the SCM for not responding.
+        let bin_path_with_args = format!("\"{}\" run-service", bin_path);
 
         let output = std::process::Command::new("sc.exe")
             .args(&["create", service_name])
@@ -165,6 +169,12 @@ pub fn install_service(verbose: bool) -> Result<()> {
             .args(&["failure", service_name, "reset= 86400", "actions= restart/10000/restart/10000/restart/10000"])
             .output();
 
+        // Also restart after an error exit, which is how the agent restarts
+        // into a new version after updating itself
+        let _ = std::process::Command::new("sc.exe")
+            .args(&["failureflag", service_name, "1"])
+            .output();
+
         println!("✓ Service recovery configured");
         println!();
         println!("Service installed successfully!");
@@ -191,6 +201,11 @@ pub fn uninstall_service(verbose: bool) -> Result<()> {
         std::process::exit(1);
     }
 
+    remove_service()
+}
+
+/// Stop the agent service and remove its registration
+pub(super) fn remove_service() -> Result<()> {
     println!("Uninstalling Family Policy Agent service");
     println!();
 
@@ -259,7 +274,7 @@ pub fn uninstall_service(verbose: bool) -> Result<()> {
 
     #[cfg(target_os = "windows")]
     {
-        let service_name = "FamilyPolicyAgent";
+        let service_name = agent::service::SERVICE_NAME;
 
         // Stop service first
         println!("Stopping service...");
@@ -303,13 +318,15 @@ pub fn uninstall_service(verbose: bool) -> Result<()> {
 /// Run as daemon (foreground mode)
 pub fn daemon(verbose: bool) -> Result<()> {
     // This is a convenience function that runs the agent in foreground mode
-    start(true, verbose)
+    start(true, &[], verbose)
 }
 
 /// Start agent daemon
-pub fn start(no_daemon: bool, verbose: bool) -> Result<()> {
-    // Initialize logging
-    init_logging(verbose);
+///
+/// `overrides` (`section.key=value`) take precedence over every other
+/// configuration layer; they only apply in the foreground, since the
+/// service loads its configuration itself.
+pub fn start(no_daemon: bool, overrides: &[String], verbose: bool) -> Result<()> {
     // Check for admin privileges
     if let Err(e) = platform::ensure_admin_privileges() {
         eprintln!("Insufficient privileges: {:#}", e);
@@ -324,8 +341,12 @@ pub fn start(no_daemon: bool, verbose: bool) -> Result<()> {
         println!();
 
         let config_path = agent::get_agent_config_path()?;
-        let config = agent::AgentConfig::load(&config_path)
-            .context("Failed to load agent configuration. Run 'family-policy setup' first.")?;
+        let config = agent::AgentConfig::load_layered(&config_path, overrides)
+            .context("Failed to load agent configuration. Run 'family-policy setup' first.")?
+            .config;
+
+        // Keep the guard alive so the log file is flushed on exit
+        let _log_guard = agent::init_agent_logging(&config.logging, verbose)?;
 
         // Run agent
         let runtime = tokio::runtime::Runtime::new()?;
@@ -333,6 +354,8 @@ pub fn start(no_daemon: bool, verbose: bool) -> Result<()> {
             agent::run_agent_daemon(config).await
         })
     } else {
+        init_logging(verbose);
+
         // Use system service instead of manual daemonization
         #[cfg(target_os = "linux")]
         {
@@ -373,7 +396,7 @@ pub fn start(no_daemon: bool, verbose: bool) -> Result<()> {
 
         #[cfg(target_os = "windows")]
         {
-            let service_name = "FamilyPolicyAgent";
+            let service_name = agent::service::SERVICE_NAME;
 
             println!("Starting Windows Service...");
             let output = std::process::Command::new("sc.exe")
@@ -406,6 +429,23 @@ pub fn start(no_daemon: bool, verbose: bool) -> Result<()> {
     }
 }
 
+/// Run under the Windows Service Control Manager
+///
+/// This is the command the installed service runs; `start --no-daemon` runs
+/// the agent in a console instead.
+pub fn run_service(verbose: bool) -> Result<()> {
+    #[cfg(target_os = "windows")]
+    {
+        agent::service::run(verbose)
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = verbose;
+        anyhow::bail!("run-service is only used by the Windows service; use 'family-policy start'")
+    }
+}
+
 /// Stop agent daemon
 pub fn stop(verbose: bool) -> Result<()> {
     // Initialize logging
@@ -456,7 +496,7 @@ pub fn stop(verbose: bool) -> Result<()> {
 
     #[cfg(target_os = "windows")]
     {
-        let service_name = "FamilyPolicyAgent";
+        let service_name = agent::service::SERVICE_NAME;
 
         let output = std::process::Command::new("sc.exe")
             .args(&["stop", service_name])
@@ -485,6 +525,9 @@ pub fn stop(verbose: bool) -> Result<()> {
 }
 
 /// Check for policy updates now
+///
+/// With `dry_run`, the remote policy is downloaded and the changes it would
+/// make are printed; nothing is applied and the state file isn't touched.
 pub fn check_now(dry_run: bool, verbose: bool) -> Result<()> {
     // Initialize logging
     init_logging(verbose);
@@ -510,27 +553,65 @@ pub fn check_now(dry_run: bool, verbose: bool) -> Result<()> {
         .context("Failed to load agent configuration. Run 'family-policy setup' first.")?;
 
     let runtime = tokio::runtime::Runtime::new()?;
-    let applied = runtime.block_on(async {
-        agent::check_and_apply_once(&config, dry_run).await
-    })?;
 
     if dry_run {
-        if applied {
-            println!("✓ Policy would be updated (dry-run)");
-        } else {
-            println!("✓ Policy unchanged");
+        // Show what the remote policy would change instead of applying it
+        match runtime.block_on(agent::preview_remote_policy(&config))? {
+            Some(diff) => {
+                println!();
+                core::diff::print_diff(&diff);
+            }
+            None => println!("✓ Policy unchanged"),
         }
+        return Ok(());
+    }
+
+    let applied = runtime.block_on(async {
+        agent::check_and_apply_once(&config, false).await
+    })?;
+
+    if applied {
+        println!("✓ Policy updated and applied successfully");
     } else {
-        if applied {
-            println!("✓ Policy updated and applied successfully");
-        } else {
-            println!("✓ Policy unchanged");
-        }
+        println!("✓ Policy unchanged");
     }
 
     Ok(())
 }
 
+/// Approve the policy change held by `confirm_before_apply` and apply it
+pub fn approve(verbose: bool) -> Result<()> {
+    init_logging(verbose);
+
+    let mut state = state::load_state()?.context("The agent hasn't checked for a policy yet")?;
+    let Some(pending) = state.approve_pending() else {
+        println!("No policy change is waiting for approval");
+        return Ok(());
+    };
+
+    println!("Approving policy {}, which removes or changes:", &pending.config_hash[..16]);
+    for removal in &pending.removals {
+        println!("  - {}", removal);
+    }
+    println!();
+    state::save_state(&state).context("Failed to save state")?;
+
+    let config_path = agent::get_agent_config_path()?;
+    let config = agent::AgentConfig::load(&config_path)
+        .context("Failed to load agent configuration. Run 'family-policy setup' first.")?;
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let applied = runtime.block_on(agent::check_and_apply_once(&config, false))?;
+
+    if applied {
+        println!("✓ Policy applied");
+    } else {
+        // The repo changed again; a new change is held if it removes anything
+        println!("The policy changed again since it was held; see 'family-policy status'");
+    }
+    Ok(())
+}
+
 /// Show agent status
 pub fn status(verbose: bool) -> Result<()> {
     // Initialize logging
@@ -556,6 +637,9 @@ pub fn status(verbose: bool) -> Result<()> {
                     last_checked.format("%Y-%m-%d %H:%M:%S %Z"),
                     format_duration(ago));
             }
+            if let Some(detection) = state.change_detection {
+                println!("Last result:   {}", detection.description());
+            }
 
             let ago = chrono::Utc::now() - state.last_updated;
             println!("Last updated:  {} ({} ago)",
@@ -564,6 +648,43 @@ pub fn status(verbose: bool) -> Result<()> {
 
             println!("Current hash:  {}...", &state.config_hash[..16]);
 
+            if let Some(failed) = &state.failed_apply {
+                println!();
+                if failed.quarantined {
+                    println!("⚠ Policy {}... is QUARANTINED after {} failed applies;",
+                        &failed.config_hash[..16], failed.failures);
+                    println!("  it won't be retried until the policy in the repo changes.");
+                } else {
+                    println!("⚠ Policy {}... failed to apply {} time(s)",
+                        &failed.config_hash[..16], failed.failures);
+                }
+                println!("  Last error: {}", failed.last_error);
+            }
+
+            if let Some(pending) = &state.pending_approval {
+                println!();
+                if pending.approved {
+                    println!("Policy {} approved, applied on the next check", &pending.config_hash[..16]);
+                } else {
+                    println!("⚠ Policy change waiting for approval since {} (run 'family-policy approve'):",
+                        pending.since.format("%Y-%m-%d %H:%M:%S %Z"));
+                    for removal in &pending.removals {
+                        println!("  - {}", removal);
+                    }
+                }
+            }
+
+            if let Some(failures) = &state.poll_failures {
+                println!();
+                println!("⚠ Last {} policy check(s) failed (since {})",
+                    failures.failures,
+                    failures.since.format("%Y-%m-%d %H:%M:%S %Z"));
+                if failures.failures >= config.agent.failure_threshold {
+                    println!("  Polling less often until a check succeeds.");
+                }
+                println!("  Last error: {}", failures.last_error);
+            }
+
             // Show applied policies
             println!();
             println!("Applied Configuration:");
@@ -579,6 +700,79 @@ pub fn status(verbose: bool) -> Result<()> {
                 let edge = state.applied_policies.edge.as_ref().unwrap();
                 println!("  Edge:       {} extensions", edge.extensions.len());
             }
+            if let Some(chromium) = &state.applied_policies.chromium {
+                println!("  Chromium:   {} extensions", chromium.extensions.len());
+            }
+            if let Some(vivaldi) = &state.applied_policies.vivaldi {
+                println!("  Vivaldi:    {} extensions", vivaldi.extensions.len());
+            }
+            if let Some(opera) = &state.applied_policies.opera {
+                println!("  Opera:      {} extensions", opera.extensions.len());
+            }
+            if let Some(safari) = &state.applied_policies.safari {
+                println!("  Safari:     {} blocked websites", safari.blocked_urls.len());
+            }
+
+            let installed: Vec<Browser> = Browser::ALL
+                .into_iter()
+                .filter(|b| is_browser_available(*b))
+                .collect();
+            let uncovered = state.applied_policies.uncovered(&installed);
+            if !uncovered.is_empty() {
+                println!();
+                for browser in uncovered {
+                    println!("⚠ {} detected but not covered by any policy entry", browser.name());
+                }
+            }
+
+            let conflicts = &state.applied_policies.conflicts;
+            if !conflicts.is_empty() {
+                println!();
+                println!("⚠ {} policy value(s) were also set by another management tool:", conflicts.len());
+                for conflict in conflicts {
+                    println!("  - {}", conflict);
+                }
+            }
+
+            let unverified = &state.applied_policies.unverified;
+            if !unverified.is_empty() {
+                println!();
+                println!("⚠ {} policy value(s) did not take effect when last applied:", unverified.len());
+                for problem in unverified {
+                    println!("  - {}", problem);
+                }
+                println!("  Another tool (MDM, Group Policy) may be overriding them.");
+            }
+
+            // The cached policy is only readable by the agent, so this is
+            // skipped when run as a regular user
+            let cached_policy = state::load_policy_cache()
+                .ok()
+                .flatten()
+                .and_then(|content| crate::config::Config::from_yaml_str(&content).ok());
+            if let Some(policy_config) = cached_policy {
+                let expired = policy_config.expired_policies(chrono::Utc::now());
+                if !expired.is_empty() {
+                    println!();
+                    println!("Expired Policies (ignored):");
+                    for policy in expired {
+                        if let Some(expires_at) = policy.expires_at {
+                            println!("  {} (expired {})",
+                                policy.name,
+                                expires_at.format("%Y-%m-%d %H:%M:%S %Z"));
+                        }
+                    }
+                }
+
+                let inactive = policy_config.inactive_policies(chrono::Utc::now());
+                if !inactive.is_empty() {
+                    println!();
+                    println!("Inactive Policies (ignored):");
+                    for (policy, reason) in inactive {
+                        println!("  {} ({})", policy.name, reason);
+                    }
+                }
+            }
 
             // Calculate next check time
             let scheduler = agent::PollingScheduler::new(
@@ -597,6 +791,24 @@ pub fn status(verbose: bool) -> Result<()> {
     Ok(())
 }
 
+/// " (from 'Policy')" for an applied item, or nothing for state written
+/// before sources were recorded
+fn source_suffix(browser: &state::BrowserState, key: &str) -> String {
+    browser
+        .sources
+        .get(key)
+        .map(|name| format!(" (from '{}')", name))
+        .unwrap_or_default()
+}
+
+fn signin_label(restriction: &crate::config::SigninRestriction) -> String {
+    match restriction {
+        crate::config::SigninRestriction::Restricted(true) => "DISABLED".to_string(),
+        crate::config::SigninRestriction::Restricted(false) => "enabled".to_string(),
+        crate::config::SigninRestriction::Pattern(pattern) => format!("only accounts matching {}", pattern),
+    }
+}
+
 /// Show currently applied configuration
 pub fn show_config(verbose: bool) -> Result<()> {
     // Initialize logging
@@ -616,17 +828,49 @@ pub fn show_config(verbose: bool) -> Result<()> {
     // Show applied policies
     let applied = state.applied_policies;
 
-    if let Some(chrome) = applied.chrome {
-        println!("Chrome:");
+    // The other Chromium-based browsers are set up exactly like Chrome
+    let chrome_like = [
+        ("Chrome", applied.chrome),
+        ("Chromium", applied.chromium),
+        ("Vivaldi", applied.vivaldi),
+        ("Opera", applied.opera),
+    ];
+    for (name, chrome) in chrome_like {
+        let Some(chrome) = chrome else {
+            continue;
+        };
+        println!("{}:", name);
         println!("  Extensions:");
         for ext_id in &chrome.extensions {
-            println!("    - {}", ext_id);
+            println!("    - {}{}", ext_id, source_suffix(&chrome, ext_id));
         }
         if let Some(disable) = chrome.disable_incognito {
-            println!("  Incognito mode: {}", if disable { "DISABLED" } else { "enabled" });
+            println!(
+                "  Incognito mode: {}{}",
+                if disable { "DISABLED" } else { "enabled" },
+                source_suffix(&chrome, "disable_incognito")
+            );
         }
         if let Some(disable) = chrome.disable_guest_mode {
-            println!("  Guest mode: {}", if disable { "DISABLED" } else { "enabled" });
+            println!(
+                "  Guest mode: {}{}",
+                if disable { "DISABLED" } else { "enabled" },
+                source_suffix(&chrome, "disable_guest_mode")
+            );
+        }
+        if let Some(disable) = chrome.disable_sync {
+            println!(
+                "  Sync: {}{}",
+                if disable { "DISABLED" } else { "enabled" },
+                source_suffix(&chrome, "disable_sync")
+            );
+        }
+        if let Some(restriction) = &chrome.restrict_signin {
+            println!(
+                "  Sign-in: {}{}",
+                signin_label(restriction),
+                source_suffix(&chrome, "restrict_signin")
+            );
         }
         println!();
     }
@@ -635,10 +879,21 @@ pub fn show_config(verbose: bool) -> Result<()> {
         println!("Firefox:");
         println!("  Extensions:");
         for ext_id in &firefox.extensions {
-            println!("    - {}", ext_id);
+            println!("    - {}{}", ext_id, source_suffix(&firefox, ext_id));
         }
         if let Some(disable) = firefox.disable_private_browsing {
-            println!("  Private browsing: {}", if disable { "DISABLED" } else { "enabled" });
+            println!(
+                "  Private browsing: {}{}",
+                if disable { "DISABLED" } else { "enabled" },
+                source_suffix(&firefox, "disable_private_browsing")
+            );
+        }
+        if let Some(disable) = firefox.disable_sync {
+            println!(
+                "  Firefox accounts: {}{}",
+                if disable { "DISABLED" } else { "enabled" },
+                source_suffix(&firefox, "disable_sync")
+            );
         }
         println!();
     }
@@ -647,13 +902,53 @@ pub fn show_config(verbose: bool) -> Result<()> {
         println!("Edge:");
         println!("  Extensions:");
         for ext_id in &edge.extensions {
-            println!("    - {}", ext_id);
+            println!("    - {}{}", ext_id, source_suffix(&edge, ext_id));
         }
         if let Some(disable) = edge.disable_inprivate {
-            println!("  InPrivate mode: {}", if disable { "DISABLED" } else { "enabled" });
+            println!(
+                "  InPrivate mode: {}{}",
+                if disable { "DISABLED" } else { "enabled" },
+                source_suffix(&edge, "disable_inprivate")
+            );
         }
         if let Some(disable) = edge.disable_guest_mode {
-            println!("  Guest mode: {}", if disable { "DISABLED" } else { "enabled" });
+            println!(
+                "  Guest mode: {}{}",
+                if disable { "DISABLED" } else { "enabled" },
+                source_suffix(&edge, "disable_guest_mode")
+            );
+        }
+        if let Some(disable) = edge.disable_sync {
+            println!(
+                "  Sync: {}{}",
+                if disable { "DISABLED" } else { "enabled" },
+                source_suffix(&edge, "disable_sync")
+            );
+        }
+        if let Some(restriction) = &edge.restrict_signin {
+            println!(
+                "  Sign-in: {}{}",
+                signin_label(restriction),
+                source_suffix(&edge, "restrict_signin")
+            );
+        }
+        println!();
+    }
+
+    if let Some(safari) = applied.safari {
+        println!("Safari:");
+        if let Some(disable) = safari.disable_private_browsing {
+            println!(
+                "  Private browsing: {}{}",
+                if disable { "DISABLED" } else { "enabled" },
+                source_suffix(&safari, "disable_private_browsing")
+            );
+        }
+        if !safari.blocked_urls.is_empty() {
+            println!("  Blocked websites:");
+            for url in &safari.blocked_urls {
+                println!("    - {}{}", url, source_suffix(&safari, url));
+            }
         }
         println!();
     }
@@ -1,14 +1,34 @@
 use anyhow::{Context, Result};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs;
 
-use crate::config::EXAMPLE_CONFIG;
+use crate::agent::{self, config::drop_in_files, AgentConfig};
+use crate::browser::Browser;
+use crate::config::{self, EXAMPLE_CONFIG};
+use crate::ui::extension_search;
 
-/// Initialize a new configuration file
-pub fn init(output: PathBuf, force: bool, _verbose: bool) -> Result<()> {
+use super::utils::init_logging;
+
+/// Initialize a new configuration file, from the documented example or a
+/// preset
+pub fn init(output: PathBuf, force: bool, preset: Option<&str>, _verbose: bool) -> Result<()> {
     println!("Family Policy - Configuration Initialization");
     println!();
 
+    let content = match preset {
+        Some(name) => {
+            let Some(preset) = config::preset(name) else {
+                let available: Vec<String> = config::PRESETS
+                    .iter()
+                    .map(|p| format!("  {:<14} {}", p.name, p.description))
+                    .collect();
+                anyhow::bail!("Unknown preset '{}'. Available presets:\n{}", name, available.join("\n"));
+            };
+            preset.content
+        }
+        None => EXAMPLE_CONFIG,
+    };
+
     // Check if file already exists
     if output.exists() && !force {
         anyhow::bail!(
@@ -19,10 +39,13 @@ pub fn init(output: PathBuf, force: bool, _verbose: bool) -> Result<()> {
     }
 
     // Write the example config to the file
-    fs::write(&output, EXAMPLE_CONFIG)
+    fs::write(&output, content)
         .with_context(|| format!("Failed to write configuration file to {}", output.display()))?;
 
-    println!("✓ Created example configuration file: {}", output.display());
+    match preset {
+        Some(name) => println!("✓ Created configuration file from the '{}' preset: {}", name, output.display()),
+        None => println!("✓ Created example configuration file: {}", output.display()),
+    }
     println!();
     println!("Next steps:");
     println!("  1. Edit the configuration file to match your needs");
@@ -34,3 +57,201 @@ pub fn init(output: PathBuf, force: bool, _verbose: bool) -> Result<()> {
 
     Ok(())
 }
+
+/// Validate a configuration file
+///
+/// With `online`, every extension ID is also looked up in its browser's
+/// store, so typos are caught before the policy is enforced.
+pub fn validate(path: &Path, online: bool, verbose: bool) -> Result<()> {
+    init_logging(verbose);
+
+    let config = config::load_config(path)
+        .with_context(|| format!("{} is not valid", path.display()))?;
+    println!("✓ {} is valid ({} policies)", path.display(), config.policies.len());
+
+    let mut warnings = config::extension_setting_conflicts(&config);
+    warnings.extend(
+        config
+            .expired_policies(chrono::Utc::now())
+            .iter()
+            .map(|p| format!("Policy '{}' has expired and will be ignored", p.name)),
+    );
+    for warning in &warnings {
+        println!("⚠ {}", warning);
+    }
+
+    if !online {
+        return Ok(());
+    }
+
+    println!();
+    println!("Checking extension IDs against the browser stores...");
+    println!();
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let client = extension_search::store_client()?;
+    let mut missing = 0;
+
+    for (browser, id, name) in extension_ids(&config) {
+        let result = runtime.block_on(extension_search::lookup_extension(&client, browser, &id));
+        match result {
+            Ok(Some(listing)) => {
+                let store_name = listing.name.as_deref().unwrap_or("(name unavailable)");
+                println!("✓ {:<8} {}  {} {}", browser.as_str(), id, store_name, listing.version);
+                if listing.name.as_deref().is_some_and(|n| !n.eq_ignore_ascii_case(&name)) {
+                    println!("  configured as '{}'", name);
+                }
+            }
+            Ok(None) => {
+                missing += 1;
+                println!("✗ {:<8} {}  not found (configured as '{}')", browser.as_str(), id, name);
+            }
+            Err(e) => {
+                println!("⚠ {:<8} {}  could not be checked: {:#}", browser.as_str(), id, e);
+            }
+        }
+    }
+
+    if missing > 0 {
+        anyhow::bail!("{} extension ID(s) not found in their store", missing);
+    }
+    Ok(())
+}
+
+/// Settings holding credentials, hidden unless they are keychain references
+const SECRET_SETTINGS: &[&str] = &["access_token", "ntfy_token", "pushover_token", "telegram_bot_token"];
+
+/// Show the agent configuration files, or with `effective` the
+/// configuration the agent would run with
+pub fn show(effective: bool, overrides: &[String], verbose: bool) -> Result<()> {
+    init_logging(verbose);
+
+    let path = agent::get_agent_config_path()?;
+    if !path.exists() {
+        anyhow::bail!("Agent is not configured (no {})", path.display());
+    }
+
+    if !effective {
+        if !overrides.is_empty() {
+            anyhow::bail!("--set only applies with --effective");
+        }
+        let mut files = vec![path.clone()];
+        files.extend(drop_in_files(&path)?);
+        for file in files {
+            let content = fs::read_to_string(&file)
+                .with_context(|| format!("Failed to read {}", file.display()))?;
+            let table: toml::Table = toml::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", file.display()))?;
+            println!("# {}", file.display());
+            println!("{}", toml::to_string_pretty(&redacted(table))?);
+        }
+        return Ok(());
+    }
+
+    let layered = AgentConfig::load_layered(&path, overrides)?;
+    println!("# Effective agent configuration, merged from (lowest precedence first):");
+    for source in &layered.sources {
+        println!("#   {}", source);
+    }
+    println!();
+    let table = toml::Table::try_from(&layered.config).context("Failed to serialize config")?;
+    print!("{}", toml::to_string_pretty(&redacted(table))?);
+    Ok(())
+}
+
+/// `table` with credentials replaced, so it can be shown or pasted into a
+/// bug report
+fn redacted(mut table: toml::Table) -> toml::Table {
+    for (key, value) in table.iter_mut() {
+        match value {
+            toml::Value::Table(section) => *section = redacted(std::mem::take(section)),
+            toml::Value::String(secret)
+                if SECRET_SETTINGS.contains(&key.as_str()) && !secret.starts_with("keychain:") =>
+            {
+                *secret = "********".to_string();
+            }
+            _ => {}
+        }
+    }
+    table
+}
+
+/// Each configured (browser, extension ID, name) once, in config order
+fn extension_ids(config: &config::Config) -> Vec<(Browser, String, String)> {
+    let mut ids: Vec<(Browser, String, String)> = Vec::new();
+    for policy in &config.policies {
+        for ext in &policy.extensions {
+            for browser in policy.browsers.iter().filter(|b| b.supports_extensions()) {
+                let Some(id) = ext.id.get_id(*browser) else {
+                    continue;
+                };
+                if !ids.iter().any(|(b, i, _)| b == browser && i == id) {
+                    ids.push((*browser, id.to_string(), ext.name.clone()));
+                }
+            }
+        }
+    }
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacted_hides_tokens_but_not_keychain_references() {
+        let table: toml::Table = toml::from_str(
+            r#"
+[github]
+policy_url = "https://raw.githubusercontent.com/user/repo/main/policy.yaml"
+access_token = "ghp_secret"
+
+[notifications]
+ntfy_token = "keychain:ntfy"
+telegram_chat_id = "12345"
+"#,
+        )
+        .unwrap();
+
+        let table = redacted(table);
+
+        assert_eq!(table["github"]["access_token"].as_str(), Some("********"));
+        assert!(table["github"]["policy_url"].as_str().unwrap().starts_with("https://"));
+        assert_eq!(table["notifications"]["ntfy_token"].as_str(), Some("keychain:ntfy"));
+        assert_eq!(table["notifications"]["telegram_chat_id"].as_str(), Some("12345"));
+    }
+
+    #[test]
+    fn extension_ids_are_listed_once_per_browser() {
+        let config = config::Config::from_yaml_str(
+            r#"
+policies:
+  - name: Blocking
+    browsers: [chrome, firefox]
+    extensions:
+      - name: uBlock Origin
+        id:
+          chrome: cjpalhdlnbpafiamejdnhcphjbkeiagm
+          firefox: uBlock0@raymondhill.net
+  - name: Blocking again
+    browsers: [chrome]
+    extensions:
+      - name: uBlock Origin
+        id: cjpalhdlnbpafiamejdnhcphjbkeiagm
+"#,
+        )
+        .unwrap();
+
+        let ids: Vec<_> = extension_ids(&config)
+            .into_iter()
+            .map(|(browser, id, _)| (browser, id))
+            .collect();
+        assert_eq!(
+            ids,
+            [
+                (Browser::Chrome, "cjpalhdlnbpafiamejdnhcphjbkeiagm".to_string()),
+                (Browser::Firefox, "uBlock0@raymondhill.net".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,358 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::agent::{self, AgentConfig, GitHubPoller, PolicyFetchResult};
+use crate::browser::{current_platform, find_unmanaged_browsers, is_browser_available, user_home_dirs, Browser};
+use crate::cli::OutputFormat;
+use crate::platform;
+use crate::policy;
+use crate::state;
+
+use super::utils::init_logging;
+
+/// The system clock is considered wrong if it is before this
+const EARLIEST_SANE_TIME: &str = "2025-01-01T00:00:00Z";
+
+/// Clock drift tolerated before a recorded state timestamp
+const CLOCK_TOLERANCE_MINUTES: i64 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+    /// Couldn't be checked (not configured, or needs admin)
+    Skip,
+}
+
+impl Status {
+    fn label(&self) -> &'static str {
+        match self {
+            Status::Pass => "✓ PASS",
+            Status::Warn => "⚠ WARN",
+            Status::Fail => "✗ FAIL",
+            Status::Skip => "- SKIP",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Self { name, status, detail: detail.into() }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    version: &'static str,
+    healthy: bool,
+    checks: Vec<Check>,
+}
+
+/// Check that the agent is installed, configured and enforcing policy
+///
+/// Exits with an error if any check fails, so monitoring can alert on it.
+pub fn run(format: OutputFormat, verbose: bool) -> Result<()> {
+    init_logging(verbose);
+    let admin = platform::ensure_admin_privileges().is_ok();
+
+    let mut checks = vec![check_service()];
+
+    let config = match load_config() {
+        Ok(Some(config)) => {
+            checks.push(Check::new("Configuration", Status::Pass, "agent configuration is valid"));
+            Some(config)
+        }
+        Ok(None) => {
+            checks.push(Check::new("Configuration", Status::Warn, "agent not configured (local mode only)"));
+            None
+        }
+        Err(e) => {
+            checks.push(Check::new("Configuration", Status::Fail, format!("{:#}", e)));
+            None
+        }
+    };
+
+    checks.push(check_policy_url(config.as_ref()));
+    checks.push(check_state_writable(admin));
+    checks.push(check_browsers());
+    checks.push(check_unmanaged_browsers());
+    checks.push(check_policies(admin));
+    checks.push(check_clock(Utc::now()));
+
+    let report = Report {
+        version: env!("CARGO_PKG_VERSION"),
+        healthy: checks.iter().all(|c| c.status != Status::Fail),
+        checks,
+    };
+
+    match format {
+        OutputFormat::Text => print_table(&report),
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&report).context("Failed to serialize report")?
+        ),
+    }
+
+    let failed = report.checks.iter().filter(|c| c.status == Status::Fail).count();
+    if failed > 0 {
+        anyhow::bail!("{} health check(s) failed", failed);
+    }
+    Ok(())
+}
+
+fn print_table(report: &Report) {
+    println!("Family Policy Health Check (version {})", report.version);
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    for check in &report.checks {
+        println!("{}  {:<16} {}", check.status.label(), check.name, check.detail);
+    }
+    println!();
+    if report.healthy {
+        println!("✓ Healthy");
+    } else {
+        println!("✗ Unhealthy");
+    }
+}
+
+/// The agent configuration, or `None` if the agent isn't set up
+fn load_config() -> Result<Option<AgentConfig>> {
+    let path = agent::get_agent_config_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    AgentConfig::load(&path).map(Some)
+}
+
+fn check_service() -> Check {
+    const NAME: &str = "Service";
+    match service_status() {
+        Ok(ServiceStatus::Running) => Check::new(NAME, Status::Pass, "installed and running"),
+        Ok(ServiceStatus::Stopped) => Check::new(NAME, Status::Fail, "installed but not running"),
+        Ok(ServiceStatus::NotInstalled) => Check::new(
+            NAME,
+            Status::Warn,
+            "not installed (run 'family-policy install-service' for remote policies)",
+        ),
+        Err(e) => Check::new(NAME, Status::Skip, format!("{:#}", e)),
+    }
+}
+
+enum ServiceStatus {
+    NotInstalled,
+    Stopped,
+    Running,
+}
+
+#[cfg(target_os = "linux")]
+fn service_status() -> Result<ServiceStatus> {
+    if !std::path::Path::new("/etc/systemd/system/family-policy-agent.service").exists() {
+        return Ok(ServiceStatus::NotInstalled);
+    }
+    let active = std::process::Command::new("systemctl")
+        .args(["is-active", "--quiet", "family-policy-agent"])
+        .status()
+        .context("Failed to run systemctl")?;
+    Ok(if active.success() { ServiceStatus::Running } else { ServiceStatus::Stopped })
+}
+
+#[cfg(target_os = "macos")]
+fn service_status() -> Result<ServiceStatus> {
+    if !std::path::Path::new("/Library/LaunchDaemons/com.family-policy.agent.plist").exists() {
+        return Ok(ServiceStatus::NotInstalled);
+    }
+    let output = std::process::Command::new("launchctl")
+        .args(["list", "com.family-policy.agent"])
+        .output()
+        .context("Failed to run launchctl")?;
+    let running = output.status.success() && String::from_utf8_lossy(&output.stdout).contains("\"PID\"");
+    Ok(if running { ServiceStatus::Running } else { ServiceStatus::Stopped })
+}
+
+#[cfg(target_os = "windows")]
+fn service_status() -> Result<ServiceStatus> {
+    let output = std::process::Command::new("sc.exe")
+        .args(["query", agent::service::SERVICE_NAME])
+        .output()
+        .context("Failed to run sc.exe")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // 1060: the service doesn't exist
+    Ok(if stdout.contains("1060") {
+        ServiceStatus::NotInstalled
+    } else if stdout.contains("RUNNING") {
+        ServiceStatus::Running
+    } else {
+        ServiceStatus::Stopped
+    })
+}
+
+fn check_policy_url(config: Option<&AgentConfig>) -> Check {
+    const NAME: &str = "Policy URL";
+    let Some(config) = config else {
+        return Check::new(NAME, Status::Skip, "agent not configured");
+    };
+
+    let result = GitHubPoller::new(config.github.clone(), &config.agent).and_then(|poller| {
+        let runtime = tokio::runtime::Runtime::new()?;
+        runtime.block_on(poller.fetch_policy(None, None))
+    });
+
+    match result {
+        Ok(PolicyFetchResult::Updated { content, .. }) => Check::new(
+            NAME,
+            Status::Pass,
+            format!("{} reachable ({} bytes)", config.github.policy_url, content.len()),
+        ),
+        Ok(PolicyFetchResult::NotModified) => {
+            Check::new(NAME, Status::Pass, format!("{} reachable", config.github.policy_url))
+        }
+        Err(e) => Check::new(NAME, Status::Fail, format!("{:#}", e)),
+    }
+}
+
+fn check_state_writable(admin: bool) -> Check {
+    const NAME: &str = "State directory";
+    if !admin {
+        return Check::new(NAME, Status::Skip, "run as admin to check");
+    }
+
+    let result = state::get_state_path().and_then(|path| {
+        let dir = path.parent().context("State file has no directory")?.to_path_buf();
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let probe = dir.join(".doctor-write-test");
+        std::fs::write(&probe, b"ok").with_context(|| format!("{} is not writable", dir.display()))?;
+        let _ = std::fs::remove_file(&probe);
+        Ok(dir)
+    });
+
+    match result {
+        Ok(dir) => Check::new(NAME, Status::Pass, format!("{} is writable", dir.display())),
+        Err(e) => Check::new(NAME, Status::Fail, format!("{:#}", e)),
+    }
+}
+
+fn check_browsers() -> Check {
+    const NAME: &str = "Browsers";
+    let found: Vec<&str> = Browser::ALL
+        .into_iter()
+        .filter(|b| is_browser_available(*b))
+        .map(|b| b.as_str())
+        .collect();
+
+    if found.is_empty() {
+        Check::new(NAME, Status::Warn, "no supported browser found")
+    } else {
+        Check::new(NAME, Status::Pass, found.join(", "))
+    }
+}
+
+fn check_unmanaged_browsers() -> Check {
+    const NAME: &str = "Other browsers";
+    // Opera and Vivaldi are managed if the last applied policy lists them
+    let managed = state::load_policy_cache()
+        .ok()
+        .flatten()
+        .and_then(|content| crate::config::Config::from_yaml_str(&content).ok())
+        .map(|config| config.browsers())
+        .unwrap_or_default();
+    let found: Vec<String> = find_unmanaged_browsers(&user_home_dirs())
+        .into_iter()
+        .filter(|(browser, _)| !browser.managed_as().is_some_and(|b| managed.contains(&b)))
+        .map(|(browser, path)| format!("{} at {}", browser.name(), path.display()))
+        .collect();
+
+    if found.is_empty() {
+        Check::new(NAME, Status::Pass, "none found")
+    } else {
+        Check::new(NAME, Status::Warn, format!("policies don't apply to {}", found.join(", ")))
+    }
+}
+
+/// Compare the policies on disk with the last policy the agent applied
+fn check_policies(admin: bool) -> Check {
+    const NAME: &str = "Policies";
+    if !admin {
+        return Check::new(NAME, Status::Skip, "run as admin to check");
+    }
+
+    let result = (|| -> Result<Option<usize>> {
+        let Some(content) = state::load_policy_cache()? else {
+            return Ok(None);
+        };
+        let config = crate::config::Config::from_yaml_str(&content)
+            .context("Invalid cached policy")?
+            .for_host(&agent::current_host());
+        let plan = policy::evaluate_policy(&config.effective(Utc::now()), current_platform())?;
+        let drift = policy::verify_plan(&plan)?;
+        if !drift.is_empty() {
+            anyhow::bail!("{} of {} policy values missing or changed", drift.len(), plan.writes.len());
+        }
+        Ok(Some(plan.writes.len()))
+    })();
+
+    match result {
+        Ok(Some(values)) => Check::new(NAME, Status::Pass, format!("{} policy values in place", values)),
+        Ok(None) => Check::new(NAME, Status::Warn, "no policy applied by the agent yet"),
+        Err(e) => Check::new(NAME, Status::Fail, format!("{:#}", e)),
+    }
+}
+
+fn check_clock(now: DateTime<Utc>) -> Check {
+    const NAME: &str = "Clock";
+    let earliest: DateTime<Utc> = EARLIEST_SANE_TIME.parse().expect("valid timestamp");
+    if now < earliest {
+        return Check::new(NAME, Status::Fail, format!("system time {} is in the past", now.to_rfc3339()));
+    }
+
+    // The agent recorded these with the clock at the time
+    let last_recorded = state::load_state()
+        .ok()
+        .flatten()
+        .map(|s| s.last_checked.unwrap_or(s.last_updated).max(s.last_updated));
+    if let Some(recorded) = last_recorded
+        && recorded - now > chrono::Duration::minutes(CLOCK_TOLERANCE_MINUTES)
+    {
+        return Check::new(
+            NAME,
+            Status::Fail,
+            format!("system time is before the agent's last check at {}", recorded.to_rfc3339()),
+        );
+    }
+
+    Check::new(NAME, Status::Pass, now.format("%Y-%m-%d %H:%M:%S %Z").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_before_release_is_rejected() {
+        let _state = state::use_test_state_dir(tempfile::tempdir().unwrap().path());
+        let past: DateTime<Utc> = "2001-01-01T00:00:00Z".parse().unwrap();
+        assert_eq!(check_clock(past).status, Status::Fail);
+        assert_eq!(check_clock(Utc::now()).status, Status::Pass);
+    }
+
+    #[test]
+    fn report_serializes_for_monitoring() {
+        let report = Report {
+            version: "1.2.3",
+            healthy: false,
+            checks: vec![Check::new("Clock", Status::Fail, "wrong")],
+        };
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["healthy"], false);
+        assert_eq!(json["checks"][0]["status"], "fail");
+    }
+}
@@ -0,0 +1,70 @@
+use anyhow::Result;
+
+use crate::inbox::{self, NotificationLevel};
+
+use super::utils::{format_duration, init_logging};
+
+/// Show recent notifications from the agent
+pub fn show(unread_only: bool, verbose: bool) -> Result<()> {
+    init_logging(verbose);
+    let inbox = inbox::load_inbox()?;
+
+    println!("Notifications ({} unread)", inbox.unread_count());
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+
+    let notifications: Vec<_> = inbox
+        .notifications
+        .iter()
+        .rev()
+        .filter(|n| !unread_only || !n.read)
+        .collect();
+
+    if notifications.is_empty() {
+        println!("No notifications");
+        return Ok(());
+    }
+
+    for n in notifications {
+        let marker = if n.read { " " } else { "●" };
+        let level = match n.level {
+            NotificationLevel::Info => "INFO",
+            NotificationLevel::Warning => "WARN",
+            NotificationLevel::Error => "ERROR",
+        };
+        let ago = chrono::Utc::now() - n.timestamp;
+
+        println!();
+        println!("{} [{}] #{} {}", marker, level, n.id, n.title);
+        println!("  {} ({} ago)",
+            n.timestamp.format("%Y-%m-%d %H:%M:%S %Z"),
+            format_duration(ago));
+        if !n.message.is_empty() {
+            println!("  {}", n.message);
+        }
+    }
+
+    Ok(())
+}
+
+/// Mark all notifications as read
+pub fn mark_read(verbose: bool) -> Result<()> {
+    init_logging(verbose);
+    let mut inbox = inbox::load_inbox()?;
+    let count = inbox.unread_count();
+    inbox.mark_all_read();
+    inbox::save_inbox(&inbox)?;
+
+    println!("✓ Marked {} notifications as read", count);
+    Ok(())
+}
+
+/// Remove all notifications
+pub fn clear(verbose: bool) -> Result<()> {
+    init_logging(verbose);
+    let mut inbox = inbox::load_inbox()?;
+    inbox.clear();
+    inbox::save_inbox(&inbox)?;
+
+    println!("✓ Inbox cleared");
+    Ok(())
+}
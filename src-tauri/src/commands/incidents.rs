@@ -0,0 +1,63 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+
+use crate::incidents::{self, IncidentKind, REPEATED_DRIFT_THRESHOLD};
+
+use super::utils::init_logging;
+
+/// Summarize suspicious events from the last `days` days
+pub fn show(days: i64, verbose: bool) -> Result<()> {
+    init_logging(verbose);
+    let log = incidents::load_incidents()?;
+    let since = Utc::now() - Duration::days(days);
+
+    println!("Incidents (last {} days)", days);
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+
+    let recent: Vec<_> = log.since(since).collect();
+    if recent.is_empty() {
+        println!("No incidents");
+        return Ok(());
+    }
+
+    for kind in [
+        IncidentKind::UnexpectedStop,
+        IncidentKind::StateMismatch,
+        IncidentKind::PolicyDrift,
+        IncidentKind::UnmanagedBrowser,
+        IncidentKind::HookRun,
+    ] {
+        let count = recent.iter().filter(|i| i.kind == kind).count();
+        println!("  {:<34} {}", kind.description(), count);
+    }
+
+    let bypasses = log.likely_bypasses(since);
+    let drift_count = recent.iter().filter(|i| i.kind == IncidentKind::PolicyDrift).count();
+    if !bypasses.is_empty() || drift_count >= REPEATED_DRIFT_THRESHOLD {
+        println!();
+    }
+    for (stop, tampering) in &bypasses {
+        println!(
+            "⚠ Likely bypass on {}: the agent was stopped, then {}",
+            stop.timestamp.format("%Y-%m-%d"),
+            tampering.kind.description().to_lowercase()
+        );
+    }
+    if drift_count >= REPEATED_DRIFT_THRESHOLD {
+        println!("⚠ Policies were tampered with {} times", drift_count);
+    }
+
+    println!();
+    for incident in recent.iter().rev() {
+        println!(
+            "{}  {}",
+            incident.timestamp.format("%Y-%m-%d %H:%M:%S %Z"),
+            incident.kind.description()
+        );
+        if !incident.detail.is_empty() {
+            println!("  {}", incident.detail);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use std::path::Path;
+
+use crate::agent;
+use crate::browser::current_platform;
+use crate::config::{self, Config};
+use crate::policy::{self, inspect::{InspectedLocation, ValueStatus}};
+use crate::state;
+
+use super::utils::init_logging;
+
+/// Print every policy value found in the browsers' policy locations
+///
+/// Values are compared with the agent's cached policy if it can be read
+/// (needs admin), otherwise with the local config file if there is one.
+pub fn run(config_path: &Path, verbose: bool) -> Result<()> {
+    init_logging(verbose);
+
+    let platform = current_platform();
+    let plan = match managed_policy(config_path)? {
+        Some((config, source)) => {
+            println!("Comparing with {}", source);
+            Some(policy::evaluate_policy(&config.effective(Utc::now()), platform)?)
+        }
+        None => {
+            println!("No policy to compare with; every value found is listed as set by something else");
+            None
+        }
+    };
+
+    let locations = policy::inspect::inspect(plan.as_ref(), platform);
+    for location in &locations {
+        print_location(location);
+    }
+
+    let count = |matches: fn(&ValueStatus) -> bool| {
+        locations
+            .iter()
+            .flat_map(|l| &l.values)
+            .filter(|v| matches(&v.status))
+            .count()
+    };
+    println!();
+    println!(
+        "{} managed values in place, {} changed, {} missing, {} set by something else",
+        count(|s| *s == ValueStatus::Managed),
+        count(|s| matches!(s, ValueStatus::Changed { .. })),
+        count(|s| matches!(s, ValueStatus::Missing { .. })),
+        count(|s| *s == ValueStatus::Foreign),
+    );
+    Ok(())
+}
+
+/// The policy whose values count as managed, and where it came from
+fn managed_policy(config_path: &Path) -> Result<Option<(Config, String)>> {
+    // Only readable by the agent's account
+    if let Ok(Some(content)) = state::load_policy_cache() {
+        let config = Config::from_yaml_str(&content)
+            .context("Invalid cached policy")?
+            .for_host(&agent::current_host());
+        return Ok(Some((config, "the policy last applied by the agent".to_string())));
+    }
+
+    if config_path.exists() {
+        let config = config::load_config(config_path)?;
+        return Ok(Some((config, config_path.display().to_string())));
+    }
+
+    Ok(None)
+}
+
+fn print_location(location: &InspectedLocation) {
+    println!();
+    println!("{} — {}", location.browser.as_str(), location.target);
+    if let Some(error) = &location.error {
+        println!("  ✗ Could not read: {}", error);
+        return;
+    }
+    if !location.exists {
+        println!("  (not present)");
+    } else if location.values.is_empty() {
+        println!("  (empty)");
+    }
+
+    for value in &location.values {
+        let actual = value.actual.as_ref().map(|v| v.to_string()).unwrap_or_default();
+        match &value.status {
+            ValueStatus::Managed => println!("  ✓ {} = {}", value.name, actual),
+            ValueStatus::Changed { expected } => {
+                println!("  ≠ {} = {} (expected {})", value.name, actual, expected)
+            }
+            ValueStatus::Missing { expected } => {
+                println!("  ✗ {} missing (expected {})", value.name, expected)
+            }
+            ValueStatus::Foreign => println!("  ? {} = {} (not set by family-policy)", value.name, actual),
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use anyhow::{Context, Result};
 use std::path::PathBuf;
 
+use crate::agent;
 use crate::browser;
 use crate::cli::Args;
 use crate::config;
@@ -56,7 +57,8 @@ fn install_policies(args: &LocalArgs) -> Result<()> {
     println!("Loading configuration from: {}", args.config.display());
 
     let config = config::load_config(&args.config)
-        .context("Failed to load configuration file")?;
+        .context("Failed to load configuration file")?
+        .for_host(&agent::current_host());
 
     if args.verbose {
         println!("Configuration loaded successfully");
@@ -76,7 +78,7 @@ fn install_policies(args: &LocalArgs) -> Result<()> {
         println!("DRY RUN MODE - No changes will be made");
         println!();
 
-        let diff = core::diff::generate_diff(&config, current_state.as_ref());
+        let diff = core::diff::generate_diff_with_conflicts(&config, current_state.as_ref())?;
         core::diff::print_diff(&diff);
 
         return Ok(());
@@ -109,6 +111,17 @@ fn install_policies(args: &LocalArgs) -> Result<()> {
         println!("  Edge: {} extensions, {} privacy settings",
             result.extensions_applied.edge,
             result.privacy_settings_applied.edge);
+        println!("  Chromium: {} extensions, {} privacy settings",
+            result.extensions_applied.chromium,
+            result.privacy_settings_applied.chromium);
+        println!("  Vivaldi: {} extensions, {} privacy settings",
+            result.extensions_applied.vivaldi,
+            result.privacy_settings_applied.vivaldi);
+        println!("  Opera: {} extensions, {} privacy settings",
+            result.extensions_applied.opera,
+            result.privacy_settings_applied.opera);
+        println!("  Safari: {} settings",
+            result.privacy_settings_applied.safari);
     }
 
     if !result.errors.is_empty() {
@@ -151,6 +164,17 @@ fn uninstall_policies(dry_run: bool) -> Result<()> {
         println!("  Edge: {} extensions, {} privacy settings",
             result.extensions_removed.edge,
             result.privacy_settings_removed.edge);
+        println!("  Chromium: {} extensions, {} privacy settings",
+            result.extensions_removed.chromium,
+            result.privacy_settings_removed.chromium);
+        println!("  Vivaldi: {} extensions, {} privacy settings",
+            result.extensions_removed.vivaldi,
+            result.privacy_settings_removed.vivaldi);
+        println!("  Opera: {} extensions, {} privacy settings",
+            result.extensions_removed.opera,
+            result.privacy_settings_removed.opera);
+        println!("  Safari: {} settings",
+            result.privacy_settings_removed.safari);
     } else {
         println!();
         println!("✓ All policies removed successfully");
@@ -165,6 +189,17 @@ fn uninstall_policies(dry_run: bool) -> Result<()> {
         println!("  Edge: {} extensions, {} privacy settings",
             result.extensions_removed.edge,
             result.privacy_settings_removed.edge);
+        println!("  Chromium: {} extensions, {} privacy settings",
+            result.extensions_removed.chromium,
+            result.privacy_settings_removed.chromium);
+        println!("  Vivaldi: {} extensions, {} privacy settings",
+            result.extensions_removed.vivaldi,
+            result.privacy_settings_removed.vivaldi);
+        println!("  Opera: {} extensions, {} privacy settings",
+            result.extensions_removed.opera,
+            result.privacy_settings_removed.opera);
+        println!("  Safari: {} settings",
+            result.privacy_settings_removed.safari);
     }
 
     if !result.errors.is_empty() {
@@ -0,0 +1,150 @@
+use anyhow::{Context, Result};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::agent;
+
+/// Log file the macOS launchd job writes stdout and stderr to
+#[cfg(target_os = "macos")]
+const LAUNCHD_LOG_FILE: &str = "/var/log/family-policy-agent.log";
+
+/// Show the agent's recent log output
+///
+/// Reads `logging.file` if one is configured, otherwise wherever the service
+/// manager puts the agent's output on this platform.
+pub fn show(lines: usize, follow: bool) -> Result<()> {
+    let configured_file = agent::get_agent_config_path()
+        .and_then(|path| agent::AgentConfig::load(&path))
+        .ok()
+        .and_then(|config| config.logging.file);
+
+    if let Some(path) = configured_file {
+        return show_file(&path, lines, follow);
+    }
+
+    show_system_log(lines, follow)
+}
+
+#[cfg(target_os = "linux")]
+fn show_system_log(lines: usize, follow: bool) -> Result<()> {
+    let mut command = std::process::Command::new("journalctl");
+    command
+        .args(["-u", "family-policy-agent", "--no-pager", "-n"])
+        .arg(lines.to_string());
+    if follow {
+        command.arg("--follow");
+    }
+
+    let status = command.status().context("Failed to run journalctl")?;
+    if !status.success() {
+        anyhow::bail!("journalctl exited with {}", status);
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn show_system_log(lines: usize, follow: bool) -> Result<()> {
+    follow_file(Path::new(LAUNCHD_LOG_FILE).to_path_buf(), lines, follow, || {
+        Ok(Some(Path::new(LAUNCHD_LOG_FILE).to_path_buf()))
+    })
+}
+
+#[cfg(target_os = "windows")]
+fn show_system_log(lines: usize, follow: bool) -> Result<()> {
+    if follow {
+        anyhow::bail!(
+            "Following the Event Log isn't supported; set logging.file in the agent \
+             configuration to use --tail"
+        );
+    }
+
+    // Only warnings and errors are written to the Event Log
+    let status = std::process::Command::new("wevtutil")
+        .args([
+            "qe",
+            "Application",
+            "/q:*[System[Provider[@Name='FamilyPolicy']]]",
+            "/rd:true",
+            "/f:text",
+        ])
+        .arg(format!("/c:{}", lines))
+        .status()
+        .context("Failed to run wevtutil")?;
+    if !status.success() {
+        anyhow::bail!("wevtutil exited with {}", status);
+    }
+    Ok(())
+}
+
+fn show_file(configured: &Path, lines: usize, follow: bool) -> Result<()> {
+    let current = agent::current_log_file(configured)?
+        .with_context(|| format!("No log file found for {}", configured.display()))?;
+
+    follow_file(current, lines, follow, || agent::current_log_file(configured))
+}
+
+/// Print the last `lines` lines of `path`, then keep printing new output if
+/// `follow` is set
+///
+/// `current` is polled to notice when the log has been rotated to a new file.
+fn follow_file(
+    mut path: PathBuf,
+    lines: usize,
+    follow: bool,
+    current: impl Fn() -> Result<Option<PathBuf>>,
+) -> Result<()> {
+    let content = std::fs::read(&path)
+        .with_context(|| format!("Failed to read log file: {}", path.display()))?;
+    print!("{}", last_lines(&String::from_utf8_lossy(&content), lines));
+
+    if !follow {
+        return Ok(());
+    }
+
+    let mut position = content.len() as u64;
+    loop {
+        std::thread::sleep(Duration::from_secs(1));
+
+        if let Some(latest) = current()?.filter(|latest| *latest != path) {
+            path = latest;
+            position = 0;
+        }
+
+        let Ok(mut file) = std::fs::File::open(&path) else {
+            continue;
+        };
+        let len = file.metadata()?.len();
+        if len < position {
+            // Truncated
+            position = 0;
+        }
+        if len == position {
+            continue;
+        }
+
+        file.seek(SeekFrom::Start(position))?;
+        let mut new = Vec::new();
+        file.read_to_end(&mut new)?;
+        position += new.len() as u64;
+        print!("{}", String::from_utf8_lossy(&new));
+    }
+}
+
+fn last_lines(content: &str, count: usize) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let start = lines.len().saturating_sub(count);
+    lines[start..].iter().map(|line| format!("{}\n", line)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_lines_keeps_the_end() {
+        assert_eq!(last_lines("a\nb\nc\n", 2), "b\nc\n");
+        assert_eq!(last_lines("a\nb", 5), "a\nb\n");
+        assert_eq!(last_lines("", 5), "");
+    }
+}
@@ -1,6 +1,18 @@
 pub mod agent;
 pub mod config;
+pub mod doctor;
+pub mod inbox;
+pub mod incidents;
+pub mod inspect;
 pub mod local;
+pub mod logs;
+pub mod package;
+pub mod provision;
+pub mod remove;
+pub mod report;
+pub mod secret;
+pub mod uninstall;
+pub mod update;
 pub mod utils;
 
 pub use local::run_local_mode;
@@ -0,0 +1,41 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+use crate::installer::{self, PackageTarget};
+
+use super::utils::init_logging;
+
+/// Write install scaffolding for a platform package
+pub fn run(target: PackageTarget, output: Option<PathBuf>, force: bool, verbose: bool) -> Result<()> {
+    init_logging(verbose);
+
+    let output = output.unwrap_or_else(|| PathBuf::from("dist").join(target.as_str()));
+    let written = installer::write_scaffolding(target, &output, force)?;
+
+    for path in &written {
+        println!("✓ Wrote {}", path.display());
+    }
+    println!();
+    println!("Next steps:");
+    match target {
+        PackageTarget::Msi => {
+            println!("  Add {} to the wix/ directory and reference", output.join("service.wxs").display());
+            println!("  <ComponentGroupRef Id=\"FamilyPolicyService\" /> from the main Feature,");
+            println!("  in place of the binary's own component (see the comment in the file)");
+        }
+        PackageTarget::Pkg => {
+            println!("  pkgbuild --scripts {} ...", output.join("scripts").display());
+            println!("  with com.family-policy.agent.plist installed to /Library/LaunchDaemons/");
+        }
+        PackageTarget::Deb => {
+            println!("  Copy {} into the package root", output.join("DEBIAN").display());
+            println!("  and family-policy-agent.service to /lib/systemd/system/");
+        }
+        PackageTarget::Rpm => {
+            println!("  Use the files in {} as the spec's", output.join("scriptlets").display());
+            println!("  %post, %preun and %postun, and install family-policy-agent.service");
+            println!("  to %{{_unitdir}}");
+        }
+    }
+    Ok(())
+}
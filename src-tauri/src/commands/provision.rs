@@ -0,0 +1,155 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::agent::{self, secrets, AgentConfig};
+
+use super::utils::init_logging;
+
+/// A provisioning file: everything needed to set up a machine in one go
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Provisioning {
+    /// Agent settings, with the same sections as agent.conf
+    agent_config: toml::Table,
+
+    /// Secrets to store in the OS credential store, by name, for
+    /// `keychain:<name>` references in `agent_config`
+    #[serde(default)]
+    secrets: BTreeMap<String, String>,
+
+    /// Install and start the agent service
+    #[serde(default = "default_true")]
+    install_service: bool,
+
+    /// Download and apply the policy before returning
+    #[serde(default = "default_true")]
+    apply_policy: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Provisioning {
+    fn from_yaml_str(content: &str) -> Result<Self> {
+        let provisioning: Provisioning = serde_yaml::from_str(content)?;
+        for name in provisioning.secrets.keys() {
+            secrets::validate_name(name)?;
+        }
+        Ok(provisioning)
+    }
+}
+
+/// Set up the agent on this machine from a provisioning file
+///
+/// Writes the agent configuration, stores its secrets, applies the policy
+/// and installs the service, stopping at the first step that fails.
+pub fn run(from: &Path, force: bool, verbose: bool) -> Result<()> {
+    init_logging(verbose);
+
+    let content = std::fs::read_to_string(from)
+        .with_context(|| format!("Failed to read {}", from.display()))?;
+    let provisioning = Provisioning::from_yaml_str(&content)
+        .with_context(|| format!("{} is not a valid provisioning file", from.display()))?;
+
+    let config = AgentConfig::from_settings(provisioning.agent_config)?;
+    config.validate()?;
+
+    let path = agent::get_agent_config_path()?;
+    if path.exists() && !force {
+        anyhow::bail!(
+            "The agent is already configured ({})\nUse --force to replace its configuration",
+            path.display()
+        );
+    }
+
+    println!("Provisioning Family Policy Agent from {}", from.display());
+    println!();
+
+    for (name, value) in &provisioning.secrets {
+        secrets::set_secret(name, value)?;
+        println!("✓ Stored secret '{}'", name);
+    }
+
+    config.save(&path)?;
+    println!("✓ Wrote agent configuration to {}", path.display());
+
+    if provisioning.apply_policy {
+        println!("Applying policy from {}...", config.github.policy_url);
+        let runtime = tokio::runtime::Runtime::new()?;
+        // Layered like the service will load it (drop-ins, environment)
+        let config = AgentConfig::load(&path)?;
+        runtime
+            .block_on(agent::check_and_apply_once(&config, false))
+            .context("Failed to apply the initial policy")?;
+        println!("✓ Policy applied");
+    }
+
+    if provisioning.install_service {
+        println!();
+        super::agent::install_service(verbose)?;
+        // The LaunchDaemon is started when it is installed
+        #[cfg(not(target_os = "macos"))]
+        {
+            println!();
+            super::agent::start(false, &[], verbose)?;
+        }
+    }
+
+    println!();
+    println!("✓ Provisioning complete");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn provisioning_file_fills_in_defaults() {
+        let provisioning = Provisioning::from_yaml_str(
+            r#"
+agent_config:
+  github:
+    policy_url: https://raw.githubusercontent.com/user/repo/main/policy.yaml
+    access_token: keychain:github-token
+  agent:
+    poll_interval: 600
+    tags: [kids, laptop]
+secrets:
+  github-token: ghp_example
+apply_policy: false
+"#,
+        )
+        .unwrap();
+
+        assert!(provisioning.install_service);
+        assert!(!provisioning.apply_policy);
+        assert_eq!(provisioning.secrets["github-token"], "ghp_example");
+
+        let config = AgentConfig::from_settings(provisioning.agent_config).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.github.access_token.as_deref(), Some("keychain:github-token"));
+        assert_eq!(config.agent.poll_interval, 600);
+        assert_eq!(config.agent.poll_jitter, 60);
+        assert_eq!(config.agent.tags, ["kids", "laptop"]);
+        assert_eq!(config.logging.level, "info");
+    }
+
+    #[test]
+    fn provisioning_file_rejects_bad_secret_names() {
+        let result = Provisioning::from_yaml_str(
+            r#"
+agent_config:
+  github:
+    policy_url: https://raw.githubusercontent.com/user/repo/main/policy.yaml
+secrets:
+  GitHub_Token: ghp_example
+"#,
+        );
+
+        assert!(result.is_err());
+    }
+}
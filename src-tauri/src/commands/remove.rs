@@ -0,0 +1,66 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::agent;
+use crate::browser::Browser;
+use crate::config::{self, PrivacySetting};
+use crate::core::apply::{remove_item, RemovalTarget};
+use crate::state;
+
+use super::utils::init_logging;
+
+/// Remove a single applied extension or privacy setting
+///
+/// The agent's cached policy is used when there is one, otherwise the local
+/// configuration file. The item comes back on the next apply of that policy.
+pub fn run(
+    config_path: &Path,
+    extension: Option<String>,
+    setting: Option<PrivacySetting>,
+    browser: Option<Browser>,
+    dry_run: bool,
+    verbose: bool,
+) -> Result<()> {
+    init_logging(verbose);
+
+    let target = match (extension, setting) {
+        (Some(id), None) => RemovalTarget::Extension(id),
+        (None, Some(setting)) => RemovalTarget::Setting(setting),
+        _ => anyhow::bail!("Specify exactly one of --extension or --setting"),
+    };
+
+    let config = match state::load_policy_cache()? {
+        Some(content) => {
+            println!("Using the agent's cached policy");
+            config::Config::from_yaml_str(&content).context("Invalid cached policy")?
+        }
+        None => {
+            println!("Using configuration from: {}", config_path.display());
+            config::load_config(config_path).context("Failed to load configuration file")?
+        }
+    }
+    .for_host(&agent::current_host());
+
+    let browsers = match browser {
+        Some(browser) => vec![browser],
+        None => Browser::ALL.to_vec(),
+    };
+
+    if dry_run {
+        println!("DRY RUN MODE - No changes will be made");
+    }
+    println!();
+
+    let removed = remove_item(&config, &target, &browsers, dry_run)?;
+    let names: Vec<&str> = removed.iter().map(|b| b.as_str()).collect();
+
+    println!();
+    if dry_run {
+        println!("Would remove {} from {}", target, names.join(", "));
+    } else {
+        println!("✓ Removed {} from {}", target, names.join(", "));
+        println!("  It will be restored the next time the full policy is applied.");
+    }
+
+    Ok(())
+}
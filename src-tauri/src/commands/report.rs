@@ -0,0 +1,257 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::browser::Browser;
+use crate::config::PrivacySetting;
+use crate::inbox::{self, Notification};
+use crate::incidents::{self, Incident, IncidentKind};
+use crate::state::{self, State};
+
+use super::utils::init_logging;
+
+const STYLE: &str = "\
+body { font-family: sans-serif; max-width: 50em; margin: 2em auto; color: #222; }
+h1 { margin-bottom: 0; }
+.period { color: #666; margin-top: 0.2em; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 1em; }
+th, td { text-align: left; padding: 0.3em 0.6em; border-bottom: 1px solid #ddd; vertical-align: top; }
+.bar { background: #c0392b; height: 1em; min-width: 1px; }
+.warning { color: #b9770e; }
+.error { color: #c0392b; }
+@media print { body { margin: 0; } }
+";
+
+/// Write an HTML report of the last `days` days to `output`
+pub fn run(days: i64, output: &Path, verbose: bool) -> Result<()> {
+    init_logging(verbose);
+    let now = Utc::now();
+    let since = now - Duration::days(days);
+
+    let incident_log = incidents::load_incidents()?;
+    let inbox = inbox::load_inbox()?;
+    let state = state::load_state()?;
+
+    let html = render(
+        &crate::agent::current_host().hostname,
+        since,
+        now,
+        state.as_ref(),
+        &incident_log.since(since).collect::<Vec<_>>(),
+        &inbox.notifications.iter().filter(|n| n.timestamp >= since).collect::<Vec<_>>(),
+    );
+
+    fs::write(output, html).with_context(|| format!("Failed to write {}", output.display()))?;
+    println!("Report written to {}", output.display());
+    Ok(())
+}
+
+fn render(
+    hostname: &str,
+    since: DateTime<Utc>,
+    now: DateTime<Utc>,
+    state: Option<&State>,
+    incidents: &[&Incident],
+    notifications: &[&Notification],
+) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">\
+         <title>Family Policy report for {host}</title><style>{STYLE}</style></head><body>\n\
+         <h1>Family Policy report for {host}</h1>\n\
+         <p class=\"period\">{} to {}</p>\n",
+        since.format("%Y-%m-%d"),
+        now.format("%Y-%m-%d"),
+        host = escape(hostname),
+    );
+
+    render_policies(&mut html, state);
+    render_incidents(&mut html, incidents);
+    render_notifications(&mut html, notifications);
+
+    let _ = write!(
+        html,
+        "<p class=\"period\">Generated {}</p>\n</body></html>\n",
+        now.format("%Y-%m-%d %H:%M:%S %Z")
+    );
+    html
+}
+
+fn render_policies(html: &mut String, state: Option<&State>) {
+    html.push_str("<h2>Applied policies</h2>\n");
+    let Some(state) = state else {
+        html.push_str("<p>No policy has been applied on this machine.</p>\n");
+        return;
+    };
+
+    let _ = writeln!(
+        html,
+        "<p>Last changed {}</p>",
+        state.last_updated.format("%Y-%m-%d %H:%M:%S %Z")
+    );
+    html.push_str("<table><tr><th>Browser</th><th>Extensions</th><th>Restricted</th></tr>\n");
+    for browser in Browser::ALL {
+        let Some(browser_state) = state.applied_policies.get(browser) else {
+            continue;
+        };
+        let extensions: Vec<String> = browser_state
+            .extensions
+            .iter()
+            .map(|id| match browser_state.sources.get(id) {
+                Some(source) => format!("{} ({})", escape(id), escape(source)),
+                None => escape(id),
+            })
+            .collect();
+        let restricted: Vec<&str> = PrivacySetting::ALL
+            .into_iter()
+            .filter(|setting| browser_state.privacy_setting(*setting, browser) == Some(true))
+            .map(|setting| setting.as_str())
+            .collect();
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+            browser.as_str(),
+            or_none(&extensions.join("<br>")),
+            or_none(&restricted.join(", ")),
+        );
+    }
+    html.push_str("</table>\n");
+
+    if let Some(pending) = &state.pending_approval
+        && !pending.approved
+    {
+        let _ = writeln!(
+            html,
+            "<p class=\"warning\">A policy change has been waiting for approval since {}.</p>",
+            pending.since.format("%Y-%m-%d")
+        );
+    }
+}
+
+fn render_incidents(html: &mut String, incidents: &[&Incident]) {
+    html.push_str("<h2>Incidents</h2>\n");
+    if incidents.is_empty() {
+        html.push_str("<p>No incidents.</p>\n");
+        return;
+    }
+
+    let kinds = [
+        IncidentKind::UnexpectedStop,
+        IncidentKind::StateMismatch,
+        IncidentKind::PolicyDrift,
+        IncidentKind::UnmanagedBrowser,
+        IncidentKind::HookRun,
+    ];
+    let counts: Vec<usize> = kinds
+        .iter()
+        .map(|kind| incidents.iter().filter(|i| i.kind == *kind).count())
+        .collect();
+    let max = counts.iter().copied().max().unwrap_or(0).max(1);
+
+    html.push_str("<table>\n");
+    for (kind, count) in kinds.iter().zip(counts) {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td style=\"width: 50%\">\
+             <div class=\"bar\" style=\"width: {}%\"></div></td></tr>",
+            kind.description(),
+            count,
+            count * 100 / max,
+        );
+    }
+    html.push_str("</table>\n<table><tr><th>When</th><th>What</th></tr>\n");
+    for incident in incidents.iter().rev() {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{}<br>{}</td></tr>",
+            incident.timestamp.format("%Y-%m-%d %H:%M"),
+            incident.kind.description(),
+            escape(&incident.detail),
+        );
+    }
+    html.push_str("</table>\n");
+}
+
+fn render_notifications(html: &mut String, notifications: &[&Notification]) {
+    html.push_str("<h2>Agent notifications</h2>\n");
+    if notifications.is_empty() {
+        html.push_str("<p>No notifications.</p>\n");
+        return;
+    }
+
+    html.push_str("<table><tr><th>When</th><th>What</th></tr>\n");
+    for notification in notifications.iter().rev() {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td class=\"{}\"><b>{}</b><br>{}</td></tr>",
+            notification.timestamp.format("%Y-%m-%d %H:%M"),
+            notification.level.as_str(),
+            escape(&notification.title),
+            escape(&notification.message),
+        );
+    }
+    html.push_str("</table>\n");
+}
+
+fn or_none(cell: &str) -> &str {
+    if cell.is_empty() { "none" } else { cell }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inbox::NotificationLevel;
+    use crate::state::BrowserState;
+
+    #[test]
+    fn renders_policies_incidents_and_notifications() {
+        let now = Utc::now();
+        let mut state = State::new_agent();
+        let mut chrome = BrowserState::new();
+        chrome.extensions.push("ddkjiahejlhfcafbddmgiahcphecmpfh".to_string());
+        chrome.disable_incognito = Some(true);
+        state.applied_policies.chrome = Some(chrome);
+
+        let incident = Incident {
+            timestamp: now,
+            kind: IncidentKind::UnmanagedBrowser,
+            detail: "Opera found at <home>".to_string(),
+        };
+        let notification = Notification {
+            id: 1,
+            timestamp: now,
+            level: NotificationLevel::Warning,
+            title: "Policies restored".to_string(),
+            message: "3 policy values were re-applied".to_string(),
+            read: false,
+        };
+
+        let html = render("kids-pc", now - Duration::days(7), now, Some(&state), &[&incident], &[&notification]);
+
+        assert!(html.contains("Family Policy report for kids-pc"));
+        assert!(html.contains("<td>chrome</td><td>ddkjiahejlhfcafbddmgiahcphecmpfh</td><td>private-mode</td>"));
+        assert!(html.contains("<td>Unmanaged browser installed</td><td>1</td>"));
+        assert!(html.contains("Opera found at &lt;home&gt;"));
+        assert!(html.contains("Policies restored"));
+    }
+
+    #[test]
+    fn renders_without_state_or_events() {
+        let now = Utc::now();
+        let html = render("kids-pc", now - Duration::days(7), now, None, &[], &[]);
+
+        assert!(html.contains("No policy has been applied"));
+        assert!(html.contains("No incidents."));
+        assert!(html.contains("No notifications."));
+    }
+}
@@ -0,0 +1,122 @@
+use anyhow::{Context, Result};
+use std::io::{BufRead, IsTerminal, Write};
+
+use crate::agent::secrets;
+
+use super::utils::init_logging;
+
+/// Store a secret in the OS credential store
+///
+/// The value is read from stdin so it doesn't end up in shell history; when
+/// stdin is a terminal it is prompted for without echo.
+pub fn set(name: &str, verbose: bool) -> Result<()> {
+    init_logging(verbose);
+    secrets::validate_name(name)?;
+
+    let value = read_value(name)?;
+    if value.is_empty() {
+        anyhow::bail!("No value given for secret '{}'", name);
+    }
+
+    secrets::set_secret(name, &value)?;
+
+    println!("✓ Stored secret '{}'", name);
+    println!("  Reference it in the agent configuration as \"{}{}\"", secrets::REFERENCE_PREFIX, name);
+    Ok(())
+}
+
+/// Delete a secret from the OS credential store
+pub fn delete(name: &str, verbose: bool) -> Result<()> {
+    init_logging(verbose);
+
+    if secrets::delete_secret(name)? {
+        println!("✓ Deleted secret '{}'", name);
+    } else {
+        println!("No secret named '{}'", name);
+    }
+    Ok(())
+}
+
+fn read_value(name: &str) -> Result<String> {
+    let stdin = std::io::stdin();
+    let interactive = stdin.is_terminal();
+
+    if interactive {
+        eprint!("Value for '{}': ", name);
+        std::io::stderr().flush()?;
+    }
+
+    let mut line = String::new();
+    {
+        let _echo = interactive.then(echo::disable);
+        stdin.lock().read_line(&mut line).context("Failed to read secret value")?;
+    }
+
+    if interactive {
+        eprintln!();
+    }
+
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Turning terminal echo off while the secret is typed
+#[cfg(unix)]
+mod echo {
+    /// Restores echo when dropped
+    pub struct Guard(Option<libc::termios>);
+
+    pub fn disable() -> Guard {
+        unsafe {
+            let mut termios: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) != 0 {
+                return Guard(None);
+            }
+            let original = termios;
+            termios.c_lflag &= !libc::ECHO;
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios);
+            Guard(Some(original))
+        }
+    }
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            if let Some(original) = &self.0 {
+                unsafe {
+                    libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, original);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod echo {
+    use windows_sys::Win32::System::Console::{
+        GetConsoleMode, GetStdHandle, SetConsoleMode, ENABLE_ECHO_INPUT, STD_INPUT_HANDLE,
+    };
+
+    /// Restores echo when dropped
+    pub struct Guard(Option<u32>);
+
+    pub fn disable() -> Guard {
+        unsafe {
+            let handle = GetStdHandle(STD_INPUT_HANDLE);
+            let mut mode = 0;
+            if GetConsoleMode(handle, &mut mode) == 0 {
+                return Guard(None);
+            }
+            SetConsoleMode(handle, mode & !ENABLE_ECHO_INPUT);
+            Guard(Some(mode))
+        }
+    }
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            if let Some(mode) = self.0 {
+                unsafe {
+                    SetConsoleMode(GetStdHandle(STD_INPUT_HANDLE), mode);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use anyhow::{Context, Result};
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use crate::agent::{self, secrets, AgentConfig};
+use crate::core;
+use crate::inbox;
+use crate::incidents;
+use crate::state;
+
+use super::utils::init_logging;
+
+/// Remove everything the tool installed, returning the machine to how it
+/// was before
+///
+/// Stops and removes the agent service, removes all applied policies and
+/// deletes the agent configuration and state. The inbox, incident log and
+/// agent log are kept unless `purge_history` is set.
+pub fn run(purge_history: bool, yes: bool, dry_run: bool, verbose: bool) -> Result<()> {
+    init_logging(verbose);
+
+    let config_path = agent::get_agent_config_path()?;
+    // Read before deleting it: it names the log file and stored secrets
+    let config = if config_path.exists() {
+        AgentConfig::load(&config_path)
+            .map_err(|e| tracing::warn!("Ignoring unreadable agent configuration: {:#}", e))
+            .ok()
+    } else {
+        None
+    };
+
+    let state_dir = state::get_state_path()?
+        .parent()
+        .context("State file has no directory")?
+        .to_path_buf();
+    let config_dir = config_path
+        .parent()
+        .context("Agent configuration has no directory")?
+        .to_path_buf();
+
+    let mut files = files_to_remove(&state_dir, purge_history)?;
+    if config_dir.exists() {
+        files.push(config_dir);
+    }
+    if purge_history && let Some(log_file) = config.as_ref().and_then(|c| c.logging.file.as_ref()) {
+        files.extend(agent::log_files(log_file)?);
+    }
+    let secret = config
+        .as_ref()
+        .and_then(|c| c.github.access_token.as_deref())
+        .map(secrets::secret_name)
+        .transpose()?
+        .flatten()
+        .map(String::from);
+
+    println!("Uninstalling Family Policy");
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    println!("This will:");
+    println!("  - stop and remove the agent service");
+    println!("  - remove all browser policies set by this tool");
+    for file in &files {
+        println!("  - delete {}", file.display());
+    }
+    if let Some(name) = &secret {
+        println!("  - delete the stored secret '{}'", name);
+    }
+    if !purge_history {
+        println!();
+        println!("The inbox, incident log and agent log are kept (use --purge-history to delete them).");
+    }
+    println!();
+
+    if dry_run {
+        println!("DRY RUN MODE - No changes were made");
+        return Ok(());
+    }
+
+    if !yes && !confirm("Continue?")? {
+        println!("Aborted");
+        return Ok(());
+    }
+
+    // Stop the agent first so it doesn't re-apply what we remove
+    println!();
+    super::agent::remove_service()?;
+
+    println!();
+    if state::load_state()?.is_some() {
+        let result = core::apply::remove_all_policies(false).context("Failed to remove policies")?;
+        for error in &result.errors {
+            eprintln!("  - {}", error);
+        }
+        println!("✓ Browser policies removed");
+    } else {
+        println!("No browser policies applied");
+    }
+
+    if let Some(name) = &secret {
+        secrets::delete_secret(name)?;
+        println!("✓ Secret '{}' deleted", name);
+    }
+
+    for file in &files {
+        remove_path(file)?;
+    }
+    // Only empty if the history went too
+    let _ = std::fs::remove_dir(&state_dir);
+    println!("✓ Configuration and state deleted");
+
+    println!();
+    println!("Family Policy has been uninstalled. Delete the family-policy binary to finish.");
+    Ok(())
+}
+
+/// Files in the state directory to delete
+///
+/// Everything the tool wrote there except the inbox and incident log, which
+/// are only included when purging history.
+fn files_to_remove(state_dir: &Path, purge_history: bool) -> Result<Vec<PathBuf>> {
+    let history = [inbox::get_inbox_path()?, incidents::get_incidents_path()?];
+
+    let entries = match std::fs::read_dir(state_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("Failed to read state directory: {}", state_dir.display()));
+        }
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| purge_history || !history.contains(path))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn remove_path(path: &Path) -> Result<()> {
+    let result = if path.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    };
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e).with_context(|| format!("Failed to delete {}", path.display())),
+    }
+}
+
+/// Ask a yes/no question on the terminal, defaulting to no
+fn confirm(question: &str) -> Result<bool> {
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        anyhow::bail!("Not running in a terminal; pass --yes to uninstall without confirmation");
+    }
+
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush()?;
+
+    let mut answer = String::new();
+    stdin.lock().read_line(&mut answer).context("Failed to read answer")?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_history_unless_purging() {
+        let dir = tempfile::tempdir().unwrap();
+        let _state = state::use_test_state_dir(dir.path());
+        for file in ["state.json", "last-policy.yaml", "inbox.json", "incidents.json"] {
+            std::fs::write(dir.path().join(file), "{}").unwrap();
+        }
+
+        let names = |files: Vec<PathBuf>| -> Vec<String> {
+            files
+                .iter()
+                .map(|f| f.file_name().unwrap().to_string_lossy().into_owned())
+                .collect()
+        };
+
+        assert_eq!(
+            names(files_to_remove(dir.path(), false).unwrap()),
+            ["last-policy.yaml", "state.json"]
+        );
+        assert_eq!(
+            names(files_to_remove(dir.path(), true).unwrap()),
+            ["inbox.json", "incidents.json", "last-policy.yaml", "state.json"]
+        );
+    }
+
+    #[test]
+    fn missing_state_directory_has_nothing_to_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let _state = state::use_test_state_dir(dir.path());
+        assert!(files_to_remove(&dir.path().join("missing"), true).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,49 @@
+use anyhow::{Context, Result};
+
+use crate::agent::{self, update};
+
+use super::utils::init_logging;
+
+/// Update the agent binary to the latest release
+///
+/// With `check_only`, just report whether a newer release exists.
+pub fn self_update(check_only: bool, verbose: bool) -> Result<()> {
+    init_logging(verbose);
+
+    let config = load_config()?;
+    let updater = update::Updater::new(config.update.clone(), &config.agent)?;
+
+    println!("Current version: {}", update::current_version());
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let Some(release) = updater.check().await? else {
+            println!("✓ Up to date");
+            return Ok(());
+        };
+
+        println!("New version available: {}", release.version);
+        if check_only {
+            return Ok(());
+        }
+
+        let binary = updater.download(&release).await?;
+        println!("✓ Downloaded and verified {}", update::binary_asset_name());
+
+        update::install(&binary)?;
+        println!("✓ Updated to {}", release.version);
+        println!("  Restart the agent service to run the new version.");
+        Ok(())
+    })
+}
+
+/// The agent configuration, for the update key and proxy settings
+///
+/// Machines without an agent configuration use the defaults.
+fn load_config() -> Result<agent::AgentConfig> {
+    let path = agent::get_agent_config_path()?;
+    if !path.exists() {
+        return Ok(agent::AgentConfig::default());
+    }
+    agent::AgentConfig::load(&path).context("Failed to load agent configuration")
+}
@@ -1,15 +1,53 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 
-use crate::browser::Browser;
+use crate::browser::{Browser, Platform};
 
 /// Main configuration structure
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Config {
+    /// Policy files merged ahead of this file's own policies, relative to it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+
     #[serde(default)]
     pub policies: Vec<PolicyEntry>,
+
+    /// What the agent does about browsers it can't manage (Opera, Vivaldi,
+    /// Tor Browser); unset ignores them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unmanaged_browsers: Option<UnmanagedBrowserAction>,
+
+    /// What to do when another management tool (Group Policy, Jamf, other
+    /// MDM) already sets a value we would write; unset warns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conflict_policy: Option<ConflictPolicy>,
+}
+
+/// How the agent responds to an unmanaged browser on the machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnmanagedBrowserAction {
+    /// Record an incident and notify the inbox
+    Warn,
+    /// Warn, and also stop it from starting where the platform allows
+    Block,
+}
+
+/// How to handle policy values another management tool already sets
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// Apply anyway and report the conflicts
+    #[default]
+    Warn,
+    /// Don't apply anything while there are conflicts
+    Refuse,
+    /// Leave conflicting settings to the other tool and apply the rest
+    Merge,
 }
 
 /// A single policy entry that can apply to multiple browsers
@@ -20,15 +58,247 @@ pub struct PolicyEntry {
 
     // Privacy controls (apply to all browsers, with browser-specific translations)
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub disable_private_mode: Option<bool>, // Chrome: incognito, Firefox: private browsing, Edge: InPrivate
+    pub disable_private_mode: Option<bool>, // Chrome: incognito, Firefox/Safari: private browsing, Edge: InPrivate
     #[serde(skip_serializing_if = "Option::is_none")]
     pub disable_guest_mode: Option<bool>, // Chrome and Edge only (ignored for Firefox)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub allow_deleting_browser_history: Option<bool>, // Chrome: AllowDeletingBrowserHistory, Edge: AllowDeletingBrowserHistory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disable_sync: Option<bool>, // Chrome/Edge: SyncDisabled, Firefox: DisableFirefoxAccounts (not Safari)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restrict_signin: Option<SigninRestriction>, // Chrome and Edge only: BrowserSignin, RestrictSigninToPattern
+
+    /// Websites to block in Safari's content filter (ignored by other
+    /// browsers)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocked_urls: Vec<String>,
 
     // Extensions
     #[serde(default)]
     pub extensions: Vec<ExtensionEntry>,
+
+    /// After this time the entry is ignored, as if it had been deleted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// Set to false to keep the entry in the file without applying it
+    #[serde(default = "default_enabled", skip_serializing_if = "is_enabled")]
+    pub enabled: bool,
+
+    /// Before this time the entry is ignored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_from: Option<DateTime<Utc>>,
+
+    /// From this time on the entry is ignored again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_until: Option<DateTime<Utc>>,
+
+    /// Only apply the entry on machines matching these conditions
+    #[serde(default, rename = "match", skip_serializing_if = "Option::is_none")]
+    pub host_match: Option<HostMatch>,
+}
+
+/// Conditions limiting a policy entry to some machines
+///
+/// Every condition given must hold, and a condition holds if any of its
+/// values matches.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct HostMatch {
+    /// Hostname globs (`*` and `?`, case-insensitive)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hostname: Vec<String>,
+
+    /// Operating systems: windows, macos or linux
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub os: Vec<Platform>,
+
+    /// Tags set in the agent configuration (`agent.tags`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl HostMatch {
+    /// Whether `host` satisfies the conditions
+    pub fn matches(&self, host: &HostContext) -> bool {
+        (self.hostname.is_empty()
+            || self.hostname.iter().any(|pattern| glob_matches(pattern, &host.hostname)))
+            && (self.os.is_empty() || self.os.contains(&host.os))
+            && (self.tags.is_empty() || self.tags.iter().any(|tag| host.tags.contains(tag)))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.hostname.is_empty() && self.os.is_empty() && self.tags.is_empty()
+    }
+}
+
+/// The machine a config is applied on, for evaluating `match` blocks
+#[derive(Debug, Clone)]
+pub struct HostContext {
+    pub hostname: String,
+    pub os: Platform,
+    pub tags: Vec<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn is_enabled(enabled: &bool) -> bool {
+    *enabled
+}
+
+impl PolicyEntry {
+    /// Whether the entry has expired at `now`
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// Whether the entry is in force at `now`: enabled, inside its active
+    /// window and not expired
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.enabled
+            && !self.is_expired(now)
+            && self.active_from.is_none_or(|from| from <= now)
+            && self.active_until.is_none_or(|until| until > now)
+    }
+
+    /// Whether the entry only applies some of the time
+    pub fn is_scheduled(&self) -> bool {
+        self.active_from.is_some() || self.active_until.is_some()
+    }
+
+    /// Why an unexpired entry isn't in force at `now`, if it isn't
+    ///
+    /// Expired entries are reported separately (`expired_policies`).
+    pub fn inactive_reason(&self, now: DateTime<Utc>) -> Option<String> {
+        let format = |time: DateTime<Utc>| time.format("%Y-%m-%d %H:%M:%S %Z").to_string();
+        if self.is_expired(now) {
+            None
+        } else if !self.enabled {
+            Some("disabled".to_string())
+        } else if let Some(from) = self.active_from.filter(|from| *from > now) {
+            Some(format!("active from {}", format(from)))
+        } else {
+            self.active_until
+                .filter(|until| *until <= now)
+                .map(|until| format!("active until {}", format(until)))
+        }
+    }
+
+    /// Whether the entry applies on `host`
+    pub fn applies_to_host(&self, host: &HostContext) -> bool {
+        self.host_match.as_ref().is_none_or(|m| m.matches(host))
+    }
+
+    /// Whether the entry sets `setting`
+    pub fn has_setting(&self, setting: PrivacySetting) -> bool {
+        match setting {
+            PrivacySetting::PrivateMode => self.disable_private_mode.is_some(),
+            PrivacySetting::GuestMode => self.disable_guest_mode.is_some(),
+            PrivacySetting::HistoryDeletion => self.allow_deleting_browser_history.is_some(),
+            PrivacySetting::Sync => self.disable_sync.is_some(),
+            PrivacySetting::Signin => self.restrict_signin.is_some(),
+        }
+    }
+
+    fn clear_setting(&mut self, setting: PrivacySetting) {
+        match setting {
+            PrivacySetting::PrivateMode => self.disable_private_mode = None,
+            PrivacySetting::GuestMode => self.disable_guest_mode = None,
+            PrivacySetting::HistoryDeletion => self.allow_deleting_browser_history = None,
+            PrivacySetting::Sync => self.disable_sync = None,
+            PrivacySetting::Signin => self.restrict_signin = None,
+        }
+    }
+}
+
+/// Value of `restrict_signin`
+///
+/// `true` turns browser sign-in off, `false` explicitly allows it, and a
+/// pattern only allows accounts whose address matches it (a regular
+/// expression, e.g. `.*@example\.com`).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum SigninRestriction {
+    Restricted(bool),
+    Pattern(String),
+}
+
+impl std::fmt::Display for SigninRestriction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SigninRestriction::Restricted(restricted) => write!(f, "{}", restricted),
+            SigninRestriction::Pattern(pattern) => write!(f, "'{}'", pattern),
+        }
+    }
+}
+
+/// A privacy control that can be set by a policy entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrivacySetting {
+    /// `disable_private_mode` (Incognito, InPrivate, private browsing)
+    PrivateMode,
+    /// `disable_guest_mode` (Chrome and Edge)
+    GuestMode,
+    /// `allow_deleting_browser_history` (Chrome and Edge)
+    HistoryDeletion,
+    /// `disable_sync` (Chrome and Edge sync, Firefox accounts; not Safari)
+    Sync,
+    /// `restrict_signin` (Chrome and Edge)
+    Signin,
+}
+
+impl PrivacySetting {
+    pub const ALL: [PrivacySetting; 5] = [
+        PrivacySetting::PrivateMode,
+        PrivacySetting::GuestMode,
+        PrivacySetting::HistoryDeletion,
+        PrivacySetting::Sync,
+        PrivacySetting::Signin,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PrivacySetting::PrivateMode => "private-mode",
+            PrivacySetting::GuestMode => "guest-mode",
+            PrivacySetting::HistoryDeletion => "history-deletion",
+            PrivacySetting::Sync => "sync",
+            PrivacySetting::Signin => "signin",
+        }
+    }
+
+    /// Whether the setting exists for `browser`
+    pub fn applies_to(&self, browser: Browser) -> bool {
+        match self {
+            PrivacySetting::PrivateMode => true,
+            PrivacySetting::Sync => browser != Browser::Safari,
+            PrivacySetting::GuestMode | PrivacySetting::HistoryDeletion | PrivacySetting::Signin => {
+                browser.is_chromium_based()
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for PrivacySetting {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "private-mode" | "incognito" | "inprivate" | "private-browsing" => {
+                Ok(PrivacySetting::PrivateMode)
+            }
+            "guest-mode" => Ok(PrivacySetting::GuestMode),
+            "history-deletion" => Ok(PrivacySetting::HistoryDeletion),
+            "sync" => Ok(PrivacySetting::Sync),
+            "signin" | "sign-in" => Ok(PrivacySetting::Signin),
+            _ => Err(format!(
+                "unknown setting '{}' (expected private-mode, incognito, inprivate, \
+                 private-browsing, guest-mode, history-deletion, sync or signin)",
+                s
+            )),
+        }
+    }
 }
 
 /// Extension entry with browser-specific IDs and arbitrary settings
@@ -65,32 +335,45 @@ impl BrowserIdMap {
 }
 
 /// Legacy Chrome-specific configuration (for internal use)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ChromeConfig {
     pub extensions: Vec<Extension>,
     pub disable_incognito: Option<bool>,
     pub disable_guest_mode: Option<bool>,
     pub allow_deleting_browser_history: Option<bool>,
+    pub disable_sync: Option<bool>,
+    pub restrict_signin: Option<SigninRestriction>,
 }
 
 /// Legacy Firefox-specific configuration (for internal use)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct FirefoxConfig {
     pub extensions: Vec<Extension>,
     pub disable_private_browsing: Option<bool>,
+    pub disable_sync: Option<bool>,
 }
 
 /// Legacy Edge-specific configuration (for internal use)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct EdgeConfig {
     pub extensions: Vec<Extension>,
     pub disable_inprivate: Option<bool>,
     pub disable_guest_mode: Option<bool>,
     pub allow_deleting_browser_history: Option<bool>,
+    pub disable_sync: Option<bool>,
+    pub restrict_signin: Option<SigninRestriction>,
+}
+
+/// Safari's part of a config (macOS only)
+#[derive(Debug, Clone, Serialize)]
+pub struct SafariConfig {
+    pub disable_private_browsing: Option<bool>,
+    /// Every entry's `blocked_urls`, sorted and without duplicates
+    pub blocked_urls: Vec<String>,
 }
 
 /// Legacy extension definition (for internal use by policy modules)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Extension {
     pub id: String,
     pub name: String,
@@ -112,13 +395,184 @@ pub const DEFAULT_CHROME_UPDATE_URL: &str = "https://clients2.google.com/service
 /// Firefox Add-ons base URL for generating install URLs
 const FIREFOX_ADDONS_BASE: &str = "https://addons.mozilla.org/firefox/downloads/latest";
 
-/// Load configuration from a YAML file
+impl Config {
+    /// Parse and validate config from a YAML string
+    pub fn from_yaml_str(content: &str) -> Result<Self> {
+        let config: Config = serde_yaml::from_str(content)
+            .context("Failed to parse YAML")?;
+
+        // Validate the config
+        validate_config(&config)?;
+
+        Ok(config)
+    }
+
+    /// The configuration that is actually in force at `now`
+    ///
+    /// Expired, disabled and out-of-schedule entries are dropped. This may
+    /// leave no policies at all, which means everything this tool manages
+    /// should be removed.
+    pub fn effective(&self, now: DateTime<Utc>) -> Config {
+        Config {
+            policies: self
+                .policies
+                .iter()
+                .filter(|p| p.is_active(now))
+                .cloned()
+                .collect(),
+            include: self.include.clone(),
+            unmanaged_browsers: self.unmanaged_browsers,
+            conflict_policy: self.conflict_policy,
+        }
+    }
+
+    /// The entries that apply on `host`
+    ///
+    /// Entries whose `match` block doesn't match are dropped, so the result
+    /// is what this machine should enforce.
+    pub fn for_host(&self, host: &HostContext) -> Config {
+        Config {
+            policies: self
+                .policies
+                .iter()
+                .filter(|p| p.applies_to_host(host))
+                .cloned()
+                .collect(),
+            include: self.include.clone(),
+            unmanaged_browsers: self.unmanaged_browsers,
+            conflict_policy: self.conflict_policy,
+        }
+    }
+
+    /// Browsers at least one entry applies to
+    pub fn browsers(&self) -> Vec<Browser> {
+        Browser::ALL
+            .into_iter()
+            .filter(|browser| self.policies.iter().any(|p| p.browsers.contains(browser)))
+            .collect()
+    }
+
+    /// Entries that have expired at `now`
+    pub fn expired_policies(&self, now: DateTime<Utc>) -> Vec<&PolicyEntry> {
+        self.policies.iter().filter(|p| p.is_expired(now)).collect()
+    }
+
+    /// Unexpired entries that aren't in force at `now`, and why
+    pub fn inactive_policies(&self, now: DateTime<Utc>) -> Vec<(&PolicyEntry, String)> {
+        self.policies
+            .iter()
+            .filter_map(|p| p.inactive_reason(now).map(|reason| (p, reason)))
+            .collect()
+    }
+
+    /// The config with extension `id` removed for `browsers`
+    ///
+    /// Entries listing the extension for other browsers too keep it for those.
+    pub fn without_extension(&self, id: &str, browsers: &[Browser]) -> Config {
+        let mut config = self.clone();
+
+        for policy in &mut config.policies {
+            let policy_browsers = policy.browsers.clone();
+            policy.extensions.retain_mut(|ext| {
+                let is_removed =
+                    |b: &Browser| browsers.contains(b) && ext.id.get_id(*b) == Some(id);
+                if !policy_browsers.iter().any(is_removed) {
+                    return true;
+                }
+
+                let remaining: HashMap<Browser, String> = policy_browsers
+                    .iter()
+                    .filter(|b| !is_removed(b))
+                    .filter_map(|b| ext.id.get_id(*b).map(|id| (*b, id.to_string())))
+                    .collect();
+                if remaining.is_empty() {
+                    return false;
+                }
+
+                ext.id = BrowserIdMap::Multiple(remaining);
+                true
+            });
+        }
+
+        config
+    }
+
+    /// The config with `setting` left unset for `browsers`
+    ///
+    /// An entry that also covers other browsers is split in two so they keep
+    /// the setting.
+    pub fn without_setting(&self, setting: PrivacySetting, browsers: &[Browser]) -> Config {
+        let mut policies = Vec::new();
+
+        for policy in &self.policies {
+            let (targeted, others): (Vec<Browser>, Vec<Browser>) = policy
+                .browsers
+                .iter()
+                .copied()
+                .partition(|b| browsers.contains(b));
+
+            if targeted.is_empty() || !policy.has_setting(setting) {
+                policies.push(policy.clone());
+                continue;
+            }
+
+            if !others.is_empty() {
+                let mut kept = policy.clone();
+                kept.browsers = others;
+                policies.push(kept);
+            }
+
+            let mut stripped = policy.clone();
+            stripped.browsers = targeted;
+            stripped.clear_setting(setting);
+            policies.push(stripped);
+        }
+
+        Config {
+            include: self.include.clone(),
+            policies,
+            unmanaged_browsers: self.unmanaged_browsers,
+            conflict_policy: self.conflict_policy,
+        }
+    }
+
+    /// Entries that expired after `since` and at or before `now`
+    ///
+    /// Used to notice that a previously applied config has changed its
+    /// effective policies without the file itself changing.
+    pub fn expired_since(&self, since: DateTime<Utc>, now: DateTime<Utc>) -> Vec<&PolicyEntry> {
+        self.policies
+            .iter()
+            .filter(|p| p.expires_at.is_some_and(|t| t > since && t <= now))
+            .collect()
+    }
+
+    /// Entries that started or stopped being in force on schedule after
+    /// `since` and at or before `now`, as (started, stopped)
+    ///
+    /// Like `expired_since`, for noticing the effective policies change
+    /// without the file changing.
+    pub fn schedule_changes_since(
+        &self,
+        since: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> (Vec<&PolicyEntry>, Vec<&PolicyEntry>) {
+        let crossed = |time: Option<DateTime<Utc>>| time.is_some_and(|t| t > since && t <= now);
+        self.policies
+            .iter()
+            .filter(|p| p.enabled && (crossed(p.active_from) || crossed(p.active_until)))
+            .partition(|p| p.is_active(now))
+    }
+}
+
+/// Load configuration from a YAML file, merging any files it includes
 pub fn load_config(path: &Path) -> Result<Config> {
-    let content = std::fs::read_to_string(path)
-        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    let location = path.to_string_lossy();
 
-    let config: Config = serde_yaml::from_str(&content)
-        .with_context(|| format!("Failed to parse YAML config file: {}", path.display()))?;
+    let config = merge_includes(&location, &mut |location| {
+        std::fs::read_to_string(location)
+            .with_context(|| format!("Failed to read config file: {}", location))
+    })?;
 
     // Validate the config
     validate_config(&config)?;
@@ -126,10 +580,88 @@ pub fn load_config(path: &Path) -> Result<Config> {
     Ok(config)
 }
 
+/// Where an included file is found, relative to the file including it
+///
+/// Includes in a remote policy are resolved against its URL; includes in a
+/// local file are paths relative to its directory.
+pub fn include_location(base: &str, include: &str) -> Result<String> {
+    let base_url = url::Url::parse(base)
+        .ok()
+        .filter(|u| matches!(u.scheme(), "http" | "https"));
+    if let Some(base_url) = base_url {
+        let url = base_url
+            .join(include)
+            .with_context(|| format!("Invalid include '{}'", include))?;
+        return Ok(url.to_string());
+    }
+
+    if url::Url::parse(include).is_ok_and(|u| matches!(u.scheme(), "http" | "https")) {
+        anyhow::bail!("Local policy files can't include URLs (got: {})", include);
+    }
+
+    let dir = Path::new(base).parent().unwrap_or(Path::new(""));
+    Ok(dir.join(include).to_string_lossy().into_owned())
+}
+
+/// The policies of the file at `location` merged with the files it includes
+///
+/// Included files' policies come first, in the order listed and depth-first,
+/// followed by the file's own, so an overlay's entries win over its base.
+/// Likewise the last file setting `unmanaged_browsers` or `conflict_policy`
+/// wins. A file reached twice is only merged the first time. `load` reads a
+/// file given its location (see [`include_location`]). The result isn't
+/// validated.
+pub fn merge_includes(location: &str, load: &mut dyn FnMut(&str) -> Result<String>) -> Result<Config> {
+    let mut merged = Config::default();
+    let mut stack = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    merge_file(location, load, &mut stack, &mut seen, &mut merged)?;
+    Ok(merged)
+}
+
+fn merge_file(
+    location: &str,
+    load: &mut dyn FnMut(&str) -> Result<String>,
+    stack: &mut Vec<String>,
+    seen: &mut std::collections::HashSet<String>,
+    merged: &mut Config,
+) -> Result<()> {
+    if stack.iter().any(|l| l == location) {
+        anyhow::bail!("Include cycle: {} -> {}", stack.join(" -> "), location);
+    }
+    if !seen.insert(location.to_string()) {
+        return Ok(());
+    }
+
+    let content = load(location)?;
+    let config: Config = serde_yaml::from_str(&content)
+        .with_context(|| format!("Failed to parse YAML config file: {}", location))?;
+
+    stack.push(location.to_string());
+    for include in &config.include {
+        let included = include_location(location, include)?;
+        merge_file(&included, load, stack, seen, merged)
+            .with_context(|| format!("Failed to include '{}' from {}", include, location))?;
+    }
+    stack.pop();
+
+    merged.policies.extend(config.policies);
+    if config.unmanaged_browsers.is_some() {
+        merged.unmanaged_browsers = config.unmanaged_browsers;
+    }
+    if config.conflict_policy.is_some() {
+        merged.conflict_policy = config.conflict_policy;
+    }
+    Ok(())
+}
+
 /// Validate configuration
+///
+/// A config with unresolved includes only needs policies of its own once
+/// they are merged in.
 pub fn validate_config(config: &Config) -> Result<()> {
     // Ensure at least one policy is configured
-    if config.policies.is_empty() {
+    if config.policies.is_empty() && config.include.is_empty() {
         anyhow::bail!("Configuration must specify at least one policy");
     }
 
@@ -139,9 +671,130 @@ pub fn validate_config(config: &Config) -> Result<()> {
             .with_context(|| format!("Invalid policy '{}'", policy.name))?;
     }
 
+    check_privacy_conflicts(config)?;
+
+    Ok(())
+}
+
+/// Privacy settings a policy entry sets for a browser, by config field name
+///
+/// Settings the browser doesn't support (guest mode on Firefox, sync on
+/// Safari) are left out, since they are ignored when applying.
+fn privacy_settings_for(policy: &PolicyEntry, browser: Browser) -> Vec<(&'static str, String)> {
+    let mut settings = Vec::new();
+
+    if let Some(value) = policy.disable_private_mode {
+        settings.push(("disable_private_mode", value.to_string()));
+    }
+    if let Some(value) = policy.disable_sync
+        && PrivacySetting::Sync.applies_to(browser)
+    {
+        settings.push(("disable_sync", value.to_string()));
+    }
+    if browser.is_chromium_based() {
+        if let Some(value) = policy.disable_guest_mode {
+            settings.push(("disable_guest_mode", value.to_string()));
+        }
+        if let Some(value) = policy.allow_deleting_browser_history {
+            settings.push(("allow_deleting_browser_history", value.to_string()));
+        }
+        if let Some(value) = &policy.restrict_signin {
+            settings.push(("restrict_signin", value.to_string()));
+        }
+    }
+
+    settings
+}
+
+/// Fail if two policies set the same privacy setting to different values
+///
+/// Applying would silently keep whichever came last, which is rarely what
+/// the author meant. Entries with a `match` block are left out: overriding
+/// the base policy on some machines is what they are for.
+fn check_privacy_conflicts(config: &Config) -> Result<()> {
+    let mut seen: HashMap<(Browser, &'static str), (&str, String)> = HashMap::new();
+
+    // Entries that only apply on some machines, some of the time or until
+    // they expire are meant to override the others (the later entry wins)
+    let unconditional = config
+        .policies
+        .iter()
+        .filter(|p| {
+            p.enabled && p.host_match.is_none() && !p.is_scheduled() && p.expires_at.is_none()
+        });
+    for policy in unconditional {
+        for browser in &policy.browsers {
+            for (setting, value) in privacy_settings_for(policy, *browser) {
+                match seen.get(&(*browser, setting)) {
+                    Some((other, other_value)) if *other_value != value => {
+                        anyhow::bail!(
+                            "Policies '{}' and '{}' conflict: {} is set to {} and {} for {}",
+                            other,
+                            policy.name,
+                            setting,
+                            other_value,
+                            value,
+                            browser.as_str()
+                        );
+                    }
+                    _ => {
+                        seen.insert((*browser, setting), (&policy.name, value));
+                    }
+                }
+            }
+        }
+    }
+
     Ok(())
 }
 
+/// Describe extension settings that different policies set to different values
+///
+/// These are warnings rather than errors: combining a base policy with a
+/// more specific one is legitimate, and the later policy wins.
+pub fn extension_setting_conflicts(config: &Config) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let mut seen: HashMap<(Browser, &str), (&str, &ExtensionEntry)> = HashMap::new();
+
+    for policy in &config.policies {
+        for ext in &policy.extensions {
+            for browser in &policy.browsers {
+                let Some(id) = ext.id.get_id(*browser) else {
+                    continue;
+                };
+
+                if let Some(&(other_policy, other_ext)) = seen.get(&(*browser, id)) {
+                    let mut keys: Vec<&String> = ext
+                        .settings
+                        .iter()
+                        .filter(|(key, value)| {
+                            other_ext.settings.get(*key).is_some_and(|other| other != *value)
+                        })
+                        .map(|(key, _)| key)
+                        .collect();
+                    keys.sort();
+
+                    for key in keys {
+                        warnings.push(format!(
+                            "Policies '{}' and '{}' set '{}' differently for extension '{}' ({}); '{}' wins",
+                            other_policy,
+                            policy.name,
+                            key,
+                            ext.name,
+                            browser.as_str(),
+                            policy.name
+                        ));
+                    }
+                }
+
+                seen.insert((*browser, id), (&policy.name, ext));
+            }
+        }
+    }
+
+    warnings
+}
+
 /// Validate a single policy entry
 fn validate_policy_entry(policy: &PolicyEntry) -> Result<()> {
     // Ensure at least one browser is specified
@@ -149,19 +802,96 @@ fn validate_policy_entry(policy: &PolicyEntry) -> Result<()> {
         anyhow::bail!("Policy must specify at least one browser");
     }
 
+    if let Some(host_match) = &policy.host_match {
+        validate_host_match(host_match)?;
+    }
+
+    if let (Some(from), Some(until)) = (policy.active_from, policy.active_until)
+        && from >= until
+    {
+        anyhow::bail!("active_from must be before active_until");
+    }
+
+    if let Some(url) = policy.blocked_urls.iter().find(|url| url.trim().is_empty()) {
+        anyhow::bail!("blocked_urls contains an empty entry ('{}')", url);
+    }
+
     // Validate each extension
     for ext in &policy.extensions {
         validate_extension_entry(ext, &policy.browsers)
             .with_context(|| format!("Invalid extension '{}'", ext.name))?;
     }
 
+    // The same extension listed twice in one policy is always a mistake
+    for browser in policy.browsers.iter().filter(|b| b.supports_extensions()) {
+        let mut names_by_id: HashMap<&str, &str> = HashMap::new();
+        for ext in &policy.extensions {
+            let Some(id) = ext.id.get_id(*browser) else {
+                continue;
+            };
+            if let Some(other) = names_by_id.insert(id, &ext.name) {
+                anyhow::bail!(
+                    "Extensions '{}' and '{}' have the same {} ID '{}'",
+                    other,
+                    ext.name,
+                    browser.as_str(),
+                    id
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate a `match` block
+fn validate_host_match(host_match: &HostMatch) -> Result<()> {
+    if host_match.is_empty() {
+        anyhow::bail!("match must specify at least one of hostname, os or tags");
+    }
+    if host_match.hostname.iter().any(|h| h.is_empty()) {
+        anyhow::bail!("match hostname patterns must not be empty");
+    }
+    if host_match.tags.iter().any(|t| t.is_empty()) {
+        anyhow::bail!("match tags must not be empty");
+    }
     Ok(())
 }
 
+/// Case-insensitive glob match supporting `*` and `?`
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it currently matches up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // Let the `*` swallow one more character
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, t));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 /// Validate an extension entry
 fn validate_extension_entry(ext: &ExtensionEntry, browsers: &[Browser]) -> Result<()> {
-    // Validate that the extension has IDs for the required browsers
-    for browser in browsers {
+    // Validate that the extension has IDs for the required browsers (Safari
+    // has no extension policies, so it needs none)
+    for browser in browsers.iter().filter(|b| b.supports_extensions()) {
         let id = ext.id.get_id(*browser);
         if id.is_none() {
             anyhow::bail!(
@@ -175,8 +905,9 @@ fn validate_extension_entry(ext: &ExtensionEntry, browsers: &[Browser]) -> Resul
 
         // Validate ID format based on browser
         match browser {
-            Browser::Chrome | Browser::Edge => {
-                // Chrome/Edge extension IDs should be 32 lowercase alphanumeric characters
+            Browser::Safari => {}
+            Browser::Chrome | Browser::Edge | Browser::Chromium | Browser::Vivaldi | Browser::Opera => {
+                // Chromium extension IDs are 32 letters from a to p
                 if id.len() != 32 {
                     anyhow::bail!(
                         "Extension '{}' has invalid {} ID length: expected 32 characters, got {}",
@@ -186,12 +917,9 @@ fn validate_extension_entry(ext: &ExtensionEntry, browsers: &[Browser]) -> Resul
                     );
                 }
 
-                if !id
-                    .chars()
-                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
-                {
+                if !is_chromium_extension_id(id) {
                     anyhow::bail!(
-                        "Extension '{}' has invalid {} ID: must contain only lowercase letters and digits",
+                        "Extension '{}' has invalid {} ID: must contain only the letters a to p",
                         ext.name,
                         browser.as_str()
                     );
@@ -209,6 +937,12 @@ fn validate_extension_entry(ext: &ExtensionEntry, browsers: &[Browser]) -> Resul
     Ok(())
 }
 
+/// Whether `id` has the form of a Chrome/Edge extension ID: 32 letters from
+/// `a` to `p` (a hex hash with the digits shifted to letters)
+pub fn is_chromium_extension_id(id: &str) -> bool {
+    id.len() == 32 && id.chars().all(|c| ('a'..='p').contains(&c))
+}
+
 /// Convert the new config format to browser-specific configurations
 pub fn to_browser_configs(
     config: &Config,
@@ -224,10 +958,15 @@ pub fn to_browser_configs(
     let mut chrome_disable_incognito = None;
     let mut chrome_disable_guest_mode = None;
     let mut chrome_allow_deleting_browser_history = None;
+    let mut chrome_disable_sync = None;
+    let mut chrome_restrict_signin = None;
     let mut firefox_disable_private_browsing = None;
+    let mut firefox_disable_sync = None;
     let mut edge_disable_inprivate = None;
     let mut edge_disable_guest_mode = None;
     let mut edge_allow_deleting_browser_history = None;
+    let mut edge_disable_sync = None;
+    let mut edge_restrict_signin = None;
 
     // Process each policy entry
     for policy in &config.policies {
@@ -244,12 +983,21 @@ pub fn to_browser_configs(
                     if let Some(allow) = policy.allow_deleting_browser_history {
                         chrome_allow_deleting_browser_history = Some(allow);
                     }
+                    if let Some(disable) = policy.disable_sync {
+                        chrome_disable_sync = Some(disable);
+                    }
+                    if let Some(restriction) = &policy.restrict_signin {
+                        chrome_restrict_signin = Some(restriction.clone());
+                    }
                 }
                 Browser::Firefox => {
                     if let Some(disable) = policy.disable_private_mode {
                         firefox_disable_private_browsing = Some(disable);
                     }
-                    // Firefox doesn't have guest mode - ignore
+                    if let Some(disable) = policy.disable_sync {
+                        firefox_disable_sync = Some(disable);
+                    }
+                    // Firefox doesn't have guest mode or sign-in restrictions - ignore
                 }
                 Browser::Edge => {
                     if let Some(disable) = policy.disable_private_mode {
@@ -261,7 +1009,15 @@ pub fn to_browser_configs(
                     if let Some(allow) = policy.allow_deleting_browser_history {
                         edge_allow_deleting_browser_history = Some(allow);
                     }
+                    if let Some(disable) = policy.disable_sync {
+                        edge_disable_sync = Some(disable);
+                    }
+                    if let Some(restriction) = &policy.restrict_signin {
+                        edge_restrict_signin = Some(restriction.clone());
+                    }
                 }
+                // See chromium_based_config and safari_config
+                Browser::Chromium | Browser::Vivaldi | Browser::Opera | Browser::Safari => {}
             }
         }
 
@@ -273,10 +1029,8 @@ pub fn to_browser_configs(
                         id: id.to_string(),
                         name: ext_entry.name.clone(),
                         update_url: match browser {
-                            Browser::Chrome | Browser::Edge => {
-                                Some(DEFAULT_CHROME_UPDATE_URL.to_string())
-                            }
                             Browser::Firefox => None,
+                            _ => Some(DEFAULT_CHROME_UPDATE_URL.to_string()),
                         },
                         install_url: match browser {
                             Browser::Firefox => Some(generate_firefox_install_url(id)),
@@ -289,22 +1043,31 @@ pub fn to_browser_configs(
                         Browser::Chrome => chrome_extensions.push(extension),
                         Browser::Firefox => firefox_extensions.push(extension),
                         Browser::Edge => edge_extensions.push(extension),
+                        Browser::Chromium | Browser::Vivaldi | Browser::Opera | Browser::Safari => {}
                     }
                 }
             }
         }
     }
 
+    let chrome_extensions = normalize_extensions(chrome_extensions);
+    let firefox_extensions = normalize_extensions(firefox_extensions);
+    let edge_extensions = normalize_extensions(edge_extensions);
+
     let chrome_config = if !chrome_extensions.is_empty()
         || chrome_disable_incognito.is_some()
         || chrome_disable_guest_mode.is_some()
         || chrome_allow_deleting_browser_history.is_some()
+        || chrome_disable_sync.is_some()
+        || chrome_restrict_signin.is_some()
     {
         Some(ChromeConfig {
             extensions: chrome_extensions,
             disable_incognito: chrome_disable_incognito,
             disable_guest_mode: chrome_disable_guest_mode,
             allow_deleting_browser_history: chrome_allow_deleting_browser_history,
+            disable_sync: chrome_disable_sync,
+            restrict_signin: chrome_restrict_signin,
         })
     } else {
         None
@@ -313,10 +1076,12 @@ pub fn to_browser_configs(
     let firefox_config =
         if !firefox_extensions.is_empty() 
         || firefox_disable_private_browsing.is_some()
+        || firefox_disable_sync.is_some()
     {
             Some(FirefoxConfig {
                 extensions: firefox_extensions,
                 disable_private_browsing: firefox_disable_private_browsing,
+                disable_sync: firefox_disable_sync,
             })
         } else {
             None
@@ -326,12 +1091,16 @@ pub fn to_browser_configs(
         || edge_disable_inprivate.is_some()
         || edge_disable_guest_mode.is_some()
         || edge_allow_deleting_browser_history.is_some()
+        || edge_disable_sync.is_some()
+        || edge_restrict_signin.is_some()
     {
         Some(EdgeConfig {
             extensions: edge_extensions,
             disable_inprivate: edge_disable_inprivate,
             disable_guest_mode: edge_disable_guest_mode,
             allow_deleting_browser_history: edge_allow_deleting_browser_history,
+            disable_sync: edge_disable_sync,
+            restrict_signin: edge_restrict_signin,
         })
     } else {
         None
@@ -340,37 +1109,203 @@ pub fn to_browser_configs(
     (chrome_config, firefox_config, edge_config)
 }
 
-/// Generate Firefox add-on install URL from extension ID
-fn generate_firefox_install_url(id: &str) -> String {
-    // For Firefox, we can generate the install URL from the extension ID
-    // Format: https://addons.mozilla.org/firefox/downloads/latest/{slug}/latest.xpi
-    // We'll use the ID as the slug for now (this may need adjustment based on actual add-on slugs)
-    format!("{}/{}/latest.xpi", FIREFOX_ADDONS_BASE, id)
-}
-
-/// Example configuration file with comprehensive documentation
+/// The part of a config for a Chromium-based browser other than Chrome and Edge
 ///
-/// The content is loaded from example-config.yaml at compile time
-pub const EXAMPLE_CONFIG: &str = include_str!("../../example-config.yaml");
+/// These browsers read the same policies as Chrome, so their part takes the
+/// shape of `ChromeConfig`. Like `to_browser_configs`, later policies win.
+pub fn chromium_based_config(config: &Config, browser: Browser) -> Option<ChromeConfig> {
+    let mut chromium = ChromeConfig {
+        extensions: Vec::new(),
+        disable_incognito: None,
+        disable_guest_mode: None,
+        allow_deleting_browser_history: None,
+        disable_sync: None,
+        restrict_signin: None,
+    };
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::Write;
+    for policy in config.policies.iter().filter(|p| p.browsers.contains(&browser)) {
+        if let Some(disable) = policy.disable_private_mode {
+            chromium.disable_incognito = Some(disable);
+        }
+        if let Some(disable) = policy.disable_guest_mode {
+            chromium.disable_guest_mode = Some(disable);
+        }
+        if let Some(allow) = policy.allow_deleting_browser_history {
+            chromium.allow_deleting_browser_history = Some(allow);
+        }
+        if let Some(disable) = policy.disable_sync {
+            chromium.disable_sync = Some(disable);
+        }
+        if let Some(restriction) = &policy.restrict_signin {
+            chromium.restrict_signin = Some(restriction.clone());
+        }
 
-    /// Helper to create a temporary YAML config file for testing
-    fn create_temp_yaml_config(content: &str) -> tempfile::NamedTempFile {
-        let mut file = tempfile::NamedTempFile::new().unwrap();
-        file.write_all(content.as_bytes()).unwrap();
-        file.flush().unwrap();
-        file
+        for ext_entry in &policy.extensions {
+            if let Some(id) = ext_entry.id.get_id(browser) {
+                chromium.extensions.push(Extension {
+                    id: id.to_string(),
+                    name: ext_entry.name.clone(),
+                    update_url: Some(DEFAULT_CHROME_UPDATE_URL.to_string()),
+                    install_url: None,
+                    settings: ext_entry.settings.clone(),
+                });
+            }
+        }
     }
+    chromium.extensions = normalize_extensions(chromium.extensions);
+
+    let configured = !chromium.extensions.is_empty()
+        || chromium.disable_incognito.is_some()
+        || chromium.disable_guest_mode.is_some()
+        || chromium.allow_deleting_browser_history.is_some()
+        || chromium.disable_sync.is_some()
+        || chromium.restrict_signin.is_some();
+    configured.then_some(chromium)
+}
 
-    // Config Validation Tests
+/// The part of a config for Safari
+///
+/// Safari has no extension policies; blocked websites are collected from
+/// every entry rather than overridden.
+pub fn safari_config(config: &Config) -> Option<SafariConfig> {
+    let mut safari = SafariConfig {
+        disable_private_browsing: None,
+        blocked_urls: Vec::new(),
+    };
+
+    for policy in config.policies.iter().filter(|p| p.browsers.contains(&Browser::Safari)) {
+        if let Some(disable) = policy.disable_private_mode {
+            safari.disable_private_browsing = Some(disable);
+        }
+        safari.blocked_urls.extend(policy.blocked_urls.iter().map(|url| url.trim().to_string()));
+    }
+    safari.blocked_urls.sort();
+    safari.blocked_urls.dedup();
+
+    let configured = safari.disable_private_browsing.is_some() || !safari.blocked_urls.is_empty();
+    configured.then_some(safari)
+}
+
+/// Name of the policy entry that produced each item applied to `browser`
+///
+/// Keys are extension IDs, blocked URLs and the `BrowserState` names of
+/// privacy settings (e.g. `disable_incognito`). Like `to_browser_configs`, a
+/// later policy wins when several set the same item.
+pub fn policy_sources(config: &Config, browser: Browser) -> BTreeMap<String, String> {
+    let mut sources = BTreeMap::new();
+
+    for policy in config.policies.iter().filter(|p| p.browsers.contains(&browser)) {
+        for (setting, _) in privacy_settings_for(policy, browser) {
+            let key = match (setting, browser) {
+                ("disable_private_mode", Browser::Firefox | Browser::Safari) => "disable_private_browsing",
+                ("disable_private_mode", Browser::Edge) => "disable_inprivate",
+                ("disable_private_mode", _) => "disable_incognito",
+                (other, _) => other,
+            };
+            sources.insert(key.to_string(), policy.name.clone());
+        }
+
+        if browser.supports_extensions() {
+            for ext in &policy.extensions {
+                if let Some(id) = ext.id.get_id(browser) {
+                    sources.insert(id.to_string(), policy.name.clone());
+                }
+            }
+        }
+        if browser == Browser::Safari {
+            for url in &policy.blocked_urls {
+                sources.insert(url.trim().to_string(), policy.name.clone());
+            }
+        }
+    }
+
+    sources
+}
+
+/// Sort extensions by ID and merge duplicates
+///
+/// Policy order then doesn't affect what gets written (or the config hash),
+/// and an extension listed by several policies is only installed once. The
+/// settings of duplicates are merged key by key, with later policies winning,
+/// so a base policy can be refined by a more specific one.
+fn normalize_extensions(extensions: Vec<Extension>) -> Vec<Extension> {
+    let mut by_id: BTreeMap<String, Extension> = BTreeMap::new();
+    for extension in extensions {
+        match by_id.get_mut(&extension.id) {
+            Some(existing) => {
+                existing.name = extension.name;
+                existing.settings.extend(extension.settings);
+            }
+            None => {
+                by_id.insert(extension.id.clone(), extension);
+            }
+        }
+    }
+    by_id.into_values().collect()
+}
+
+/// Generate Firefox add-on install URL from extension ID
+fn generate_firefox_install_url(id: &str) -> String {
+    // For Firefox, we can generate the install URL from the extension ID
+    // Format: https://addons.mozilla.org/firefox/downloads/latest/{slug}/latest.xpi
+    // We'll use the ID as the slug for now (this may need adjustment based on actual add-on slugs)
+    format!("{}/{}/latest.xpi", FIREFOX_ADDONS_BASE, id)
+}
+
+/// Example configuration file with comprehensive documentation
+///
+/// The content is loaded from example-config.yaml at compile time
+pub const EXAMPLE_CONFIG: &str = include_str!("../../example-config.yaml");
+
+/// A ready-made starting configuration for `config init --preset`
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub content: &'static str,
+}
+
+/// Presets shipped in the binary, loaded from presets/ at compile time
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "young-child",
+        description: "No private or guest windows, history kept, no sign-in, strict blocking",
+        content: include_str!("../../presets/young-child.yaml"),
+    },
+    Preset {
+        name: "teen",
+        description: "No private or guest windows, history kept, own account allowed",
+        content: include_str!("../../presets/teen.yaml"),
+    },
+    Preset {
+        name: "homework-mode",
+        description: "Fewer distractions: annoyance blocking, no private windows, optionally scheduled",
+        content: include_str!("../../presets/homework-mode.yaml"),
+    },
+];
+
+/// The preset called `name`
+pub fn preset(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|p| p.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// Helper to create a temporary YAML config file for testing
+    fn create_temp_yaml_config(content: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        file.flush().unwrap();
+        file
+    }
+
+    // Config Validation Tests
 
     #[test]
     fn config_with_no_policies_fails_validation() {
-        let config = Config { policies: vec![] };
+        let config = Config::default();
         assert!(validate_config(&config).is_err());
     }
 
@@ -419,6 +1354,23 @@ policies:
         assert!(load_config(file.path()).is_err());
     }
 
+    #[test]
+    fn chrome_id_outside_a_to_p_fails_validation() {
+        let yaml = r#"
+policies:
+  - name: Test Policy
+    browsers: [chrome]
+    extensions:
+      - name: Test Extension
+        id: ddkjiahejlhfcafbddmgiahcphecmpfh
+"#;
+        assert!(Config::from_yaml_str(yaml).is_ok());
+        for id in ["ddkjiahejlhfcafbddmgiahcphecmpfz", "0dkjiahejlhfcafbddmgiahcphecmpfh"] {
+            let err = Config::from_yaml_str(&yaml.replace("ddkjiahejlhfcafbddmgiahcphecmpfh", id)).unwrap_err();
+            assert!(format!("{:#}", err).contains("letters a to p"), "{}", id);
+        }
+    }
+
     #[test]
     fn policy_with_missing_browser_id_fails_validation() {
         let yaml = r#"
@@ -502,6 +1454,674 @@ policies:
         assert_eq!(config.policies.len(), 2);
     }
 
+    #[test]
+    fn policy_expires_at_is_parsed() {
+        let yaml = r#"
+policies:
+  - name: Exam week
+    browsers:
+      - chrome
+    disable_private_mode: true
+    expires_at: 2025-06-01T00:00:00Z
+"#;
+        let config = Config::from_yaml_str(yaml).unwrap();
+        assert_eq!(
+            config.policies[0].expires_at,
+            Some("2025-06-01T00:00:00Z".parse().unwrap())
+        );
+    }
+
+    // Expiry Tests
+
+    fn make_expiring_config() -> Config {
+        let yaml = r#"
+policies:
+  - name: Permanent
+    browsers:
+      - chrome
+    disable_private_mode: true
+  - name: Temporary
+    browsers:
+      - chrome
+    disable_guest_mode: true
+    expires_at: 2025-06-01T00:00:00Z
+"#;
+        Config::from_yaml_str(yaml).unwrap()
+    }
+
+    fn at(timestamp: &str) -> DateTime<Utc> {
+        timestamp.parse().unwrap()
+    }
+
+    #[test]
+    fn effective_config_keeps_unexpired_policies() {
+        let config = make_expiring_config();
+        let effective = config.effective(at("2025-05-31T23:59:59Z"));
+
+        assert_eq!(effective.policies.len(), 2);
+        assert!(config.expired_policies(at("2025-05-31T23:59:59Z")).is_empty());
+    }
+
+    #[test]
+    fn effective_config_drops_expired_policies() {
+        let config = make_expiring_config();
+        let now = at("2025-06-01T00:00:00Z");
+        let effective = config.effective(now);
+
+        assert_eq!(effective.policies.len(), 1);
+        assert_eq!(effective.policies[0].name, "Permanent");
+
+        let expired = config.expired_policies(now);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].name, "Temporary");
+
+        let (chrome, _, _) = to_browser_configs(&effective);
+        assert_eq!(chrome.unwrap().disable_guest_mode, None);
+    }
+
+    #[test]
+    fn expired_since_only_reports_newly_expired_policies() {
+        let config = make_expiring_config();
+
+        assert_eq!(
+            config
+                .expired_since(at("2025-05-01T00:00:00Z"), at("2025-06-02T00:00:00Z"))
+                .len(),
+            1
+        );
+        // Already expired when last applied
+        assert!(config
+            .expired_since(at("2025-06-01T12:00:00Z"), at("2025-06-02T00:00:00Z"))
+            .is_empty());
+        // Not expired yet
+        assert!(config
+            .expired_since(at("2025-05-01T00:00:00Z"), at("2025-05-02T00:00:00Z"))
+            .is_empty());
+    }
+
+    #[test]
+    fn presets_are_valid_configs() {
+        for preset in PRESETS {
+            let config = Config::from_yaml_str(preset.content)
+                .unwrap_or_else(|e| panic!("preset {} is invalid: {:#}", preset.name, e));
+            assert!(!config.policies.is_empty());
+        }
+        assert!(preset("teen").is_some());
+        assert!(preset("adult").is_none());
+    }
+
+    // Scheduling Tests
+
+    fn make_scheduled_config() -> Config {
+        let yaml = r#"
+policies:
+  - name: Everyday
+    browsers:
+      - chrome
+    disable_private_mode: false
+  - name: Paused
+    browsers:
+      - chrome
+    disable_guest_mode: true
+    enabled: false
+  - name: Exam week
+    browsers:
+      - chrome
+    disable_private_mode: true
+    active_from: 2025-06-02T00:00:00Z
+    active_until: 2025-06-09T00:00:00Z
+"#;
+        Config::from_yaml_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn disabled_and_scheduled_policies_apply_only_when_active() {
+        let config = make_scheduled_config();
+
+        let names = |now: &str| -> Vec<String> {
+            config.effective(at(now)).policies.into_iter().map(|p| p.name).collect()
+        };
+        assert_eq!(names("2025-06-01T00:00:00Z"), ["Everyday"]);
+        assert_eq!(names("2025-06-02T00:00:00Z"), ["Everyday", "Exam week"]);
+        assert_eq!(names("2025-06-09T00:00:00Z"), ["Everyday"]);
+
+        // The later, scheduled entry wins while it is active
+        let (chrome, _, _) = to_browser_configs(&config.effective(at("2025-06-03T00:00:00Z")));
+        assert_eq!(chrome.unwrap().disable_incognito, Some(true));
+
+        let inactive = config.inactive_policies(at("2025-06-01T00:00:00Z"));
+        let reasons: Vec<(&str, &str)> = inactive.iter().map(|(p, r)| (p.name.as_str(), r.as_str())).collect();
+        assert_eq!(
+            reasons,
+            [("Paused", "disabled"), ("Exam week", "active from 2025-06-02 00:00:00 UTC")]
+        );
+    }
+
+    #[test]
+    fn schedule_changes_since_reports_started_and_stopped_policies() {
+        let config = make_scheduled_config();
+
+        let (started, stopped) = config.schedule_changes_since(at("2025-06-01T00:00:00Z"), at("2025-06-03T00:00:00Z"));
+        assert_eq!(started.len(), 1);
+        assert!(stopped.is_empty());
+
+        let (started, stopped) = config.schedule_changes_since(at("2025-06-03T00:00:00Z"), at("2025-06-10T00:00:00Z"));
+        assert!(started.is_empty());
+        assert_eq!(stopped[0].name, "Exam week");
+
+        let (started, stopped) = config.schedule_changes_since(at("2025-06-03T00:00:00Z"), at("2025-06-04T00:00:00Z"));
+        assert!(started.is_empty() && stopped.is_empty());
+    }
+
+    #[test]
+    fn active_window_must_not_be_empty() {
+        let yaml = r#"
+policies:
+  - name: Backwards
+    browsers:
+      - chrome
+    disable_private_mode: true
+    active_from: 2025-06-09T00:00:00Z
+    active_until: 2025-06-02T00:00:00Z
+"#;
+        assert!(Config::from_yaml_str(yaml).is_err());
+    }
+
+    // Conflict Tests
+
+    #[test]
+    fn conflicting_privacy_settings_fail_validation() {
+        let yaml = r#"
+policies:
+  - name: Strict
+    browsers:
+      - chrome
+      - firefox
+    disable_private_mode: true
+  - name: Relaxed
+    browsers:
+      - chrome
+    disable_private_mode: false
+"#;
+        let err = format!("{:#}", Config::from_yaml_str(yaml).unwrap_err());
+        assert!(err.contains("'Strict' and 'Relaxed'"), "{}", err);
+        assert!(err.contains("disable_private_mode"));
+        assert!(err.contains("chrome"));
+    }
+
+    #[test]
+    fn matching_privacy_settings_pass_validation() {
+        let yaml = r#"
+policies:
+  - name: A
+    browsers:
+      - chrome
+    disable_private_mode: true
+  - name: B
+    browsers:
+      - chrome
+      - edge
+    disable_private_mode: true
+"#;
+        assert!(Config::from_yaml_str(yaml).is_ok());
+    }
+
+    #[test]
+    fn expiring_override_is_not_a_conflict() {
+        let yaml = r#"
+policies:
+  - name: Base
+    browsers: [chrome]
+    disable_private_mode: true
+  - name: Private browsing until Friday
+    browsers: [chrome]
+    disable_private_mode: false
+    expires_at: 2025-06-06T18:00:00Z
+"#;
+        assert!(Config::from_yaml_str(yaml).is_ok());
+    }
+
+    #[test]
+    fn guest_mode_conflict_ignored_for_firefox() {
+        let yaml = r#"
+policies:
+  - name: A
+    browsers:
+      - chrome
+    disable_guest_mode: true
+  - name: B
+    browsers:
+      - firefox
+    disable_guest_mode: false
+"#;
+        assert!(Config::from_yaml_str(yaml).is_ok());
+    }
+
+    fn host(hostname: &str, os: Platform, tags: &[&str]) -> HostContext {
+        HostContext {
+            hostname: hostname.to_string(),
+            os,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn glob_matches_wildcards_case_insensitively() {
+        assert!(glob_matches("kids-*", "KIDS-LAPTOP"));
+        assert!(glob_matches("*-pc", "family-pc"));
+        assert!(glob_matches("pc-?", "pc-1"));
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("a*b*c", "aXXbYYbc"));
+        assert!(!glob_matches("pc-?", "pc-10"));
+        assert!(!glob_matches("kids-*", "parents-laptop"));
+    }
+
+    #[test]
+    fn match_blocks_select_policies_for_host() {
+        let yaml = r#"
+policies:
+  - name: Everywhere
+    browsers: [chrome]
+    disable_guest_mode: true
+  - name: Kids laptop
+    browsers: [edge]
+    disable_private_mode: true
+    match:
+      hostname: ["kids-*"]
+      os: [windows]
+  - name: Shared desktop
+    browsers: [firefox]
+    disable_private_mode: true
+    match:
+      tags: [shared]
+"#;
+        let config = Config::from_yaml_str(yaml).unwrap();
+        let names = |host: &HostContext| -> Vec<String> {
+            config.for_host(host).policies.into_iter().map(|p| p.name).collect()
+        };
+
+        assert_eq!(
+            names(&host("kids-laptop", Platform::Windows, &[])),
+            ["Everywhere", "Kids laptop"]
+        );
+        // Every condition has to hold
+        assert_eq!(names(&host("kids-laptop", Platform::Linux, &[])), ["Everywhere"]);
+        assert_eq!(
+            names(&host("desktop", Platform::Linux, &["shared", "basement"])),
+            ["Everywhere", "Shared desktop"]
+        );
+    }
+
+    #[test]
+    fn empty_match_block_fails_validation() {
+        let yaml = r#"
+policies:
+  - name: Nowhere
+    browsers: [chrome]
+    disable_private_mode: true
+    match: {}
+"#;
+        let err = format!("{:#}", Config::from_yaml_str(yaml).unwrap_err());
+        assert!(err.contains("at least one of hostname, os or tags"), "{}", err);
+
+        let unknown = yaml.replace("match: {}", "match: { user: kid }");
+        assert!(Config::from_yaml_str(&unknown).is_err());
+    }
+
+    #[test]
+    fn conditional_policy_may_override_privacy_setting() {
+        let yaml = r#"
+policies:
+  - name: Strict
+    browsers: [chrome]
+    disable_private_mode: true
+  - name: Parents
+    browsers: [chrome]
+    disable_private_mode: false
+    match:
+      hostname: ["parents-*"]
+"#;
+        let config = Config::from_yaml_str(yaml).unwrap();
+        let parents = config.for_host(&host("parents-mac", Platform::MacOS, &[]));
+        let (chrome, _, _) = to_browser_configs(&parents);
+        assert_eq!(chrome.unwrap().disable_incognito, Some(false));
+    }
+
+    // Include Tests
+
+    #[test]
+    fn include_location_resolves_urls_and_paths() {
+        assert_eq!(
+            include_location("https://example.com/policies/kids.yaml", "base.yaml").unwrap(),
+            "https://example.com/policies/base.yaml"
+        );
+        assert_eq!(
+            include_location("https://example.com/policies/kids.yaml", "../shared/base.yaml").unwrap(),
+            "https://example.com/shared/base.yaml"
+        );
+        assert_eq!(
+            include_location("/etc/family-policy/kids.yaml", "base.yaml").unwrap(),
+            Path::new("/etc/family-policy").join("base.yaml").to_string_lossy()
+        );
+        assert!(include_location("/etc/family-policy/kids.yaml", "https://example.com/base.yaml").is_err());
+    }
+
+    #[test]
+    fn load_config_merges_includes_base_first() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(temp_dir.path().join("shared")).unwrap();
+        std::fs::write(
+            temp_dir.path().join("shared/base.yaml"),
+            r#"
+policies:
+  - name: Base
+    browsers: [chrome]
+    disable_guest_mode: true
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            temp_dir.path().join("shared/extensions.yaml"),
+            r#"
+include: [base.yaml]
+policies:
+  - name: Extensions
+    browsers: [chrome]
+    extensions:
+      - name: uBlock Origin Lite
+        id: ddkjiahejlhfcafbddmgiahcphecmpfh
+"#,
+        )
+        .unwrap();
+        let overlay = temp_dir.path().join("kids.yaml");
+        std::fs::write(
+            &overlay,
+            r#"
+include:
+  - shared/base.yaml
+  - shared/extensions.yaml
+policies:
+  - name: Kids laptop
+    browsers: [chrome]
+    disable_private_mode: true
+"#,
+        )
+        .unwrap();
+
+        let config = load_config(&overlay).unwrap();
+        let names: Vec<&str> = config.policies.iter().map(|p| p.name.as_str()).collect();
+        // base.yaml is included twice but merged once
+        assert_eq!(names, ["Base", "Extensions", "Kids laptop"]);
+        assert!(config.include.is_empty());
+    }
+
+    #[test]
+    fn include_cycle_is_an_error() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let a = temp_dir.path().join("a.yaml");
+        std::fs::write(&a, "include: [b.yaml]\npolicies: []\n").unwrap();
+        std::fs::write(temp_dir.path().join("b.yaml"), "include: [a.yaml]\npolicies: []\n").unwrap();
+
+        let err = format!("{:#}", load_config(&a).unwrap_err());
+        assert!(err.contains("Include cycle"), "{}", err);
+    }
+
+    #[test]
+    fn missing_include_is_an_error() {
+        let file = create_temp_yaml_config("include: [missing.yaml]\npolicies: []\n");
+        assert!(load_config(file.path()).is_err());
+    }
+
+    #[test]
+    fn duplicate_extension_id_in_policy_fails_validation() {
+        let yaml = r#"
+policies:
+  - name: Extensions
+    browsers:
+      - chrome
+    extensions:
+      - name: First
+        id: ddkjiahejlhfcafbddmgiahcphecmpfh
+      - name: Second
+        id: ddkjiahejlhfcafbddmgiahcphecmpfh
+"#;
+        let err = format!("{:#}", Config::from_yaml_str(yaml).unwrap_err());
+        assert!(err.contains("'First' and 'Second'"), "{}", err);
+    }
+
+    #[test]
+    fn extension_setting_conflicts_are_reported() {
+        let yaml = r#"
+policies:
+  - name: Base
+    browsers:
+      - chrome
+    extensions:
+      - name: uBO Lite
+        id: ddkjiahejlhfcafbddmgiahcphecmpfh
+        settings:
+          strictBlockMode: true
+          defaultFiltering: optimal
+  - name: Override
+    browsers:
+      - chrome
+    extensions:
+      - name: uBO Lite
+        id: ddkjiahejlhfcafbddmgiahcphecmpfh
+        settings:
+          strictBlockMode: false
+          defaultFiltering: optimal
+"#;
+        let config = Config::from_yaml_str(yaml).unwrap();
+        let warnings = extension_setting_conflicts(&config);
+
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("'Base' and 'Override'"));
+        assert!(warnings[0].contains("strictBlockMode"));
+    }
+
+    #[test]
+    fn to_browser_configs_sorts_and_dedupes_extensions() {
+        let yaml = r#"
+policies:
+  - name: First
+    browsers:
+      - chrome
+    extensions:
+      - name: Zeta
+        id: pppppppppppppppppppppppppppppppp
+      - name: Alpha
+        id: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa
+  - name: Second
+    browsers:
+      - chrome
+    extensions:
+      - name: Zeta (again)
+        id: pppppppppppppppppppppppppppppppp
+"#;
+        let config = Config::from_yaml_str(yaml).unwrap();
+        let (chrome, _, _) = to_browser_configs(&config);
+        let extensions = chrome.unwrap().extensions;
+
+        let ids: Vec<&str> = extensions.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec!["aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", "pppppppppppppppppppppppppppppppp"]
+        );
+        assert_eq!(extensions[1].name, "Zeta (again)");
+    }
+
+    #[test]
+    fn duplicate_extensions_merge_settings_with_later_policy_winning() {
+        let yaml = r#"
+policies:
+  - name: Base
+    browsers:
+      - chrome
+      - edge
+    extensions:
+      - name: uBO Lite
+        id: ddkjiahejlhfcafbddmgiahcphecmpfh
+        settings:
+          strictBlockMode: true
+          defaultFiltering: optimal
+  - name: Machine override
+    browsers:
+      - chrome
+    extensions:
+      - name: uBO Lite
+        id: ddkjiahejlhfcafbddmgiahcphecmpfh
+        settings:
+          strictBlockMode: false
+          disableFirstRunPage: true
+"#;
+        let config = Config::from_yaml_str(yaml).unwrap();
+        let (chrome, _, edge) = to_browser_configs(&config);
+
+        let chrome = chrome.unwrap();
+        assert_eq!(chrome.extensions.len(), 1);
+        let settings = &chrome.extensions[0].settings;
+        assert_eq!(settings.len(), 3);
+        assert_eq!(settings["strictBlockMode"], serde_json::json!(false));
+        assert_eq!(settings["defaultFiltering"], serde_json::json!("optimal"));
+        assert_eq!(settings["disableFirstRunPage"], serde_json::json!(true));
+
+        // The override only targets Chrome
+        let edge = edge.unwrap();
+        assert_eq!(edge.extensions[0].settings["strictBlockMode"], serde_json::json!(true));
+    }
+
+    #[test]
+    fn policy_sources_attribute_items_to_last_policy() {
+        let yaml = r#"
+policies:
+  - name: Base
+    browsers:
+      - chrome
+      - firefox
+    disable_private_mode: true
+    extensions:
+      - name: uBO Lite
+        id:
+          chrome: ddkjiahejlhfcafbddmgiahcphecmpfh
+          firefox: uBOLite@raymondhill.net
+  - name: Homework
+    browsers:
+      - chrome
+    disable_guest_mode: true
+    extensions:
+      - name: uBO Lite
+        id: ddkjiahejlhfcafbddmgiahcphecmpfh
+"#;
+        let config = Config::from_yaml_str(yaml).unwrap();
+
+        let chrome = policy_sources(&config, Browser::Chrome);
+        assert_eq!(chrome["ddkjiahejlhfcafbddmgiahcphecmpfh"], "Homework");
+        assert_eq!(chrome["disable_incognito"], "Base");
+        assert_eq!(chrome["disable_guest_mode"], "Homework");
+
+        let firefox = policy_sources(&config, Browser::Firefox);
+        assert_eq!(firefox["uBOLite@raymondhill.net"], "Base");
+        assert_eq!(firefox["disable_private_browsing"], "Base");
+        assert_eq!(firefox.len(), 2);
+
+        assert!(policy_sources(&config, Browser::Edge).is_empty());
+    }
+
+    #[test]
+    fn safari_ignores_extensions_and_sync() {
+        let yaml = r#"
+policies:
+  - name: Everywhere
+    browsers: [chrome, safari]
+    disable_private_mode: true
+    disable_sync: true
+    blocked_urls: [tiktok.com]
+    extensions:
+      - name: uBO Lite
+        id:
+          chrome: ddkjiahejlhfcafbddmgiahcphecmpfh
+  - name: Homework
+    browsers: [safari]
+    blocked_urls: [reddit.com, tiktok.com]
+"#;
+        let config = Config::from_yaml_str(yaml).unwrap();
+        validate_config(&config).unwrap();
+
+        let safari = safari_config(&config).unwrap();
+        assert_eq!(safari.disable_private_browsing, Some(true));
+        assert_eq!(safari.blocked_urls, ["reddit.com", "tiktok.com"]);
+
+        let sources = policy_sources(&config, Browser::Safari);
+        let keys: Vec<_> = sources.keys().map(String::as_str).collect();
+        assert_eq!(keys, ["disable_private_browsing", "reddit.com", "tiktok.com"]);
+        assert_eq!(sources["tiktok.com"], "Homework");
+
+        let chrome_only = Config::from_yaml_str(&yaml.replace("[chrome, safari]", "[chrome]").replace("[safari]", "[chrome]"))
+            .unwrap();
+        assert!(safari_config(&chrome_only).is_none());
+    }
+
+    #[test]
+    fn without_extension_keeps_it_for_other_browsers() {
+        let yaml = r#"
+policies:
+  - name: Base
+    browsers:
+      - chrome
+      - edge
+    extensions:
+      - name: uBO Lite
+        id: ddkjiahejlhfcafbddmgiahcphecmpfh
+      - name: Other
+        id: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa
+"#;
+        let config = Config::from_yaml_str(yaml).unwrap();
+
+        let reduced = config.without_extension("ddkjiahejlhfcafbddmgiahcphecmpfh", &[Browser::Chrome]);
+        let (chrome, _, edge) = to_browser_configs(&reduced);
+        let chrome_ids: Vec<String> = chrome.unwrap().extensions.into_iter().map(|e| e.id).collect();
+        assert_eq!(chrome_ids, vec!["aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"]);
+        assert_eq!(edge.unwrap().extensions.len(), 2);
+
+        let reduced = config.without_extension(
+            "ddkjiahejlhfcafbddmgiahcphecmpfh",
+            &[Browser::Chrome, Browser::Edge],
+        );
+        assert_eq!(reduced.policies[0].extensions.len(), 1);
+    }
+
+    #[test]
+    fn without_setting_splits_multi_browser_entries() {
+        let yaml = r#"
+policies:
+  - name: Base
+    browsers:
+      - chrome
+      - firefox
+    disable_private_mode: true
+"#;
+        let config = Config::from_yaml_str(yaml).unwrap();
+
+        let reduced = config.without_setting(PrivacySetting::PrivateMode, &[Browser::Chrome]);
+        let (chrome, firefox, _) = to_browser_configs(&reduced);
+        assert!(chrome.is_none());
+        assert_eq!(firefox.unwrap().disable_private_browsing, Some(true));
+        assert_eq!(reduced.policies.len(), 2);
+
+        // Settings that an entry doesn't set leave it untouched
+        let unchanged = config.without_setting(PrivacySetting::GuestMode, &[Browser::Chrome]);
+        assert_eq!(unchanged.policies.len(), 1);
+    }
+
+    #[test]
+    fn privacy_setting_parses_browser_specific_names() {
+        assert_eq!("incognito".parse(), Ok(PrivacySetting::PrivateMode));
+        assert_eq!("inprivate".parse(), Ok(PrivacySetting::PrivateMode));
+        assert_eq!("guest-mode".parse(), Ok(PrivacySetting::GuestMode));
+        assert!("cookies".parse::<PrivacySetting>().is_err());
+    }
+
     // Browser ID Map Tests
 
     #[test]
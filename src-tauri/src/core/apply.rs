@@ -1,7 +1,8 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use crate::config::Config;
-use crate::state::{State, load_state, save_state, compute_config_hash, create_state, delete_state, AppliedPolicies, BrowserState};
+use crate::browser::Browser;
+use crate::config::{Config, PrivacySetting};
+use crate::state::{State, load_state, save_state, applied_config_hash, create_state, delete_state, AppliedPolicies, BrowserState};
 use crate::policy;
 
 /// Result of applying policies
@@ -35,6 +36,14 @@ pub struct BrowserCounts {
     pub chrome: usize,
     pub firefox: usize,
     pub edge: usize,
+    #[serde(default)]
+    pub chromium: usize,
+    #[serde(default)]
+    pub vivaldi: usize,
+    #[serde(default)]
+    pub opera: usize,
+    #[serde(default)]
+    pub safari: usize,
 }
 
 /// Apply policies from a configuration
@@ -46,25 +55,55 @@ pub struct BrowserCounts {
 /// # Returns
 /// * `ApplyResult` with details of what was applied
 pub fn apply_policies_from_config(config: &Config, dry_run: bool) -> Result<ApplyResult> {
-    // Compute hash of new config
-    let config_hash = compute_config_hash(config)?;
+    if !config.include.is_empty() {
+        anyhow::bail!("Policy includes must be resolved before applying (use load_config)");
+    }
+
+    // Expired, disabled and out-of-schedule entries are ignored
+    let now = chrono::Utc::now();
+    let mut notices = crate::config::extension_setting_conflicts(config);
+    notices.extend(
+        config
+            .expired_policies(now)
+            .iter()
+            .map(|p| format!("Policy '{}' has expired and was ignored", p.name)),
+    );
+    notices.extend(
+        config
+            .inactive_policies(now)
+            .iter()
+            .map(|(p, reason)| format!("Policy '{}' is not in force ({}) and was ignored", p.name, reason)),
+    );
+    // Same hash as the agent records, so either can tell the other's apply
+    // is current
+    let host = crate::agent::current_host();
+    let config_hash = applied_config_hash(config, &host)?;
+    let hosted = config.for_host(&host);
+    let effective = config.effective(now);
+    let config = &effective;
 
     // Load current state
     let current_state = load_state().ok().flatten();
 
-    // Check if config has changed
-    let changed = current_state
-        .as_ref()
-        .map(|s| s.config_hash != config_hash)
-        .unwrap_or(true);
+    // Check if config has changed. An entry expiring or its schedule starting
+    // or ending also counts, even though the file didn't change.
+    let changed = current_state.as_ref().is_none_or(|s| {
+        let (started, stopped) = hosted.schedule_changes_since(s.last_updated, now);
+        s.config_hash != config_hash
+            || !started.is_empty()
+            || !stopped.is_empty()
+            || !hosted.expired_since(s.last_updated, now).is_empty()
+    });
 
     if !changed && !dry_run {
+        let mut warnings = vec!["No changes detected, policies already applied".to_string()];
+        warnings.extend(notices);
         return Ok(ApplyResult {
             changed: false,
             extensions_applied: BrowserCounts::default(),
             privacy_settings_applied: BrowserCounts::default(),
             errors: vec![],
-            warnings: vec!["No changes detected, policies already applied".to_string()],
+            warnings,
         });
     }
 
@@ -73,12 +112,24 @@ pub fn apply_policies_from_config(config: &Config, dry_run: bool) -> Result<Appl
         extensions_applied: BrowserCounts::default(),
         privacy_settings_applied: BrowserCounts::default(),
         errors: vec![],
-        warnings: vec![],
+        warnings: notices,
     };
 
     // Apply policies using existing policy module
     let applied_policies = policy::apply_policies(config, current_state.as_ref(), dry_run)
         .context("Failed to apply policies")?;
+    result.warnings.extend(
+        applied_policies
+            .conflicts
+            .iter()
+            .map(|conflict| format!("Conflicting policy: {}", conflict)),
+    );
+    result.warnings.extend(
+        applied_policies
+            .unverified
+            .iter()
+            .map(|problem| format!("Policy did not take effect: {}", problem)),
+    );
 
     // Count what was applied
     if let Some(ref chrome) = applied_policies.chrome {
@@ -93,14 +144,30 @@ pub fn apply_policies_from_config(config: &Config, dry_run: bool) -> Result<Appl
         result.extensions_applied.edge = edge.extensions.len();
         result.privacy_settings_applied.edge = count_privacy_in_state(edge);
     }
+    if let Some(ref chromium) = applied_policies.chromium {
+        result.extensions_applied.chromium = chromium.extensions.len();
+        result.privacy_settings_applied.chromium = count_privacy_in_state(chromium);
+    }
+    if let Some(ref vivaldi) = applied_policies.vivaldi {
+        result.extensions_applied.vivaldi = vivaldi.extensions.len();
+        result.privacy_settings_applied.vivaldi = count_privacy_in_state(vivaldi);
+    }
+    if let Some(ref opera) = applied_policies.opera {
+        result.extensions_applied.opera = opera.extensions.len();
+        result.privacy_settings_applied.opera = count_privacy_in_state(opera);
+    }
+    if let Some(ref safari) = applied_policies.safari {
+        result.privacy_settings_applied.safari = count_privacy_in_state(safari);
+    }
 
     if dry_run {
         return Ok(result);
     }
 
     // Save new state
-    let new_state = create_state(config, applied_policies)
+    let mut new_state = create_state(config, applied_policies)
         .context("Failed to create state")?;
+    new_state.config_hash = config_hash;
 
     save_state(&new_state).context("Failed to save state")?;
 
@@ -138,6 +205,21 @@ pub fn remove_all_policies(dry_run: bool) -> Result<RemovalResult> {
         result.extensions_removed.edge = edge.extensions.len();
         result.privacy_settings_removed.edge = count_privacy_in_state(edge);
     }
+    if let Some(ref chromium) = current_state.applied_policies.chromium {
+        result.extensions_removed.chromium = chromium.extensions.len();
+        result.privacy_settings_removed.chromium = count_privacy_in_state(chromium);
+    }
+    if let Some(ref vivaldi) = current_state.applied_policies.vivaldi {
+        result.extensions_removed.vivaldi = vivaldi.extensions.len();
+        result.privacy_settings_removed.vivaldi = count_privacy_in_state(vivaldi);
+    }
+    if let Some(ref opera) = current_state.applied_policies.opera {
+        result.extensions_removed.opera = opera.extensions.len();
+        result.privacy_settings_removed.opera = count_privacy_in_state(opera);
+    }
+    if let Some(ref safari) = current_state.applied_policies.safari {
+        result.privacy_settings_removed.safari = count_privacy_in_state(safari);
+    }
 
     if dry_run {
         return Ok(result);
@@ -160,6 +242,147 @@ pub fn remove_all_policies(dry_run: bool) -> Result<RemovalResult> {
     Ok(result)
 }
 
+/// A single applied item for [`remove_item`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemovalTarget {
+    Extension(String),
+    Setting(PrivacySetting),
+}
+
+impl RemovalTarget {
+    fn is_applied(&self, state: &BrowserState, browser: Browser) -> bool {
+        match self {
+            RemovalTarget::Extension(id) => state.extensions.contains(id),
+            RemovalTarget::Setting(setting) => state.privacy_setting(*setting, browser).is_some(),
+        }
+    }
+
+    fn strip(&self, config: &Config, browsers: &[Browser]) -> Config {
+        match self {
+            RemovalTarget::Extension(id) => config.without_extension(id, browsers),
+            RemovalTarget::Setting(setting) => config.without_setting(*setting, browsers),
+        }
+    }
+}
+
+impl std::fmt::Display for RemovalTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RemovalTarget::Extension(id) => write!(f, "extension '{}'", id),
+            RemovalTarget::Setting(setting) => write!(f, "setting '{}'", setting.as_str()),
+        }
+    }
+}
+
+/// Remove one extension or privacy setting from the applied policies
+///
+/// `config` is the configuration the policies were applied from. It is first
+/// narrowed to what the state says is applied (so earlier removals stick),
+/// then the item is stripped for `browsers` and the result applied. The state
+/// records the reduced config's hash, so applying the full config again
+/// restores the item.
+///
+/// # Returns
+/// * The browsers the item was removed from
+pub fn remove_item(
+    config: &Config,
+    target: &RemovalTarget,
+    browsers: &[Browser],
+    dry_run: bool,
+) -> Result<Vec<Browser>> {
+    let mut state = load_state()
+        .context("Failed to load state")?
+        .ok_or_else(|| anyhow::anyhow!("No state file found, nothing to remove"))?;
+
+    let affected: Vec<Browser> = browsers
+        .iter()
+        .copied()
+        .filter(|b| {
+            state
+                .applied_policies
+                .get(*b)
+                .is_some_and(|s| target.is_applied(s, *b))
+        })
+        .collect();
+
+    if affected.is_empty() {
+        anyhow::bail!("{} is not applied to any of the selected browsers", target);
+    }
+
+    let applied = restrict_to_state(&config.effective(chrono::Utc::now()), &state.applied_policies)?;
+    let reduced = target.strip(&applied, &affected);
+
+    let applied_policies = policy::apply_policies(&reduced, Some(&state), dry_run)
+        .context("Failed to apply policies")?;
+
+    if !dry_run {
+        state.config_hash = applied_config_hash(&reduced, &crate::agent::current_host())?;
+        state.last_updated = chrono::Utc::now();
+        state.applied_policies = applied_policies;
+        save_state(&state).context("Failed to save state")?;
+    }
+
+    Ok(affected)
+}
+
+/// Drop everything from `config` that isn't currently applied
+///
+/// Fails if something is applied that `config` doesn't contain, since
+/// applying the result would then remove more than asked.
+fn restrict_to_state(config: &Config, applied: &AppliedPolicies) -> Result<Config> {
+    let mut restricted = config.clone();
+    let (chrome, firefox, edge) = crate::config::to_browser_configs(config);
+    let configured = [
+        (Browser::Chrome, chrome.map(|c| c.extensions)),
+        (Browser::Firefox, firefox.map(|c| c.extensions)),
+        (Browser::Edge, edge.map(|c| c.extensions)),
+    ]
+    .into_iter()
+    .chain([Browser::Chromium, Browser::Vivaldi, Browser::Opera].map(|browser| {
+        (browser, crate::config::chromium_based_config(config, browser).map(|c| c.extensions))
+    }))
+    .chain([(Browser::Safari, None)]);
+
+    for (browser, extensions) in configured {
+        let state = applied.get(browser);
+        let extensions = extensions.unwrap_or_default();
+
+        let missing = state
+            .into_iter()
+            .flat_map(|s| &s.extensions)
+            .find(|id| !extensions.iter().any(|e| &e.id == *id));
+        if let Some(missing) = missing {
+            anyhow::bail!(
+                "Extension '{}' is applied to {} but not in the configuration; \
+                 is this the configuration that was applied?",
+                missing,
+                browser.as_str()
+            );
+        }
+
+        for ext in &extensions {
+            if !state.is_some_and(|s| s.extensions.contains(&ext.id)) {
+                restricted = restricted.without_extension(&ext.id, &[browser]);
+            }
+        }
+
+        for setting in PrivacySetting::ALL {
+            if setting.applies_to(browser)
+                && state.and_then(|s| s.privacy_setting(setting, browser)).is_none()
+            {
+                restricted = restricted.without_setting(setting, &[browser]);
+            }
+        }
+    }
+
+    let blocked = applied.safari.as_ref().map(|s| s.blocked_urls.as_slice()).unwrap_or_default();
+    for policy in &mut restricted.policies {
+        policy.blocked_urls.retain(|url| blocked.iter().any(|b| b == url.trim()));
+    }
+
+    Ok(restricted)
+}
+
 // Helper functions
 
 fn count_privacy_in_state(state: &BrowserState) -> usize {
@@ -176,7 +399,14 @@ fn count_privacy_in_state(state: &BrowserState) -> usize {
     if state.disable_guest_mode.is_some() {
         count += 1;
     }
-    count
+    if state.disable_sync.is_some() {
+        count += 1;
+    }
+    if state.restrict_signin.is_some() {
+        count += 1;
+    }
+    // Each blocked website counts as a setting
+    count + state.blocked_urls.len()
 }
 
 #[cfg(test)]
@@ -191,6 +421,9 @@ mod tests {
         assert_eq!(counts.chrome, 0);
         assert_eq!(counts.firefox, 0);
         assert_eq!(counts.edge, 0);
+        assert_eq!(counts.chromium, 0);
+        assert_eq!(counts.vivaldi, 0);
+        assert_eq!(counts.opera, 0);
     }
 
     #[test]
@@ -201,11 +434,13 @@ mod tests {
                 chrome: 2,
                 firefox: 1,
                 edge: 3,
+                ..Default::default()
             },
             privacy_settings_applied: BrowserCounts {
                 chrome: 1,
                 firefox: 1,
                 edge: 2,
+                ..Default::default()
             },
             errors: vec![],
             warnings: vec!["Test warning".to_string()],
@@ -232,4 +467,54 @@ mod tests {
         let json = serde_json::to_string(&result).unwrap();
         let _deserialized: RemovalResult = serde_json::from_str(&json).unwrap();
     }
+
+    #[test]
+    fn restrict_to_state_drops_items_that_are_not_applied() {
+        let config = Config::from_yaml_str(
+            r#"
+policies:
+  - name: Base
+    browsers:
+      - chrome
+    disable_private_mode: true
+    disable_guest_mode: true
+    extensions:
+      - name: Kept
+        id: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa
+      - name: Removed earlier
+        id: bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb
+"#,
+        )
+        .unwrap();
+
+        let mut chrome = BrowserState::new();
+        chrome.extensions = vec!["aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".to_string()];
+        chrome.disable_incognito = Some(true);
+        let applied = AppliedPolicies {
+            chrome: Some(chrome),
+            ..Default::default()
+        };
+
+        let restricted = restrict_to_state(&config, &applied).unwrap();
+        let (chrome, firefox, edge) = crate::config::to_browser_configs(&restricted);
+        let chrome = chrome.unwrap();
+        assert_eq!(chrome.extensions.len(), 1);
+        assert_eq!(chrome.extensions[0].id, "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+        assert_eq!(chrome.disable_incognito, Some(true));
+        assert_eq!(chrome.disable_guest_mode, None);
+        assert!(firefox.is_none() && edge.is_none());
+    }
+
+    #[test]
+    fn restrict_to_state_rejects_unknown_applied_extensions() {
+        let config = Config::default();
+        let mut chrome = BrowserState::new();
+        chrome.extensions = vec!["aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".to_string()];
+        let applied = AppliedPolicies {
+            chrome: Some(chrome),
+            ..Default::default()
+        };
+
+        assert!(restrict_to_state(&config, &applied).is_err());
+    }
 }
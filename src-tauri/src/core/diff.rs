@@ -1,7 +1,9 @@
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use crate::browser::Browser;
-use crate::config::{Config, ChromeConfig, FirefoxConfig, EdgeConfig, Extension};
+use crate::config::{Config, ChromeConfig, FirefoxConfig, EdgeConfig, Extension, SafariConfig, SigninRestriction};
+use crate::policy::PolicyConflict;
 use crate::state::{State, BrowserState};
 
 /// Complete policy diff across all browsers
@@ -10,7 +12,19 @@ pub struct PolicyDiff {
     pub chrome: Option<BrowserDiff>,
     pub firefox: Option<BrowserDiff>,
     pub edge: Option<BrowserDiff>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chromium: Option<BrowserDiff>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vivaldi: Option<BrowserDiff>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opera: Option<BrowserDiff>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub safari: Option<BrowserDiff>,
     pub summary: DiffSummary,
+    /// Values another management tool already sets differently (only filled
+    /// in by `generate_diff_with_conflicts`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<PolicyConflict>,
 }
 
 /// Diff for a single browser
@@ -28,14 +42,23 @@ pub enum ExtensionDiff {
     Added {
         id: String,
         name: String,
+        /// Policy entry the extension comes from
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<String>,
     },
     Removed {
         id: String,
         name: Option<String>,
+        /// Policy entry the extension comes from
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<String>,
     },
     Unchanged {
         id: String,
         name: String,
+        /// Policy entry the extension comes from
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<String>,
     },
 }
 
@@ -45,6 +68,9 @@ pub struct PrivacySettingDiff {
     pub setting_name: String,
     pub old_value: Option<String>,
     pub new_value: Option<String>,
+    /// Policy entry that sets (or, when removed, used to set) the value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
 }
 
 /// Summary of changes
@@ -64,45 +90,91 @@ pub struct DiffSummary {
 /// # Returns
 /// * `PolicyDiff` describing all changes
 pub fn generate_diff(new_config: &Config, current_state: Option<&State>) -> PolicyDiff {
-    let (new_chrome, new_firefox, new_edge) = crate::config::to_browser_configs(new_config);
+    // Expired entries won't be applied, so they aren't part of the diff
+    let new_config = new_config.effective(chrono::Utc::now());
+    let (new_chrome, new_firefox, new_edge) = crate::config::to_browser_configs(&new_config);
 
     let chrome_diff = new_chrome.map(|chrome_config| {
         let current_chrome = current_state
             .and_then(|s| s.applied_policies.chrome.as_ref());
-        generate_chrome_diff(&chrome_config, current_chrome)
+        let sources = crate::config::policy_sources(&new_config, Browser::Chrome);
+        generate_chrome_diff(Browser::Chrome, &chrome_config, current_chrome, &sources)
     });
 
     let firefox_diff = new_firefox.map(|firefox_config| {
         let current_firefox = current_state
             .and_then(|s| s.applied_policies.firefox.as_ref());
-        generate_firefox_diff(&firefox_config, current_firefox)
+        let sources = crate::config::policy_sources(&new_config, Browser::Firefox);
+        generate_firefox_diff(&firefox_config, current_firefox, &sources)
     });
 
     let edge_diff = new_edge.map(|edge_config| {
         let current_edge = current_state
             .and_then(|s| s.applied_policies.edge.as_ref());
-        generate_edge_diff(&edge_config, current_edge)
+        let sources = crate::config::policy_sources(&new_config, Browser::Edge);
+        generate_edge_diff(&edge_config, current_edge, &sources)
     });
 
-    let summary = create_summary(&chrome_diff, &firefox_diff, &edge_diff);
+    // These take Chrome's policies, so their diffs have the same shape
+    let [chromium_diff, vivaldi_diff, opera_diff] = [Browser::Chromium, Browser::Vivaldi, Browser::Opera].map(|browser| {
+        crate::config::chromium_based_config(&new_config, browser).map(|config| {
+            let current = current_state.and_then(|s| s.applied_policies.get(browser));
+            let sources = crate::config::policy_sources(&new_config, browser);
+            generate_chrome_diff(browser, &config, current, &sources)
+        })
+    });
+
+    let safari_diff = crate::config::safari_config(&new_config).map(|safari_config| {
+        let current_safari = current_state
+            .and_then(|s| s.applied_policies.safari.as_ref());
+        let sources = crate::config::policy_sources(&new_config, Browser::Safari);
+        generate_safari_diff(&safari_config, current_safari, &sources)
+    });
+
+    let summary = create_summary(&[
+        &chrome_diff,
+        &firefox_diff,
+        &edge_diff,
+        &chromium_diff,
+        &vivaldi_diff,
+        &opera_diff,
+        &safari_diff,
+    ]);
 
     PolicyDiff {
         chrome: chrome_diff,
         firefox: firefox_diff,
         edge: edge_diff,
+        chromium: chromium_diff,
+        vivaldi: vivaldi_diff,
+        opera: opera_diff,
+        safari: safari_diff,
         summary,
+        conflicts: Vec::new(),
     }
 }
 
+/// `generate_diff`, plus the values another management tool already sets
+///
+/// Unlike `generate_diff` this reads the browsers' policy locations on the
+/// current system.
+pub fn generate_diff_with_conflicts(new_config: &Config, current_state: Option<&State>) -> Result<PolicyDiff> {
+    let mut diff = generate_diff(new_config, current_state);
+    diff.conflicts = crate::policy::detect_conflicts(new_config, current_state.map(|s| &s.applied_policies))?;
+    Ok(diff)
+}
+
 fn generate_chrome_diff(
+    browser: Browser,
     new_config: &ChromeConfig,
     current_state: Option<&BrowserState>,
+    sources: &BTreeMap<String, String>,
 ) -> BrowserDiff {
-    let extensions = generate_extension_diffs(&new_config.extensions, current_state);
-    let privacy_settings = generate_chrome_privacy_diff(new_config, current_state);
+    let extensions = generate_extension_diffs(&new_config.extensions, current_state, sources);
+    let privacy_settings = generate_chrome_privacy_diff(new_config, current_state, sources);
 
     BrowserDiff {
-        browser: Browser::Chrome,
+        browser,
         extensions,
         privacy_settings,
     }
@@ -111,9 +183,10 @@ fn generate_chrome_diff(
 fn generate_firefox_diff(
     new_config: &FirefoxConfig,
     current_state: Option<&BrowserState>,
+    sources: &BTreeMap<String, String>,
 ) -> BrowserDiff {
-    let extensions = generate_extension_diffs(&new_config.extensions, current_state);
-    let privacy_settings = generate_firefox_privacy_diff(new_config, current_state);
+    let extensions = generate_extension_diffs(&new_config.extensions, current_state, sources);
+    let privacy_settings = generate_firefox_privacy_diff(new_config, current_state, sources);
 
     BrowserDiff {
         browser: Browser::Firefox,
@@ -125,9 +198,10 @@ fn generate_firefox_diff(
 fn generate_edge_diff(
     new_config: &EdgeConfig,
     current_state: Option<&BrowserState>,
+    sources: &BTreeMap<String, String>,
 ) -> BrowserDiff {
-    let extensions = generate_extension_diffs(&new_config.extensions, current_state);
-    let privacy_settings = generate_edge_privacy_diff(new_config, current_state);
+    let extensions = generate_extension_diffs(&new_config.extensions, current_state, sources);
+    let privacy_settings = generate_edge_privacy_diff(new_config, current_state, sources);
 
     BrowserDiff {
         browser: Browser::Edge,
@@ -136,9 +210,48 @@ fn generate_edge_diff(
     }
 }
 
+fn generate_safari_diff(
+    new_config: &SafariConfig,
+    current_state: Option<&BrowserState>,
+    sources: &BTreeMap<String, String>,
+) -> BrowserDiff {
+    let mut privacy_settings = Vec::new();
+
+    let old_private = current_state.and_then(|s| s.disable_private_browsing);
+    let new_private = new_config.disable_private_browsing;
+    if old_private != new_private {
+        privacy_settings.push(PrivacySettingDiff {
+            setting_name: "Disable Private Browsing".to_string(),
+            old_value: old_private.map(|v| v.to_string()),
+            new_value: new_private.map(|v| v.to_string()),
+            source: item_source("disable_private_browsing", sources, current_state),
+        });
+    }
+
+    // Each blocked website is a setting of its own, so the diff names it
+    let old_urls: &[String] = current_state.map(|s| s.blocked_urls.as_slice()).unwrap_or_default();
+    let added = new_config.blocked_urls.iter().filter(|url| !old_urls.contains(url));
+    let removed = old_urls.iter().filter(|url| !new_config.blocked_urls.contains(url));
+    for (url, blocked) in added.map(|url| (url, true)).chain(removed.map(|url| (url, false))) {
+        privacy_settings.push(PrivacySettingDiff {
+            setting_name: format!("Block {}", url),
+            old_value: (!blocked).then(|| "true".to_string()),
+            new_value: blocked.then(|| "true".to_string()),
+            source: item_source(url, sources, current_state),
+        });
+    }
+
+    BrowserDiff {
+        browser: Browser::Safari,
+        extensions: Vec::new(),
+        privacy_settings,
+    }
+}
+
 fn generate_extension_diffs(
     new_extensions: &[Extension],
     current_state: Option<&BrowserState>,
+    sources: &BTreeMap<String, String>,
 ) -> Vec<ExtensionDiff> {
     let mut diffs = Vec::new();
 
@@ -161,11 +274,13 @@ fn generate_extension_diffs(
             diffs.push(ExtensionDiff::Unchanged {
                 id: id.clone(),
                 name: name.clone(),
+                source: sources.get(id).cloned(),
             });
         } else {
             diffs.push(ExtensionDiff::Added {
                 id: id.clone(),
                 name: name.clone(),
+                source: sources.get(id).cloned(),
             });
         }
     }
@@ -176,6 +291,7 @@ fn generate_extension_diffs(
             diffs.push(ExtensionDiff::Removed {
                 id: id.clone(),
                 name: None,
+                source: item_source(id, sources, current_state),
             });
         }
     }
@@ -186,6 +302,7 @@ fn generate_extension_diffs(
 fn generate_chrome_privacy_diff(
     new_config: &ChromeConfig,
     current_state: Option<&BrowserState>,
+    sources: &BTreeMap<String, String>,
 ) -> Vec<PrivacySettingDiff> {
     let mut diffs = Vec::new();
 
@@ -196,6 +313,7 @@ fn generate_chrome_privacy_diff(
             setting_name: "Disable Incognito Mode".to_string(),
             old_value: old_incognito.map(|v| v.to_string()),
             new_value: new_incognito.map(|v| v.to_string()),
+            source: item_source("disable_incognito", sources, current_state),
         });
     }
 
@@ -206,6 +324,7 @@ fn generate_chrome_privacy_diff(
             setting_name: "Disable Guest Mode".to_string(),
             old_value: old_guest.map(|v| v.to_string()),
             new_value: new_guest.map(|v| v.to_string()),
+            source: item_source("disable_guest_mode", sources, current_state),
         });
     }
 
@@ -216,15 +335,24 @@ fn generate_chrome_privacy_diff(
             setting_name: "Allow Deleting Browser History".to_string(),
             old_value: old_history.map(|v| v.to_string()),
             new_value: new_history.map(|v| v.to_string()),
+            source: item_source("allow_deleting_browser_history", sources, current_state),
         });
     }
 
+    diffs.extend(generate_sync_signin_diff(
+        new_config.disable_sync,
+        new_config.restrict_signin.as_ref(),
+        current_state,
+        sources,
+    ));
+
     diffs
 }
 
 fn generate_firefox_privacy_diff(
     new_config: &FirefoxConfig,
     current_state: Option<&BrowserState>,
+    sources: &BTreeMap<String, String>,
 ) -> Vec<PrivacySettingDiff> {
     let mut diffs = Vec::new();
 
@@ -235,6 +363,18 @@ fn generate_firefox_privacy_diff(
             setting_name: "Disable Private Browsing".to_string(),
             old_value: old_private.map(|v| v.to_string()),
             new_value: new_private.map(|v| v.to_string()),
+            source: item_source("disable_private_browsing", sources, current_state),
+        });
+    }
+
+    let old_sync = current_state.and_then(|s| s.disable_sync);
+    let new_sync = new_config.disable_sync;
+    if old_sync != new_sync {
+        diffs.push(PrivacySettingDiff {
+            setting_name: "Disable Firefox Accounts".to_string(),
+            old_value: old_sync.map(|v| v.to_string()),
+            new_value: new_sync.map(|v| v.to_string()),
+            source: item_source("disable_sync", sources, current_state),
         });
     }
 
@@ -244,6 +384,7 @@ fn generate_firefox_privacy_diff(
 fn generate_edge_privacy_diff(
     new_config: &EdgeConfig,
     current_state: Option<&BrowserState>,
+    sources: &BTreeMap<String, String>,
 ) -> Vec<PrivacySettingDiff> {
     let mut diffs = Vec::new();
 
@@ -254,6 +395,7 @@ fn generate_edge_privacy_diff(
             setting_name: "Disable InPrivate Mode".to_string(),
             old_value: old_inprivate.map(|v| v.to_string()),
             new_value: new_inprivate.map(|v| v.to_string()),
+            source: item_source("disable_inprivate", sources, current_state),
         });
     }
 
@@ -264,6 +406,7 @@ fn generate_edge_privacy_diff(
             setting_name: "Disable Guest Mode".to_string(),
             old_value: old_guest.map(|v| v.to_string()),
             new_value: new_guest.map(|v| v.to_string()),
+            source: item_source("disable_guest_mode", sources, current_state),
         });
     }
 
@@ -274,22 +417,71 @@ fn generate_edge_privacy_diff(
             setting_name: "Allow Deleting Browser History".to_string(),
             old_value: old_history.map(|v| v.to_string()),
             new_value: new_history.map(|v| v.to_string()),
+            source: item_source("allow_deleting_browser_history", sources, current_state),
         });
     }
 
+    diffs.extend(generate_sync_signin_diff(
+        new_config.disable_sync,
+        new_config.restrict_signin.as_ref(),
+        current_state,
+        sources,
+    ));
+
     diffs
 }
 
-fn create_summary(
-    chrome: &Option<BrowserDiff>,
-    firefox: &Option<BrowserDiff>,
-    edge: &Option<BrowserDiff>,
-) -> DiffSummary {
+/// Sync and sign-in changes, which the Chromium-based browsers share
+fn generate_sync_signin_diff(
+    new_sync: Option<bool>,
+    new_signin: Option<&SigninRestriction>,
+    current_state: Option<&BrowserState>,
+    sources: &BTreeMap<String, String>,
+) -> Vec<PrivacySettingDiff> {
+    let mut diffs = Vec::new();
+
+    let old_sync = current_state.and_then(|s| s.disable_sync);
+    if old_sync != new_sync {
+        diffs.push(PrivacySettingDiff {
+            setting_name: "Disable Sync".to_string(),
+            old_value: old_sync.map(|v| v.to_string()),
+            new_value: new_sync.map(|v| v.to_string()),
+            source: item_source("disable_sync", sources, current_state),
+        });
+    }
+
+    let old_signin = current_state.and_then(|s| s.restrict_signin.as_ref());
+    if old_signin != new_signin {
+        diffs.push(PrivacySettingDiff {
+            setting_name: "Restrict Sign-in".to_string(),
+            old_value: old_signin.map(|v| v.to_string()),
+            new_value: new_signin.map(|v| v.to_string()),
+            source: item_source("restrict_signin", sources, current_state),
+        });
+    }
+
+    diffs
+}
+
+/// Policy entry an item comes from, falling back to the one recorded in the
+/// current state for items that are being removed
+fn item_source(
+    key: &str,
+    sources: &BTreeMap<String, String>,
+    current_state: Option<&BrowserState>,
+) -> Option<String> {
+    sources
+        .get(key)
+        .or_else(|| current_state.and_then(|s| s.sources.get(key)))
+        .cloned()
+}
+
+fn create_summary(diffs: &[&Option<BrowserDiff>]) -> DiffSummary {
     let mut total_additions = 0;
     let mut total_removals = 0;
     let mut total_changes = 0;
 
-    for diff in [chrome, firefox, edge].iter().filter_map(|d| d.as_ref()) {
+    for diff in diffs.iter().filter_map(|d| d.as_ref()) {
         for ext_diff in &diff.extensions {
             match ext_diff {
                 ExtensionDiff::Added { .. } => total_additions += 1,
@@ -324,11 +516,38 @@ pub fn print_diff(diff: &PolicyDiff) {
         print_browser_diff("Edge", edge_diff);
     }
 
+    if let Some(chromium_diff) = &diff.chromium {
+        print_browser_diff("Chromium", chromium_diff);
+    }
+
+    if let Some(vivaldi_diff) = &diff.vivaldi {
+        print_browser_diff("Vivaldi", vivaldi_diff);
+    }
+
+    if let Some(opera_diff) = &diff.opera {
+        print_browser_diff("Opera", opera_diff);
+    }
+
+    if let Some(safari_diff) = &diff.safari {
+        print_browser_diff("Safari", safari_diff);
+    }
+
+    if !diff.conflicts.is_empty() {
+        println!("Conflicts with other management tools:");
+        for conflict in &diff.conflicts {
+            println!("  ! {}", conflict);
+        }
+        println!();
+    }
+
     println!();
     println!("Summary:");
     println!("  Additions: {}", diff.summary.total_additions);
     println!("  Removals: {}", diff.summary.total_removals);
     println!("  Changes: {}", diff.summary.total_changes);
+    if !diff.conflicts.is_empty() {
+        println!("  Conflicts: {}", diff.conflicts.len());
+    }
 }
 
 fn print_browser_diff(browser_name: &str, diff: &BrowserDiff) {
@@ -336,12 +555,12 @@ fn print_browser_diff(browser_name: &str, diff: &BrowserDiff) {
 
     for ext_diff in &diff.extensions {
         match ext_diff {
-            ExtensionDiff::Added { id, name } => {
-                println!("  + Add extension: {} ({})", name, id);
+            ExtensionDiff::Added { id, name, source } => {
+                println!("  + Add extension: {} ({}){}", name, id, from_policy(source));
             }
-            ExtensionDiff::Removed { id, name } => {
+            ExtensionDiff::Removed { id, name, source } => {
                 let name_str = name.as_deref().unwrap_or("unknown");
-                println!("  - Remove extension: {} ({})", name_str, id);
+                println!("  - Remove extension: {} ({}){}", name_str, id, from_policy(source));
             }
             ExtensionDiff::Unchanged { .. } => {
                 // Don't print unchanged items unless verbose mode
@@ -353,14 +572,24 @@ fn print_browser_diff(browser_name: &str, diff: &BrowserDiff) {
         let old_val = privacy_diff.old_value.as_deref().unwrap_or("none");
         let new_val = privacy_diff.new_value.as_deref().unwrap_or("none");
         println!(
-            "  ~ {}: {} -> {}",
-            privacy_diff.setting_name, old_val, new_val
+            "  ~ {}: {} -> {}{}",
+            privacy_diff.setting_name,
+            old_val,
+            new_val,
+            from_policy(&privacy_diff.source)
         );
     }
 
     println!();
 }
 
+fn from_policy(source: &Option<String>) -> String {
+    source
+        .as_ref()
+        .map(|name| format!(" [policy '{}']", name))
+        .unwrap_or_default()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -368,7 +597,7 @@ mod tests {
 
     #[test]
     fn test_diff_summary_empty() {
-        let summary = create_summary(&None, &None, &None);
+        let summary = create_summary(&[]);
         assert_eq!(summary.total_additions, 0);
         assert_eq!(summary.total_removals, 0);
         assert_eq!(summary.total_changes, 0);
@@ -384,11 +613,11 @@ mod tests {
             settings: HashMap::new(),
         }];
 
-        let diffs = generate_extension_diffs(&new_extensions, None);
+        let diffs = generate_extension_diffs(&new_extensions, None, &BTreeMap::new());
 
         assert_eq!(diffs.len(), 1);
         match &diffs[0] {
-            ExtensionDiff::Added { id, name } => {
+            ExtensionDiff::Added { id, name, .. } => {
                 assert_eq!(id, "test-id");
                 assert_eq!(name, "Test Extension");
             }
@@ -405,9 +634,13 @@ mod tests {
             disable_private_browsing: None,
             disable_guest_mode: None,
             allow_deleting_browser_history: None,
+            disable_sync: None,
+            restrict_signin: None,
+            blocked_urls: Vec::new(),
+            sources: Default::default(),
         };
 
-        let diffs = generate_extension_diffs(&[], Some(&current_state));
+        let diffs = generate_extension_diffs(&[], Some(&current_state), &BTreeMap::new());
 
         assert_eq!(diffs.len(), 1);
         match &diffs[0] {
@@ -435,9 +668,13 @@ mod tests {
             disable_private_browsing: None,
             disable_guest_mode: None,
             allow_deleting_browser_history: None,
+            disable_sync: None,
+            restrict_signin: None,
+            blocked_urls: Vec::new(),
+            sources: Default::default(),
         };
 
-        let diffs = generate_extension_diffs(&new_extensions, Some(&current_state));
+        let diffs = generate_extension_diffs(&new_extensions, Some(&current_state), &BTreeMap::new());
 
         assert_eq!(diffs.len(), 1);
         match &diffs[0] {
@@ -455,6 +692,8 @@ mod tests {
             disable_incognito: Some(true),
             disable_guest_mode: Some(false),
             allow_deleting_browser_history: None,
+            disable_sync: None,
+            restrict_signin: None,
         };
 
         let current_state = BrowserState {
@@ -464,12 +703,120 @@ mod tests {
             disable_private_browsing: None,
             disable_guest_mode: None,
             allow_deleting_browser_history: None,
+            disable_sync: None,
+            restrict_signin: None,
+            blocked_urls: Vec::new(),
+            sources: Default::default(),
         };
 
-        let diffs = generate_chrome_privacy_diff(&new_config, Some(&current_state));
+        let diffs = generate_chrome_privacy_diff(&new_config, Some(&current_state), &BTreeMap::new());
 
         assert_eq!(diffs.len(), 2); // incognito changed, guest mode added
         assert!(diffs.iter().any(|d| d.setting_name.contains("Incognito")));
         assert!(diffs.iter().any(|d| d.setting_name.contains("Guest")));
     }
+
+    #[test]
+    fn test_edge_sync_and_signin_diff() {
+        let new_config = EdgeConfig {
+            extensions: vec![],
+            disable_inprivate: None,
+            disable_guest_mode: None,
+            allow_deleting_browser_history: None,
+            disable_sync: Some(true),
+            restrict_signin: Some(SigninRestriction::Pattern(".*@example\\.com".to_string())),
+        };
+
+        let mut current_state = BrowserState::new();
+        current_state.disable_sync = Some(true);
+        current_state.restrict_signin = Some(SigninRestriction::Restricted(true));
+
+        let diffs = generate_edge_privacy_diff(&new_config, Some(&current_state), &BTreeMap::new());
+
+        assert_eq!(diffs.len(), 1); // sync unchanged
+        assert_eq!(diffs[0].setting_name, "Restrict Sign-in");
+        assert_eq!(diffs[0].old_value.as_deref(), Some("true"));
+        assert_eq!(diffs[0].new_value.as_deref(), Some("'.*@example\\.com'"));
+    }
+
+    #[test]
+    fn test_safari_diff_lists_each_blocked_website() {
+        let new_config = SafariConfig {
+            disable_private_browsing: Some(true),
+            blocked_urls: vec!["reddit.com".to_string(), "tiktok.com".to_string()],
+        };
+        let mut current_state = BrowserState::new();
+        current_state.disable_private_browsing = Some(true);
+        current_state.blocked_urls = vec!["reddit.com".to_string(), "youtube.com".to_string()];
+
+        let diff = generate_safari_diff(&new_config, Some(&current_state), &BTreeMap::new());
+
+        let changes: Vec<_> = diff
+            .privacy_settings
+            .iter()
+            .map(|d| (d.setting_name.as_str(), d.old_value.as_deref(), d.new_value.as_deref()))
+            .collect();
+        assert_eq!(
+            changes,
+            [("Block tiktok.com", None, Some("true")), ("Block youtube.com", Some("true"), None)]
+        );
+    }
+
+    #[test]
+    fn test_diff_attributes_items_to_policies() {
+        let new_extensions = vec![Extension {
+            id: "added-id".to_string(),
+            name: "Added".to_string(),
+            update_url: None,
+            install_url: None,
+            settings: HashMap::new(),
+        }];
+        let sources = BTreeMap::from([
+            ("added-id".to_string(), "Homework".to_string()),
+            ("disable_incognito".to_string(), "Base".to_string()),
+        ]);
+
+        let current_state = BrowserState {
+            extensions: vec!["removed-id".to_string()],
+            disable_incognito: None,
+            disable_inprivate: None,
+            disable_private_browsing: None,
+            disable_guest_mode: Some(true),
+            allow_deleting_browser_history: None,
+            disable_sync: None,
+            restrict_signin: None,
+            blocked_urls: Vec::new(),
+            sources: BTreeMap::from([
+                ("removed-id".to_string(), "Old policy".to_string()),
+                ("disable_guest_mode".to_string(), "Old policy".to_string()),
+            ]),
+        };
+
+        let diffs = generate_extension_diffs(&new_extensions, Some(&current_state), &sources);
+        for diff in &diffs {
+            match diff {
+                ExtensionDiff::Added { source, .. } => assert_eq!(source.as_deref(), Some("Homework")),
+                ExtensionDiff::Removed { source, .. } => assert_eq!(source.as_deref(), Some("Old policy")),
+                ExtensionDiff::Unchanged { .. } => panic!("Unexpected Unchanged diff"),
+            }
+        }
+
+        let new_config = ChromeConfig {
+            extensions: new_extensions,
+            disable_incognito: Some(true),
+            disable_guest_mode: None,
+            allow_deleting_browser_history: None,
+            disable_sync: None,
+            restrict_signin: None,
+        };
+        let privacy = generate_chrome_privacy_diff(&new_config, Some(&current_state), &sources);
+        let source_of = |label: &str| {
+            privacy
+                .iter()
+                .find(|d| d.setting_name.contains(label))
+                .and_then(|d| d.source.as_deref())
+        };
+        assert_eq!(source_of("Incognito"), Some("Base"));
+        assert_eq!(source_of("Guest"), Some("Old policy"));
+    }
 }
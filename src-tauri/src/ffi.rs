@@ -0,0 +1,251 @@
+//! C-compatible FFI surface for the policy engine
+//!
+//! Lets non-Rust programs (a C# tray app, Python admin scripts) apply,
+//! remove and verify policies in-process instead of spawning the CLI.
+//!
+//! Conventions:
+//! - Strings passed in must be NUL-terminated UTF-8.
+//! - Functions returning `char*` hand back JSON that the caller must release
+//!   with `fp_string_free`. NULL means failure.
+//! - On failure, `fp_last_error` returns the error message for the calling
+//!   thread.
+//!
+//! The matching C declarations are in `include/family_policy.h`.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::agent::current_host;
+use crate::config::Config;
+use crate::core::privileges::{check_privileges, PrivilegeCheck};
+use crate::core::{self, ApplyResult, RemovalResult};
+use crate::state;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(err: &anyhow::Error) {
+    // Interior NULs would truncate the C string, so replace them
+    let message = format!("{:#}", err).replace('\0', " ");
+    LAST_ERROR.with(|e| *e.borrow_mut() = CString::new(message).ok());
+}
+
+fn clear_last_error() {
+    LAST_ERROR.with(|e| *e.borrow_mut() = None);
+}
+
+/// Run an operation, converting panics into errors so they never unwind into C
+fn guarded<T>(f: impl FnOnce() -> Result<T>) -> Result<T> {
+    catch_unwind(AssertUnwindSafe(f))
+        .unwrap_or_else(|_| Err(anyhow::anyhow!("Internal error (panic) in family-policy")))
+}
+
+/// Borrow a C string argument as `&str`
+///
+/// # Safety
+/// `ptr` must be NULL or point to a valid NUL-terminated string.
+unsafe fn read_str<'a>(ptr: *const c_char, name: &str) -> Result<&'a str> {
+    if ptr.is_null() {
+        anyhow::bail!("Argument '{}' must not be NULL", name);
+    }
+
+    unsafe { CStr::from_ptr(ptr) }
+        .to_str()
+        .with_context(|| format!("Argument '{}' is not valid UTF-8", name))
+}
+
+/// Convert a result into an owned JSON C string (NULL on error)
+fn json_result<T: Serialize>(result: Result<T>) -> *mut c_char {
+    let json = result.and_then(|value| {
+        let json = serde_json::to_string(&value).context("Failed to serialize result")?;
+        CString::new(json).context("Result contains a NUL byte")
+    });
+
+    match json {
+        Ok(json) => {
+            clear_last_error();
+            json.into_raw()
+        }
+        Err(e) => {
+            set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Apply policies from a YAML document
+///
+/// Returns an `ApplyResult` serialized as JSON, or NULL on error. Applying
+/// requires administrator privileges unless `dry_run` is set.
+///
+/// # Safety
+/// `yaml` must be NULL or point to a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fp_apply_policy_yaml(yaml: *const c_char, dry_run: bool) -> *mut c_char {
+    json_result(guarded(|| -> Result<ApplyResult> {
+        let yaml = unsafe { read_str(yaml, "yaml") }?;
+        check_privileges(PrivilegeCheck::admin_or_dry_run(), dry_run)?;
+
+        let config = Config::from_yaml_str(yaml)?.for_host(&current_host());
+        core::apply_policies_from_config(&config, dry_run)
+    }))
+}
+
+/// Remove all policies created by this tool
+///
+/// Returns a `RemovalResult` serialized as JSON, or NULL on error. Removal
+/// requires administrator privileges unless `dry_run` is set.
+#[unsafe(no_mangle)]
+pub extern "C" fn fp_remove_policies(dry_run: bool) -> *mut c_char {
+    json_result(guarded(|| -> Result<RemovalResult> {
+        check_privileges(PrivilegeCheck::admin_or_dry_run(), dry_run)?;
+        core::remove_all_policies(dry_run)
+    }))
+}
+
+/// Verify that the given YAML document is the currently applied policy
+///
+/// Returns 1 if the applied state matches the document, 0 if it does not
+/// (or nothing has been applied yet), and -1 on error.
+///
+/// # Safety
+/// `yaml` must be NULL or point to a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fp_verify(yaml: *const c_char) -> c_int {
+    let result = guarded(|| -> Result<bool> {
+        let yaml = unsafe { read_str(yaml, "yaml") }?;
+        let config = Config::from_yaml_str(yaml)?;
+        let config_hash = state::applied_config_hash(&config, &current_host())?;
+
+        Ok(state::load_state()?
+            .map(|s| s.config_hash == config_hash)
+            .unwrap_or(false))
+    });
+
+    match result {
+        Ok(applied) => {
+            clear_last_error();
+            c_int::from(applied)
+        }
+        Err(e) => {
+            set_last_error(&e);
+            -1
+        }
+    }
+}
+
+/// Get the last error message for the calling thread
+///
+/// Returns a copy the caller must release with `fp_string_free`, or NULL if
+/// the last call succeeded.
+#[unsafe(no_mangle)]
+pub extern "C" fn fp_last_error() -> *mut c_char {
+    LAST_ERROR.with(|e| {
+        e.borrow()
+            .as_ref()
+            .map(|msg| msg.clone().into_raw())
+            .unwrap_or(std::ptr::null_mut())
+    })
+}
+
+/// Release a string returned by this library
+///
+/// # Safety
+/// `ptr` must be NULL or a pointer previously returned by this library that
+/// has not already been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fp_string_free(ptr: *mut c_char) {
+    if !ptr.is_null() {
+        drop(unsafe { CString::from_raw(ptr) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn take_last_error() -> Option<String> {
+        let ptr = fp_last_error();
+        if ptr.is_null() {
+            return None;
+        }
+        let message = unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned();
+        unsafe { fp_string_free(ptr) };
+        Some(message)
+    }
+
+    #[test]
+    fn apply_with_null_yaml_returns_null_and_sets_error() {
+        let result = unsafe { fp_apply_policy_yaml(std::ptr::null(), true) };
+        assert!(result.is_null());
+
+        let error = take_last_error().unwrap();
+        assert!(error.contains("must not be NULL"));
+    }
+
+    #[test]
+    fn apply_with_invalid_yaml_returns_null() {
+        let yaml = CString::new("policies: [").unwrap();
+        let result = unsafe { fp_apply_policy_yaml(yaml.as_ptr(), true) };
+        assert!(result.is_null());
+        assert!(take_last_error().is_some());
+    }
+
+    #[test]
+    fn verify_with_invalid_config_returns_error_code() {
+        let yaml = CString::new("policies: []").unwrap();
+        assert_eq!(unsafe { fp_verify(yaml.as_ptr()) }, -1);
+        assert!(take_last_error().unwrap().contains("at least one policy"));
+    }
+
+    #[test]
+    fn verify_matches_the_hash_the_agent_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let _state = state::use_test_state_dir(dir.path());
+        // An expired entry and one for another machine, which the agent
+        // keeps and drops respectively when hashing
+        let yaml = r#"
+policies:
+  - name: Privacy
+    browsers: [chrome]
+    disable_private_mode: true
+  - name: Last week
+    browsers: [chrome]
+    disable_guest_mode: true
+    expires_at: 2020-01-01T00:00:00Z
+  - name: Elsewhere
+    browsers: [edge]
+    disable_private_mode: true
+    match:
+      hostname: [no-such-host-*]
+"#;
+        let mut applied = state::State::new_agent();
+        applied.config_hash =
+            state::applied_config_hash(&Config::from_yaml_str(yaml).unwrap(), &current_host()).unwrap();
+        state::save_state(&applied).unwrap();
+
+        let yaml = CString::new(yaml).unwrap();
+        assert_eq!(unsafe { fp_verify(yaml.as_ptr()) }, 1);
+    }
+
+    #[test]
+    fn json_result_clears_previous_error() {
+        set_last_error(&anyhow::anyhow!("previous failure"));
+
+        let ptr = json_result(Ok(vec![1, 2, 3]));
+        let json = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { fp_string_free(ptr) };
+
+        assert_eq!(json, "[1,2,3]");
+        assert!(take_last_error().is_none());
+    }
+
+    #[test]
+    fn string_free_accepts_null() {
+        unsafe { fp_string_free(std::ptr::null_mut()) };
+    }
+}
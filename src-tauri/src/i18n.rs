@@ -0,0 +1,269 @@
+//! Translations of user-facing text
+//!
+//! Messages live in bundles written in a subset of Fluent (`.ftl`) syntax:
+//! `id = text` with `{ $name }` placeholders, indented lines continuing the
+//! text on a new line, and `#` comments. The bundles in `locales/` are
+//! built in; more can be added without a rebuild as `<locale>.ftl` files
+//! (e.g. `fr.ftl`, `pt-BR.ftl`) in a `locales` directory next to the agent
+//! configuration, where they also override built-in messages.
+//!
+//! The agent uses `agent.locale` if it is set and the system locale
+//! (`LC_ALL`, `LC_MESSAGES`, `LANG`) otherwise; the UI passes the
+//! webview's language. A message missing from a locale falls back to the
+//! base language (`pt` for `pt-BR`), then to English, then to its id.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// The locale every other one falls back to
+pub const DEFAULT_LOCALE: &str = "en";
+
+const BUILT_IN: &[(&str, &str)] = &[
+    ("en", include_str!("../../locales/en.ftl")),
+    ("de", include_str!("../../locales/de.ftl")),
+];
+
+/// Parse a bundle into message ids and their text
+pub fn parse_bundle(source: &str) -> Result<HashMap<String, String>> {
+    let mut messages = HashMap::new();
+    let mut current: Option<(String, String)> = None;
+    let mut blank_lines = 0;
+
+    for (number, line) in source.lines().enumerate() {
+        if line.trim().is_empty() {
+            blank_lines += 1;
+            continue;
+        }
+        if line.starts_with(char::is_whitespace) {
+            let Some((_, text)) = &mut current else {
+                anyhow::bail!("Line {}: indented text outside a message", number + 1);
+            };
+            if !text.is_empty() {
+                text.push_str(&"\n".repeat(blank_lines + 1));
+            }
+            // Continuation lines are indented by four spaces; anything beyond
+            // that is kept
+            text.push_str(line.strip_prefix("    ").unwrap_or(line.trim_start()));
+            blank_lines = 0;
+            continue;
+        }
+
+        blank_lines = 0;
+        if let Some((id, text)) = current.take() {
+            messages.insert(id, text);
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        let Some((id, text)) = line.split_once('=') else {
+            anyhow::bail!("Line {}: expected 'id = text'", number + 1);
+        };
+        let id = id.trim();
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            anyhow::bail!("Line {}: invalid message id '{}'", number + 1, id);
+        }
+        current = Some((id.to_string(), text.trim().to_string()));
+    }
+    if let Some((id, text)) = current {
+        messages.insert(id, text);
+    }
+
+    Ok(messages)
+}
+
+/// Turn a POSIX or BCP 47 locale name into BCP 47 (`de_DE.UTF-8` → `de-DE`)
+///
+/// Returns `None` for the `C`/`POSIX` locales, which say nothing about
+/// language.
+pub fn normalize_locale(raw: &str) -> Option<String> {
+    let name = raw.split(['.', '@']).next().unwrap_or_default().trim();
+    if name.is_empty() || name == "C" || name == "POSIX" {
+        return None;
+    }
+    Some(name.replace('_', "-"))
+}
+
+/// The locale the system is set to, if any
+pub fn system_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| normalize_locale(&value))
+}
+
+/// Where extra bundles are looked for
+pub fn locales_dir() -> Option<PathBuf> {
+    crate::agent::get_agent_config_path()
+        .ok()
+        .map(|path| path.with_file_name("locales"))
+}
+
+/// Messages for one locale, with fallbacks already resolved
+#[derive(Debug, Clone)]
+pub struct Localizer {
+    locale: String,
+    messages: HashMap<String, String>,
+}
+
+impl Localizer {
+    /// Load `locale`'s messages from the built-in bundles and `extra_dir`
+    pub fn new(locale: &str, extra_dir: Option<&Path>) -> Self {
+        let mut chain = vec![DEFAULT_LOCALE.to_string()];
+        if let Some((language, _)) = locale.split_once('-') {
+            chain.push(language.to_string());
+        }
+        chain.push(locale.to_string());
+        chain.dedup();
+
+        // Later (more specific) bundles override earlier ones
+        let mut messages = HashMap::new();
+        for name in &chain {
+            if let Some((_, source)) = BUILT_IN.iter().find(|(built_in, _)| built_in == name) {
+                match parse_bundle(source) {
+                    Ok(bundle) => messages.extend(bundle),
+                    Err(e) => tracing::warn!("Invalid built-in {} translations: {:#}", name, e),
+                }
+            }
+            let Some(dir) = extra_dir else {
+                continue;
+            };
+            let path = dir.join(format!("{}.ftl", name));
+            let Ok(source) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            match parse_bundle(&source) {
+                Ok(bundle) => messages.extend(bundle),
+                Err(e) => tracing::warn!("Ignoring {}: {:#}", path.display(), e),
+            }
+        }
+
+        Self {
+            locale: locale.to_string(),
+            messages,
+        }
+    }
+
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// Every message, for handing to the UI
+    pub fn messages(&self) -> &HashMap<String, String> {
+        &self.messages
+    }
+
+    /// The message `id` with its placeholders filled in from `args`
+    ///
+    /// An unknown id is returned as is, so a missing translation shows up
+    /// without breaking anything.
+    pub fn format(&self, id: &str, args: &[(&str, &dyn Display)]) -> String {
+        match self.messages.get(id) {
+            Some(pattern) => fill(pattern, args),
+            None => id.to_string(),
+        }
+    }
+}
+
+/// Replace `{ $name }` placeholders in `pattern`
+///
+/// Placeholders without a value are left in place.
+fn fill(pattern: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut out = String::with_capacity(pattern.len());
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        let placeholder = &rest[start..start + len + 1];
+        let inner = placeholder[1..placeholder.len() - 1].trim();
+        match inner.strip_prefix('$').and_then(|name| args.iter().find(|(arg, _)| *arg == name)) {
+            Some((_, value)) => out.push_str(&value.to_string()),
+            None => out.push_str(placeholder),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+static LOCALIZER: OnceLock<Localizer> = OnceLock::new();
+
+/// Choose the locale for this process's messages
+///
+/// `configured` wins over the system locale. Only the first call has any
+/// effect; messages formatted before it use the system locale.
+pub fn init(configured: Option<&str>) {
+    let locale = configured
+        .and_then(normalize_locale)
+        .or_else(system_locale)
+        .unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+    let _ = LOCALIZER.set(Localizer::new(&locale, locales_dir().as_deref()));
+}
+
+/// The message `id` in this process's locale (see `Localizer::format`)
+pub fn tr(id: &str, args: &[(&str, &dyn Display)]) -> String {
+    LOCALIZER
+        .get_or_init(|| {
+            let locale = system_locale().unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+            Localizer::new(&locale, locales_dir().as_deref())
+        })
+        .format(id, args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_multiline_messages_and_comments() {
+        let bundle = parse_bundle(
+            "# comment\nshort = One line\nlong =\n    First\n      indented { $x }\n\n    Last\nnext = x\n",
+        )
+        .unwrap();
+
+        assert_eq!(bundle["short"], "One line");
+        assert_eq!(bundle["long"], "First\n  indented { $x }\n\nLast");
+        assert_eq!(bundle["next"], "x");
+        assert!(parse_bundle("no equals sign").is_err());
+    }
+
+    #[test]
+    fn falls_back_through_base_language_to_english() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("de-AT.ftl"), "agent-updated-title = Agent aktualisiert (AT)\n").unwrap();
+
+        let austrian = Localizer::new("de-AT", Some(dir.path()));
+        assert_eq!(austrian.format("agent-updated-title", &[]), "Agent aktualisiert (AT)");
+        assert_eq!(
+            austrian.format("agent-updated", &[("version", &"1.2.0")]),
+            "Der Agent wurde auf Version 1.2.0 aktualisiert"
+        );
+
+        let unknown = Localizer::new("xx", None);
+        assert_eq!(unknown.format("agent-updated", &[("version", &"1.2.0")]), "The agent was updated to version 1.2.0");
+        assert_eq!(unknown.format("no-such-message", &[]), "no-such-message");
+    }
+
+    #[test]
+    fn every_built_in_bundle_matches_english() {
+        let english = parse_bundle(BUILT_IN[0].1).unwrap();
+        for (name, source) in BUILT_IN {
+            let bundle = parse_bundle(source).unwrap();
+            let mut missing: Vec<_> = english.keys().filter(|id| !bundle.contains_key(*id)).collect();
+            missing.sort();
+            assert!(missing.is_empty(), "{} is missing {:?}", name, missing);
+        }
+    }
+
+    #[test]
+    fn normalizes_posix_locale_names() {
+        assert_eq!(normalize_locale("de_DE.UTF-8").as_deref(), Some("de-DE"));
+        assert_eq!(normalize_locale("pt-BR").as_deref(), Some("pt-BR"));
+        assert_eq!(normalize_locale("C.UTF-8"), None);
+    }
+}
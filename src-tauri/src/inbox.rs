@@ -0,0 +1,234 @@
+//! Persistent notification inbox
+//!
+//! The agent records alerts here (failed applies, restored tampering, vetoed
+//! updates) so a parent who missed the log or a toast can catch up later with
+//! `family-policy inbox` or from the admin UI. The inbox is stored next to the
+//! state file and is world-readable like it.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Maximum number of notifications kept; the oldest are dropped first
+const MAX_NOTIFICATIONS: usize = 100;
+
+/// Severity of a notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+impl NotificationLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationLevel::Info => "info",
+            NotificationLevel::Warning => "warning",
+            NotificationLevel::Error => "error",
+        }
+    }
+}
+
+/// A single inbox entry
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Notification {
+    pub id: u64,
+    pub timestamp: DateTime<Utc>,
+    pub level: NotificationLevel,
+    pub title: String,
+    pub message: String,
+    #[serde(default)]
+    pub read: bool,
+}
+
+/// List of recent notifications, oldest first
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Inbox {
+    #[serde(default)]
+    pub notifications: Vec<Notification>,
+
+    /// Id given to the next notification (ids are never reused)
+    #[serde(default)]
+    pub next_id: u64,
+}
+
+impl Inbox {
+    /// Add a notification, dropping the oldest ones past the size limit
+    pub fn push(&mut self, level: NotificationLevel, title: &str, message: &str) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.notifications.push(Notification {
+            id,
+            timestamp: Utc::now(),
+            level,
+            title: title.to_string(),
+            message: message.to_string(),
+            read: false,
+        });
+
+        if self.notifications.len() > MAX_NOTIFICATIONS {
+            let excess = self.notifications.len() - MAX_NOTIFICATIONS;
+            self.notifications.drain(..excess);
+        }
+
+        id
+    }
+
+    pub fn unread_count(&self) -> usize {
+        self.notifications.iter().filter(|n| !n.read).count()
+    }
+
+    /// Mark one notification as read; returns false if the id is unknown
+    pub fn mark_read(&mut self, id: u64) -> bool {
+        match self.notifications.iter_mut().find(|n| n.id == id) {
+            Some(notification) => {
+                notification.read = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn mark_all_read(&mut self) {
+        for notification in &mut self.notifications {
+            notification.read = true;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.notifications.clear();
+    }
+}
+
+/// Get the path of the inbox file (next to the state file)
+pub fn get_inbox_path() -> Result<PathBuf> {
+    Ok(crate::state::get_state_path()?.with_file_name("inbox.json"))
+}
+
+/// Load the inbox, returning an empty one if none has been written yet
+pub fn load_inbox() -> Result<Inbox> {
+    load_inbox_from(&get_inbox_path()?)
+}
+
+/// Save the inbox
+pub fn save_inbox(inbox: &Inbox) -> Result<()> {
+    save_inbox_to(&get_inbox_path()?, inbox)
+}
+
+fn load_inbox_from(path: &Path) -> Result<Inbox> {
+    if !path.exists() {
+        return Ok(Inbox::default());
+    }
+
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read inbox: {}", path.display()))?;
+
+    serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse inbox: {}", path.display()))
+}
+
+fn save_inbox_to(path: &Path, inbox: &Inbox) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).with_context(|| {
+            format!("Failed to create state directory: {}", parent.display())
+        })?;
+    }
+
+    let content = serde_json::to_string_pretty(inbox)
+        .context("Failed to serialize inbox")?;
+
+    crate::platform::common::atomic_write(path, content.as_bytes())
+        .with_context(|| format!("Failed to write inbox: {}", path.display()))?;
+
+    // World-readable like the state file so the user UI can show it
+    crate::platform::common::set_file_permissions(path, 0o644)?;
+
+    Ok(())
+}
+
+/// Record a notification in the inbox
+///
+/// Failing to record an alert must never make the operation that raised it
+/// fail, so errors are only logged.
+pub fn notify(level: NotificationLevel, title: &str, message: &str) {
+    let result = load_inbox().and_then(|mut inbox| {
+        inbox.push(level, title, message);
+        save_inbox(&inbox)
+    });
+
+    if let Err(e) = result {
+        tracing::warn!("Failed to record notification '{}': {:#}", title, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_assigns_increasing_ids_and_starts_unread() {
+        let mut inbox = Inbox::default();
+        let first = inbox.push(NotificationLevel::Error, "Apply failed", "boom");
+        let second = inbox.push(NotificationLevel::Info, "Restored", "ok");
+
+        assert_eq!(first, 0);
+        assert_eq!(second, 1);
+        assert_eq!(inbox.unread_count(), 2);
+    }
+
+    #[test]
+    fn push_drops_oldest_past_limit() {
+        let mut inbox = Inbox::default();
+        for i in 0..MAX_NOTIFICATIONS + 5 {
+            inbox.push(NotificationLevel::Info, &format!("n{}", i), "");
+        }
+
+        assert_eq!(inbox.notifications.len(), MAX_NOTIFICATIONS);
+        assert_eq!(inbox.notifications[0].id, 5);
+        assert_eq!(inbox.next_id, (MAX_NOTIFICATIONS + 5) as u64);
+    }
+
+    #[test]
+    fn mark_read_updates_single_notification() {
+        let mut inbox = Inbox::default();
+        let id = inbox.push(NotificationLevel::Warning, "a", "");
+        inbox.push(NotificationLevel::Warning, "b", "");
+
+        assert!(inbox.mark_read(id));
+        assert!(!inbox.mark_read(42));
+        assert_eq!(inbox.unread_count(), 1);
+
+        inbox.mark_all_read();
+        assert_eq!(inbox.unread_count(), 0);
+    }
+
+    #[test]
+    fn clear_keeps_id_sequence() {
+        let mut inbox = Inbox::default();
+        inbox.push(NotificationLevel::Info, "a", "");
+        inbox.clear();
+
+        assert!(inbox.notifications.is_empty());
+        assert_eq!(inbox.push(NotificationLevel::Info, "b", ""), 1);
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("inbox.json");
+
+        assert!(load_inbox_from(&path).unwrap().notifications.is_empty());
+
+        let mut inbox = Inbox::default();
+        inbox.push(NotificationLevel::Error, "Apply failed", "network down");
+        save_inbox_to(&path, &inbox).unwrap();
+
+        let loaded = load_inbox_from(&path).unwrap();
+        assert_eq!(loaded.notifications, inbox.notifications);
+        assert_eq!(loaded.next_id, 1);
+    }
+}
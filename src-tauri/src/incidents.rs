@@ -0,0 +1,276 @@
+//! Log of events suggesting enforcement was bypassed
+//!
+//! The agent records an incident when it finds it was stopped without a clean
+//! shutdown, the state file no longer matches the policy it applied, or policy
+//! values were removed behind its back. Each on its own can be innocent (a
+//! power cut, an upgrade), so `family-policy incidents` correlates them: a
+//! stop followed by tampering at the next start is the pattern of someone
+//! killing the agent to remove policies. Hook runs are logged here too, as
+//! an audit trail of the commands the agent ran as root. Stored next to the
+//! state file.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Maximum number of incidents kept; the oldest are dropped first
+const MAX_INCIDENTS: usize = 500;
+
+/// Tampering found within this long of an unexpected stop is attributed to it
+const CORRELATION_WINDOW_MINUTES: i64 = 10;
+
+/// Policy drift this many times within the summary period counts as repeated
+pub const REPEATED_DRIFT_THRESHOLD: usize = 3;
+
+/// What was detected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IncidentKind {
+    /// The agent wasn't shut down cleanly (killed, crashed or power lost)
+    UnexpectedStop,
+    /// The state file was deleted or doesn't match the applied policy
+    StateMismatch,
+    /// Applied policy values were changed or removed
+    PolicyDrift,
+    /// A browser the policy can't manage was found
+    UnmanagedBrowser,
+    /// A `pre_apply` or `post_apply` hook ran (not a sign of tampering on
+    /// its own)
+    HookRun,
+}
+
+impl IncidentKind {
+    pub fn description(&self) -> &'static str {
+        match self {
+            IncidentKind::UnexpectedStop => "Agent stopped unexpectedly",
+            IncidentKind::StateMismatch => "State file replaced or deleted",
+            IncidentKind::PolicyDrift => "Policy values removed or changed",
+            IncidentKind::UnmanagedBrowser => "Unmanaged browser installed",
+            IncidentKind::HookRun => "Hook command run",
+        }
+    }
+}
+
+/// A single detected event
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Incident {
+    pub timestamp: DateTime<Utc>,
+    pub kind: IncidentKind,
+    pub detail: String,
+}
+
+/// Recorded incidents, oldest first
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct IncidentLog {
+    #[serde(default)]
+    pub incidents: Vec<Incident>,
+}
+
+impl IncidentLog {
+    /// Add an incident, dropping the oldest ones past the size limit
+    pub fn push(&mut self, kind: IncidentKind, detail: &str) {
+        self.incidents.push(Incident {
+            timestamp: Utc::now(),
+            kind,
+            detail: detail.to_string(),
+        });
+
+        if self.incidents.len() > MAX_INCIDENTS {
+            let excess = self.incidents.len() - MAX_INCIDENTS;
+            self.incidents.drain(..excess);
+        }
+    }
+
+    /// Incidents recorded at or after `since`
+    pub fn since(&self, since: DateTime<Utc>) -> impl Iterator<Item = &Incident> {
+        self.incidents.iter().filter(move |i| i.timestamp >= since)
+    }
+
+    /// Tampering found shortly after an unexpected stop, paired with the stop
+    pub fn likely_bypasses(&self, since: DateTime<Utc>) -> Vec<(&Incident, &Incident)> {
+        let window = Duration::minutes(CORRELATION_WINDOW_MINUTES);
+        let incidents: Vec<&Incident> = self.since(since).collect();
+
+        incidents
+            .iter()
+            .enumerate()
+            .filter(|(_, stop)| stop.kind == IncidentKind::UnexpectedStop)
+            .filter_map(|(i, stop)| {
+                incidents[i + 1..]
+                    .iter()
+                    .take_while(|later| later.timestamp - stop.timestamp <= window)
+                    .find(|later| {
+                        !matches!(later.kind, IncidentKind::UnexpectedStop | IncidentKind::HookRun)
+                    })
+                    .map(|tampering| (*stop, *tampering))
+            })
+            .collect()
+    }
+}
+
+/// Get the path of the incident log (next to the state file)
+pub fn get_incidents_path() -> Result<PathBuf> {
+    Ok(crate::state::get_state_path()?.with_file_name("incidents.json"))
+}
+
+/// Load the incident log, returning an empty one if none has been written yet
+pub fn load_incidents() -> Result<IncidentLog> {
+    load_incidents_from(&get_incidents_path()?)
+}
+
+fn load_incidents_from(path: &Path) -> Result<IncidentLog> {
+    if !path.exists() {
+        return Ok(IncidentLog::default());
+    }
+
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read incident log: {}", path.display()))?;
+
+    serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse incident log: {}", path.display()))
+}
+
+fn save_incidents_to(path: &Path, log: &IncidentLog) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).with_context(|| {
+            format!("Failed to create state directory: {}", parent.display())
+        })?;
+    }
+
+    let content = serde_json::to_string_pretty(log)
+        .context("Failed to serialize incident log")?;
+
+    crate::platform::common::atomic_write(path, content.as_bytes())
+        .with_context(|| format!("Failed to write incident log: {}", path.display()))?;
+
+    // World-readable like the state file so the user UI can show it
+    crate::platform::common::set_file_permissions(path, 0o644)?;
+
+    Ok(())
+}
+
+/// Record an incident
+///
+/// Like inbox notifications, failing to record one is only logged.
+pub fn record(kind: IncidentKind, detail: &str) {
+    tracing::warn!("{}: {}", kind.description(), detail);
+
+    let result = get_incidents_path().and_then(|path| {
+        let mut log = load_incidents_from(&path)?;
+        log.push(kind, detail);
+        save_incidents_to(&path, &log)
+    });
+
+    if let Err(e) = result {
+        tracing::warn!("Failed to record incident: {:#}", e);
+    }
+}
+
+/// Path of the marker present while the agent daemon is running
+fn running_marker_path() -> Result<PathBuf> {
+    Ok(crate::state::get_state_path()?.with_file_name("agent.running"))
+}
+
+/// Note that the daemon started
+///
+/// Returns when the previous run started if it never reached
+/// [`agent_stopped`], i.e. it was killed, crashed or the machine lost power.
+pub fn agent_started() -> Result<Option<DateTime<Utc>>> {
+    let path = running_marker_path()?;
+
+    let previous = match std::fs::read_to_string(&path) {
+        Ok(content) => Some(
+            DateTime::parse_from_rfc3339(content.trim())
+                .map(|t| t.with_timezone(&Utc))
+                .unwrap_or(DateTime::UNIX_EPOCH),
+        ),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("Failed to read {}", path.display()));
+        }
+    };
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).with_context(|| {
+            format!("Failed to create state directory: {}", parent.display())
+        })?;
+    }
+    crate::platform::common::atomic_write(&path, Utc::now().to_rfc3339().as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    Ok(previous)
+}
+
+/// Note that the daemon is shutting down cleanly
+pub fn agent_stopped() -> Result<()> {
+    let path = running_marker_path()?;
+    match std::fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e).with_context(|| format!("Failed to remove {}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn incident(minutes_ago: i64, kind: IncidentKind) -> Incident {
+        Incident {
+            timestamp: Utc::now() - Duration::minutes(minutes_ago),
+            kind,
+            detail: String::new(),
+        }
+    }
+
+    #[test]
+    fn push_drops_oldest_past_limit() {
+        let mut log = IncidentLog::default();
+        for i in 0..MAX_INCIDENTS + 3 {
+            log.push(IncidentKind::PolicyDrift, &i.to_string());
+        }
+
+        assert_eq!(log.incidents.len(), MAX_INCIDENTS);
+        assert_eq!(log.incidents[0].detail, "3");
+    }
+
+    #[test]
+    fn tampering_right_after_unexpected_stop_is_a_likely_bypass() {
+        let log = IncidentLog {
+            incidents: vec![
+                incident(600, IncidentKind::UnexpectedStop),
+                // Unrelated: hours after the stop
+                incident(300, IncidentKind::PolicyDrift),
+                incident(60, IncidentKind::UnexpectedStop),
+                // Restoring the policy at startup runs the hooks
+                incident(60, IncidentKind::HookRun),
+                incident(59, IncidentKind::PolicyDrift),
+                incident(58, IncidentKind::StateMismatch),
+            ],
+        };
+
+        let bypasses = log.likely_bypasses(Utc::now() - Duration::days(1));
+        assert_eq!(bypasses.len(), 1);
+        assert_eq!(bypasses[0].0, &log.incidents[2]);
+        assert_eq!(bypasses[0].1, &log.incidents[4]);
+
+        // Outside the summary period
+        assert!(log.likely_bypasses(Utc::now() - Duration::minutes(30)).is_empty());
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("incidents.json");
+
+        assert!(load_incidents_from(&path).unwrap().incidents.is_empty());
+
+        let mut log = IncidentLog::default();
+        log.push(IncidentKind::StateMismatch, "state file deleted");
+        save_incidents_to(&path, &log).unwrap();
+
+        assert_eq!(load_incidents_from(&path).unwrap().incidents, log.incidents);
+    }
+}
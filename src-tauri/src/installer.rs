@@ -0,0 +1,144 @@
+//! Install scaffolding for platform packages
+//!
+//! The service registration and maintainer scripts each package format
+//! needs, kept under `packaging/installer/` and embedded in the binary so
+//! `family-policy package --target <target>` can write them out for a
+//! package build. The scripts only manage the service; installing the
+//! binary itself is left to the package tooling.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+const SYSTEMD_UNIT: &str = include_str!("../../packaging/linux/family-policy-agent.service");
+const LAUNCH_DAEMON: &str = include_str!("../../packaging/macos/com.family-policy.agent.plist");
+
+/// Package format to write scaffolding for
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PackageTarget {
+    /// Windows Installer (WiX fragment registering the service)
+    Msi,
+    /// macOS installer package (preinstall/postinstall scripts)
+    Pkg,
+    /// Debian package (maintainer scripts)
+    Deb,
+    /// RPM package (scriptlets)
+    Rpm,
+}
+
+impl PackageTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PackageTarget::Msi => "msi",
+            PackageTarget::Pkg => "pkg",
+            PackageTarget::Deb => "deb",
+            PackageTarget::Rpm => "rpm",
+        }
+    }
+}
+
+/// One file of a package's scaffolding
+#[derive(Debug, Clone)]
+pub struct ScaffoldFile {
+    /// Relative to the output directory
+    pub path: &'static str,
+    pub content: &'static str,
+    pub executable: bool,
+}
+
+const fn file(path: &'static str, content: &'static str, executable: bool) -> ScaffoldFile {
+    ScaffoldFile {
+        path,
+        content,
+        executable,
+    }
+}
+
+/// The files a `target` package build needs
+pub fn scaffolding(target: PackageTarget) -> Vec<ScaffoldFile> {
+    match target {
+        PackageTarget::Msi => vec![file(
+            "service.wxs",
+            include_str!("../../packaging/installer/msi/service.wxs"),
+            false,
+        )],
+        PackageTarget::Pkg => vec![
+            file("scripts/preinstall", include_str!("../../packaging/installer/pkg/preinstall"), true),
+            file("scripts/postinstall", include_str!("../../packaging/installer/pkg/postinstall"), true),
+            file("com.family-policy.agent.plist", LAUNCH_DAEMON, false),
+        ],
+        PackageTarget::Deb => vec![
+            file("DEBIAN/postinst", include_str!("../../packaging/installer/deb/postinst"), true),
+            file("DEBIAN/prerm", include_str!("../../packaging/installer/deb/prerm"), true),
+            file("DEBIAN/postrm", include_str!("../../packaging/installer/deb/postrm"), true),
+            file("family-policy-agent.service", SYSTEMD_UNIT, false),
+        ],
+        PackageTarget::Rpm => vec![
+            file("scriptlets/post.sh", include_str!("../../packaging/installer/rpm/post.sh"), false),
+            file("scriptlets/preun.sh", include_str!("../../packaging/installer/rpm/preun.sh"), false),
+            file("scriptlets/postun.sh", include_str!("../../packaging/installer/rpm/postun.sh"), false),
+            file("family-policy-agent.service", SYSTEMD_UNIT, false),
+        ],
+    }
+}
+
+/// Write `target`'s scaffolding under `output`, returning the files written
+///
+/// Existing files are only replaced with `force`.
+pub fn write_scaffolding(target: PackageTarget, output: &Path, force: bool) -> Result<Vec<PathBuf>> {
+    let files = scaffolding(target);
+
+    if !force
+        && let Some(existing) = files.iter().map(|f| output.join(f.path)).find(|p| p.exists())
+    {
+        anyhow::bail!("{} already exists (use --force to overwrite)", existing.display());
+    }
+
+    let mut written = Vec::new();
+    for file in files {
+        let path = output.join(file.path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+        std::fs::write(&path, file.content)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        let mode = if file.executable { 0o755 } else { 0o644 };
+        crate::platform::common::set_file_permissions(&path, mode)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaffolding_registers_the_service_for_each_target() {
+        let msi = scaffolding(PackageTarget::Msi);
+        assert!(msi[0].content.contains(r#"Arguments="run-service""#));
+
+        let pkg = scaffolding(PackageTarget::Pkg);
+        let postinstall = pkg.iter().find(|f| f.path == "scripts/postinstall").unwrap();
+        assert!(postinstall.executable);
+        assert!(postinstall.content.contains("launchctl bootstrap system"));
+
+        for target in [PackageTarget::Deb, PackageTarget::Rpm] {
+            let files = scaffolding(target);
+            assert!(files.iter().any(|f| f.content.contains("systemctl enable family-policy-agent.service")));
+            assert!(files.iter().any(|f| f.path == "family-policy-agent.service"));
+        }
+    }
+
+    #[test]
+    fn write_scaffolding_refuses_to_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let written = write_scaffolding(PackageTarget::Deb, dir.path(), false).unwrap();
+        assert_eq!(written.len(), 4);
+        assert!(dir.path().join("DEBIAN/postinst").exists());
+
+        assert!(write_scaffolding(PackageTarget::Deb, dir.path(), false).is_err());
+        assert!(write_scaffolding(PackageTarget::Deb, dir.path(), true).is_ok());
+    }
+}
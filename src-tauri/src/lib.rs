@@ -0,0 +1,37 @@
+//! Family Policy library
+//!
+//! The policy engine (config parsing, state tracking and the per-browser
+//! policy writers) lives in this library so it can be embedded by other
+//! programs through the C FFI in `ffi.rs`. The `family-policy` binary in
+//! `main.rs` is a thin CLI on top of it.
+//!
+//! On `wasm32` only the engine modules are built; the agent, CLI and UI need
+//! a real OS underneath them.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod agent;
+pub mod browser;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cli;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod commands;
+pub mod config;
+pub mod core;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ffi;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod i18n;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod inbox;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod incidents;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod installer;
+pub mod migrate;
+pub mod platform;
+pub mod policy;
+pub mod state;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ui;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -1,19 +1,9 @@
 use anyhow::Result;
 use clap::Parser;
 
-mod agent;
-mod browser;
-mod cli;
-mod commands;
-mod config;
-mod core;
-mod platform;
-mod policy;
-mod state;
-mod ui;
-
-use cli::{Args, Commands, ConfigCommands};
-use core::privileges::{check_privileges, PrivilegeCheck};
+use family_policy_lib::cli::{Args, Commands, ConfigCommands, SecretCommands};
+use family_policy_lib::core::privileges::{check_privileges, PrivilegeCheck};
+use family_policy_lib::{commands, ui};
 
 fn main() {
     if let Err(e) = run() {
@@ -33,11 +23,20 @@ fn run() -> Result<()> {
             commands::run_local_mode(args)
         }
         Some(Commands::Config { command }) => {
-            // Config init doesn't require admin
-            check_privileges(PrivilegeCheck::user(), false)?;
             match command {
-                ConfigCommands::Init { output, force } => {
-                    commands::config::init(output, force, args.verbose)
+                ConfigCommands::Init { output, force, preset } => {
+                    // Config init doesn't require admin
+                    check_privileges(PrivilegeCheck::user(), false)?;
+                    commands::config::init(output, force, preset.as_deref(), args.verbose)
+                }
+                ConfigCommands::Validate { online } => {
+                    check_privileges(PrivilegeCheck::user(), false)?;
+                    commands::config::validate(&args.config, online, args.verbose)
+                }
+                ConfigCommands::Show { effective, set } => {
+                    // The agent config is only readable by admins
+                    check_privileges(PrivilegeCheck::admin(), false)?;
+                    commands::config::show(effective, &set, args.verbose)
                 }
             }
         }
@@ -53,9 +52,21 @@ fn run() -> Result<()> {
             check_privileges(PrivilegeCheck::admin(), false)?;
             commands::agent::uninstall_service(args.verbose)
         }
-        Some(Commands::Start { no_daemon }) => {
+        Some(Commands::Package { target, output, force }) => {
+            check_privileges(PrivilegeCheck::user(), false)?;
+            commands::package::run(target, output, force, args.verbose)
+        }
+        Some(Commands::Provision { from, force }) => {
+            check_privileges(PrivilegeCheck::admin(), false)?;
+            commands::provision::run(&from, force, args.verbose)
+        }
+        Some(Commands::Start { no_daemon, set }) => {
             check_privileges(PrivilegeCheck::admin(), false)?;
-            commands::agent::start(no_daemon, args.verbose)
+            commands::agent::start(no_daemon, &set, args.verbose)
+        }
+        Some(Commands::RunService) => {
+            check_privileges(PrivilegeCheck::admin(), false)?;
+            commands::agent::run_service(args.verbose)
         }
         Some(Commands::Stop) => {
             check_privileges(PrivilegeCheck::admin(), false)?;
@@ -65,6 +76,10 @@ fn run() -> Result<()> {
             check_privileges(PrivilegeCheck::admin_or_dry_run(), args.dry_run)?;
             commands::agent::check_now(args.dry_run, args.verbose)
         }
+        Some(Commands::Approve) => {
+            check_privileges(PrivilegeCheck::admin(), false)?;
+            commands::agent::approve(args.verbose)
+        }
         Some(Commands::Status) => {
             check_privileges(PrivilegeCheck::user(), false)?;
             commands::agent::status(args.verbose)
@@ -73,6 +88,63 @@ fn run() -> Result<()> {
             check_privileges(PrivilegeCheck::user(), false)?;
             commands::agent::show_config(args.verbose)
         }
+        Some(Commands::Logs { lines, tail }) => {
+            check_privileges(PrivilegeCheck::admin(), false)?;
+            commands::logs::show(lines, tail)
+        }
+        Some(Commands::Remove { extension, setting, browser }) => {
+            check_privileges(PrivilegeCheck::admin_or_dry_run(), args.dry_run)?;
+            commands::remove::run(&args.config, extension, setting, browser, args.dry_run, args.verbose)
+        }
+        Some(Commands::Secret { command }) => {
+            // Secrets live in the credential store of the account the agent runs as
+            check_privileges(PrivilegeCheck::admin(), false)?;
+            match command {
+                SecretCommands::Set { name } => commands::secret::set(&name, args.verbose),
+                SecretCommands::Delete { name } => commands::secret::delete(&name, args.verbose),
+            }
+        }
+        Some(Commands::Inbox { unread, mark_read, clear }) => {
+            // Anyone can read the inbox; changing it needs admin
+            if mark_read || clear {
+                check_privileges(PrivilegeCheck::admin(), false)?;
+                if clear {
+                    commands::inbox::clear(args.verbose)
+                } else {
+                    commands::inbox::mark_read(args.verbose)
+                }
+            } else {
+                check_privileges(PrivilegeCheck::user(), false)?;
+                commands::inbox::show(unread, args.verbose)
+            }
+        }
+        Some(Commands::Incidents { days }) => {
+            check_privileges(PrivilegeCheck::user(), false)?;
+            commands::incidents::show(days, args.verbose)
+        }
+        Some(Commands::Inspect) => {
+            check_privileges(PrivilegeCheck::user(), false)?;
+            commands::inspect::run(&args.config, args.verbose)
+        }
+        Some(Commands::Report { output, days, week }) => {
+            check_privileges(PrivilegeCheck::user(), false)?;
+            let days = if week { 7 } else { days };
+            commands::report::run(days, &output, args.verbose)
+        }
+        Some(Commands::Doctor { format }) => {
+            check_privileges(PrivilegeCheck::user(), false)?;
+            commands::doctor::run(format, args.verbose)
+        }
+        Some(Commands::Uninstall { purge_history, yes }) => {
+            check_privileges(PrivilegeCheck::admin_or_dry_run(), args.dry_run)?;
+            commands::uninstall::run(purge_history, yes, args.dry_run, args.verbose)
+        }
+        Some(Commands::SelfUpdate { check }) => {
+            // Checking is harmless; replacing the binary needs admin
+            let check = check || args.dry_run;
+            check_privileges(PrivilegeCheck::admin_or_dry_run(), check)?;
+            commands::update::self_update(check, args.verbose)
+        }
         Some(Commands::UserUi { systray, window }) => {
             check_privileges(PrivilegeCheck::user(), false)?;
             let systray_mode = systray || !window; // Default to systray if neither specified
@@ -0,0 +1,129 @@
+//! Upgrading versioned files written by older releases
+//!
+//! A file's raw JSON is taken through a chain of migrations, one version at
+//! a time, before it is deserialized. Steps work on the JSON rather than on
+//! the current types so they can rename or restructure fields the current
+//! code no longer knows. Adding a field with `#[serde(default)]` doesn't
+//! need a migration; anything else does, along with a version bump.
+
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+/// One step from a file version to the next
+pub struct Migration {
+    pub from: &'static str,
+    pub to: &'static str,
+    /// Edit the top-level object in place (`version` is updated afterwards)
+    pub apply: fn(&mut Map<String, Value>) -> Result<()>,
+}
+
+/// What `upgrade` did
+#[derive(Debug, PartialEq, Eq)]
+pub enum Upgrade {
+    /// Already at the current version
+    Current,
+    /// Migrated from this version to the current one
+    Migrated { from: String },
+    /// A version there is no migration from (e.g. written by a newer release)
+    Unsupported { version: String },
+}
+
+/// Bring `document` up to version `current` using `migrations`
+///
+/// On `Unsupported` the document is left as it was.
+pub fn upgrade(document: &mut Value, current: &str, migrations: &[Migration]) -> Result<Upgrade> {
+    let object = document.as_object_mut().context("Not a JSON object")?;
+    let original = match object.get("version").and_then(Value::as_str) {
+        Some(version) => version.to_string(),
+        None => return Ok(Upgrade::Unsupported { version: "(none)".to_string() }),
+    };
+    if original == current {
+        return Ok(Upgrade::Current);
+    }
+
+    // Check the whole chain exists before changing anything
+    let mut steps = Vec::new();
+    let mut version = original.as_str();
+    while version != current {
+        match migrations.iter().find(|m| m.from == version) {
+            // A longer chain than there are migrations means a cycle
+            Some(step) if steps.len() < migrations.len() => {
+                steps.push(step);
+                version = step.to;
+            }
+            _ => return Ok(Upgrade::Unsupported { version: original }),
+        }
+    }
+
+    for step in steps {
+        (step.apply)(object)
+            .with_context(|| format!("Failed to migrate from version {} to {}", step.from, step.to))?;
+        object.insert("version".to_string(), Value::String(step.to.to_string()));
+    }
+
+    Ok(Upgrade::Migrated { from: original })
+}
+
+/// Where the copy of a file is kept before it is migrated or replaced
+///
+/// e.g. `state.json` at version 1.0 is backed up to `state.json.v1.0.bak`.
+pub fn backup_path(path: &Path, version: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{}.bak", version));
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rename_hash(object: &mut Map<String, Value>) -> Result<()> {
+        if let Some(hash) = object.remove("hash") {
+            object.insert("config_hash".to_string(), hash);
+        }
+        Ok(())
+    }
+
+    fn add_flag(object: &mut Map<String, Value>) -> Result<()> {
+        object.insert("flag".to_string(), json!(true));
+        Ok(())
+    }
+
+    const MIGRATIONS: &[Migration] = &[
+        Migration { from: "1", to: "2", apply: rename_hash },
+        Migration { from: "2", to: "3", apply: add_flag },
+    ];
+
+    #[test]
+    fn migrates_through_every_step() {
+        let mut document = json!({"version": "1", "hash": "abc"});
+
+        let result = upgrade(&mut document, "3", MIGRATIONS).unwrap();
+
+        assert_eq!(result, Upgrade::Migrated { from: "1".to_string() });
+        assert_eq!(document, json!({"version": "3", "config_hash": "abc", "flag": true}));
+    }
+
+    #[test]
+    fn leaves_current_and_unknown_versions_alone() {
+        let mut current = json!({"version": "3"});
+        assert_eq!(upgrade(&mut current, "3", MIGRATIONS).unwrap(), Upgrade::Current);
+
+        let mut newer = json!({"version": "4", "hash": "abc"});
+        assert_eq!(
+            upgrade(&mut newer, "3", MIGRATIONS).unwrap(),
+            Upgrade::Unsupported { version: "4".to_string() }
+        );
+        assert_eq!(newer, json!({"version": "4", "hash": "abc"}));
+    }
+
+    #[test]
+    fn backup_path_keeps_the_version() {
+        assert_eq!(
+            backup_path(Path::new("/var/lib/family-policy/state.json"), "1.0"),
+            PathBuf::from("/var/lib/family-policy/state.json.v1.0.bak")
+        );
+    }
+}
@@ -140,13 +140,11 @@ pub fn apply_json_file_with_preview(
         println!("JSON File: {}", file_path.display());
 
         // Try to read existing file
-        let existing_data = if file_path.exists() {
-            std::fs::read_to_string(file_path)
-                .ok()
-                .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
-        } else {
-            None
-        };
+        let existing_data = super::writer::writer()
+            .read_file(file_path)
+            .ok()
+            .flatten()
+            .and_then(|content| serde_json::from_slice::<serde_json::Value>(&content).ok());
 
         if existing_data.is_none() {
             println!("  Action: CREATE new file");
@@ -194,20 +192,14 @@ pub fn apply_json_file_with_preview(
         println!();
         Ok(())
     } else {
-        // Ensure parent directory exists
-        if let Some(parent) = file_path.parent() {
-            ensure_directory_exists(parent)?;
-        }
-
-        // Write JSON file
+        // Write JSON file (creating its directory), readable by all
         let content = serde_json::to_string_pretty(&data)
             .context("Failed to serialize JSON")?;
 
-        atomic_write(file_path, content.as_bytes())
+        super::writer::writer()
+            .write_file(file_path, content.as_bytes(), 0o644)
             .with_context(|| format!("Failed to write file: {}", file_path.display()))?;
 
-        set_permissions_readable_all(file_path)?;
-
         Ok(())
     }
 }
@@ -13,9 +13,6 @@ pub fn write_json_policy(
     policy_name: &str,
     data: serde_json::Value,
 ) -> Result<()> {
-    // Ensure policy directory exists
-    crate::platform::common::ensure_directory_exists(policy_dir)?;
-
     // Build file path
     let mut policy_path = policy_dir.to_path_buf();
     policy_path.push(format!("{}.json", policy_name));
@@ -24,13 +21,11 @@ pub fn write_json_policy(
     let content = serde_json::to_string_pretty(&data)
         .context("Failed to serialize JSON policy")?;
 
-    // Write atomically
-    crate::platform::common::atomic_write(&policy_path, content.as_bytes())
+    // Write atomically, creating the policy directory, readable by all
+    crate::platform::writer::writer()
+        .write_file(&policy_path, content.as_bytes(), 0o644)
         .with_context(|| format!("Failed to write policy file: {}", policy_path.display()))?;
 
-    // Set permissions (readable by all)
-    crate::platform::common::set_permissions_readable_all(&policy_path)?;
-
     Ok(())
 }
 
@@ -47,14 +42,14 @@ pub fn read_json_policy(
     let mut policy_path = policy_dir.to_path_buf();
     policy_path.push(format!("{}.json", policy_name));
 
-    if !policy_path.exists() {
+    let Some(content) = crate::platform::writer::writer()
+        .read_file(&policy_path)
+        .with_context(|| format!("Failed to read policy file: {}", policy_path.display()))?
+    else {
         return Ok(None);
-    }
+    };
 
-    let content = std::fs::read_to_string(&policy_path)
-        .with_context(|| format!("Failed to read policy file: {}", policy_path.display()))?;
-
-    let data: serde_json::Value = serde_json::from_str(&content)
+    let data: serde_json::Value = serde_json::from_slice(&content)
         .with_context(|| format!("Failed to parse policy file: {}", policy_path.display()))?;
 
     Ok(Some(data))
@@ -68,20 +63,13 @@ pub fn remove_json_policy(policy_dir: &Path, policy_name: &str) -> Result<()> {
     let mut policy_path = policy_dir.to_path_buf();
     policy_path.push(format!("{}.json", policy_name));
 
-    if policy_path.exists() {
-        std::fs::remove_file(&policy_path)
-            .with_context(|| format!("Failed to delete policy file: {}", policy_path.display()))?;
-    }
+    let writer = crate::platform::writer::writer();
+    writer
+        .remove_file(&policy_path)
+        .with_context(|| format!("Failed to delete policy file: {}", policy_path.display()))?;
 
     // Try to remove the directory if it's empty
-    if policy_dir.exists() {
-        if let Ok(mut entries) = std::fs::read_dir(policy_dir) {
-            if entries.next().is_none() {
-                // Directory is empty, remove it
-                let _ = std::fs::remove_dir(policy_dir);
-            }
-        }
-    }
+    writer.remove_dir_if_empty(policy_dir)?;
 
     Ok(())
 }
@@ -148,12 +148,55 @@ pub fn string_vec_to_plist_array(strings: Vec<String>) -> Value {
     )
 }
 
+/// Read a single key from a managed preferences plist
+///
+/// Returns `None` if the plist or key doesn't exist.
+#[cfg(target_os = "macos")]
+pub fn read_plist_value(bundle_id: &str, key: &str) -> Result<Option<Value>> {
+    let plist_path = get_plist_path(bundle_id)?;
+
+    if !plist_path.exists() {
+        return Ok(None);
+    }
+
+    let value = Value::from_file(&plist_path)
+        .with_context(|| format!("Failed to read plist file: {}", plist_path.display()))?;
+
+    Ok(value.as_dictionary().and_then(|dict| dict.get(key)).cloned())
+}
+
+/// Read every key in a managed preferences plist
+///
+/// Returns `None` if the plist doesn't exist.
+#[cfg(target_os = "macos")]
+pub fn read_plist(bundle_id: &str) -> Result<Option<plist::Dictionary>> {
+    let plist_path = get_plist_path(bundle_id)?;
+
+    if !plist_path.exists() {
+        return Ok(None);
+    }
+
+    let value = Value::from_file(&plist_path)
+        .with_context(|| format!("Failed to read plist file: {}", plist_path.display()))?;
+
+    match value {
+        Value::Dictionary(dict) => Ok(Some(dict)),
+        _ => anyhow::bail!("Not a dictionary: {}", plist_path.display()),
+    }
+}
+
 /// Helper to create a plist integer
 #[cfg(target_os = "macos")]
 pub fn integer_to_plist(val: i64) -> Value {
     Value::Integer(val.into())
 }
 
+/// Helper to create a plist string
+#[cfg(target_os = "macos")]
+pub fn string_to_plist(val: &str) -> Value {
+    Value::String(val.to_string())
+}
+
 /// Helper to create a plist boolean
 #[cfg(target_os = "macos")]
 pub fn bool_to_plist(val: bool) -> Value {
@@ -195,6 +238,29 @@ pub fn json_to_plist(value: &serde_json::Value) -> Option<Value> {
     }
 }
 
+/// Convert plist::Value to serde_json::Value
+///
+/// Dates and data have no JSON equivalent and are shown as text.
+#[cfg(target_os = "macos")]
+pub fn plist_to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Boolean(b) => serde_json::json!(b),
+        Value::Integer(i) => match i.as_signed() {
+            Some(i) => serde_json::json!(i),
+            None => serde_json::json!(i.as_unsigned()),
+        },
+        Value::Real(f) => serde_json::json!(f),
+        Value::String(s) => serde_json::json!(s),
+        Value::Array(items) => serde_json::Value::Array(items.iter().map(plist_to_json).collect()),
+        Value::Dictionary(dict) => serde_json::Value::Object(
+            dict.iter()
+                .map(|(key, val)| (key.clone(), plist_to_json(val)))
+                .collect(),
+        ),
+        other => serde_json::json!(format!("{:?}", other)),
+    }
+}
+
 /// Write extension settings to a separate plist file
 /// Extension settings go in: /Library/Managed Preferences/com.{browser}.extensions.{extension_id}.plist
 #[cfg(target_os = "macos")]
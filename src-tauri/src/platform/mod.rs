@@ -1,6 +1,9 @@
 /// Common cross-platform utilities
 pub mod common;
 
+/// Filesystem access the policy writers and state go through
+pub mod writer;
+
 /// Windows-specific operations (registry)
 #[cfg(target_os = "windows")]
 pub mod windows;
@@ -8,7 +8,7 @@ use winreg::RegKey;
 
 /// Registry value types
 #[cfg(target_os = "windows")]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RegistryValue {
     Dword(u32),
     String(String),
@@ -163,6 +163,107 @@ pub fn read_registry_policy(key_path: &str) -> Result<Vec<String>> {
     }
 }
 
+/// Read a single named registry value
+///
+/// Returns `None` if the key or value doesn't exist.
+#[cfg(target_os = "windows")]
+pub fn read_registry_value(key_path: &str, value_name: &str) -> Result<Option<RegistryValue>> {
+    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+
+    let key = match hklm.open_subkey(key_path) {
+        Ok(key) => key,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to open registry key: HKLM\\{}", key_path))
+        }
+    };
+
+    let raw = match key.get_raw_value(value_name) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(e).with_context(|| {
+                format!("Failed to read registry value: HKLM\\{}\\{}", key_path, value_name)
+            })
+        }
+    };
+
+    let value = match raw.vtype {
+        REG_DWORD => RegistryValue::Dword(key.get_value(value_name)?),
+        REG_SZ | REG_EXPAND_SZ => RegistryValue::String(key.get_value(value_name)?),
+        other => anyhow::bail!(
+            "Unsupported registry value type {:?}: HKLM\\{}\\{}",
+            other,
+            key_path,
+            value_name
+        ),
+    };
+
+    Ok(Some(value))
+}
+
+/// Read every value directly under a registry key, in registry order
+///
+/// Returns `None` if the key doesn't exist. Value types other than DWORD and
+/// strings are shown by their type name.
+#[cfg(target_os = "windows")]
+pub fn read_registry_values(key_path: &str) -> Result<Option<Vec<(String, RegistryValue)>>> {
+    use winreg::types::FromRegValue;
+
+    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+
+    let key = match hklm.open_subkey(key_path) {
+        Ok(key) => key,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to open registry key: HKLM\\{}", key_path))
+        }
+    };
+
+    let mut values = Vec::new();
+    for entry in key.enum_values() {
+        let (name, raw) =
+            entry.with_context(|| format!("Failed to read registry key: HKLM\\{}", key_path))?;
+        let value = match raw.vtype {
+            REG_DWORD => RegistryValue::Dword(u32::from_reg_value(&raw)?),
+            REG_SZ | REG_EXPAND_SZ => RegistryValue::String(String::from_reg_value(&raw)?),
+            other => RegistryValue::String(format!("<{:?}>", other)),
+        };
+        values.push((name, value));
+    }
+
+    Ok(Some(values))
+}
+
+/// Image File Execution Options, where Windows looks up a "debugger" to run
+/// instead of an executable
+#[cfg(target_os = "windows")]
+const IFEO_KEY: &str = r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Image File Execution Options";
+
+/// Stand-in "debugger" for blocked executables: it exits without doing
+/// anything, so the blocked program never starts
+#[cfg(target_os = "windows")]
+const BLOCKED_DEBUGGER: &str = r"C:\Windows\System32\systray.exe";
+
+/// Stop executables named `executable` (e.g. `opera.exe`) from starting,
+/// wherever they are installed
+#[cfg(target_os = "windows")]
+pub fn block_executable(executable: &str) -> Result<()> {
+    write_registry_value(
+        &format!(r"{}\{}", IFEO_KEY, executable),
+        "Debugger",
+        RegistryValue::String(BLOCKED_DEBUGGER.to_string()),
+    )
+}
+
+/// Undo [`block_executable`]
+///
+/// Only the value is removed: the key can hold unrelated settings.
+#[cfg(target_os = "windows")]
+pub fn unblock_executable(executable: &str) -> Result<()> {
+    remove_registry_value(&format!(r"{}\{}", IFEO_KEY, executable), "Debugger")
+}
+
 /// Apply registry policy with dry-run support
 /// Shows diff in dry-run mode, actually writes in normal mode
 #[cfg(target_os = "windows")]
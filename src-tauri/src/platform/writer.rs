@@ -0,0 +1,234 @@
+//! File operations behind the policy writers and state persistence
+//!
+//! JSON policy files (Chromium on Linux, Firefox everywhere), the state file
+//! and the policy cache are read and written through `writer()`, as are
+//! deletions of individual registry values and plist keys. Normally that is
+//! the real system; tests can install a `RecordingWriter` with
+//! `use_test_writer` to keep everything in memory and inspect what would
+//! have been changed. Registry and plist writes still go to the system
+//! directly.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::rc::Rc;
+
+/// Reads and changes files on behalf of the policy writers
+pub trait PlatformWriter {
+    /// Read a file, or `None` if it doesn't exist
+    fn read_file(&self, path: &Path) -> Result<Option<Vec<u8>>>;
+
+    /// Atomically replace a file, creating its directory, and set its mode
+    /// (Unix permission bits; ignored on Windows)
+    fn write_file(&self, path: &Path, content: &[u8], mode: u32) -> Result<()>;
+
+    /// Delete a file, returning whether it existed
+    fn remove_file(&self, path: &Path) -> Result<bool>;
+
+    /// Delete a directory if nothing is left in it
+    fn remove_dir_if_empty(&self, dir: &Path) -> Result<()>;
+
+    /// Delete named values from a key under HKLM (Windows only)
+    fn remove_registry_values(&self, key: &str, names: &[&str]) -> Result<()>;
+
+    /// Delete a key under HKLM and everything below it (Windows only)
+    fn remove_registry_key(&self, key: &str) -> Result<()>;
+
+    /// Delete keys from a managed preferences plist (macOS only)
+    fn remove_plist_keys(&self, domain: &str, keys: &[&str]) -> Result<()>;
+}
+
+/// The real filesystem
+pub struct SystemWriter;
+
+impl PlatformWriter for SystemWriter {
+    fn read_file(&self, path: &Path) -> Result<Option<Vec<u8>>> {
+        match std::fs::read(path) {
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    fn write_file(&self, path: &Path, content: &[u8], mode: u32) -> Result<()> {
+        super::common::atomic_write(path, content)?;
+        super::common::set_file_permissions(path, mode)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<bool> {
+        match std::fs::remove_file(path) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e).with_context(|| format!("Failed to delete {}", path.display())),
+        }
+    }
+
+    fn remove_dir_if_empty(&self, dir: &Path) -> Result<()> {
+        if let Ok(mut entries) = std::fs::read_dir(dir)
+            && entries.next().is_none()
+        {
+            // Something else may have created a file since; that's fine
+            let _ = std::fs::remove_dir(dir);
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os = "windows")]
+    fn remove_registry_values(&self, key: &str, names: &[&str]) -> Result<()> {
+        names
+            .iter()
+            .try_for_each(|name| super::windows::remove_registry_value(key, name))
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    fn remove_registry_values(&self, key: &str, _names: &[&str]) -> Result<()> {
+        anyhow::bail!("Cannot change HKLM\\{} outside Windows", key)
+    }
+
+    #[cfg(target_os = "windows")]
+    fn remove_registry_key(&self, key: &str) -> Result<()> {
+        super::windows::remove_registry_policy(key)
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    fn remove_registry_key(&self, key: &str) -> Result<()> {
+        anyhow::bail!("Cannot change HKLM\\{} outside Windows", key)
+    }
+
+    #[cfg(target_os = "macos")]
+    fn remove_plist_keys(&self, domain: &str, keys: &[&str]) -> Result<()> {
+        let keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+        super::macos::remove_plist_keys(domain, &keys)
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn remove_plist_keys(&self, domain: &str, _keys: &[&str]) -> Result<()> {
+        anyhow::bail!("Cannot change the {} plist outside macOS", domain)
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    static TEST_WRITER: std::cell::RefCell<Option<Rc<dyn PlatformWriter>>> = const { std::cell::RefCell::new(None) };
+}
+
+/// The writer to use on this thread
+pub fn writer() -> Rc<dyn PlatformWriter> {
+    #[cfg(test)]
+    if let Some(writer) = TEST_WRITER.with(|w| w.borrow().clone()) {
+        return writer;
+    }
+
+    Rc::new(SystemWriter)
+}
+
+/// Send file operations on the current thread to `writer` until the
+/// returned guard is dropped
+#[cfg(test)]
+pub(crate) fn use_test_writer(writer: Rc<RecordingWriter>) -> TestWriterGuard {
+    TEST_WRITER.with(|w| *w.borrow_mut() = Some(writer));
+    TestWriterGuard
+}
+
+#[cfg(test)]
+pub(crate) struct TestWriterGuard;
+
+#[cfg(test)]
+impl Drop for TestWriterGuard {
+    fn drop(&mut self) {
+        TEST_WRITER.with(|w| *w.borrow_mut() = None);
+    }
+}
+
+/// A change seen by `RecordingWriter`
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum WriterOp {
+    Write { path: std::path::PathBuf, mode: u32 },
+    Remove { path: std::path::PathBuf },
+    RemoveRegistryValues { key: String, names: Vec<String> },
+    RemoveRegistryKey { key: String },
+    RemovePlistKeys { domain: String, keys: Vec<String> },
+}
+
+/// Keeps files in memory and records every change
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct RecordingWriter {
+    files: std::cell::RefCell<std::collections::BTreeMap<std::path::PathBuf, Vec<u8>>>,
+    operations: std::cell::RefCell<Vec<WriterOp>>,
+}
+
+#[cfg(test)]
+impl RecordingWriter {
+    /// Contents of a file as a string
+    pub fn file(&self, path: impl AsRef<Path>) -> Option<String> {
+        self.files
+            .borrow()
+            .get(path.as_ref())
+            .map(|content| String::from_utf8_lossy(content).into_owned())
+    }
+
+    /// Contents of a JSON file
+    pub fn json(&self, path: impl AsRef<Path>) -> Option<serde_json::Value> {
+        self.file(path).map(|content| serde_json::from_str(&content).unwrap())
+    }
+
+    /// Every change so far, in order
+    pub fn operations(&self) -> Vec<WriterOp> {
+        self.operations.borrow().clone()
+    }
+}
+
+#[cfg(test)]
+impl PlatformWriter for RecordingWriter {
+    fn read_file(&self, path: &Path) -> Result<Option<Vec<u8>>> {
+        Ok(self.files.borrow().get(path).cloned())
+    }
+
+    fn write_file(&self, path: &Path, content: &[u8], mode: u32) -> Result<()> {
+        self.files.borrow_mut().insert(path.to_path_buf(), content.to_vec());
+        self.operations.borrow_mut().push(WriterOp::Write {
+            path: path.to_path_buf(),
+            mode,
+        });
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<bool> {
+        let existed = self.files.borrow_mut().remove(path).is_some();
+        if existed {
+            self.operations.borrow_mut().push(WriterOp::Remove {
+                path: path.to_path_buf(),
+            });
+        }
+        Ok(existed)
+    }
+
+    fn remove_dir_if_empty(&self, _dir: &Path) -> Result<()> {
+        // Directories aren't tracked
+        Ok(())
+    }
+
+    fn remove_registry_values(&self, key: &str, names: &[&str]) -> Result<()> {
+        self.operations.borrow_mut().push(WriterOp::RemoveRegistryValues {
+            key: key.to_string(),
+            names: names.iter().map(|name| name.to_string()).collect(),
+        });
+        Ok(())
+    }
+
+    fn remove_registry_key(&self, key: &str) -> Result<()> {
+        self.operations.borrow_mut().push(WriterOp::RemoveRegistryKey {
+            key: key.to_string(),
+        });
+        Ok(())
+    }
+
+    fn remove_plist_keys(&self, domain: &str, keys: &[&str]) -> Result<()> {
+        self.operations.borrow_mut().push(WriterOp::RemovePlistKeys {
+            domain: domain.to_string(),
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+//! The interface every browser's policy writer implements
+//!
+//! `policy::mod`, the change plan and verification drive browsers only
+//! through `PolicyBackend`, so supporting another browser means adding a
+//! backend to `backends()`. Tests can substitute a mock backend to exercise
+//! the apply/remove bookkeeping without touching the system.
+
+use anyhow::Result;
+
+use crate::browser::{Browser, Platform};
+use crate::config::Config;
+use crate::state::BrowserState;
+
+use super::plan::{PolicyTarget, PolicyWrite};
+use super::verify::{self, PolicyDrift};
+use super::{chrome, chromium, edge, firefox, opera, safari, vivaldi};
+
+/// Writes, removes and reads back one browser's policies
+pub trait PolicyBackend {
+    /// The browser this backend manages
+    fn browser(&self) -> Browser;
+
+    /// Browser name for messages
+    fn name(&self) -> &'static str;
+
+    /// Whether `config` sets anything for this browser
+    fn is_configured(&self, config: &Config) -> bool;
+
+    /// Policy values `config` produces on `platform` (no I/O)
+    ///
+    /// Empty if `config` doesn't configure this browser.
+    fn evaluate(&self, config: &Config, platform: Platform) -> Result<Vec<PolicyWrite>>;
+
+    /// Where this browser reads machine policies from on `platform`,
+    /// including places only other tools write to
+    fn locations(&self, platform: Platform) -> Vec<PolicyTarget>;
+
+    /// Write the policies `config` sets for this browser, returning what was
+    /// applied
+    fn apply(&self, config: &Config, dry_run: bool) -> Result<BrowserState>;
+
+    /// Remove every policy this backend may have written
+    fn remove(&self) -> Result<()>;
+
+    /// Read back `writes` (this browser's part of a plan for the current
+    /// platform) and return the values that are missing or different
+    fn diff(&self, writes: &[PolicyWrite]) -> Result<Vec<PolicyDrift>> {
+        verify::verify_writes(writes)
+    }
+}
+
+/// Backends for every supported browser, in the order they are applied
+pub fn backends() -> Vec<Box<dyn PolicyBackend>> {
+    vec![
+        Box::new(chrome::ChromeBackend),
+        Box::new(firefox::FirefoxBackend),
+        Box::new(edge::EdgeBackend),
+        Box::new(chromium::ChromiumBackend),
+        Box::new(vivaldi::VivaldiBackend),
+        Box::new(opera::OperaBackend),
+        Box::new(safari::SafariBackend),
+    ]
+}
@@ -1,31 +1,21 @@
 use anyhow::Result;
-use std::path::Path;
 
-use crate::config::ChromeConfig;
+use crate::browser::{Browser, Platform};
+use crate::config::{self, Config, ChromeConfig};
 use crate::state::BrowserState;
 
+use super::backend::PolicyBackend;
 use super::chromium_common::{self, ChromiumBrowserConfig, ChromiumConfig};
+use super::plan::{PolicyTarget, PolicyWrite};
 
 /// Chrome-specific browser configuration
-fn get_chrome_browser_config() -> ChromiumBrowserConfig {
+pub(super) fn get_chrome_browser_config() -> ChromiumBrowserConfig {
     ChromiumBrowserConfig {
+        browser: Browser::Chrome,
         browser_name: "Chrome",
         registry_key: r"SOFTWARE\Policies\Google\Chrome",
         bundle_id: "com.google.Chrome",
-        policy_dir_fn: get_chrome_policy_dir,
-    }
-}
-
-/// Get Chrome policy directory (Linux)
-fn get_chrome_policy_dir() -> &'static Path {
-    #[cfg(target_os = "linux")]
-    {
-        crate::platform::linux::get_chrome_policy_dir()
-    }
-
-    #[cfg(not(target_os = "linux"))]
-    {
-        Path::new("") // Not used on non-Linux platforms
+        linux_policy_dir: "/etc/opt/chrome/policies/managed",
     }
 }
 
@@ -43,6 +33,55 @@ pub fn remove_chrome_policies() -> Result<()> {
     chromium_common::remove_chromium_policies(&browser_config)
 }
 
+/// Chrome's part of a config
+fn chrome_config(config: &Config) -> Option<ChromeConfig> {
+    config::to_browser_configs(config).0
+}
+
+/// Writes Chrome policies through the Chromium policy mechanisms
+pub struct ChromeBackend;
+
+impl PolicyBackend for ChromeBackend {
+    fn browser(&self) -> Browser {
+        Browser::Chrome
+    }
+
+    fn name(&self) -> &'static str {
+        "Chrome"
+    }
+
+    fn is_configured(&self, config: &Config) -> bool {
+        chrome_config(config).is_some()
+    }
+
+    fn evaluate(&self, config: &Config, platform: Platform) -> Result<Vec<PolicyWrite>> {
+        Ok(chrome_config(config)
+            .map(|c| {
+                chromium_common::evaluate_chromium_policies(
+                    &ChromiumConfig::from_chrome(&c),
+                    &get_chrome_browser_config(),
+                    platform,
+                )
+            })
+            .unwrap_or_default())
+    }
+
+    fn locations(&self, platform: Platform) -> Vec<PolicyTarget> {
+        chromium_common::chromium_policy_locations(&get_chrome_browser_config(), platform)
+    }
+
+    fn apply(&self, config: &Config, dry_run: bool) -> Result<BrowserState> {
+        match chrome_config(config) {
+            Some(c) => apply_chrome_policies(&c, dry_run),
+            None => Ok(BrowserState::new()),
+        }
+    }
+
+    fn remove(&self) -> Result<()> {
+        remove_chrome_policies()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -66,6 +105,8 @@ mod tests {
             disable_incognito: None,
             disable_guest_mode: None,
             allow_deleting_browser_history: None,
+            disable_sync: None,
+            restrict_signin: None,
         }
     }
 
@@ -89,6 +130,8 @@ mod tests {
             disable_incognito: Some(true),
             disable_guest_mode: Some(true),
             allow_deleting_browser_history: Some(false),
+            disable_sync: None,
+            restrict_signin: None,
         };
 
         let state = build_chrome_state(&config);
@@ -144,6 +187,8 @@ mod tests {
             disable_incognito: Some(true),
             disable_guest_mode: Some(false),
             allow_deleting_browser_history: Some(false),
+            disable_sync: None,
+            restrict_signin: None,
         };
 
         let state = build_chrome_state(&config);
@@ -161,6 +206,8 @@ mod tests {
             disable_incognito: Some(true),
             disable_guest_mode: None,
             allow_deleting_browser_history: None,
+            disable_sync: None,
+            restrict_signin: None,
         };
 
         let state = build_chrome_state(&config);
@@ -0,0 +1,132 @@
+use anyhow::Result;
+
+use crate::browser::{Browser, Platform};
+use crate::config::{self, Config, ChromeConfig};
+use crate::state::BrowserState;
+
+use super::backend::PolicyBackend;
+use super::chromium_common::{self, ChromiumBrowserConfig, ChromiumConfig};
+use super::plan::{PolicyTarget, PolicyWrite};
+
+/// Chromium-specific browser configuration
+///
+/// Chromium reads the same policies as Chrome, but from its own places.
+pub(super) fn get_chromium_browser_config() -> ChromiumBrowserConfig {
+    ChromiumBrowserConfig {
+        browser: Browser::Chromium,
+        browser_name: "Chromium",
+        registry_key: r"SOFTWARE\Policies\Chromium",
+        bundle_id: "org.chromium.Chromium",
+        linux_policy_dir: "/etc/chromium/policies/managed",
+    }
+}
+
+/// Apply Chromium policies (extensions and privacy controls)
+pub fn apply_chromium_policies(config: &ChromeConfig, dry_run: bool) -> Result<BrowserState> {
+    let chromium_config = ChromiumConfig::from_chrome(config);
+    let browser_config = get_chromium_browser_config();
+
+    chromium_common::apply_chromium_policies(&chromium_config, &browser_config, dry_run)
+}
+
+/// Remove all Chromium policies
+pub fn remove_chromium_policies() -> Result<()> {
+    let browser_config = get_chromium_browser_config();
+    chromium_common::remove_chromium_policies(&browser_config)
+}
+
+/// Chromium's part of a config
+fn chromium_config(config: &Config) -> Option<ChromeConfig> {
+    config::chromium_based_config(config, Browser::Chromium)
+}
+
+/// Writes Chromium policies
+pub struct ChromiumBackend;
+
+impl PolicyBackend for ChromiumBackend {
+    fn browser(&self) -> Browser {
+        Browser::Chromium
+    }
+
+    fn name(&self) -> &'static str {
+        "Chromium"
+    }
+
+    fn is_configured(&self, config: &Config) -> bool {
+        chromium_config(config).is_some()
+    }
+
+    fn evaluate(&self, config: &Config, platform: Platform) -> Result<Vec<PolicyWrite>> {
+        Ok(chromium_config(config)
+            .map(|c| {
+                chromium_common::evaluate_chromium_policies(
+                    &ChromiumConfig::from_chrome(&c),
+                    &get_chromium_browser_config(),
+                    platform,
+                )
+            })
+            .unwrap_or_default())
+    }
+
+    fn locations(&self, platform: Platform) -> Vec<PolicyTarget> {
+        chromium_common::chromium_policy_locations(&get_chromium_browser_config(), platform)
+    }
+
+    fn apply(&self, config: &Config, dry_run: bool) -> Result<BrowserState> {
+        match chromium_config(config) {
+            Some(c) => apply_chromium_policies(&c, dry_run),
+            None => Ok(BrowserState::new()),
+        }
+    }
+
+    fn remove(&self) -> Result<()> {
+        remove_chromium_policies()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chromium_browser_config() {
+        let config = get_chromium_browser_config();
+        assert_eq!(config.browser_name, "Chromium");
+        assert_eq!(config.registry_key, r"SOFTWARE\Policies\Chromium");
+        assert_eq!(config.bundle_id, "org.chromium.Chromium");
+        assert_eq!(config.private_mode_policy(), "IncognitoModeAvailability");
+    }
+
+    #[test]
+    fn test_chromium_is_configured_only_by_its_own_entries() {
+        let config = Config::from_yaml_str(
+            r#"
+policies:
+  - name: Chrome only
+    browsers: [chrome]
+    disable_sync: true
+"#,
+        )
+        .unwrap();
+        assert!(!ChromiumBackend.is_configured(&config));
+
+        let config = Config::from_yaml_str(
+            r#"
+policies:
+  - name: Chromium too
+    browsers: [chrome, chromium]
+    disable_private_mode: true
+"#,
+        )
+        .unwrap();
+        let writes = ChromiumBackend.evaluate(&config, Platform::Linux).unwrap();
+        assert_eq!(writes.len(), 1);
+        assert_eq!(writes[0].name, "IncognitoModeAvailability");
+        assert_eq!(
+            writes[0].target,
+            PolicyTarget::JsonFile {
+                path: std::path::PathBuf::from("/etc/chromium/policies/managed/browser-policy.json")
+            }
+        );
+    }
+}
@@ -1,26 +1,47 @@
-/// Common functionality for Chromium-based browsers (Chrome, Edge, Brave, etc.)
+/// Common functionality for Chromium-based browsers (Chrome, Edge, Chromium, Vivaldi, Opera)
 ///
 /// This module extracts shared policy application logic to reduce code duplication
-/// between Chrome and Edge, which both use the same underlying policy mechanisms.
+/// between the Chromium-based browsers, which all use the same underlying policy mechanisms.
 
 use anyhow::{Context, Result};
 use serde_json::json;
 use std::path::Path;
 
-use crate::config::Extension;
+use crate::browser::{Browser, Platform};
+use crate::config::{Extension, SigninRestriction};
 use crate::state::BrowserState;
 
+use super::plan::{PolicyTarget, PolicyValue, PolicyWrite};
+
 /// Configuration for a specific Chromium-based browser
 #[derive(Debug, Clone)]
 pub struct ChromiumBrowserConfig {
+    /// Which browser this is
+    pub browser: Browser,
     /// Human-readable browser name (for logging)
     pub browser_name: &'static str,
     /// Windows registry key path
     pub registry_key: &'static str,
     /// macOS bundle identifier
     pub bundle_id: &'static str,
-    /// Linux policy directory function
-    pub policy_dir_fn: fn() -> &'static Path,
+    /// Linux managed policy directory
+    pub linux_policy_dir: &'static str,
+}
+
+impl ChromiumBrowserConfig {
+    /// Name of the policy controlling private browsing
+    /// (InPrivate for Edge, Incognito for the others)
+    pub fn private_mode_policy(&self) -> &'static str {
+        match self.browser {
+            Browser::Edge => "InPrivateModeAvailability",
+            _ => "IncognitoModeAvailability",
+        }
+    }
+
+    /// Path of the JSON policy file written on Linux
+    pub fn linux_policy_file(&self) -> std::path::PathBuf {
+        Path::new(self.linux_policy_dir).join("browser-policy.json")
+    }
 }
 
 /// Generic configuration for Chromium-based browsers
@@ -30,6 +51,8 @@ pub struct ChromiumConfig {
     pub disable_private_mode: Option<bool>,  // Incognito/InPrivate
     pub disable_guest_mode: Option<bool>,
     pub allow_deleting_browser_history: Option<bool>,
+    pub disable_sync: Option<bool>,
+    pub restrict_signin: Option<SigninRestriction>,
 }
 
 impl ChromiumConfig {
@@ -40,6 +63,8 @@ impl ChromiumConfig {
             disable_private_mode: config.disable_incognito,
             disable_guest_mode: config.disable_guest_mode,
             allow_deleting_browser_history: config.allow_deleting_browser_history,
+            disable_sync: config.disable_sync,
+            restrict_signin: config.restrict_signin.clone(),
         }
     }
 
@@ -50,6 +75,8 @@ impl ChromiumConfig {
             disable_private_mode: config.disable_inprivate,
             disable_guest_mode: config.disable_guest_mode,
             allow_deleting_browser_history: config.allow_deleting_browser_history,
+            disable_sync: config.disable_sync,
+            restrict_signin: config.restrict_signin.clone(),
         }
     }
 }
@@ -74,6 +101,7 @@ pub fn apply_chromium_policies(
             apply_chromium_linux(config, browser_config, dry_run)?
         }
     }
+    remove_stale_chromium_policies(config, browser_config, platform, dry_run)?;
 
     // Build and return state (identical for all Chromium browsers)
     let mut state = BrowserState::new();
@@ -82,6 +110,8 @@ pub fn apply_chromium_policies(
     state.disable_inprivate = config.disable_private_mode;
     state.disable_guest_mode = config.disable_guest_mode;
     state.allow_deleting_browser_history = config.allow_deleting_browser_history;
+    state.disable_sync = config.disable_sync;
+    state.restrict_signin = config.restrict_signin.clone();
 
     Ok(state)
 }
@@ -105,6 +135,90 @@ pub fn remove_chromium_policies(browser_config: &ChromiumBrowserConfig) -> Resul
     Ok(())
 }
 
+/// Delete the registry values (Windows) or plist keys (macOS) that an
+/// earlier apply may have written but `config` no longer sets
+///
+/// Applying there only adds and overwrites, so without this a setting
+/// dropped from the config, e.g. by `remove --setting`, would stay in force.
+/// On Linux the whole policy file is rewritten and there is nothing to do.
+fn remove_stale_chromium_policies(
+    config: &ChromiumConfig,
+    browser_config: &ChromiumBrowserConfig,
+    platform: crate::browser::Platform,
+    dry_run: bool,
+) -> Result<()> {
+    let mut stale = Vec::new();
+    if config.disable_private_mode != Some(true) {
+        stale.push(browser_config.private_mode_policy());
+    }
+    if config.disable_guest_mode.is_none() {
+        stale.push("BrowserGuestModeEnabled");
+    }
+    if config.allow_deleting_browser_history.is_none() {
+        stale.push("AllowDeletingBrowserHistory");
+    }
+    if config.disable_sync.is_none() {
+        stale.push("SyncDisabled");
+    }
+    let signin = config.restrict_signin.as_ref().map(signin_policy);
+    if signin.is_none() {
+        stale.push("BrowserSignin");
+    }
+    if signin.is_none_or(|(_, pattern)| pattern.is_none()) {
+        stale.push("RestrictSigninToPattern");
+    }
+    let stale_forcelist = config.extensions.is_empty();
+
+    if dry_run {
+        // Dry runs only show what would be written
+        return Ok(());
+    }
+
+    let writer = crate::platform::writer::writer();
+    match platform {
+        crate::browser::Platform::Windows => {
+            if !stale.is_empty() {
+                writer
+                    .remove_registry_values(browser_config.registry_key, &stale)
+                    .with_context(|| {
+                        format!(
+                            "Failed to remove unset {} policies from the registry",
+                            browser_config.browser_name
+                        )
+                    })?;
+            }
+            if stale_forcelist {
+                let extension_key =
+                    format!("{}\\ExtensionInstallForcelist", browser_config.registry_key);
+                writer.remove_registry_key(&extension_key).with_context(|| {
+                    format!(
+                        "Failed to remove {} extension policy at {}",
+                        browser_config.browser_name, extension_key
+                    )
+                })?;
+            }
+        }
+        crate::browser::Platform::MacOS => {
+            if stale_forcelist {
+                stale.push("ExtensionInstallForcelist");
+            }
+            if !stale.is_empty() {
+                writer
+                    .remove_plist_keys(browser_config.bundle_id, &stale)
+                    .with_context(|| {
+                        format!(
+                            "Failed to remove unset {} plist keys",
+                            browser_config.browser_name
+                        )
+                    })?;
+            }
+        }
+        crate::browser::Platform::Linux => {}
+    }
+
+    Ok(())
+}
+
 /// Format a Chromium extension entry for policies
 /// Format: "{extension_id};{update_url}"
 pub fn format_chromium_extension_entry(ext: &Extension) -> String {
@@ -116,6 +230,366 @@ pub fn format_chromium_extension_entry(ext: &Extension) -> String {
     format!("{};{}", ext.id, update_url)
 }
 
+/// `BrowserSignin` and `RestrictSigninToPattern` values for a sign-in
+/// restriction
+///
+/// BrowserSignin is 0 (disabled) or 1 (enabled); a pattern leaves sign-in
+/// enabled but only for matching accounts.
+pub fn signin_policy(restriction: &SigninRestriction) -> (u32, Option<&str>) {
+    match restriction {
+        SigninRestriction::Restricted(true) => (0, None),
+        SigninRestriction::Restricted(false) => (1, None),
+        SigninRestriction::Pattern(pattern) => (1, Some(pattern)),
+    }
+}
+
+/// Build the Linux JSON policy document for a Chromium browser
+pub fn build_chromium_linux_policy(
+    config: &ChromiumConfig,
+    browser_config: &ChromiumBrowserConfig,
+) -> serde_json::Value {
+    let mut policy = json!({});
+
+    // Apply extension policies
+    if !config.extensions.is_empty() {
+        let extension_strings: Vec<String> = config
+            .extensions
+            .iter()
+            .map(format_chromium_extension_entry)
+            .collect();
+
+        policy["ExtensionInstallForcelist"] = json!(extension_strings);
+    }
+
+    // Apply privacy controls
+    if config.disable_private_mode == Some(true) {
+        policy[browser_config.private_mode_policy()] = json!(1); // 1 = Disabled
+    }
+
+    // Apply guest mode control
+    if let Some(disable_guest_mode) = config.disable_guest_mode {
+        policy["BrowserGuestModeEnabled"] = json!(!disable_guest_mode);
+    }
+
+    // Apply AllowDeletingBrowserHistory
+    if let Some(allow_deleting_history) = config.allow_deleting_browser_history {
+        policy["AllowDeletingBrowserHistory"] = json!(allow_deleting_history);
+    }
+
+    // Apply sync and sign-in controls
+    if let Some(disable_sync) = config.disable_sync {
+        policy["SyncDisabled"] = json!(disable_sync);
+    }
+    if let Some(restriction) = &config.restrict_signin {
+        let (browser_signin, pattern) = signin_policy(restriction);
+        policy["BrowserSignin"] = json!(browser_signin);
+        if let Some(pattern) = pattern {
+            policy["RestrictSigninToPattern"] = json!(pattern);
+        }
+    }
+
+    // Apply extension settings if configured
+    let mut extensions_settings = serde_json::Map::new();
+
+    for ext in &config.extensions {
+        if !ext.settings.is_empty() {
+            extensions_settings.insert(ext.id.clone(), json!(ext.settings));
+        }
+    }
+
+    if !extensions_settings.is_empty() {
+        let mut thirdparty = serde_json::Map::new();
+        thirdparty.insert(
+            "extensions".to_string(),
+            serde_json::Value::Object(extensions_settings),
+        );
+        policy["3rdparty"] = serde_json::Value::Object(thirdparty);
+    }
+
+    policy
+}
+
+/// Evaluate the policy values a Chromium browser gets on `platform`
+///
+/// Mirrors what the platform appliers below write, without doing any I/O.
+pub fn evaluate_chromium_policies(
+    config: &ChromiumConfig,
+    browser_config: &ChromiumBrowserConfig,
+    platform: Platform,
+) -> Vec<PolicyWrite> {
+    match platform {
+        Platform::Windows => evaluate_chromium_windows(config, browser_config),
+        Platform::MacOS => evaluate_chromium_macos(config, browser_config),
+        Platform::Linux => {
+            let target = PolicyTarget::JsonFile {
+                path: browser_config.linux_policy_file(),
+            };
+            let policy = build_chromium_linux_policy(config, browser_config);
+
+            policy
+                .as_object()
+                .into_iter()
+                .flatten()
+                .map(|(name, value)| {
+                    PolicyWrite::new(
+                        browser_config.browser,
+                        target.clone(),
+                        name,
+                        PolicyValue::from_json(value),
+                    )
+                })
+                .collect()
+        }
+    }
+}
+
+/// Where a Chromium-based browser reads machine policies from on `platform`
+///
+/// On Linux this is every JSON file in the managed policy directory, ours
+/// first, since the browser merges them all.
+pub fn chromium_policy_locations(browser_config: &ChromiumBrowserConfig, platform: Platform) -> Vec<PolicyTarget> {
+    match platform {
+        Platform::Windows => vec![
+            PolicyTarget::Registry {
+                key: browser_config.registry_key.to_string(),
+            },
+            PolicyTarget::Registry {
+                key: format!("{}\\ExtensionInstallForcelist", browser_config.registry_key),
+            },
+        ],
+        Platform::MacOS => vec![PolicyTarget::Plist {
+            domain: browser_config.bundle_id.to_string(),
+        }],
+        Platform::Linux => {
+            let ours = browser_config.linux_policy_file();
+            let mut others: Vec<_> = std::fs::read_dir(browser_config.linux_policy_dir)
+                .into_iter()
+                .flatten()
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json") && *path != ours)
+                .collect();
+            others.sort();
+
+            std::iter::once(ours)
+                .chain(others)
+                .map(|path| PolicyTarget::JsonFile { path })
+                .collect()
+        }
+    }
+}
+
+fn evaluate_chromium_windows(
+    config: &ChromiumConfig,
+    browser_config: &ChromiumBrowserConfig,
+) -> Vec<PolicyWrite> {
+    use serde_json::Value;
+
+    let browser = browser_config.browser;
+    let key = || PolicyTarget::Registry {
+        key: browser_config.registry_key.to_string(),
+    };
+    let mut writes = Vec::new();
+
+    // Extension list: numbered values under ExtensionInstallForcelist
+    let forcelist_key = format!("{}\\ExtensionInstallForcelist", browser_config.registry_key);
+    for (index, ext) in config.extensions.iter().enumerate() {
+        writes.push(PolicyWrite::new(
+            browser,
+            PolicyTarget::Registry { key: forcelist_key.clone() },
+            (index + 1).to_string(),
+            PolicyValue::String(format_chromium_extension_entry(ext)),
+        ));
+    }
+
+    // Extension settings under 3rdparty (see platform::windows::write_extension_settings)
+    for ext in &config.extensions {
+        let policy_key = format!(
+            "{}\\3rdparty\\extensions\\{}\\policy",
+            browser_config.registry_key, ext.id
+        );
+
+        let mut settings: Vec<_> = ext.settings.iter().collect();
+        settings.sort_by(|a, b| a.0.cmp(b.0));
+
+        for (name, value) in settings {
+            let target = PolicyTarget::Registry { key: policy_key.clone() };
+            match value {
+                Value::Bool(b) => {
+                    writes.push(PolicyWrite::new(browser, target, name, PolicyValue::Dword(u32::from(*b))))
+                }
+                Value::Number(n) => {
+                    if let Some(val) = n.as_u64() {
+                        writes.push(PolicyWrite::new(browser, target, name, PolicyValue::Dword(val as u32)));
+                    }
+                }
+                Value::String(s) => {
+                    writes.push(PolicyWrite::new(browser, target, name, PolicyValue::String(s.clone())))
+                }
+                Value::Array(items) => {
+                    let array_key = format!("{}\\{}", policy_key, name);
+                    for (index, item) in items.iter().enumerate() {
+                        if let Value::String(s) = item {
+                            writes.push(PolicyWrite::new(
+                                browser,
+                                PolicyTarget::Registry { key: array_key.clone() },
+                                (index + 1).to_string(),
+                                PolicyValue::String(s.clone()),
+                            ));
+                        }
+                    }
+                }
+                // Unsupported types are skipped by the applier
+                _ => {}
+            }
+        }
+    }
+
+    if config.disable_private_mode == Some(true) {
+        writes.push(PolicyWrite::new(
+            browser,
+            key(),
+            browser_config.private_mode_policy(),
+            PolicyValue::Dword(1), // 1 = Disabled
+        ));
+    }
+
+    if let Some(disable_guest_mode) = config.disable_guest_mode {
+        writes.push(PolicyWrite::new(
+            browser,
+            key(),
+            "BrowserGuestModeEnabled",
+            PolicyValue::Dword(u32::from(!disable_guest_mode)),
+        ));
+    }
+
+    if let Some(allow_deleting_history) = config.allow_deleting_browser_history {
+        writes.push(PolicyWrite::new(
+            browser,
+            key(),
+            "AllowDeletingBrowserHistory",
+            PolicyValue::Dword(u32::from(allow_deleting_history)),
+        ));
+    }
+
+    if let Some(disable_sync) = config.disable_sync {
+        writes.push(PolicyWrite::new(
+            browser,
+            key(),
+            "SyncDisabled",
+            PolicyValue::Dword(u32::from(disable_sync)),
+        ));
+    }
+
+    if let Some(restriction) = &config.restrict_signin {
+        let (browser_signin, pattern) = signin_policy(restriction);
+        writes.push(PolicyWrite::new(browser, key(), "BrowserSignin", PolicyValue::Dword(browser_signin)));
+        if let Some(pattern) = pattern {
+            writes.push(PolicyWrite::new(
+                browser,
+                key(),
+                "RestrictSigninToPattern",
+                PolicyValue::String(pattern.to_string()),
+            ));
+        }
+    }
+
+    writes
+}
+
+fn evaluate_chromium_macos(
+    config: &ChromiumConfig,
+    browser_config: &ChromiumBrowserConfig,
+) -> Vec<PolicyWrite> {
+    let browser = browser_config.browser;
+    let domain = || PolicyTarget::Plist {
+        domain: browser_config.bundle_id.to_string(),
+    };
+    let mut writes = Vec::new();
+
+    if !config.extensions.is_empty() {
+        writes.push(PolicyWrite::new(
+            browser,
+            domain(),
+            "ExtensionInstallForcelist",
+            PolicyValue::StringList(
+                config
+                    .extensions
+                    .iter()
+                    .map(format_chromium_extension_entry)
+                    .collect(),
+            ),
+        ));
+    }
+
+    if config.disable_private_mode == Some(true) {
+        writes.push(PolicyWrite::new(
+            browser,
+            domain(),
+            browser_config.private_mode_policy(),
+            PolicyValue::Integer(1), // 1 = Disabled
+        ));
+    }
+
+    if let Some(disable_guest_mode) = config.disable_guest_mode {
+        writes.push(PolicyWrite::new(
+            browser,
+            domain(),
+            "BrowserGuestModeEnabled",
+            PolicyValue::Bool(!disable_guest_mode),
+        ));
+    }
+
+    if let Some(allow_deleting_history) = config.allow_deleting_browser_history {
+        writes.push(PolicyWrite::new(
+            browser,
+            domain(),
+            "AllowDeletingBrowserHistory",
+            PolicyValue::Bool(allow_deleting_history),
+        ));
+    }
+
+    if let Some(disable_sync) = config.disable_sync {
+        writes.push(PolicyWrite::new(browser, domain(), "SyncDisabled", PolicyValue::Bool(disable_sync)));
+    }
+
+    if let Some(restriction) = &config.restrict_signin {
+        let (browser_signin, pattern) = signin_policy(restriction);
+        writes.push(PolicyWrite::new(
+            browser,
+            domain(),
+            "BrowserSignin",
+            PolicyValue::Integer(i64::from(browser_signin)),
+        ));
+        if let Some(pattern) = pattern {
+            writes.push(PolicyWrite::new(
+                browser,
+                domain(),
+                "RestrictSigninToPattern",
+                PolicyValue::String(pattern.to_string()),
+            ));
+        }
+    }
+
+    // Extension settings go in a separate plist per extension
+    for ext in &config.extensions {
+        let mut settings: Vec<_> = ext.settings.iter().filter(|(_, v)| !v.is_null()).collect();
+        settings.sort_by(|a, b| a.0.cmp(b.0));
+
+        for (name, value) in settings {
+            writes.push(PolicyWrite::new(
+                browser,
+                PolicyTarget::Plist {
+                    domain: format!("{}.extensions.{}", browser_config.bundle_id, ext.id),
+                },
+                name,
+                PolicyValue::Json(value.clone()),
+            ));
+        }
+    }
+
+    writes
+}
+
 // ============================================================================
 // Platform-Specific Implementations
 // ============================================================================
@@ -198,11 +672,7 @@ fn apply_chromium_windows(
     // Apply privacy controls - Incognito/InPrivate mode
     if let Some(disable_private_mode) = config.disable_private_mode {
         if disable_private_mode {
-            let key_name = if browser_config.browser_name == "Chrome" {
-                "IncognitoModeAvailability"
-            } else {
-                "InPrivateModeAvailability"
-            };
+            let key_name = browser_config.private_mode_policy();
 
             apply_registry_value_with_preview(
                 browser_config.registry_key,
@@ -251,6 +721,54 @@ fn apply_chromium_windows(
         })?;
     }
 
+    // Apply sync control
+    if let Some(disable_sync) = config.disable_sync {
+        apply_registry_value_with_preview(
+            browser_config.registry_key,
+            "SyncDisabled",
+            RegistryValue::Dword(if disable_sync { 1 } else { 0 }),
+            dry_run,
+        )
+        .with_context(|| {
+            format!(
+                "Failed to apply SyncDisabled to {} registry",
+                browser_config.browser_name
+            )
+        })?;
+    }
+
+    // Apply sign-in restriction
+    if let Some(restriction) = &config.restrict_signin {
+        let (browser_signin, pattern) = signin_policy(restriction);
+        apply_registry_value_with_preview(
+            browser_config.registry_key,
+            "BrowserSignin",
+            RegistryValue::Dword(browser_signin),
+            dry_run,
+        )
+        .with_context(|| {
+            format!(
+                "Failed to apply BrowserSignin to {} registry",
+                browser_config.browser_name
+            )
+        })?;
+
+        if let Some(pattern) = pattern {
+            apply_registry_value_with_preview(
+                browser_config.registry_key,
+                "RestrictSigninToPattern",
+                RegistryValue::String(pattern.to_string()),
+                dry_run,
+            )
+            .with_context(|| {
+                format!(
+                    "Failed to apply RestrictSigninToPattern to {} registry",
+                    browser_config.browser_name
+                )
+            })?;
+        }
+    }
+
     Ok(())
 }
 
@@ -262,7 +780,8 @@ fn apply_chromium_macos(
     dry_run: bool,
 ) -> Result<()> {
     use crate::platform::macos::{
-        apply_plist_policy_with_preview, bool_to_plist, integer_to_plist, string_vec_to_plist_array,
+        apply_plist_policy_with_preview, bool_to_plist, integer_to_plist, string_to_plist,
+        string_vec_to_plist_array,
     };
     use std::collections::HashMap;
 
@@ -291,11 +810,7 @@ fn apply_chromium_macos(
     // Apply privacy controls
     if let Some(disable_private_mode) = config.disable_private_mode {
         if disable_private_mode {
-            let key_name = if browser_config.browser_name == "Chrome" {
-                "IncognitoModeAvailability"
-            } else {
-                "InPrivateModeAvailability"
-            };
+            let key_name = browser_config.private_mode_policy();
 
             updates.insert(
                 key_name.to_string(),
@@ -320,6 +835,20 @@ fn apply_chromium_macos(
         );
     }
 
+    // Apply sync control
+    if let Some(disable_sync) = config.disable_sync {
+        updates.insert("SyncDisabled".to_string(), bool_to_plist(disable_sync));
+    }
+
+    // Apply sign-in restriction
+    if let Some(restriction) = &config.restrict_signin {
+        let (browser_signin, pattern) = signin_policy(restriction);
+        updates.insert("BrowserSignin".to_string(), integer_to_plist(i64::from(browser_signin)));
+        if let Some(pattern) = pattern {
+            updates.insert("RestrictSigninToPattern".to_string(), string_to_plist(pattern));
+        }
+    }
+
     apply_plist_policy_with_preview(browser_config.bundle_id, updates, dry_run)
         .with_context(|| {
             format!(
@@ -382,70 +911,15 @@ fn apply_chromium_linux(
 ) -> Result<()> {
     use crate::platform::common::apply_json_file_with_preview;
 
-    let policy_dir = (browser_config.policy_dir_fn)();
-    let policy_file = policy_dir.join("browser-policy.json");
+    let policy_file = browser_config.linux_policy_file();
 
     tracing::debug!(
-        "Applying {} policies on Linux (dir: {})",
+        "Applying {} policies on Linux (file: {})",
         browser_config.browser_name,
-        policy_dir.display()
+        policy_file.display()
     );
 
-    let mut policy = json!({});
-
-    // Apply extension policies
-    if !config.extensions.is_empty() {
-        let extension_strings: Vec<String> = config
-            .extensions
-            .iter()
-            .map(format_chromium_extension_entry)
-            .collect();
-
-        policy["ExtensionInstallForcelist"] = json!(extension_strings);
-    }
-
-    // Apply privacy controls
-    if let Some(disable_private_mode) = config.disable_private_mode {
-        if disable_private_mode {
-            let key_name = if browser_config.browser_name == "Chrome" {
-                "IncognitoModeAvailability"
-            } else {
-                "InPrivateModeAvailability"
-            };
-
-            policy[key_name] = json!(1); // 1 = Disabled
-        }
-    }
-
-    // Apply guest mode control
-    if let Some(disable_guest_mode) = config.disable_guest_mode {
-        policy["BrowserGuestModeEnabled"] = json!(!disable_guest_mode);
-    }
-
-    // Apply AllowDeletingBrowserHistory
-    if let Some(allow_deleting_history) = config.allow_deleting_browser_history {
-        policy["AllowDeletingBrowserHistory"] = json!(allow_deleting_history);
-    }
-
-    // Apply extension settings if configured
-    let mut has_extension_settings = false;
-    let mut extensions_settings = serde_json::Map::new();
-
-    for ext in &config.extensions {
-        if !ext.settings.is_empty() {
-            has_extension_settings = true;
-            extensions_settings.insert(ext.id.clone(), json!(ext.settings));
-        }
-    }
-
-    if has_extension_settings {
-        let mut thirdparty = serde_json::Map::new();
-        thirdparty.insert(
-            "extensions".to_string(),
-            serde_json::Value::Object(extensions_settings),
-        );
-        policy["3rdparty"] = serde_json::Value::Object(thirdparty);
-    }
+    let policy = build_chromium_linux_policy(config, browser_config);
 
     apply_json_file_with_preview(&policy_file, policy, dry_run)
         .with_context(|| {
@@ -494,11 +968,7 @@ fn remove_chromium_windows(browser_config: &ChromiumBrowserConfig) -> Result<()>
     }
 
     // Remove privacy controls
-    let privacy_key = if browser_config.browser_name == "Chrome" {
-        "IncognitoModeAvailability"
-    } else {
-        "InPrivateModeAvailability"
-    };
+    let privacy_key = browser_config.private_mode_policy();
 
     if let Err(e) = remove_registry_value(browser_config.registry_key, privacy_key) {
         tracing::warn!(
@@ -525,6 +995,17 @@ fn remove_chromium_windows(browser_config: &ChromiumBrowserConfig) -> Result<()>
         );
     }
 
+    for value in ["SyncDisabled", "BrowserSignin", "RestrictSigninToPattern"] {
+        if let Err(e) = remove_registry_value(browser_config.registry_key, value) {
+            tracing::warn!(
+                "Failed to remove {} {}: {}",
+                browser_config.browser_name,
+                value,
+                e
+            );
+        }
+    }
+
     Ok(())
 }
 
@@ -538,17 +1019,16 @@ fn remove_chromium_macos(browser_config: &ChromiumBrowserConfig) -> Result<()> {
         browser_config.browser_name
     );
 
-    let privacy_key = if browser_config.browser_name == "Chrome" {
-        "IncognitoModeAvailability"
-    } else {
-        "InPrivateModeAvailability"
-    };
+    let privacy_key = browser_config.private_mode_policy();
 
     let keys = vec![
         "ExtensionInstallForcelist".to_string(),
         privacy_key.to_string(),
         "BrowserGuestModeEnabled".to_string(),
         "AllowDeletingBrowserHistory".to_string(),
+        "SyncDisabled".to_string(),
+        "BrowserSignin".to_string(),
+        "RestrictSigninToPattern".to_string(),
     ];
 
     remove_plist_keys(browser_config.bundle_id, &keys)
@@ -581,7 +1061,7 @@ fn remove_chromium_linux(browser_config: &ChromiumBrowserConfig) -> Result<()> {
         browser_config.browser_name
     );
 
-    let policy_dir = (browser_config.policy_dir_fn)();
+    let policy_dir = Path::new(browser_config.linux_policy_dir);
 
     remove_json_policy(policy_dir, "browser-policy")
         .with_context(|| {
@@ -682,6 +1162,8 @@ mod tests {
             disable_incognito: Some(true),
             disable_guest_mode: Some(false),
             allow_deleting_browser_history: None,
+            disable_sync: None,
+            restrict_signin: None,
         };
 
         let chromium_config = ChromiumConfig::from_chrome(&chrome_config);
@@ -698,6 +1180,8 @@ mod tests {
             disable_inprivate: Some(true),
             disable_guest_mode: Some(true),
             allow_deleting_browser_history: None,
+            disable_sync: None,
+            restrict_signin: None,
         };
 
         let chromium_config = ChromiumConfig::from_edge(&edge_config);
@@ -706,4 +1190,169 @@ mod tests {
         assert_eq!(chromium_config.disable_private_mode, Some(true));
         assert_eq!(chromium_config.disable_guest_mode, Some(true));
     }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_apply_and_remove_linux_policy_file() {
+        use crate::platform::writer::{use_test_writer, RecordingWriter, WriterOp};
+        use std::rc::Rc;
+
+        let writer = Rc::new(RecordingWriter::default());
+        let _writer = use_test_writer(writer.clone());
+        let browser_config = crate::policy::chrome::get_chrome_browser_config();
+        let config = ChromiumConfig {
+            extensions: vec![make_test_extension("abcdefghijklmnopqrstuvwxyzabcdef")],
+            disable_private_mode: Some(true),
+            disable_guest_mode: None,
+            allow_deleting_browser_history: None,
+            disable_sync: None,
+            restrict_signin: None,
+        };
+
+        // Dry runs only read
+        apply_chromium_policies(&config, &browser_config, true).unwrap();
+        assert!(writer.operations().is_empty());
+
+        let state = apply_chromium_policies(&config, &browser_config, false).unwrap();
+        assert_eq!(state.disable_incognito, Some(true));
+
+        let path = browser_config.linux_policy_file();
+        let policy = writer.json(&path).unwrap();
+        assert_eq!(policy["IncognitoModeAvailability"], 1);
+        assert_eq!(
+            policy["ExtensionInstallForcelist"][0],
+            "abcdefghijklmnopqrstuvwxyzabcdef;https://clients2.google.com/service/update2/crx"
+        );
+
+        remove_chromium_policies(&browser_config).unwrap();
+        assert_eq!(
+            writer.operations(),
+            [
+                WriterOp::Write { path: path.clone(), mode: 0o644 },
+                WriterOp::Remove { path },
+            ]
+        );
+    }
+
+    #[test]
+    fn stripped_settings_are_removed_on_windows_and_macos() {
+        use crate::browser::Platform;
+        use crate::platform::writer::{use_test_writer, RecordingWriter, WriterOp};
+        use std::rc::Rc;
+
+        let writer = Rc::new(RecordingWriter::default());
+        let _writer = use_test_writer(writer.clone());
+        let browser_config = crate::policy::edge::get_edge_browser_config();
+        // What's left after `remove --setting` took out guest mode
+        let config = ChromiumConfig {
+            extensions: vec![make_test_extension("abcdefghijklmnopabcdefghijklmnop")],
+            disable_private_mode: Some(true),
+            disable_guest_mode: None,
+            allow_deleting_browser_history: Some(false),
+            disable_sync: Some(true),
+            restrict_signin: Some(SigninRestriction::Restricted(true)),
+        };
+
+        remove_stale_chromium_policies(&config, &browser_config, Platform::Windows, true).unwrap();
+        assert!(writer.operations().is_empty());
+
+        remove_stale_chromium_policies(&config, &browser_config, Platform::Windows, false).unwrap();
+        remove_stale_chromium_policies(&config, &browser_config, Platform::MacOS, false).unwrap();
+        assert_eq!(
+            writer.operations(),
+            [
+                WriterOp::RemoveRegistryValues {
+                    key: browser_config.registry_key.to_string(),
+                    names: vec![
+                        "BrowserGuestModeEnabled".to_string(),
+                        "RestrictSigninToPattern".to_string(),
+                    ],
+                },
+                WriterOp::RemovePlistKeys {
+                    domain: browser_config.bundle_id.to_string(),
+                    keys: vec![
+                        "BrowserGuestModeEnabled".to_string(),
+                        "RestrictSigninToPattern".to_string(),
+                    ],
+                },
+            ]
+        );
+
+        // With nothing left, every value goes, including the forcelist
+        let empty = ChromiumConfig {
+            extensions: Vec::new(),
+            disable_private_mode: None,
+            disable_guest_mode: None,
+            allow_deleting_browser_history: None,
+            disable_sync: None,
+            restrict_signin: None,
+        };
+        let writer = Rc::new(RecordingWriter::default());
+        let _writer = use_test_writer(writer.clone());
+        remove_stale_chromium_policies(&empty, &browser_config, Platform::Windows, false).unwrap();
+        assert_eq!(
+            writer.operations(),
+            [
+                WriterOp::RemoveRegistryValues {
+                    key: browser_config.registry_key.to_string(),
+                    names: vec![
+                        "InPrivateModeAvailability".to_string(),
+                        "BrowserGuestModeEnabled".to_string(),
+                        "AllowDeletingBrowserHistory".to_string(),
+                        "SyncDisabled".to_string(),
+                        "BrowserSignin".to_string(),
+                        "RestrictSigninToPattern".to_string(),
+                    ],
+                },
+                WriterOp::RemoveRegistryKey {
+                    key: format!("{}\\ExtensionInstallForcelist", browser_config.registry_key),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn signin_pattern_is_removed_when_signin_is_disabled() {
+        use crate::browser::Platform;
+        use crate::platform::writer::{use_test_writer, RecordingWriter, WriterOp};
+        use std::rc::Rc;
+
+        let writer = Rc::new(RecordingWriter::default());
+        let _writer = use_test_writer(writer.clone());
+        let browser_config = crate::policy::chrome::get_chrome_browser_config();
+        let mut config = ChromiumConfig {
+            extensions: vec![make_test_extension("abcdefghijklmnopabcdefghijklmnop")],
+            disable_private_mode: Some(true),
+            disable_guest_mode: Some(true),
+            allow_deleting_browser_history: Some(false),
+            disable_sync: Some(true),
+            restrict_signin: Some(SigninRestriction::Pattern(".*@example\\.com".to_string())),
+        };
+
+        remove_stale_chromium_policies(&config, &browser_config, Platform::MacOS, false).unwrap();
+        assert!(writer.operations().is_empty());
+
+        config.restrict_signin = Some(SigninRestriction::Restricted(true));
+        remove_stale_chromium_policies(&config, &browser_config, Platform::MacOS, false).unwrap();
+        config.disable_sync = None;
+        config.restrict_signin = None;
+        remove_stale_chromium_policies(&config, &browser_config, Platform::Windows, false).unwrap();
+        assert_eq!(
+            writer.operations(),
+            [
+                WriterOp::RemovePlistKeys {
+                    domain: browser_config.bundle_id.to_string(),
+                    keys: vec!["RestrictSigninToPattern".to_string()],
+                },
+                WriterOp::RemoveRegistryValues {
+                    key: browser_config.registry_key.to_string(),
+                    names: vec![
+                        "SyncDisabled".to_string(),
+                        "BrowserSignin".to_string(),
+                        "RestrictSigninToPattern".to_string(),
+                    ],
+                },
+            ]
+        );
+    }
 }
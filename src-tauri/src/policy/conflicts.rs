@@ -0,0 +1,298 @@
+//! Detect values another management tool already sets
+//!
+//! Before applying, the plan is compared with what the browsers' policy
+//! locations hold (see `inspect`). A value counts as a conflict when
+//! something other than us sets it to a different value: another location
+//! the browser reads (a Group Policy key, an MDM profile, another JSON
+//! policy file), or our own location before we ever managed that browser.
+//! `conflict_policy` in the config decides whether to warn, refuse or leave
+//! those settings to the other tool.
+
+use anyhow::Result;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::browser::{current_platform, Browser, Platform};
+use crate::config::{Config, ConflictPolicy, PrivacySetting};
+use crate::state::AppliedPolicies;
+
+use super::backend::backends;
+use super::inspect::{inspect, InspectedLocation, ValueStatus};
+use super::plan::{evaluate_policy, ChangePlan, PolicyTarget};
+
+/// A value we would write that something else already sets differently
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyConflict {
+    pub browser: Browser,
+    pub name: String,
+    /// Where the other value was found
+    pub location: String,
+    /// What we would set
+    pub ours: String,
+    /// What is set there now
+    pub theirs: String,
+}
+
+impl std::fmt::Display for PolicyConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} is already set to {} at {} (we would set {})",
+            self.browser.as_str(),
+            self.name,
+            self.theirs,
+            self.location,
+            self.ours
+        )
+    }
+}
+
+/// Find values in `config` that another management tool already sets
+///
+/// `previous` is what we applied before, if anything; a browser we already
+/// manage can't conflict with itself at our own locations.
+pub fn detect_conflicts(config: &Config, previous: Option<&AppliedPolicies>) -> Result<Vec<PolicyConflict>> {
+    let platform = current_platform();
+    let plan = evaluate_policy(&config.effective(Utc::now()), platform)?;
+    let base: Vec<PolicyTarget> = backends().iter().flat_map(|b| b.locations(platform)).collect();
+    let locations = inspect(Some(&plan), platform);
+    Ok(find_conflicts(&plan, &base, &locations, previous))
+}
+
+/// Apply `config`'s `conflict_policy` to `conflicts`
+///
+/// Returns the config to apply: unchanged for `warn`, without the
+/// conflicting settings for `merge`. Fails for `refuse` if there are any
+/// conflicts.
+pub fn resolve_conflicts(config: &Config, conflicts: &[PolicyConflict]) -> Result<Config> {
+    if conflicts.is_empty() {
+        return Ok(config.clone());
+    }
+
+    match config.conflict_policy.unwrap_or_default() {
+        ConflictPolicy::Warn => Ok(config.clone()),
+        ConflictPolicy::Refuse => {
+            let list: Vec<String> = conflicts.iter().map(|c| format!("  {}", c)).collect();
+            anyhow::bail!(
+                "{} policy values are already set by another management tool \
+                 (conflict_policy: refuse):\n{}",
+                conflicts.len(),
+                list.join("\n")
+            )
+        }
+        ConflictPolicy::Merge => without_conflicting(config, conflicts, current_platform()),
+    }
+}
+
+/// Compare a plan with the values found at the browsers' policy locations
+///
+/// `base` lists the locations the browsers read their top-level policies
+/// from; only writes to those are matched by name across locations.
+fn find_conflicts(
+    plan: &ChangePlan,
+    base: &[PolicyTarget],
+    locations: &[InspectedLocation],
+    previous: Option<&AppliedPolicies>,
+) -> Vec<PolicyConflict> {
+    let mut conflicts = Vec::new();
+
+    for location in locations {
+        let browser = location.browser;
+        let ours = plan.for_browser(browser).any(|w| w.target == location.target);
+        let managed_before = previous.and_then(|p| p.get(browser)).is_some();
+
+        for value in &location.values {
+            let Some(actual) = &value.actual else {
+                continue;
+            };
+
+            let expected = if ours {
+                match &value.status {
+                    ValueStatus::Changed { expected } if !managed_before => expected.clone(),
+                    _ => continue,
+                }
+            } else {
+                match plan
+                    .for_browser(browser)
+                    .find(|w| w.name == value.name && base.contains(&w.target))
+                {
+                    Some(write) if write.value.to_json() != *actual => write.value.to_json(),
+                    _ => continue,
+                }
+            };
+
+            conflicts.push(PolicyConflict {
+                browser,
+                name: value.name.clone(),
+                location: location.target.to_string(),
+                ours: expected.to_string(),
+                theirs: actual.to_string(),
+            });
+        }
+    }
+
+    conflicts
+}
+
+/// Drop the privacy settings that produce conflicting values
+///
+/// Conflicts that don't come from a privacy setting (extension lists) can't
+/// be left to the other tool and are applied anyway.
+fn without_conflicting(config: &Config, conflicts: &[PolicyConflict], platform: Platform) -> Result<Config> {
+    let now = Utc::now();
+    let mut merged = config.clone();
+
+    for conflict in conflicts {
+        let writes = |config: &Config| -> Result<bool> {
+            Ok(evaluate_policy(&config.effective(now), platform)?
+                .find(conflict.browser, &conflict.name)
+                .is_some())
+        };
+        if !writes(&merged)? {
+            continue;
+        }
+
+        let mut left = false;
+        for setting in PrivacySetting::ALL {
+            if !setting.applies_to(conflict.browser) {
+                continue;
+            }
+            let candidate = merged.without_setting(setting, &[conflict.browser]);
+            if !writes(&candidate)? {
+                println!(
+                    "Leaving {} {} to the other management tool",
+                    conflict.browser.as_str(),
+                    setting.as_str()
+                );
+                merged = candidate;
+                left = true;
+                break;
+            }
+        }
+        if !left {
+            eprintln!("⚠ Can't leave {} to the other management tool; applying it anyway", conflict.name);
+        }
+    }
+
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::inspect::InspectedValue;
+    use crate::policy::{PolicyValue, PolicyWrite};
+    use crate::state::BrowserState;
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    fn policies_json() -> PolicyTarget {
+        PolicyTarget::JsonFile {
+            path: PathBuf::from(r"C:\Program Files\Mozilla Firefox\distribution\policies.json"),
+        }
+    }
+
+    fn group_policy() -> PolicyTarget {
+        PolicyTarget::Registry {
+            key: r"SOFTWARE\Policies\Mozilla\Firefox".to_string(),
+        }
+    }
+
+    fn location(target: PolicyTarget, name: &str, actual: serde_json::Value, status: ValueStatus) -> InspectedLocation {
+        InspectedLocation {
+            browser: Browser::Firefox,
+            target,
+            exists: true,
+            error: None,
+            values: vec![InspectedValue {
+                name: name.to_string(),
+                actual: Some(actual),
+                status,
+            }],
+        }
+    }
+
+    #[test]
+    fn finds_values_set_elsewhere_and_before_we_managed_the_browser() {
+        let plan = ChangePlan {
+            platform: Platform::Windows,
+            writes: vec![PolicyWrite::new(
+                Browser::Firefox,
+                policies_json(),
+                "DisablePrivateBrowsing",
+                PolicyValue::Bool(true),
+            )],
+        };
+        let base = [policies_json(), group_policy()];
+        let locations = [
+            location(
+                policies_json(),
+                "DisablePrivateBrowsing",
+                json!(false),
+                ValueStatus::Changed { expected: json!(true) },
+            ),
+            location(group_policy(), "DisablePrivateBrowsing", json!(0), ValueStatus::Foreign),
+        ];
+
+        let conflicts = find_conflicts(&plan, &base, &locations, None);
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(conflicts[1].location, r"HKLM\SOFTWARE\Policies\Mozilla\Firefox");
+        assert_eq!(conflicts[1].theirs, "0");
+        assert_eq!(conflicts[1].ours, "true");
+
+        // Once we manage Firefox, a different value in our own file is drift
+        let mut previous = AppliedPolicies::default();
+        previous.set(Browser::Firefox, BrowserState::new());
+        let conflicts = find_conflicts(&plan, &base, &locations, Some(&previous));
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].location, r"HKLM\SOFTWARE\Policies\Mozilla\Firefox");
+    }
+
+    #[test]
+    fn merge_leaves_conflicting_settings_to_the_other_tool() {
+        let config = Config::from_yaml_str(
+            r#"
+conflict_policy: merge
+policies:
+  - name: Restrictions
+    browsers: [chrome, edge]
+    disable_private_mode: true
+    disable_sync: true
+"#,
+        )
+        .unwrap();
+        let conflict = PolicyConflict {
+            browser: Browser::Chrome,
+            name: "SyncDisabled".to_string(),
+            location: "/etc/opt/chrome/policies/managed/corp.json".to_string(),
+            ours: "true".to_string(),
+            theirs: "false".to_string(),
+        };
+
+        let merged = without_conflicting(&config, &[conflict], Platform::Linux).unwrap();
+        let plan = evaluate_policy(&merged, Platform::Linux).unwrap();
+
+        assert!(plan.find(Browser::Chrome, "SyncDisabled").is_none());
+        assert!(plan.find(Browser::Chrome, "IncognitoModeAvailability").is_some());
+        assert!(plan.find(Browser::Edge, "SyncDisabled").is_some());
+    }
+
+    #[test]
+    fn refuse_fails_only_with_conflicts() {
+        let config = Config {
+            conflict_policy: Some(ConflictPolicy::Refuse),
+            ..Default::default()
+        };
+        let conflict = PolicyConflict {
+            browser: Browser::Edge,
+            name: "InPrivateModeAvailability".to_string(),
+            location: r"HKLM\SOFTWARE\Policies\Microsoft\Edge".to_string(),
+            ours: "1".to_string(),
+            theirs: "0".to_string(),
+        };
+
+        assert!(resolve_conflicts(&config, &[]).is_ok());
+        let err = resolve_conflicts(&config, &[conflict]).unwrap_err();
+        assert!(format!("{:#}", err).contains("edge InPrivateModeAvailability is already set to 0"));
+    }
+}
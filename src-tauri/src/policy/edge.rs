@@ -1,31 +1,21 @@
 use anyhow::Result;
-use std::path::Path;
 
-use crate::config::EdgeConfig;
+use crate::browser::{Browser, Platform};
+use crate::config::{self, Config, EdgeConfig};
 use crate::state::BrowserState;
 
+use super::backend::PolicyBackend;
 use super::chromium_common::{self, ChromiumBrowserConfig, ChromiumConfig};
+use super::plan::{PolicyTarget, PolicyWrite};
 
 /// Edge-specific browser configuration
-fn get_edge_browser_config() -> ChromiumBrowserConfig {
+pub(super) fn get_edge_browser_config() -> ChromiumBrowserConfig {
     ChromiumBrowserConfig {
+        browser: Browser::Edge,
         browser_name: "Edge",
         registry_key: r"SOFTWARE\Policies\Microsoft\Edge",
         bundle_id: "com.microsoft.Edge",
-        policy_dir_fn: get_edge_policy_dir,
-    }
-}
-
-/// Get Edge policy directory (Linux)
-fn get_edge_policy_dir() -> &'static Path {
-    #[cfg(target_os = "linux")]
-    {
-        crate::platform::linux::get_edge_policy_dir()
-    }
-
-    #[cfg(not(target_os = "linux"))]
-    {
-        Path::new("") // Not used on non-Linux platforms
+        linux_policy_dir: "/etc/opt/microsoft/edge/policies/managed",
     }
 }
 
@@ -43,6 +33,55 @@ pub fn remove_edge_policies() -> Result<()> {
     chromium_common::remove_chromium_policies(&browser_config)
 }
 
+/// Edge's part of a config
+fn edge_config(config: &Config) -> Option<EdgeConfig> {
+    config::to_browser_configs(config).2
+}
+
+/// Writes Edge policies through the Chromium policy mechanisms
+pub struct EdgeBackend;
+
+impl PolicyBackend for EdgeBackend {
+    fn browser(&self) -> Browser {
+        Browser::Edge
+    }
+
+    fn name(&self) -> &'static str {
+        "Edge"
+    }
+
+    fn is_configured(&self, config: &Config) -> bool {
+        edge_config(config).is_some()
+    }
+
+    fn evaluate(&self, config: &Config, platform: Platform) -> Result<Vec<PolicyWrite>> {
+        Ok(edge_config(config)
+            .map(|c| {
+                chromium_common::evaluate_chromium_policies(
+                    &ChromiumConfig::from_edge(&c),
+                    &get_edge_browser_config(),
+                    platform,
+                )
+            })
+            .unwrap_or_default())
+    }
+
+    fn locations(&self, platform: Platform) -> Vec<PolicyTarget> {
+        chromium_common::chromium_policy_locations(&get_edge_browser_config(), platform)
+    }
+
+    fn apply(&self, config: &Config, dry_run: bool) -> Result<BrowserState> {
+        match edge_config(config) {
+            Some(c) => apply_edge_policies(&c, dry_run),
+            None => Ok(BrowserState::new()),
+        }
+    }
+
+    fn remove(&self) -> Result<()> {
+        remove_edge_policies()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -66,6 +105,8 @@ mod tests {
             disable_inprivate: None,
             disable_guest_mode: None,
             allow_deleting_browser_history: None,
+            disable_sync: None,
+            restrict_signin: None,
         }
     }
 
@@ -89,6 +130,8 @@ mod tests {
             disable_inprivate: Some(true),
             disable_guest_mode: Some(true),
             allow_deleting_browser_history: Some(false),
+            disable_sync: None,
+            restrict_signin: None,
         };
 
         let state = build_edge_state(&config);
@@ -144,6 +187,8 @@ mod tests {
             disable_inprivate: Some(true),
             disable_guest_mode: Some(false),
             allow_deleting_browser_history: Some(false),
+            disable_sync: None,
+            restrict_signin: None,
         };
 
         let state = build_edge_state(&config);
@@ -161,6 +206,8 @@ mod tests {
             disable_inprivate: Some(true),
             disable_guest_mode: None,
             allow_deleting_browser_history: None,
+            disable_sync: None,
+            restrict_signin: None,
         };
 
         let state = build_edge_state(&config);
@@ -1,20 +1,26 @@
 use anyhow::{Context, Result};
 use serde_json::json;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::config::FirefoxConfig;
+use crate::browser::{current_platform, Browser, Platform};
+use crate::config::{self, Config, FirefoxConfig};
 use crate::state::BrowserState;
 
+use super::backend::PolicyBackend;
+use super::plan::{PolicyTarget, PolicyValue, PolicyWrite};
+
 /// Apply Firefox policies (extensions and privacy controls)
+///
+/// The same policies.json is written for every installed edition.
 pub fn apply_firefox_policies(config: &FirefoxConfig, dry_run: bool) -> Result<BrowserState> {
-    let policy_path = get_firefox_policy_path()?;
-
     // Create policies.json content
     let policies_json = create_firefox_policies_json(config)?;
 
-    // Use common JSON file helper
-    crate::platform::common::apply_json_file_with_preview(&policy_path, policies_json, dry_run)
-        .with_context(|| format!("Failed to apply Firefox policies: {}", policy_path.display()))?;
+    for policy_path in installed_firefox_policy_paths() {
+        // Use common JSON file helper
+        crate::platform::common::apply_json_file_with_preview(&policy_path, policies_json.clone(), dry_run)
+            .with_context(|| format!("Failed to apply Firefox policies: {}", policy_path.display()))?;
+    }
 
     // Build and return state
     let mut state = BrowserState::new();
@@ -24,80 +30,185 @@ pub fn apply_firefox_policies(config: &FirefoxConfig, dry_run: bool) -> Result<B
         .map(|e| e.id.clone())
         .collect();
     state.disable_private_browsing = config.disable_private_browsing;
+    state.disable_sync = config.disable_sync;
 
     Ok(state)
 }
 
 /// Remove all Firefox policies
+///
+/// Every edition's location is cleaned up, including those of editions
+/// uninstalled since the policies were applied.
 pub fn remove_firefox_policies() -> Result<()> {
-    let policy_path = get_firefox_policy_path()?;
+    let writer = crate::platform::writer::writer();
 
-    if policy_path.exists() {
-        std::fs::remove_file(&policy_path)
+    for policy_path in firefox_policy_paths(current_platform(), |_| true) {
+        let removed = writer
+            .remove_file(&policy_path)
             .with_context(|| format!("Failed to remove Firefox policies: {}", policy_path.display()))?;
 
         // Try to remove the distribution directory if it's empty
-        if let Some(parent) = policy_path.parent() {
-            if let Ok(mut entries) = std::fs::read_dir(parent) {
-                if entries.next().is_none() {
-                    let _ = std::fs::remove_dir(parent);
-                }
-            }
+        if removed && let Some(parent) = policy_path.parent() {
+            writer.remove_dir_if_empty(parent)?;
         }
     }
 
     Ok(())
 }
 
-/// Get platform-specific Firefox policy path
-fn get_firefox_policy_path() -> Result<PathBuf> {
-    #[cfg(target_os = "windows")]
-    {
-        // Windows: C:\Program Files\Mozilla Firefox\distribution\policies.json
-        let paths = vec![
-            PathBuf::from(r"C:\Program Files\Mozilla Firefox\distribution\policies.json"),
-            PathBuf::from(r"C:\Program Files (x86)\Mozilla Firefox\distribution\policies.json"),
-        ];
-
-        // Use the first existing Firefox installation
-        for path in paths {
-            if let Some(parent) = path.parent() {
-                if let Some(grandparent) = parent.parent() {
-                    if grandparent.exists() {
-                        return Ok(path);
-                    }
-                }
-            }
+/// Evaluate the policies.json values Firefox gets on `platform` (no I/O)
+///
+/// The applier writes the same file for every installed edition; the plan
+/// only uses the default location.
+pub fn evaluate_firefox_policies(config: &FirefoxConfig, platform: Platform) -> Result<Vec<PolicyWrite>> {
+    let target = PolicyTarget::JsonFile {
+        path: PathBuf::from(default_firefox_policy_path(platform)),
+    };
+    let policies_json = create_firefox_policies_json(config)?;
+
+    Ok(policies_json["policies"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(name, value)| {
+            PolicyWrite::new(Browser::Firefox, target.clone(), name, PolicyValue::from_json(value))
+        })
+        .collect())
+}
+
+/// Firefox's part of a config
+fn firefox_config(config: &Config) -> Option<FirefoxConfig> {
+    config::to_browser_configs(config).1
+}
+
+/// Writes Firefox's policies.json
+pub struct FirefoxBackend;
+
+impl PolicyBackend for FirefoxBackend {
+    fn browser(&self) -> Browser {
+        Browser::Firefox
+    }
+
+    fn name(&self) -> &'static str {
+        "Firefox"
+    }
+
+    fn is_configured(&self, config: &Config) -> bool {
+        firefox_config(config).is_some()
+    }
+
+    fn evaluate(&self, config: &Config, platform: Platform) -> Result<Vec<PolicyWrite>> {
+        match firefox_config(config) {
+            Some(c) => evaluate_firefox_policies(&c, platform),
+            None => Ok(Vec::new()),
         }
+    }
 
-        // Default to first path if none exist yet
-        Ok(PathBuf::from(
-            r"C:\Program Files\Mozilla Firefox\distribution\policies.json",
-        ))
+    fn locations(&self, platform: Platform) -> Vec<PolicyTarget> {
+        // Group Policy and MDM profiles take precedence over policies.json
+        let mut locations = vec![PolicyTarget::JsonFile {
+            path: PathBuf::from(default_firefox_policy_path(platform)),
+        }];
+        match platform {
+            Platform::Windows => locations.push(PolicyTarget::Registry {
+                key: r"SOFTWARE\Policies\Mozilla\Firefox".to_string(),
+            }),
+            Platform::MacOS => locations.push(PolicyTarget::Plist {
+                domain: "org.mozilla.firefox".to_string(),
+            }),
+            Platform::Linux => {}
+        }
+        locations
     }
 
-    #[cfg(target_os = "macos")]
-    {
-        // macOS: /Applications/Firefox.app/Contents/Resources/distribution/policies.json
-        Ok(PathBuf::from(
-            "/Applications/Firefox.app/Contents/Resources/distribution/policies.json",
-        ))
+    fn apply(&self, config: &Config, dry_run: bool) -> Result<BrowserState> {
+        match firefox_config(config) {
+            Some(c) => apply_firefox_policies(&c, dry_run),
+            None => Ok(BrowserState::new()),
+        }
     }
 
-    #[cfg(target_os = "linux")]
-    {
+    fn remove(&self) -> Result<()> {
+        remove_firefox_policies()
+    }
+}
+
+/// Default Firefox policies.json location for a platform
+fn default_firefox_policy_path(platform: Platform) -> &'static str {
+    match platform {
+        // Windows: C:\Program Files\Mozilla Firefox\distribution\policies.json
+        Platform::Windows => r"C:\Program Files\Mozilla Firefox\distribution\policies.json",
+        // macOS: /Applications/Firefox.app/Contents/Resources/distribution/policies.json
+        Platform::MacOS => "/Applications/Firefox.app/Contents/Resources/distribution/policies.json",
         // Linux: /etc/firefox/policies/policies.json (system-wide)
-        Ok(PathBuf::from("/etc/firefox/policies/policies.json"))
+        Platform::Linux => "/etc/firefox/policies/policies.json",
+    }
+}
+
+/// Install directories of the Firefox editions (release, ESR, Developer
+/// Edition, Nightly) and distribution packages on a platform
+///
+/// Each reads `distribution/policies.json` from its own directory (on macOS,
+/// from the app bundle's `Contents/Resources`). ESR installs into the same
+/// place as release Firefox on Windows and macOS.
+fn firefox_install_dirs(platform: Platform) -> &'static [&'static str] {
+    match platform {
+        Platform::Windows => &[
+            r"C:\Program Files\Mozilla Firefox",
+            r"C:\Program Files (x86)\Mozilla Firefox",
+            r"C:\Program Files\Firefox Developer Edition",
+            r"C:\Program Files\Firefox Nightly",
+        ],
+        Platform::MacOS => &[
+            "/Applications/Firefox.app/Contents/Resources",
+            "/Applications/Firefox Developer Edition.app/Contents/Resources",
+            "/Applications/Firefox Nightly.app/Contents/Resources",
+        ],
+        Platform::Linux => &[
+            "/usr/lib/firefox",
+            "/usr/lib64/firefox",
+            "/usr/lib/firefox-esr",
+            "/usr/lib64/firefox-esr",
+            "/usr/lib/firefox-developer-edition",
+            "/opt/firefox",
+            "/opt/firefox-developer-edition",
+        ],
     }
+}
+
+/// Every policies.json to write on `platform`, given which install
+/// directories `exists`
+fn firefox_policy_paths(platform: Platform, exists: impl Fn(&Path) -> bool) -> Vec<PathBuf> {
+    let separator = if platform == Platform::Windows { '\\' } else { '/' };
+    let installed = firefox_install_dirs(platform)
+        .iter()
+        .filter(|dir| exists(Path::new(dir)))
+        .map(|dir| PathBuf::from(format!("{dir}{separator}distribution{separator}policies.json")));
 
-    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
-    {
-        anyhow::bail!("Unsupported platform for Firefox policies");
+    match platform {
+        // Read by every Firefox on Linux, including Snap and Flatpak builds
+        Platform::Linux => std::iter::once(PathBuf::from(default_firefox_policy_path(platform)))
+            .chain(installed)
+            .collect(),
+        _ => {
+            let paths: Vec<PathBuf> = installed.collect();
+            if paths.is_empty() {
+                // Ready for when Firefox is installed
+                vec![PathBuf::from(default_firefox_policy_path(platform))]
+            } else {
+                paths
+            }
+        }
     }
 }
 
+/// The policies.json locations for the Firefox editions installed here
+fn installed_firefox_policy_paths() -> Vec<PathBuf> {
+    firefox_policy_paths(current_platform(), |dir| dir.exists())
+}
+
 /// Create Firefox policies.json structure
-fn create_firefox_policies_json(config: &FirefoxConfig) -> Result<serde_json::Value> {
+pub(super) fn create_firefox_policies_json(config: &FirefoxConfig) -> Result<serde_json::Value> {
     let mut policies = json!({});
 
     // Add extension settings
@@ -126,6 +237,11 @@ fn create_firefox_policies_json(config: &FirefoxConfig) -> Result<serde_json::Va
         }
     }
 
+    // Firefox sync needs a Firefox account, so disabling accounts disables sync
+    if config.disable_sync == Some(true) {
+        policies["DisableFirefoxAccounts"] = json!(true);
+    }
+
     // Wrap in policies object
     Ok(json!({
         "policies": policies
@@ -149,6 +265,7 @@ mod tests {
                 settings: HashMap::new(),
             }],
             disable_private_browsing: Some(true),
+            disable_sync: Some(true),
         };
 
         let policies = create_firefox_policies_json(&config).unwrap();
@@ -159,6 +276,7 @@ mod tests {
             "force_installed"
         );
         assert_eq!(policies["policies"]["DisablePrivateBrowsing"], true);
+        assert_eq!(policies["policies"]["DisableFirefoxAccounts"], true);
     }
 
     #[test]
@@ -172,11 +290,66 @@ mod tests {
                 settings: HashMap::new(),
             }],
             disable_private_browsing: None,
+            disable_sync: None,
         };
 
         let policies = create_firefox_policies_json(&config).unwrap();
 
         assert!(policies["policies"]["ExtensionSettings"]["test@example.com"].is_object());
         assert!(policies["policies"]["DisablePrivateBrowsing"].is_null());
+        assert!(policies["policies"]["DisableFirefoxAccounts"].is_null());
+    }
+
+    #[test]
+    fn test_policy_paths_cover_installed_editions() {
+        let installed = |dirs: &'static [&'static str]| move |dir: &Path| dirs.iter().any(|d| Path::new(d) == dir);
+
+        // Linux always writes the system-wide file, plus each install's own
+        assert_eq!(
+            firefox_policy_paths(Platform::Linux, installed(&["/usr/lib/firefox-esr"])),
+            vec![
+                PathBuf::from("/etc/firefox/policies/policies.json"),
+                PathBuf::from("/usr/lib/firefox-esr/distribution/policies.json"),
+            ]
+        );
+
+        assert_eq!(
+            firefox_policy_paths(
+                Platform::MacOS,
+                installed(&["/Applications/Firefox Developer Edition.app/Contents/Resources"])
+            ),
+            vec![PathBuf::from(
+                "/Applications/Firefox Developer Edition.app/Contents/Resources/distribution/policies.json"
+            )]
+        );
+
+        assert_eq!(
+            firefox_policy_paths(Platform::Windows, |_| false),
+            vec![PathBuf::from(r"C:\Program Files\Mozilla Firefox\distribution\policies.json")]
+        );
+    }
+
+    #[test]
+    fn test_apply_and_remove_through_writer() {
+        use crate::platform::writer::{use_test_writer, RecordingWriter, WriterOp};
+        use std::rc::Rc;
+
+        let writer = Rc::new(RecordingWriter::default());
+        let _writer = use_test_writer(writer.clone());
+        let config = FirefoxConfig {
+            extensions: vec![],
+            disable_private_browsing: Some(true),
+            disable_sync: None,
+        };
+
+        let state = apply_firefox_policies(&config, false).unwrap();
+        assert_eq!(state.disable_private_browsing, Some(true));
+
+        let path = installed_firefox_policy_paths()[0].clone();
+        assert_eq!(writer.json(&path).unwrap()["policies"]["DisablePrivateBrowsing"], true);
+
+        remove_firefox_policies().unwrap();
+        assert!(writer.file(&path).is_none());
+        assert_eq!(writer.operations().last(), Some(&WriterOp::Remove { path }));
     }
 }
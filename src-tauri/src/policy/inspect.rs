@@ -0,0 +1,279 @@
+//! Read everything at the places browsers take machine policies from
+//!
+//! Verification only checks the values a plan expects. Inspection lists
+//! every value found at each browser's policy locations and sorts them
+//! against the plan, so values set by something else (Group Policy, an MDM
+//! profile, another JSON policy file) show up next to ours.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::browser::{Browser, Platform};
+use crate::platform::writer::writer;
+
+use super::backend::backends;
+use super::plan::{ChangePlan, PolicyTarget, PolicyWrite};
+
+/// How a value found on the system relates to the plan
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ValueStatus {
+    /// Set by the plan and in place
+    Managed,
+    /// Set by the plan but holding something else
+    Changed { expected: serde_json::Value },
+    /// Set by the plan but not found
+    Missing { expected: serde_json::Value },
+    /// Not part of the plan
+    Foreign,
+}
+
+/// One value at a policy location
+#[derive(Debug, Clone, Serialize)]
+pub struct InspectedValue {
+    pub name: String,
+    /// What was found (`None` if missing)
+    pub actual: Option<serde_json::Value>,
+    #[serde(flatten)]
+    pub status: ValueStatus,
+}
+
+/// Everything found at one policy location
+#[derive(Debug, Clone, Serialize)]
+pub struct InspectedLocation {
+    pub browser: Browser,
+    pub target: PolicyTarget,
+    /// Whether the file, key or plist exists
+    pub exists: bool,
+    /// Why the location couldn't be read, if it couldn't
+    pub error: Option<String>,
+    pub values: Vec<InspectedValue>,
+}
+
+/// Read every browser's policy locations on the current system
+///
+/// `plan` (for `platform`, which must be the current one) decides which
+/// values count as managed; without one every value found is foreign.
+/// Locations the plan writes to are included even if the browser wouldn't
+/// otherwise be inspected there (e.g. per-extension settings).
+pub fn inspect(plan: Option<&ChangePlan>, platform: Platform) -> Vec<InspectedLocation> {
+    let mut locations = Vec::new();
+    for backend in backends() {
+        let browser = backend.browser();
+        let writes: Vec<&PolicyWrite> = plan.into_iter().flat_map(|p| p.for_browser(browser)).collect();
+
+        let mut targets = backend.locations(platform);
+        for write in &writes {
+            if !targets.contains(&write.target) {
+                targets.push(write.target.clone());
+            }
+        }
+
+        for target in targets {
+            let planned: Vec<&PolicyWrite> = writes.iter().copied().filter(|w| w.target == target).collect();
+            let found = read_location(browser, &target);
+            locations.push(classify(browser, target, found, &planned));
+        }
+    }
+    locations
+}
+
+/// Sort the values found at a location against the planned writes there
+fn classify(
+    browser: Browser,
+    target: PolicyTarget,
+    found: Result<Option<BTreeMap<String, serde_json::Value>>>,
+    planned: &[&PolicyWrite],
+) -> InspectedLocation {
+    let (exists, error, found) = match found {
+        Ok(Some(values)) => (true, None, values),
+        Ok(None) => (false, None, BTreeMap::new()),
+        Err(e) => (false, Some(format!("{:#}", e)), BTreeMap::new()),
+    };
+
+    let mut values: Vec<InspectedValue> = found
+        .into_iter()
+        .map(|(name, actual)| {
+            let status = match planned.iter().find(|w| w.name == name) {
+                Some(write) if write.value.to_json() == actual => ValueStatus::Managed,
+                Some(write) => ValueStatus::Changed {
+                    expected: write.value.to_json(),
+                },
+                None => ValueStatus::Foreign,
+            };
+            InspectedValue {
+                name,
+                actual: Some(actual),
+                status,
+            }
+        })
+        .collect();
+
+    // A location that couldn't be read says nothing about what's missing
+    if error.is_none() {
+        for write in planned {
+            if !values.iter().any(|v| v.name == write.name) {
+                values.push(InspectedValue {
+                    name: write.name.clone(),
+                    actual: None,
+                    status: ValueStatus::Missing {
+                        expected: write.value.to_json(),
+                    },
+                });
+            }
+        }
+    }
+
+    InspectedLocation {
+        browser,
+        target,
+        exists,
+        error,
+        values,
+    }
+}
+
+/// Every value at a location, or `None` if it doesn't exist
+fn read_location(browser: Browser, target: &PolicyTarget) -> Result<Option<BTreeMap<String, serde_json::Value>>> {
+    match target {
+        PolicyTarget::JsonFile { path } => {
+            let Some(content) = writer().read_file(path)? else {
+                return Ok(None);
+            };
+            let document: serde_json::Value = serde_json::from_slice(&content)
+                .with_context(|| format!("Invalid JSON in {}", path.display()))?;
+            // Firefox nests everything under a top-level "policies" object
+            let root = match browser {
+                Browser::Firefox => document.get("policies").cloned().unwrap_or_default(),
+                _ => document,
+            };
+            match root {
+                serde_json::Value::Object(values) => Ok(Some(values.into_iter().collect())),
+                serde_json::Value::Null => Ok(Some(BTreeMap::new())),
+                _ => anyhow::bail!("Not a JSON object: {}", path.display()),
+            }
+        }
+        PolicyTarget::Registry { key } => read_registry_key(key),
+        PolicyTarget::Plist { domain } => read_plist_domain(domain),
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn read_registry_key(key: &str) -> Result<Option<BTreeMap<String, serde_json::Value>>> {
+    use crate::platform::windows::{read_registry_values, RegistryValue};
+
+    Ok(read_registry_values(key)?.map(|values| {
+        values
+            .into_iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    RegistryValue::Dword(d) => serde_json::json!(d),
+                    RegistryValue::String(s) => serde_json::json!(s),
+                };
+                (name, value)
+            })
+            .collect()
+    }))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn read_registry_key(_key: &str) -> Result<Option<BTreeMap<String, serde_json::Value>>> {
+    anyhow::bail!("The registry can only be read on Windows")
+}
+
+#[cfg(target_os = "macos")]
+fn read_plist_domain(domain: &str) -> Result<Option<BTreeMap<String, serde_json::Value>>> {
+    use crate::platform::macos::{plist_to_json, read_plist};
+
+    Ok(read_plist(domain)?.map(|dict| {
+        dict.iter()
+            .map(|(key, value)| (key.clone(), plist_to_json(value)))
+            .collect()
+    }))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn read_plist_domain(_domain: &str) -> Result<Option<BTreeMap<String, serde_json::Value>>> {
+    anyhow::bail!("Managed preferences can only be read on macOS")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::writer::{use_test_writer, PlatformWriter, RecordingWriter};
+    use crate::policy::PolicyValue;
+    use serde_json::json;
+    use std::path::PathBuf;
+    use std::rc::Rc;
+
+    fn target() -> PolicyTarget {
+        PolicyTarget::JsonFile {
+            path: PathBuf::from("/etc/firefox/policies/policies.json"),
+        }
+    }
+
+    fn write(name: &str, value: PolicyValue) -> PolicyWrite {
+        PolicyWrite::new(Browser::Firefox, target(), name, value)
+    }
+
+    #[test]
+    fn classifies_managed_changed_missing_and_foreign_values() {
+        let private = write("DisablePrivateBrowsing", PolicyValue::Bool(true));
+        let sync = write("DisableFirefoxAccounts", PolicyValue::Bool(true));
+        let extensions = write("ExtensionSettings", PolicyValue::Json(json!({})));
+        let found = BTreeMap::from([
+            ("DisablePrivateBrowsing".to_string(), json!(true)),
+            ("DisableFirefoxAccounts".to_string(), json!(false)),
+            ("Homepage".to_string(), json!({"URL": "https://intranet"})),
+        ]);
+
+        let location = classify(Browser::Firefox, target(), Ok(Some(found)), &[&private, &sync, &extensions]);
+
+        assert!(location.exists);
+        let statuses: Vec<(&str, &ValueStatus)> =
+            location.values.iter().map(|v| (v.name.as_str(), &v.status)).collect();
+        assert_eq!(
+            statuses,
+            [
+                ("DisableFirefoxAccounts", &ValueStatus::Changed { expected: json!(true) }),
+                ("DisablePrivateBrowsing", &ValueStatus::Managed),
+                ("Homepage", &ValueStatus::Foreign),
+                ("ExtensionSettings", &ValueStatus::Missing { expected: json!({}) }),
+            ]
+        );
+    }
+
+    #[test]
+    fn unreadable_location_reports_nothing_missing() {
+        let private = write("DisablePrivateBrowsing", PolicyValue::Bool(true));
+
+        let location = classify(
+            Browser::Firefox,
+            target(),
+            Err(anyhow::anyhow!("Permission denied")),
+            &[&private],
+        );
+
+        assert!(!location.exists);
+        assert_eq!(location.error.as_deref(), Some("Permission denied"));
+        assert!(location.values.is_empty());
+    }
+
+    #[test]
+    fn reads_firefox_policies_json() {
+        let writer = Rc::new(RecordingWriter::default());
+        let _writer = use_test_writer(writer.clone());
+        writer
+            .write_file(
+                &PathBuf::from("/etc/firefox/policies/policies.json"),
+                br#"{"policies": {"DisablePrivateBrowsing": true}}"#,
+                0o644,
+            )
+            .unwrap();
+
+        let values = read_location(Browser::Firefox, &target()).unwrap().unwrap();
+
+        assert_eq!(values, BTreeMap::from([("DisablePrivateBrowsing".to_string(), json!(true))]));
+    }
+}
@@ -1,121 +1,195 @@
 use anyhow::{Context, Result};
+use chrono::Utc;
 
+use crate::browser::current_platform;
 use crate::config::Config;
 use crate::state::{AppliedPolicies, State};
 
-mod chromium_common;
+pub mod backend;
 pub mod chrome;
+pub mod chromium;
+mod chromium_common;
+pub mod conflicts;
 pub mod edge;
 pub mod firefox;
+pub mod inspect;
+pub mod opera;
+pub mod plan;
+pub mod safari;
+pub mod verify;
+pub mod vivaldi;
+
+#[cfg(test)]
+mod snapshot_tests;
+
+pub use backend::{backends, PolicyBackend};
+pub use conflicts::{detect_conflicts, PolicyConflict};
+pub use plan::{evaluate_policy, ChangePlan, PolicyTarget, PolicyValue, PolicyWrite};
+pub use verify::{verify_plan, PolicyDrift};
 
 /// Apply policies for all configured browsers
-pub fn apply_policies(config: &Config, _current_state: Option<&State>, dry_run: bool) -> Result<AppliedPolicies> {
-    let mut applied = AppliedPolicies::default();
+///
+/// Expired policy entries are skipped. Browsers recorded in `current_state`
+/// that have nothing left to apply (e.g. their only entry expired) get their
+/// policies removed. Each browser's values are read back after writing and
+/// any that didn't take effect are listed in `AppliedPolicies::unverified`.
+///
+/// Values another management tool already sets are handled according to
+/// the config's `conflict_policy` and listed in `AppliedPolicies::conflicts`.
+pub fn apply_policies(config: &Config, current_state: Option<&State>, dry_run: bool) -> Result<AppliedPolicies> {
+    let conflicts = detect_conflicts(config, current_state.map(|s| &s.applied_policies))
+        .context("Failed to check for conflicting policies")?;
+    for conflict in &conflicts {
+        eprintln!("⚠ Conflict: {}", conflict);
+    }
+    let config = conflicts::resolve_conflicts(config, &conflicts)?;
 
-    // Convert new config format to browser-specific configs
-    let (chrome_config, firefox_config, edge_config) = crate::config::to_browser_configs(config);
+    let mut applied = apply_with(&backends(), &config, current_state, dry_run)?;
+    applied.conflicts = conflicts.iter().map(|c| c.to_string()).collect();
+    Ok(applied)
+}
 
-    // Apply Chrome policies
-    if let Some(chrome_config) = chrome_config {
-        if dry_run {
-            println!("═══ Chrome Policies (Dry Run) ═══");
-        } else {
-            println!("Applying Chrome policies...");
-        }
-        let state = chrome::apply_chrome_policies(&chrome_config, dry_run)
-            .context("Failed to apply Chrome policies")?;
+fn apply_with(
+    backends: &[Box<dyn PolicyBackend>],
+    config: &Config,
+    current_state: Option<&State>,
+    dry_run: bool,
+) -> Result<AppliedPolicies> {
+    let mut applied = AppliedPolicies::default();
 
-        if !state.is_empty() {
-            applied.chrome = Some(state);
-            if !dry_run {
-                println!("✓ Chrome policies applied successfully");
-            }
-        }
-        if dry_run {
-            println!();
+    let now = Utc::now();
+    for expired in config.expired_policies(now) {
+        if let Some(expires_at) = expired.expires_at {
+            println!(
+                "Skipping expired policy '{}' (expired {})",
+                expired.name,
+                expires_at.format("%Y-%m-%d %H:%M:%S %Z")
+            );
         }
     }
+    for (policy, reason) in config.inactive_policies(now) {
+        println!("Skipping policy '{}' ({})", policy.name, reason);
+    }
+    let config = &config.effective(now);
 
-    // Apply Firefox policies
-    if let Some(firefox_config) = firefox_config {
+    for backend in backends {
+        if !backend.is_configured(config) {
+            continue;
+        }
+
+        let name = backend.name();
         if dry_run {
-            println!("═══ Firefox Policies (Dry Run) ═══");
+            println!("═══ {} Policies (Dry Run) ═══", name);
         } else {
-            println!("Applying Firefox policies...");
+            println!("Applying {} policies...", name);
         }
-        let state = firefox::apply_firefox_policies(&firefox_config, dry_run)
-            .context("Failed to apply Firefox policies")?;
+        let mut state = backend
+            .apply(config, dry_run)
+            .with_context(|| format!("Failed to apply {} policies", name))?;
+        state.sources = crate::config::policy_sources(config, backend.browser());
 
         if !state.is_empty() {
-            applied.firefox = Some(state);
+            applied.set(backend.browser(), state);
             if !dry_run {
-                println!("✓ Firefox policies applied successfully");
+                println!("✓ {} policies applied successfully", name);
             }
         }
+        if !dry_run {
+            let unverified = read_back(backend.as_ref(), config);
+            if !unverified.is_empty() {
+                eprintln!("⚠ {} {} policy values did not take effect:", unverified.len(), name);
+                for problem in &unverified {
+                    eprintln!("  {}", problem);
+                }
+            }
+            applied.unverified.extend(unverified);
+        }
         if dry_run {
             println!();
         }
     }
 
-    // Apply Edge policies
-    if let Some(edge_config) = edge_config {
-        if dry_run {
-            println!("═══ Edge Policies (Dry Run) ═══");
-        } else {
-            println!("Applying Edge policies...");
-        }
-        let state = edge::apply_edge_policies(&edge_config, dry_run)
-            .context("Failed to apply Edge policies")?;
+    if let Some(state) = current_state {
+        remove_unconfigured_browsers(backends, &state.applied_policies, &applied, dry_run)?;
+    }
 
-        if !state.is_empty() {
-            applied.edge = Some(state);
-            if !dry_run {
-                println!("✓ Edge policies applied successfully");
-            }
+    Ok(applied)
+}
+
+/// Read back what `backend` just wrote and describe the values that differ
+///
+/// Something else managing the machine (MDM, Group Policy) can override our
+/// values, and a write can fail silently without the right permissions.
+fn read_back(backend: &dyn PolicyBackend, config: &Config) -> Vec<String> {
+    let drift = backend
+        .evaluate(config, current_platform())
+        .and_then(|writes| backend.diff(&writes));
+    match drift {
+        Ok(drift) => drift.iter().map(|d| d.to_string()).collect(),
+        Err(e) => vec![format!("could not read back {} policies: {:#}", backend.name(), e)],
+    }
+}
+
+/// Remove policies for browsers that were applied before but no longer are
+fn remove_unconfigured_browsers(
+    backends: &[Box<dyn PolicyBackend>],
+    previous: &AppliedPolicies,
+    applied: &AppliedPolicies,
+    dry_run: bool,
+) -> Result<()> {
+    for backend in backends {
+        let browser = backend.browser();
+        if previous.get(browser).is_none() || applied.get(browser).is_some() {
+            continue;
         }
+
+        let name = backend.name();
         if dry_run {
-            println!();
+            println!("Would remove {} policies (no longer configured)", name);
+            continue;
         }
+
+        println!("Removing {} policies (no longer configured)...", name);
+        backend
+            .remove()
+            .with_context(|| format!("Failed to remove {} policies", name))?;
+        println!("✓ {} policies removed successfully", name);
     }
 
-    Ok(applied)
+    Ok(())
 }
 
 /// Remove all policies for browsers tracked in the state
 pub fn remove_policies(state: &State) -> Result<()> {
+    remove_with(&backends(), state)
+}
+
+fn remove_with(backends: &[Box<dyn PolicyBackend>], state: &State) -> Result<()> {
     let mut any_errors = false;
 
-    // Remove Chrome policies
-    if state.applied_policies.chrome.is_some() {
-        println!("Removing Chrome policies...");
-        match chrome::remove_chrome_policies() {
-            Ok(_) => println!("✓ Chrome policies removed successfully"),
-            Err(e) => {
-                eprintln!("✗ Failed to remove Chrome policies: {:#}", e);
-                any_errors = true;
-            }
+    for backend in backends {
+        if state.applied_policies.get(backend.browser()).is_none() {
+            continue;
         }
-    }
 
-    // Remove Firefox policies
-    if state.applied_policies.firefox.is_some() {
-        println!("Removing Firefox policies...");
-        match firefox::remove_firefox_policies() {
-            Ok(_) => println!("✓ Firefox policies removed successfully"),
+        let name = backend.name();
+        println!("Removing {} policies...", name);
+        match backend.remove() {
+            Ok(_) => println!("✓ {} policies removed successfully", name),
             Err(e) => {
-                eprintln!("✗ Failed to remove Firefox policies: {:#}", e);
+                eprintln!("✗ Failed to remove {} policies: {:#}", name, e);
                 any_errors = true;
             }
         }
     }
 
-    // Remove Edge policies
-    if state.applied_policies.edge.is_some() {
-        println!("Removing Edge policies...");
-        match edge::remove_edge_policies() {
-            Ok(_) => println!("✓ Edge policies removed successfully"),
+    // Lift blocks placed by `unmanaged_browsers: block`
+    #[cfg(target_os = "windows")]
+    for executable in &state.blocked_executables {
+        match crate::platform::windows::unblock_executable(executable) {
+            Ok(_) => println!("✓ Unblocked {}", executable),
             Err(e) => {
-                eprintln!("✗ Failed to remove Edge policies: {:#}", e);
+                eprintln!("✗ Failed to unblock {}: {:#}", executable, e);
                 any_errors = true;
             }
         }
@@ -131,14 +205,170 @@ pub fn remove_policies(state: &State) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::browser::{Browser, Platform};
+    use crate::state::BrowserState;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Records what it was asked to do instead of writing anything
+    struct MockBackend {
+        browser: Browser,
+        calls: Rc<RefCell<Vec<String>>>,
+        /// Pretend something else overrides our sync setting
+        overridden: bool,
+    }
+
+    impl PolicyBackend for MockBackend {
+        fn browser(&self) -> Browser {
+            self.browser
+        }
+
+        fn name(&self) -> &'static str {
+            self.browser.as_str()
+        }
+
+        fn is_configured(&self, config: &Config) -> bool {
+            config.policies.iter().any(|p| p.browsers.contains(&self.browser))
+        }
+
+        fn evaluate(&self, _config: &Config, _platform: Platform) -> Result<Vec<PolicyWrite>> {
+            Ok(Vec::new())
+        }
+
+        fn locations(&self, _platform: Platform) -> Vec<PolicyTarget> {
+            Vec::new()
+        }
+
+        fn apply(&self, _config: &Config, _dry_run: bool) -> Result<BrowserState> {
+            self.calls.borrow_mut().push(format!("apply {}", self.name()));
+            let mut state = BrowserState::new();
+            state.disable_sync = Some(true);
+            Ok(state)
+        }
+
+        fn remove(&self) -> Result<()> {
+            self.calls.borrow_mut().push(format!("remove {}", self.name()));
+            Ok(())
+        }
+
+        fn diff(&self, _writes: &[PolicyWrite]) -> Result<Vec<PolicyDrift>> {
+            if !self.overridden {
+                return Ok(Vec::new());
+            }
+            Ok(vec![PolicyDrift {
+                browser: self.browser,
+                target: PolicyTarget::Registry {
+                    key: "SOFTWARE\\Policies\\Google\\Chrome".to_string(),
+                },
+                name: "SyncDisabled".to_string(),
+                expected: PolicyValue::Dword(1),
+                actual: Some("0".to_string()),
+            }])
+        }
+    }
+
+    fn mock_backends(calls: &Rc<RefCell<Vec<String>>>) -> Vec<Box<dyn PolicyBackend>> {
+        [Browser::Chrome, Browser::Firefox, Browser::Edge]
+            .into_iter()
+            .map(|browser| {
+                Box::new(MockBackend {
+                    browser,
+                    calls: calls.clone(),
+                    overridden: false,
+                }) as Box<dyn PolicyBackend>
+            })
+            .collect()
+    }
 
     #[test]
     fn test_apply_policies_empty_config() {
-        let config = Config {
-            policies: vec![],
-        };
+        let config = Config::default();
 
         // This should fail because at least one policy must be configured
         assert!(crate::config::validate_config(&config).is_err());
     }
+
+    #[test]
+    fn test_apply_removes_browsers_no_longer_configured() {
+        let config = Config::from_yaml_str(
+            r#"
+policies:
+  - name: No sync
+    browsers: [chrome]
+    disable_sync: true
+"#,
+        )
+        .unwrap();
+        let mut state = State::new_agent();
+        state.applied_policies.set(Browser::Chrome, BrowserState::new());
+        state.applied_policies.set(Browser::Edge, BrowserState::new());
+
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let applied = apply_with(&mock_backends(&calls), &config, Some(&state), false).unwrap();
+
+        assert_eq!(*calls.borrow(), ["apply chrome", "remove edge"]);
+        assert!(applied.chrome.is_some());
+        assert!(applied.firefox.is_none());
+        assert!(applied.edge.is_none());
+    }
+
+    #[test]
+    fn test_apply_reports_values_that_did_not_take_effect() {
+        let config = Config::from_yaml_str(
+            r#"
+policies:
+  - name: No sync
+    browsers: [chrome]
+    disable_sync: true
+"#,
+        )
+        .unwrap();
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let overridden: Vec<Box<dyn PolicyBackend>> = vec![Box::new(MockBackend {
+            browser: Browser::Chrome,
+            calls: calls.clone(),
+            overridden: true,
+        })];
+
+        let applied = apply_with(&overridden, &config, None, false).unwrap();
+        assert_eq!(applied.unverified.len(), 1);
+        assert!(applied.unverified[0].starts_with("chrome SyncDisabled"));
+
+        // Nothing is written, so nothing is read back
+        let applied = apply_with(&overridden, &config, None, true).unwrap();
+        assert!(applied.unverified.is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_apply_reads_back_written_values() {
+        use crate::platform::writer::{use_test_writer, RecordingWriter};
+
+        let writer = Rc::new(RecordingWriter::default());
+        let _writer = use_test_writer(writer.clone());
+        let config = Config::from_yaml_str(
+            r#"
+policies:
+  - name: No private browsing
+    browsers: [chrome, firefox]
+    disable_private_mode: true
+"#,
+        )
+        .unwrap();
+
+        let applied = apply_policies(&config, None, false).unwrap();
+        assert!(applied.chrome.is_some() && applied.firefox.is_some());
+        assert_eq!(applied.unverified, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_remove_only_touches_applied_browsers() {
+        let mut state = State::new_agent();
+        state.applied_policies.set(Browser::Firefox, BrowserState::new());
+
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        remove_with(&mock_backends(&calls), &state).unwrap();
+
+        assert_eq!(*calls.borrow(), ["remove firefox"]);
+    }
 }
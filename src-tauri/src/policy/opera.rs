@@ -0,0 +1,100 @@
+use anyhow::Result;
+
+use crate::browser::{Browser, Platform};
+use crate::config::{self, Config, ChromeConfig};
+use crate::state::BrowserState;
+
+use super::backend::PolicyBackend;
+use super::chromium_common::{self, ChromiumBrowserConfig, ChromiumConfig};
+use super::plan::{PolicyTarget, PolicyWrite};
+
+/// Opera-specific browser configuration
+///
+/// Opera reads Chrome's policies from its own places, though it doesn't
+/// document which ones it honours.
+pub(super) fn get_opera_browser_config() -> ChromiumBrowserConfig {
+    ChromiumBrowserConfig {
+        browser: Browser::Opera,
+        browser_name: "Opera",
+        registry_key: r"SOFTWARE\Policies\Opera Software\Opera",
+        bundle_id: "com.operasoftware.Opera",
+        linux_policy_dir: "/etc/opt/opera/policies/managed",
+    }
+}
+
+/// Apply Opera policies (extensions and privacy controls)
+pub fn apply_opera_policies(config: &ChromeConfig, dry_run: bool) -> Result<BrowserState> {
+    let chromium_config = ChromiumConfig::from_chrome(config);
+    let browser_config = get_opera_browser_config();
+
+    chromium_common::apply_chromium_policies(&chromium_config, &browser_config, dry_run)
+}
+
+/// Remove all Opera policies
+pub fn remove_opera_policies() -> Result<()> {
+    let browser_config = get_opera_browser_config();
+    chromium_common::remove_chromium_policies(&browser_config)
+}
+
+/// Opera's part of a config
+fn opera_config(config: &Config) -> Option<ChromeConfig> {
+    config::chromium_based_config(config, Browser::Opera)
+}
+
+/// Writes Opera policies
+pub struct OperaBackend;
+
+impl PolicyBackend for OperaBackend {
+    fn browser(&self) -> Browser {
+        Browser::Opera
+    }
+
+    fn name(&self) -> &'static str {
+        "Opera"
+    }
+
+    fn is_configured(&self, config: &Config) -> bool {
+        opera_config(config).is_some()
+    }
+
+    fn evaluate(&self, config: &Config, platform: Platform) -> Result<Vec<PolicyWrite>> {
+        Ok(opera_config(config)
+            .map(|c| {
+                chromium_common::evaluate_chromium_policies(
+                    &ChromiumConfig::from_chrome(&c),
+                    &get_opera_browser_config(),
+                    platform,
+                )
+            })
+            .unwrap_or_default())
+    }
+
+    fn locations(&self, platform: Platform) -> Vec<PolicyTarget> {
+        chromium_common::chromium_policy_locations(&get_opera_browser_config(), platform)
+    }
+
+    fn apply(&self, config: &Config, dry_run: bool) -> Result<BrowserState> {
+        match opera_config(config) {
+            Some(c) => apply_opera_policies(&c, dry_run),
+            None => Ok(BrowserState::new()),
+        }
+    }
+
+    fn remove(&self) -> Result<()> {
+        remove_opera_policies()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opera_browser_config() {
+        let config = get_opera_browser_config();
+        assert_eq!(config.browser_name, "Opera");
+        assert_eq!(config.registry_key, r"SOFTWARE\Policies\Opera Software\Opera");
+        assert_eq!(config.bundle_id, "com.operasoftware.Opera");
+        assert_eq!(config.private_mode_policy(), "IncognitoModeAvailability");
+    }
+}
@@ -0,0 +1,421 @@
+//! Pure policy evaluation
+//!
+//! `evaluate_policy` computes every value the appliers would write for a
+//! config on a given platform, without touching the registry, plists or the
+//! filesystem. This keeps policy semantics testable on any host and gives
+//! previews, verification and snapshot tests a single source of truth.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::browser::{Browser, Platform};
+use crate::config::Config;
+
+use super::backend::backends;
+
+/// Where a policy value is written
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PolicyTarget {
+    /// Windows registry key (under HKLM)
+    Registry { key: String },
+    /// macOS managed preferences domain (/Library/Managed Preferences/<domain>.plist)
+    Plist { domain: String },
+    /// JSON policy file (Linux Chromium policies, Firefox policies.json)
+    JsonFile { path: PathBuf },
+}
+
+/// A typed policy value
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum PolicyValue {
+    /// REG_DWORD (Windows)
+    Dword(u32),
+    Integer(i64),
+    Bool(bool),
+    String(String),
+    StringList(Vec<String>),
+    /// Structured value (nested JSON objects, plist dictionaries)
+    Json(serde_json::Value),
+}
+
+impl PolicyValue {
+    /// Map a JSON value onto the closest typed policy value
+    pub fn from_json(value: &serde_json::Value) -> Self {
+        use serde_json::Value;
+
+        match value {
+            Value::Bool(b) => PolicyValue::Bool(*b),
+            Value::Number(n) if n.is_i64() => PolicyValue::Integer(n.as_i64().unwrap_or_default()),
+            Value::String(s) => PolicyValue::String(s.clone()),
+            Value::Array(items) if items.iter().all(Value::is_string) => PolicyValue::StringList(
+                items
+                    .iter()
+                    .filter_map(|v| v.as_str().map(str::to_string))
+                    .collect(),
+            ),
+            other => PolicyValue::Json(other.clone()),
+        }
+    }
+
+    /// The JSON form of the value (as written to JSON policy files)
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            PolicyValue::Dword(d) => serde_json::json!(d),
+            PolicyValue::Integer(i) => serde_json::json!(i),
+            PolicyValue::Bool(b) => serde_json::json!(b),
+            PolicyValue::String(s) => serde_json::json!(s),
+            PolicyValue::StringList(items) => serde_json::json!(items),
+            PolicyValue::Json(value) => value.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for PolicyTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolicyTarget::Registry { key } => write!(f, "HKLM\\{}", key),
+            PolicyTarget::Plist { domain } => write!(f, "/Library/Managed Preferences/{}.plist", domain),
+            PolicyTarget::JsonFile { path } => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// A single value the appliers intend to write
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PolicyWrite {
+    pub browser: Browser,
+    pub target: PolicyTarget,
+    pub name: String,
+    pub value: PolicyValue,
+}
+
+impl PolicyWrite {
+    pub fn new(browser: Browser, target: PolicyTarget, name: impl Into<String>, value: PolicyValue) -> Self {
+        Self {
+            browser,
+            target,
+            name: name.into(),
+            value,
+        }
+    }
+}
+
+/// Every policy write for a config on one platform, in apply order
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChangePlan {
+    pub platform: Platform,
+    pub writes: Vec<PolicyWrite>,
+}
+
+impl ChangePlan {
+    /// Whether the plan writes nothing
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+
+    /// All writes for one browser
+    pub fn for_browser(&self, browser: Browser) -> impl Iterator<Item = &PolicyWrite> {
+        self.writes.iter().filter(move |w| w.browser == browser)
+    }
+
+    /// Find a write by browser and value name
+    pub fn find(&self, browser: Browser, name: &str) -> Option<&PolicyWrite> {
+        self.for_browser(browser).find(|w| w.name == name)
+    }
+}
+
+/// Evaluate a config into the writes the appliers would perform on `platform`
+///
+/// This is pure: nothing on the current system is read or modified, so any
+/// platform can be evaluated from any host.
+pub fn evaluate_policy(config: &Config, platform: Platform) -> Result<ChangePlan> {
+    let mut writes = Vec::new();
+    for backend in backends() {
+        writes.extend(
+            backend
+                .evaluate(config, platform)
+                .with_context(|| format!("Failed to evaluate {} policies", backend.name()))?,
+        );
+    }
+
+    Ok(ChangePlan { platform, writes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const CHROME_ID: &str = "ddkjiahejlhfcafbddmgiahcphecmpfh";
+
+    fn reference_config() -> Config {
+        Config::from_yaml_str(
+            r#"
+policies:
+  - name: Privacy
+    browsers: [chrome, firefox, edge]
+    disable_private_mode: true
+    disable_guest_mode: true
+    allow_deleting_browser_history: false
+  - name: uBlock Origin Lite
+    browsers: [chrome, firefox, edge]
+    extensions:
+      - name: uBlock Origin Lite
+        id:
+          chrome: ddkjiahejlhfcafbddmgiahcphecmpfh
+          firefox: uBOLite@raymondhill.net
+          edge: ddkjiahejlhfcafbddmgiahcphecmpfh
+        settings:
+          rulesets: ["+default", "-easylist"]
+          strictBlockMode: true
+"#,
+        )
+        .unwrap()
+    }
+
+    fn value(plan: &ChangePlan, browser: Browser, name: &str) -> PolicyValue {
+        plan.find(browser, name)
+            .unwrap_or_else(|| panic!("missing {:?} {}", browser, name))
+            .value
+            .clone()
+    }
+
+    fn forcelist_entry() -> String {
+        format!("{};{}", CHROME_ID, crate::config::DEFAULT_CHROME_UPDATE_URL)
+    }
+
+    #[test]
+    fn test_evaluate_chrome_windows() {
+        let plan = evaluate_policy(&reference_config(), Platform::Windows).unwrap();
+
+        let forcelist = plan.find(Browser::Chrome, "1").unwrap();
+        assert_eq!(
+            forcelist.target,
+            PolicyTarget::Registry {
+                key: r"SOFTWARE\Policies\Google\Chrome\ExtensionInstallForcelist".to_string()
+            }
+        );
+        assert_eq!(forcelist.value, PolicyValue::String(forcelist_entry()));
+
+        assert_eq!(value(&plan, Browser::Chrome, "IncognitoModeAvailability"), PolicyValue::Dword(1));
+        assert_eq!(value(&plan, Browser::Chrome, "BrowserGuestModeEnabled"), PolicyValue::Dword(0));
+        assert_eq!(value(&plan, Browser::Chrome, "AllowDeletingBrowserHistory"), PolicyValue::Dword(0));
+    }
+
+    #[test]
+    fn test_evaluate_chrome_windows_extension_settings() {
+        let plan = evaluate_policy(&reference_config(), Platform::Windows).unwrap();
+        let policy_key = format!(r"SOFTWARE\Policies\Google\Chrome\3rdparty\extensions\{}\policy", CHROME_ID);
+
+        let strict = plan.find(Browser::Chrome, "strictBlockMode").unwrap();
+        assert_eq!(strict.target, PolicyTarget::Registry { key: policy_key.clone() });
+        assert_eq!(strict.value, PolicyValue::Dword(1));
+
+        let rulesets: Vec<_> = plan
+            .for_browser(Browser::Chrome)
+            .filter(|w| w.target == PolicyTarget::Registry { key: format!(r"{}\rulesets", policy_key) })
+            .map(|w| (w.name.as_str(), w.value.clone()))
+            .collect();
+        assert_eq!(
+            rulesets,
+            vec![
+                ("1", PolicyValue::String("+default".to_string())),
+                ("2", PolicyValue::String("-easylist".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_chrome_macos() {
+        let plan = evaluate_policy(&reference_config(), Platform::MacOS).unwrap();
+
+        let forcelist = plan.find(Browser::Chrome, "ExtensionInstallForcelist").unwrap();
+        assert_eq!(forcelist.target, PolicyTarget::Plist { domain: "com.google.Chrome".to_string() });
+        assert_eq!(forcelist.value, PolicyValue::StringList(vec![forcelist_entry()]));
+
+        assert_eq!(value(&plan, Browser::Chrome, "IncognitoModeAvailability"), PolicyValue::Integer(1));
+        assert_eq!(value(&plan, Browser::Chrome, "BrowserGuestModeEnabled"), PolicyValue::Bool(false));
+        assert_eq!(value(&plan, Browser::Chrome, "AllowDeletingBrowserHistory"), PolicyValue::Bool(false));
+
+        let strict = plan.find(Browser::Chrome, "strictBlockMode").unwrap();
+        assert_eq!(
+            strict.target,
+            PolicyTarget::Plist { domain: format!("com.google.Chrome.extensions.{}", CHROME_ID) }
+        );
+        assert_eq!(strict.value, PolicyValue::Json(json!(true)));
+    }
+
+    #[test]
+    fn test_evaluate_chrome_linux() {
+        let plan = evaluate_policy(&reference_config(), Platform::Linux).unwrap();
+        let target = PolicyTarget::JsonFile {
+            path: PathBuf::from("/etc/opt/chrome/policies/managed/browser-policy.json"),
+        };
+
+        let chrome: Vec<_> = plan.for_browser(Browser::Chrome).collect();
+        assert!(chrome.iter().all(|w| w.target == target));
+
+        assert_eq!(
+            value(&plan, Browser::Chrome, "ExtensionInstallForcelist"),
+            PolicyValue::StringList(vec![forcelist_entry()])
+        );
+        assert_eq!(value(&plan, Browser::Chrome, "IncognitoModeAvailability"), PolicyValue::Integer(1));
+        assert_eq!(value(&plan, Browser::Chrome, "BrowserGuestModeEnabled"), PolicyValue::Bool(false));
+        assert_eq!(
+            value(&plan, Browser::Chrome, "3rdparty"),
+            PolicyValue::Json(json!({
+                "extensions": {
+                    CHROME_ID: {
+                        "rulesets": ["+default", "-easylist"],
+                        "strictBlockMode": true,
+                    }
+                }
+            }))
+        );
+    }
+
+    #[test]
+    fn test_evaluate_edge_uses_inprivate_and_edge_locations() {
+        let windows = evaluate_policy(&reference_config(), Platform::Windows).unwrap();
+        let inprivate = windows.find(Browser::Edge, "InPrivateModeAvailability").unwrap();
+        assert_eq!(
+            inprivate.target,
+            PolicyTarget::Registry { key: r"SOFTWARE\Policies\Microsoft\Edge".to_string() }
+        );
+        assert!(windows.find(Browser::Edge, "IncognitoModeAvailability").is_none());
+
+        let macos = evaluate_policy(&reference_config(), Platform::MacOS).unwrap();
+        assert_eq!(
+            macos.find(Browser::Edge, "InPrivateModeAvailability").unwrap().target,
+            PolicyTarget::Plist { domain: "com.microsoft.Edge".to_string() }
+        );
+
+        let linux = evaluate_policy(&reference_config(), Platform::Linux).unwrap();
+        assert_eq!(
+            linux.find(Browser::Edge, "InPrivateModeAvailability").unwrap().target,
+            PolicyTarget::JsonFile {
+                path: PathBuf::from("/etc/opt/microsoft/edge/policies/managed/browser-policy.json")
+            }
+        );
+    }
+
+    #[test]
+    fn test_evaluate_sync_and_signin() {
+        let config = Config::from_yaml_str(
+            r#"
+policies:
+  - name: No sync
+    browsers: [chrome, firefox, edge]
+    disable_sync: true
+  - name: School accounts only
+    browsers: [chrome]
+    restrict_signin: ".*@school\\.example"
+  - name: No sign-in
+    browsers: [edge]
+    restrict_signin: true
+"#,
+        )
+        .unwrap();
+
+        let windows = evaluate_policy(&config, Platform::Windows).unwrap();
+        assert_eq!(value(&windows, Browser::Chrome, "SyncDisabled"), PolicyValue::Dword(1));
+        assert_eq!(value(&windows, Browser::Chrome, "BrowserSignin"), PolicyValue::Dword(1));
+        assert_eq!(
+            value(&windows, Browser::Chrome, "RestrictSigninToPattern"),
+            PolicyValue::String(r".*@school\.example".to_string())
+        );
+        assert_eq!(value(&windows, Browser::Edge, "BrowserSignin"), PolicyValue::Dword(0));
+        assert!(windows.find(Browser::Edge, "RestrictSigninToPattern").is_none());
+        assert_eq!(value(&windows, Browser::Firefox, "DisableFirefoxAccounts"), PolicyValue::Bool(true));
+
+        let macos = evaluate_policy(&config, Platform::MacOS).unwrap();
+        assert_eq!(value(&macos, Browser::Edge, "SyncDisabled"), PolicyValue::Bool(true));
+        assert_eq!(value(&macos, Browser::Edge, "BrowserSignin"), PolicyValue::Integer(0));
+
+        let linux = evaluate_policy(&config, Platform::Linux).unwrap();
+        assert_eq!(value(&linux, Browser::Chrome, "SyncDisabled"), PolicyValue::Bool(true));
+        assert_eq!(value(&linux, Browser::Chrome, "BrowserSignin"), PolicyValue::Integer(1));
+    }
+
+    #[test]
+    fn test_evaluate_firefox_per_platform() {
+        let cases = [
+            (Platform::Windows, r"C:\Program Files\Mozilla Firefox\distribution\policies.json"),
+            (Platform::MacOS, "/Applications/Firefox.app/Contents/Resources/distribution/policies.json"),
+            (Platform::Linux, "/etc/firefox/policies/policies.json"),
+        ];
+
+        for (platform, path) in cases {
+            let plan = evaluate_policy(&reference_config(), platform).unwrap();
+            let firefox: Vec<_> = plan.for_browser(Browser::Firefox).collect();
+
+            assert!(firefox
+                .iter()
+                .all(|w| w.target == PolicyTarget::JsonFile { path: PathBuf::from(path) }));
+            assert_eq!(value(&plan, Browser::Firefox, "DisablePrivateBrowsing"), PolicyValue::Bool(true));
+
+            let PolicyValue::Json(settings) = value(&plan, Browser::Firefox, "ExtensionSettings") else {
+                panic!("ExtensionSettings should be a JSON object");
+            };
+            assert_eq!(
+                settings["uBOLite@raymondhill.net"]["installation_mode"],
+                "force_installed"
+            );
+        }
+    }
+
+    #[test]
+    fn test_evaluate_skips_unset_privacy_controls() {
+        let config = Config::from_yaml_str(
+            r#"
+policies:
+  - name: Extension only
+    browsers: [chrome]
+    extensions:
+      - name: uBlock Origin Lite
+        id: ddkjiahejlhfcafbddmgiahcphecmpfh
+"#,
+        )
+        .unwrap();
+
+        for platform in [Platform::Windows, Platform::MacOS, Platform::Linux] {
+            let plan = evaluate_policy(&config, platform).unwrap();
+            assert!(plan.find(Browser::Chrome, "IncognitoModeAvailability").is_none());
+            assert!(plan.find(Browser::Chrome, "BrowserGuestModeEnabled").is_none());
+            assert!(plan.find(Browser::Chrome, "AllowDeletingBrowserHistory").is_none());
+            assert_eq!(plan.for_browser(Browser::Firefox).count(), 0);
+            assert_eq!(plan.for_browser(Browser::Edge).count(), 0);
+        }
+    }
+
+    #[test]
+    fn test_evaluate_enabled_private_mode_writes_nothing() {
+        // Appliers only write the private mode policy when disabling it
+        let config = Config::from_yaml_str(
+            r#"
+policies:
+  - name: Allow incognito
+    browsers: [chrome, firefox]
+    disable_private_mode: false
+"#,
+        )
+        .unwrap();
+
+        let plan = evaluate_policy(&config, Platform::Linux).unwrap();
+        assert!(plan.find(Browser::Chrome, "IncognitoModeAvailability").is_none());
+        assert!(plan.find(Browser::Firefox, "DisablePrivateBrowsing").is_none());
+    }
+
+    #[test]
+    fn test_policy_value_from_json() {
+        assert_eq!(PolicyValue::from_json(&json!(true)), PolicyValue::Bool(true));
+        assert_eq!(PolicyValue::from_json(&json!(1)), PolicyValue::Integer(1));
+        assert_eq!(
+            PolicyValue::from_json(&json!(["a", "b"])),
+            PolicyValue::StringList(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(PolicyValue::from_json(&json!({"a": 1})), PolicyValue::Json(json!({"a": 1})));
+    }
+}
@@ -0,0 +1,205 @@
+//! Safari restrictions through the Screen Time web content filter
+//!
+//! Safari has no policy for Private Browsing or blocked sites, so we write
+//! the managed preferences domain of the Screen Time content filter: the
+//! same payload a configuration profile's "Content Restrictions" install.
+//! Turning on the filter ("Limit Adult Websites") also disables Private
+//! Browsing, and `filterBlacklist` adds the configured websites to what it
+//! blocks.
+
+use anyhow::Result;
+
+use crate::browser::{current_platform, Browser, Platform};
+use crate::config::{self, Config, SafariConfig};
+use crate::state::BrowserState;
+
+use super::backend::PolicyBackend;
+use super::plan::{PolicyTarget, PolicyValue, PolicyWrite};
+
+/// Managed preferences domain of the Screen Time web content filter
+pub const CONTENT_FILTER_DOMAIN: &str = "com.apple.familycontrols.contentfilter";
+
+/// Content filter values for `config` (the same on every Mac)
+///
+/// Nothing is written if Private Browsing stays allowed and no website is
+/// blocked, since any filter would disable it.
+fn evaluate_safari(config: &SafariConfig) -> Vec<PolicyWrite> {
+    if config.disable_private_browsing != Some(true) && config.blocked_urls.is_empty() {
+        return Vec::new();
+    }
+
+    let target = PolicyTarget::Plist {
+        domain: CONTENT_FILTER_DOMAIN.to_string(),
+    };
+    let write = |name: &str, value| PolicyWrite::new(Browser::Safari, target.clone(), name, value);
+
+    let mut writes = vec![
+        write("restrictWeb", PolicyValue::Bool(true)),
+        write("useContentFilter", PolicyValue::Bool(true)),
+        // Block the listed sites rather than allowing only a list of sites
+        write("whitelistEnabled", PolicyValue::Bool(false)),
+    ];
+    if !config.blocked_urls.is_empty() {
+        writes.push(write("filterBlacklist", PolicyValue::StringList(config.blocked_urls.clone())));
+    }
+    writes
+}
+
+/// Apply Safari restrictions (private browsing and blocked websites)
+///
+/// Skipped with a warning on other platforms, so one policy can cover every
+/// family computer.
+pub fn apply_safari_policies(config: &SafariConfig, dry_run: bool) -> Result<BrowserState> {
+    if current_platform() != Platform::MacOS {
+        eprintln!("⚠ Safari restrictions can only be applied on macOS, skipping");
+        return Ok(BrowserState::new());
+    }
+    apply_safari_macos(&evaluate_safari(config), dry_run)?;
+
+    let mut state = BrowserState::new();
+    state.disable_private_browsing = config.disable_private_browsing;
+    state.blocked_urls = config.blocked_urls.clone();
+    Ok(state)
+}
+
+/// Remove the Safari restrictions we may have written
+pub fn remove_safari_policies() -> Result<()> {
+    remove_safari_macos()
+}
+
+#[cfg(target_os = "macos")]
+fn apply_safari_macos(writes: &[PolicyWrite], dry_run: bool) -> Result<()> {
+    use crate::platform::macos::{apply_plist_policy_with_preview, json_to_plist};
+    use anyhow::Context;
+
+    if writes.is_empty() {
+        return Ok(());
+    }
+
+    let updates = writes
+        .iter()
+        .filter_map(|w| json_to_plist(&w.value.to_json()).map(|value| (w.name.clone(), value)))
+        .collect();
+    apply_plist_policy_with_preview(CONTENT_FILTER_DOMAIN, updates, dry_run)
+        .context("Failed to write the Safari content filter")
+}
+
+#[cfg(target_os = "macos")]
+fn remove_safari_macos() -> Result<()> {
+    use crate::platform::macos::remove_plist_keys;
+    use anyhow::Context;
+
+    // Every key evaluate_safari may write
+    let keys: Vec<String> = ["restrictWeb", "useContentFilter", "whitelistEnabled", "filterBlacklist"]
+        .iter()
+        .map(|k| k.to_string())
+        .collect();
+    remove_plist_keys(CONTENT_FILTER_DOMAIN, &keys).context("Failed to remove the Safari content filter")
+}
+
+// Stub implementations for platforms not compiled
+#[cfg(not(target_os = "macos"))]
+fn apply_safari_macos(_writes: &[PolicyWrite], _dry_run: bool) -> Result<()> {
+    anyhow::bail!("macOS platform not supported in this build")
+}
+
+#[cfg(not(target_os = "macos"))]
+fn remove_safari_macos() -> Result<()> {
+    Ok(())
+}
+
+/// Writes Safari restrictions
+pub struct SafariBackend;
+
+impl PolicyBackend for SafariBackend {
+    fn browser(&self) -> Browser {
+        Browser::Safari
+    }
+
+    fn name(&self) -> &'static str {
+        "Safari"
+    }
+
+    fn is_configured(&self, config: &Config) -> bool {
+        config::safari_config(config).is_some()
+    }
+
+    fn evaluate(&self, config: &Config, platform: Platform) -> Result<Vec<PolicyWrite>> {
+        if platform != Platform::MacOS {
+            return Ok(Vec::new());
+        }
+        Ok(config::safari_config(config)
+            .map(|c| evaluate_safari(&c))
+            .unwrap_or_default())
+    }
+
+    fn locations(&self, platform: Platform) -> Vec<PolicyTarget> {
+        match platform {
+            Platform::MacOS => vec![PolicyTarget::Plist {
+                domain: CONTENT_FILTER_DOMAIN.to_string(),
+            }],
+            Platform::Windows | Platform::Linux => Vec::new(),
+        }
+    }
+
+    fn apply(&self, config: &Config, dry_run: bool) -> Result<BrowserState> {
+        match config::safari_config(config) {
+            Some(c) => apply_safari_policies(&c, dry_run),
+            None => Ok(BrowserState::new()),
+        }
+    }
+
+    fn remove(&self) -> Result<()> {
+        remove_safari_policies()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn safari(yaml: &str) -> Vec<PolicyWrite> {
+        let config = Config::from_yaml_str(yaml).unwrap();
+        SafariBackend.evaluate(&config, Platform::MacOS).unwrap()
+    }
+
+    #[test]
+    fn test_evaluate_safari_content_filter() {
+        let writes = safari(
+            r#"
+policies:
+  - name: Safari
+    browsers: [safari]
+    disable_private_mode: true
+    blocked_urls: [reddit.com, " tiktok.com"]
+  - name: More
+    browsers: [safari, chrome]
+    blocked_urls: [reddit.com]
+"#,
+        );
+
+        let names: Vec<_> = writes.iter().map(|w| w.name.as_str()).collect();
+        assert_eq!(names, ["restrictWeb", "useContentFilter", "whitelistEnabled", "filterBlacklist"]);
+        assert_eq!(
+            writes[3].value,
+            PolicyValue::StringList(vec!["reddit.com".to_string(), "tiktok.com".to_string()])
+        );
+        assert!(writes.iter().all(|w| w.target.to_string().ends_with("com.apple.familycontrols.contentfilter.plist")));
+    }
+
+    #[test]
+    fn test_evaluate_safari_only_on_macos_and_when_restricting() {
+        let yaml = r#"
+policies:
+  - name: Private browsing allowed
+    browsers: [safari]
+    disable_private_mode: false
+"#;
+        assert!(safari(yaml).is_empty());
+
+        let config = Config::from_yaml_str(&yaml.replace("false", "true")).unwrap();
+        assert_eq!(SafariBackend.evaluate(&config, Platform::MacOS).unwrap().len(), 3);
+        assert!(SafariBackend.evaluate(&config, Platform::Windows).unwrap().is_empty());
+        assert!(SafariBackend.evaluate(&config, Platform::Linux).unwrap().is_empty());
+    }
+}
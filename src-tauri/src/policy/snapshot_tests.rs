@@ -0,0 +1,154 @@
+//! Golden-file snapshots of every policy artifact the crate writes
+//!
+//! Each test renders, for one reference config, exactly what would land on
+//! disk or in the registry on a platform. Any change to policy output shows
+//! up as a snapshot diff in review. After an intended change, regenerate
+//! with `cargo insta review` (or `INSTA_UPDATE=always cargo test`).
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::browser::{Browser, Platform};
+use crate::config::{self, Config};
+
+use super::chromium_common::{self, ChromiumConfig};
+use super::plan::{evaluate_policy, PolicyTarget, PolicyValue};
+use super::{chrome, edge, firefox};
+
+const REFERENCE_CONFIG: &str = r#"
+policies:
+  - name: Privacy
+    browsers: [chrome, firefox, edge]
+    disable_private_mode: true
+    disable_guest_mode: true
+    allow_deleting_browser_history: false
+  - name: uBlock Origin Lite
+    browsers: [chrome, firefox, edge]
+    extensions:
+      - name: uBlock Origin Lite
+        id:
+          chrome: ddkjiahejlhfcafbddmgiahcphecmpfh
+          firefox: uBOLite@raymondhill.net
+          edge: ddkjiahejlhfcafbddmgiahcphecmpfh
+        settings:
+          rulesets: ["+default", "-easylist"]
+          strictBlockMode: true
+          noFiltering: 0
+  - name: Bitwarden
+    browsers: [chrome, edge]
+    extensions:
+      - name: Bitwarden
+        id: nngceckbapebfimnlniiiahkandclblb
+"#;
+
+fn reference_config() -> Config {
+    Config::from_yaml_str(REFERENCE_CONFIG).unwrap()
+}
+
+/// Render the Windows writes as a .reg file
+fn render_registry(browser: Browser) -> String {
+    let plan = evaluate_policy(&reference_config(), Platform::Windows).unwrap();
+
+    // Group values by key, keeping the order values are written in
+    let mut keys: Vec<(String, Vec<(String, PolicyValue)>)> = Vec::new();
+    for write in plan.for_browser(browser) {
+        let PolicyTarget::Registry { key } = &write.target else {
+            panic!("non-registry target on Windows: {:?}", write.target);
+        };
+        match keys.iter_mut().find(|(k, _)| k == key) {
+            Some((_, values)) => values.push((write.name.clone(), write.value.clone())),
+            None => keys.push((key.clone(), vec![(write.name.clone(), write.value.clone())])),
+        }
+    }
+
+    let mut reg = String::from("Windows Registry Editor Version 5.00\n");
+    for (key, values) in keys {
+        writeln!(reg, "\n[HKEY_LOCAL_MACHINE\\{}]", key).unwrap();
+        for (name, value) in values {
+            let data = match value {
+                PolicyValue::Dword(d) => format!("dword:{:08x}", d),
+                PolicyValue::String(s) => format!("\"{}\"", s.replace('\\', "\\\\")),
+                other => panic!("unexpected registry value type: {:?}", other),
+            };
+            writeln!(reg, "\"{}\"={}", name, data).unwrap();
+        }
+    }
+    reg
+}
+
+/// Render the macOS writes as managed preference domains
+fn render_plists(browser: Browser) -> BTreeMap<String, BTreeMap<String, PolicyValue>> {
+    let plan = evaluate_policy(&reference_config(), Platform::MacOS).unwrap();
+
+    let mut domains: BTreeMap<String, BTreeMap<String, PolicyValue>> = BTreeMap::new();
+    for write in plan.for_browser(browser) {
+        let PolicyTarget::Plist { domain } = &write.target else {
+            panic!("non-plist target on macOS: {:?}", write.target);
+        };
+        domains
+            .entry(domain.clone())
+            .or_default()
+            .insert(write.name.clone(), write.value.clone());
+    }
+    domains
+}
+
+fn chromium_configs() -> (ChromiumConfig, ChromiumConfig) {
+    let (chrome_config, _, edge_config) = config::to_browser_configs(&reference_config());
+    (
+        ChromiumConfig::from_chrome(&chrome_config.unwrap()),
+        ChromiumConfig::from_edge(&edge_config.unwrap()),
+    )
+}
+
+#[test]
+fn snapshot_windows_chrome_registry() {
+    insta::assert_snapshot!(render_registry(Browser::Chrome));
+}
+
+#[test]
+fn snapshot_windows_edge_registry() {
+    insta::assert_snapshot!(render_registry(Browser::Edge));
+}
+
+#[test]
+fn snapshot_macos_chrome_plists() {
+    insta::assert_json_snapshot!(render_plists(Browser::Chrome));
+}
+
+#[test]
+fn snapshot_macos_edge_plists() {
+    insta::assert_json_snapshot!(render_plists(Browser::Edge));
+}
+
+#[test]
+fn snapshot_linux_chrome_policy_json() {
+    let (chrome_config, _) = chromium_configs();
+    insta::assert_json_snapshot!(chromium_common::build_chromium_linux_policy(
+        &chrome_config,
+        &chrome::get_chrome_browser_config()
+    ));
+}
+
+#[test]
+fn snapshot_linux_edge_policy_json() {
+    let (_, edge_config) = chromium_configs();
+    insta::assert_json_snapshot!(chromium_common::build_chromium_linux_policy(
+        &edge_config,
+        &edge::get_edge_browser_config()
+    ));
+}
+
+#[test]
+fn snapshot_firefox_policies_json() {
+    let (_, firefox_config, _) = config::to_browser_configs(&reference_config());
+    insta::assert_json_snapshot!(firefox::create_firefox_policies_json(&firefox_config.unwrap()).unwrap());
+}
+
+#[test]
+fn snapshot_change_plans() {
+    for platform in [Platform::Windows, Platform::MacOS, Platform::Linux] {
+        let plan = evaluate_policy(&reference_config(), platform).unwrap();
+        insta::assert_json_snapshot!(format!("change_plan_{}", platform.name().to_lowercase()), plan);
+    }
+}
@@ -0,0 +1,272 @@
+//! Read back applied policies and compare them with a `ChangePlan`
+//!
+//! Used to detect drift: policies removed or edited behind our back (for
+//! example while the agent wasn't running), or overridden by something else
+//! managing the machine.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::browser::Browser;
+use crate::platform::writer::writer;
+
+use super::backend::backends;
+use super::plan::{ChangePlan, PolicyTarget, PolicyValue, PolicyWrite};
+
+/// A planned policy value that isn't what's currently on the system
+#[derive(Debug, Clone, Serialize)]
+pub struct PolicyDrift {
+    pub browser: Browser,
+    pub target: PolicyTarget,
+    pub name: String,
+    pub expected: PolicyValue,
+    /// What was found instead (`None` if the value is missing)
+    pub actual: Option<String>,
+}
+
+impl std::fmt::Display for PolicyDrift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} at {}: expected {}, found {}",
+            self.browser.as_str(),
+            self.name,
+            self.target,
+            self.expected.to_json(),
+            self.actual.as_deref().unwrap_or("nothing")
+        )
+    }
+}
+
+/// Check every write in the plan against the current system
+///
+/// The plan must be for the current platform. Each browser's writes are
+/// read back by its backend. Returns the writes whose values are missing or
+/// different.
+pub fn verify_plan(plan: &ChangePlan) -> Result<Vec<PolicyDrift>> {
+    let mut drift = Vec::new();
+    for backend in backends() {
+        let writes: Vec<PolicyWrite> = plan.for_browser(backend.browser()).cloned().collect();
+        if !writes.is_empty() {
+            drift.extend(backend.diff(&writes)?);
+        }
+    }
+    Ok(drift)
+}
+
+/// Read back `writes` from wherever their targets say they live
+///
+/// This is how backends check their values unless they override
+/// `PolicyBackend::diff`.
+pub fn verify_writes(writes: &[PolicyWrite]) -> Result<Vec<PolicyDrift>> {
+    let mut json_files: HashMap<PathBuf, Option<serde_json::Value>> = HashMap::new();
+    let mut drift = Vec::new();
+
+    for write in writes {
+        let actual = match &write.target {
+            PolicyTarget::JsonFile { path } => {
+                if !json_files.contains_key(path) {
+                    json_files.insert(path.clone(), read_json_file(path)?);
+                }
+                check_json_value(write, json_files[path].as_ref())
+            }
+            PolicyTarget::Registry { key } => check_registry_value(write, key)?,
+            PolicyTarget::Plist { domain } => check_plist_value(write, domain)?,
+        };
+
+        if let Err(actual) = actual {
+            drift.push(PolicyDrift {
+                browser: write.browser,
+                target: write.target.clone(),
+                name: write.name.clone(),
+                expected: write.value.clone(),
+                actual,
+            });
+        }
+    }
+
+    Ok(drift)
+}
+
+/// Outcome of checking one value: `Err` carries what was found instead
+type Check = std::result::Result<(), Option<String>>;
+
+fn read_json_file(path: &Path) -> Result<Option<serde_json::Value>> {
+    let Some(content) = writer()
+        .read_file(path)
+        .with_context(|| format!("Failed to read policy file: {}", path.display()))?
+    else {
+        return Ok(None);
+    };
+
+    // A corrupted file counts as drift, not as a verification failure
+    Ok(Some(serde_json::from_slice(&content).unwrap_or(serde_json::Value::Null)))
+}
+
+fn check_json_value(write: &PolicyWrite, document: Option<&serde_json::Value>) -> Check {
+    // Firefox nests everything under a top-level "policies" object
+    let root = match write.browser {
+        Browser::Firefox => document.and_then(|d| d.get("policies")),
+        _ => document,
+    };
+
+    match root.and_then(|r| r.get(&write.name)) {
+        Some(actual) if *actual == write.value.to_json() => Ok(()),
+        Some(actual) => Err(Some(actual.to_string())),
+        None => Err(None),
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn check_registry_value(write: &PolicyWrite, key: &str) -> Result<Check> {
+    use crate::platform::windows::{read_registry_value, RegistryValue};
+
+    let expected = match &write.value {
+        PolicyValue::Dword(d) => RegistryValue::Dword(*d),
+        PolicyValue::String(s) => RegistryValue::String(s.clone()),
+        other => anyhow::bail!("Cannot verify {:?} in the registry", other),
+    };
+
+    Ok(match read_registry_value(key, &write.name)? {
+        Some(actual) if actual == expected => Ok(()),
+        Some(actual) => Err(Some(format!("{:?}", actual))),
+        None => Err(None),
+    })
+}
+
+#[cfg(not(target_os = "windows"))]
+fn check_registry_value(_write: &PolicyWrite, _key: &str) -> Result<Check> {
+    anyhow::bail!("Registry policies can only be verified on Windows")
+}
+
+#[cfg(target_os = "macos")]
+fn check_plist_value(write: &PolicyWrite, domain: &str) -> Result<Check> {
+    use crate::platform::macos::{json_to_plist, read_plist_value};
+
+    let expected = json_to_plist(&write.value.to_json());
+
+    Ok(match read_plist_value(domain, &write.name)? {
+        Some(actual) if Some(&actual) == expected.as_ref() => Ok(()),
+        Some(actual) => Err(Some(format!("{:?}", actual))),
+        None => Err(None),
+    })
+}
+
+#[cfg(not(target_os = "macos"))]
+fn check_plist_value(_write: &PolicyWrite, _domain: &str) -> Result<Check> {
+    anyhow::bail!("Plist policies can only be verified on macOS")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::browser::Platform;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn json_write(browser: Browser, path: &Path, name: &str, value: PolicyValue) -> PolicyWrite {
+        PolicyWrite::new(
+            browser,
+            PolicyTarget::JsonFile { path: path.to_path_buf() },
+            name,
+            value,
+        )
+    }
+
+    fn plan(writes: Vec<PolicyWrite>) -> ChangePlan {
+        ChangePlan {
+            platform: Platform::Linux,
+            writes,
+        }
+    }
+
+    #[test]
+    fn test_verify_matching_json_policy() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("browser-policy.json");
+        std::fs::write(&path, r#"{"BrowserGuestModeEnabled": false, "IncognitoModeAvailability": 1}"#).unwrap();
+
+        let drift = verify_plan(&plan(vec![
+            json_write(Browser::Chrome, &path, "BrowserGuestModeEnabled", PolicyValue::Bool(false)),
+            json_write(Browser::Chrome, &path, "IncognitoModeAvailability", PolicyValue::Integer(1)),
+        ]))
+        .unwrap();
+
+        assert!(drift.is_empty());
+    }
+
+    #[test]
+    fn test_verify_detects_changed_and_missing_values() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("browser-policy.json");
+        std::fs::write(&path, r#"{"BrowserGuestModeEnabled": true}"#).unwrap();
+
+        let drift = verify_plan(&plan(vec![
+            json_write(Browser::Chrome, &path, "BrowserGuestModeEnabled", PolicyValue::Bool(false)),
+            json_write(Browser::Chrome, &path, "IncognitoModeAvailability", PolicyValue::Integer(1)),
+        ]))
+        .unwrap();
+
+        assert_eq!(drift.len(), 2);
+        assert_eq!(drift[0].name, "BrowserGuestModeEnabled");
+        assert_eq!(drift[0].actual.as_deref(), Some("true"));
+        assert_eq!(drift[1].name, "IncognitoModeAvailability");
+        assert!(drift[1].actual.is_none());
+    }
+
+    #[test]
+    fn test_verify_missing_file_is_drift() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("policies.json");
+
+        let drift = verify_plan(&plan(vec![json_write(
+            Browser::Firefox,
+            &path,
+            "DisablePrivateBrowsing",
+            PolicyValue::Bool(true),
+        )]))
+        .unwrap();
+
+        assert_eq!(drift.len(), 1);
+    }
+
+    #[test]
+    fn test_verify_firefox_reads_under_policies() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("policies.json");
+        std::fs::write(
+            &path,
+            json!({"policies": {"DisablePrivateBrowsing": true}}).to_string(),
+        )
+        .unwrap();
+
+        let drift = verify_plan(&plan(vec![json_write(
+            Browser::Firefox,
+            &path,
+            "DisablePrivateBrowsing",
+            PolicyValue::Bool(true),
+        )]))
+        .unwrap();
+
+        assert!(drift.is_empty());
+    }
+
+    #[test]
+    fn test_verify_corrupted_file_is_drift() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("browser-policy.json");
+        std::fs::write(&path, "not json").unwrap();
+
+        let drift = verify_plan(&plan(vec![json_write(
+            Browser::Chrome,
+            &path,
+            "BrowserGuestModeEnabled",
+            PolicyValue::Bool(false),
+        )]))
+        .unwrap();
+
+        assert_eq!(drift.len(), 1);
+    }
+}
@@ -0,0 +1,99 @@
+use anyhow::Result;
+
+use crate::browser::{Browser, Platform};
+use crate::config::{self, Config, ChromeConfig};
+use crate::state::BrowserState;
+
+use super::backend::PolicyBackend;
+use super::chromium_common::{self, ChromiumBrowserConfig, ChromiumConfig};
+use super::plan::{PolicyTarget, PolicyWrite};
+
+/// Vivaldi-specific browser configuration
+///
+/// Vivaldi reads Chrome's policies from its own places.
+pub(super) fn get_vivaldi_browser_config() -> ChromiumBrowserConfig {
+    ChromiumBrowserConfig {
+        browser: Browser::Vivaldi,
+        browser_name: "Vivaldi",
+        registry_key: r"SOFTWARE\Policies\Vivaldi",
+        bundle_id: "com.vivaldi.Vivaldi",
+        linux_policy_dir: "/etc/opt/vivaldi/policies/managed",
+    }
+}
+
+/// Apply Vivaldi policies (extensions and privacy controls)
+pub fn apply_vivaldi_policies(config: &ChromeConfig, dry_run: bool) -> Result<BrowserState> {
+    let chromium_config = ChromiumConfig::from_chrome(config);
+    let browser_config = get_vivaldi_browser_config();
+
+    chromium_common::apply_chromium_policies(&chromium_config, &browser_config, dry_run)
+}
+
+/// Remove all Vivaldi policies
+pub fn remove_vivaldi_policies() -> Result<()> {
+    let browser_config = get_vivaldi_browser_config();
+    chromium_common::remove_chromium_policies(&browser_config)
+}
+
+/// Vivaldi's part of a config
+fn vivaldi_config(config: &Config) -> Option<ChromeConfig> {
+    config::chromium_based_config(config, Browser::Vivaldi)
+}
+
+/// Writes Vivaldi policies
+pub struct VivaldiBackend;
+
+impl PolicyBackend for VivaldiBackend {
+    fn browser(&self) -> Browser {
+        Browser::Vivaldi
+    }
+
+    fn name(&self) -> &'static str {
+        "Vivaldi"
+    }
+
+    fn is_configured(&self, config: &Config) -> bool {
+        vivaldi_config(config).is_some()
+    }
+
+    fn evaluate(&self, config: &Config, platform: Platform) -> Result<Vec<PolicyWrite>> {
+        Ok(vivaldi_config(config)
+            .map(|c| {
+                chromium_common::evaluate_chromium_policies(
+                    &ChromiumConfig::from_chrome(&c),
+                    &get_vivaldi_browser_config(),
+                    platform,
+                )
+            })
+            .unwrap_or_default())
+    }
+
+    fn locations(&self, platform: Platform) -> Vec<PolicyTarget> {
+        chromium_common::chromium_policy_locations(&get_vivaldi_browser_config(), platform)
+    }
+
+    fn apply(&self, config: &Config, dry_run: bool) -> Result<BrowserState> {
+        match vivaldi_config(config) {
+            Some(c) => apply_vivaldi_policies(&c, dry_run),
+            None => Ok(BrowserState::new()),
+        }
+    }
+
+    fn remove(&self) -> Result<()> {
+        remove_vivaldi_policies()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vivaldi_browser_config() {
+        let config = get_vivaldi_browser_config();
+        assert_eq!(config.browser_name, "Vivaldi");
+        assert_eq!(config.registry_key, r"SOFTWARE\Policies\Vivaldi");
+        assert_eq!(config.bundle_id, "com.vivaldi.Vivaldi");
+        assert_eq!(config.private_mode_policy(), "IncognitoModeAvailability");
+    }
+}
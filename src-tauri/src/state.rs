@@ -2,14 +2,36 @@ use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
-use crate::config::Config;
+use crate::browser::{Browser, UnmanagedBrowser};
+use crate::config::{
+    chromium_based_config, safari_config, to_browser_configs, Config, HostContext, PrivacySetting,
+    SigninRestriction,
+};
+use crate::migrate::{self, Migration, Upgrade};
+use crate::platform::writer::writer;
 
 use uuid::Uuid;
 
 /// Current state version
-const STATE_VERSION: &str = "1.0";
+const STATE_VERSION: &str = "1.1";
+
+/// Upgrades from older state file versions (see `crate::migrate`)
+const STATE_MIGRATIONS: &[Migration] = &[Migration {
+    from: "1.0",
+    to: "1.1",
+    apply: record_machine_id,
+}];
+
+/// 1.0 files from before `machine_id` existed got a new id on every load
+fn record_machine_id(state: &mut serde_json::Map<String, serde_json::Value>) -> Result<()> {
+    state
+        .entry("machine_id")
+        .or_insert_with(|| serde_json::Value::String(generate_machine_id()));
+    Ok(())
+}
 
 /// State tracking for idempotent operations
 /// Works for both local mode and agent mode
@@ -32,6 +54,110 @@ pub struct State {
     /// HTTP ETag from last remote policy fetch (for caching)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub etag: Option<String>,
+
+    /// HTTP Last-Modified from the last fetch, for servers without ETags
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+
+    /// Hash of the last downloaded policy file, to skip parsing it again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+
+    /// How the last poll found out whether the policy changed (diagnostics)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change_detection: Option<ChangeDetection>,
+
+    /// Consecutive failures applying a fetched policy (agent mode)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failed_apply: Option<FailedApply>,
+
+    /// Consecutive failed policy checks (agent mode)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll_failures: Option<PollFailures>,
+
+    /// Policy change held until it is approved (agent mode,
+    /// `confirm_before_apply`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_approval: Option<PendingApproval>,
+
+    /// Unmanaged browsers found by the last check, which have been reported
+    /// (agent mode, `unmanaged_browsers`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unmanaged_browsers: Vec<UnmanagedBrowser>,
+
+    /// Executables blocked from starting by `unmanaged_browsers: block`
+    /// (Windows only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocked_executables: Vec<String>,
+}
+
+/// How a poll decided whether the remote policy changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeDetection {
+    /// 304 Not Modified for If-None-Match
+    Etag,
+    /// 304 Not Modified for If-Modified-Since
+    LastModified,
+    /// Downloaded, but byte-for-byte the same as before
+    ContentHash,
+    /// Downloaded and different, but the same policy once parsed
+    ConfigHash,
+    /// The policy changed
+    Changed,
+}
+
+impl ChangeDetection {
+    pub fn description(&self) -> &'static str {
+        match self {
+            ChangeDetection::Etag => "unchanged (ETag)",
+            ChangeDetection::LastModified => "unchanged (Last-Modified)",
+            ChangeDetection::ContentHash => "unchanged (content hash)",
+            ChangeDetection::ConfigHash => "reformatted, same policy",
+            ChangeDetection::Changed => "changed",
+        }
+    }
+}
+
+/// Repeated failures applying one policy version
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FailedApply {
+    /// Hash of the policy that failed to apply
+    pub config_hash: String,
+    /// Consecutive failed attempts
+    pub failures: u32,
+    /// Set once the policy is no longer retried
+    #[serde(default)]
+    pub quarantined: bool,
+    pub last_error: String,
+}
+
+/// Policy checks failing in a row (network down, bad token, invalid policy)
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PollFailures {
+    /// Consecutive failed checks
+    pub failures: u32,
+    /// When the first of them failed
+    pub since: DateTime<Utc>,
+    pub last_error: String,
+    /// Set once the failure threshold is reached and the alert was sent
+    #[serde(default)]
+    pub alerted: bool,
+}
+
+/// A remote policy change that removes or relaxes something, waiting for
+/// `family-policy approve`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PendingApproval {
+    /// Hash of the policy waiting to be applied
+    pub config_hash: String,
+    /// When the change was first seen
+    pub since: DateTime<Utc>,
+    /// What applying it would remove or change
+    pub removals: Vec<String>,
+    /// Set by `family-policy approve`; the next check applies it
+    #[serde(default)]
+    pub approved: bool,
 }
 
 fn generate_machine_id() -> String {
@@ -49,6 +175,14 @@ impl State {
             machine_id: Uuid::new_v4().to_string(),
             last_checked: None,
             etag: None,
+            last_modified: None,
+            content_hash: None,
+            change_detection: None,
+            failed_apply: None,
+            poll_failures: None,
+            pending_approval: None,
+            unmanaged_browsers: Vec::new(),
+            blocked_executables: Vec::new(),
         }
     }
 
@@ -66,11 +200,125 @@ impl State {
         self.applied_policies = applied_policies;
     }
 
+    /// Record a failed attempt to apply the policy with `config_hash`
+    ///
+    /// After `quarantine_after` consecutive failures of the same policy
+    /// (0 = never) it is quarantined. Returns true if this failure
+    /// quarantined it.
+    pub fn record_apply_failure(&mut self, config_hash: &str, error: &str, quarantine_after: u32) -> bool {
+        let failures = match &self.failed_apply {
+            Some(failed) if failed.config_hash == config_hash => failed.failures + 1,
+            _ => 1,
+        };
+        let was_quarantined = self.is_quarantined(config_hash);
+        let quarantined = was_quarantined || (quarantine_after > 0 && failures >= quarantine_after);
+
+        self.failed_apply = Some(FailedApply {
+            config_hash: config_hash.to_string(),
+            failures,
+            quarantined,
+            last_error: error.to_string(),
+        });
+
+        quarantined && !was_quarantined
+    }
+
+    /// Record a failed policy check
+    ///
+    /// Returns true if this failure reached `threshold`, i.e. the one time
+    /// the administrator should be alerted.
+    pub fn record_poll_failure(&mut self, error: &str, threshold: u32) -> bool {
+        let failures = self.poll_failures.take().unwrap_or_else(|| PollFailures {
+            failures: 0,
+            since: Utc::now(),
+            last_error: String::new(),
+            alerted: false,
+        });
+        let count = failures.failures + 1;
+        let alert = !failures.alerted && count >= threshold;
+
+        self.poll_failures = Some(PollFailures {
+            failures: count,
+            last_error: error.to_string(),
+            alerted: failures.alerted || alert,
+            ..failures
+        });
+        alert
+    }
+
+    /// Clear the failure count after a successful check
+    ///
+    /// Returns the failures that ended, if any.
+    pub fn record_poll_success(&mut self) -> Option<PollFailures> {
+        self.poll_failures.take()
+    }
+
+    /// Consecutive failed policy checks so far
+    pub fn consecutive_poll_failures(&self) -> u32 {
+        self.poll_failures.as_ref().map_or(0, |f| f.failures)
+    }
+
+    /// Whether the policy with `config_hash` has been quarantined
+    pub fn is_quarantined(&self, config_hash: &str) -> bool {
+        self.failed_apply
+            .as_ref()
+            .is_some_and(|f| f.quarantined && f.config_hash == config_hash)
+    }
+
+    /// Hold the policy `config_hash` until it is approved
+    ///
+    /// Returns true if it wasn't already held, i.e. the one time the
+    /// administrator should be asked.
+    pub fn hold_for_approval(&mut self, config_hash: &str, removals: Vec<String>) -> bool {
+        if self
+            .pending_approval
+            .as_ref()
+            .is_some_and(|p| p.config_hash == config_hash)
+        {
+            return false;
+        }
+
+        self.pending_approval = Some(PendingApproval {
+            config_hash: config_hash.to_string(),
+            since: Utc::now(),
+            removals,
+            approved: false,
+        });
+        true
+    }
+
+    /// Whether the policy `config_hash` was approved
+    pub fn is_approved(&self, config_hash: &str) -> bool {
+        self.pending_approval
+            .as_ref()
+            .is_some_and(|p| p.approved && p.config_hash == config_hash)
+    }
+
+    /// Approve the held policy change, returning it (None if nothing is held)
+    pub fn approve_pending(&mut self) -> Option<&PendingApproval> {
+        let pending = self.pending_approval.as_mut()?;
+        pending.approved = true;
+        Some(pending)
+    }
+
     /// Update ETag without applying policy (agent mode)
     pub fn update_etag(&mut self, etag: Option<String>) {
         self.etag = etag;
         self.last_checked = Some(Utc::now());
     }
+
+    /// Remember what identifies the downloaded policy file, so the next
+    /// poll can tell it hasn't changed (agent mode)
+    pub fn update_validators(
+        &mut self,
+        etag: Option<String>,
+        last_modified: Option<String>,
+        content_hash: String,
+    ) {
+        self.update_etag(etag);
+        self.last_modified = last_modified;
+        self.content_hash = Some(content_hash);
+    }
 }
 
 /// Applied policies for all browsers
@@ -82,6 +330,60 @@ pub struct AppliedPolicies {
     pub firefox: Option<BrowserState>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub edge: Option<BrowserState>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chromium: Option<BrowserState>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vivaldi: Option<BrowserState>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opera: Option<BrowserState>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub safari: Option<BrowserState>,
+
+    /// Values that read back differently right after they were written
+    /// (e.g. overridden by MDM or Group Policy, or not writable)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unverified: Vec<String>,
+
+    /// Values another management tool was setting when these were applied
+    /// (see `conflict_policy`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<String>,
+}
+
+impl AppliedPolicies {
+    /// State of a single browser, if anything is applied to it
+    pub fn get(&self, browser: Browser) -> Option<&BrowserState> {
+        match browser {
+            Browser::Chrome => self.chrome.as_ref(),
+            Browser::Firefox => self.firefox.as_ref(),
+            Browser::Edge => self.edge.as_ref(),
+            Browser::Chromium => self.chromium.as_ref(),
+            Browser::Vivaldi => self.vivaldi.as_ref(),
+            Browser::Opera => self.opera.as_ref(),
+            Browser::Safari => self.safari.as_ref(),
+        }
+    }
+
+    /// Record what was applied to a browser
+    pub fn set(&mut self, browser: Browser, state: BrowserState) {
+        match browser {
+            Browser::Chrome => self.chrome = Some(state),
+            Browser::Firefox => self.firefox = Some(state),
+            Browser::Edge => self.edge = Some(state),
+            Browser::Chromium => self.chromium = Some(state),
+            Browser::Vivaldi => self.vivaldi = Some(state),
+            Browser::Opera => self.opera = Some(state),
+            Browser::Safari => self.safari = Some(state),
+        }
+    }
+
+    /// The `installed` browsers nothing is applied to
+    ///
+    /// No policy entry lists them (or none is in force), so they are an
+    /// enforcement gap.
+    pub fn uncovered(&self, installed: &[Browser]) -> Vec<Browser> {
+        installed.iter().copied().filter(|b| self.get(*b).is_none()).collect()
+    }
 }
 
 /// State for a single browser
@@ -89,15 +391,27 @@ pub struct AppliedPolicies {
 pub struct BrowserState {
     pub extensions: Vec<String>, // Extension IDs
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub disable_incognito: Option<bool>, // Chrome only
+    pub disable_incognito: Option<bool>, // Chromium-based browsers except Edge
     #[serde(skip_serializing_if = "Option::is_none")]
     pub disable_inprivate: Option<bool>, // Edge only
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub disable_private_browsing: Option<bool>, // Firefox only
+    pub disable_private_browsing: Option<bool>, // Firefox/Safari only
     #[serde(skip_serializing_if = "Option::is_none")]
     pub disable_guest_mode: Option<bool>, // Chrome/Edge only
     #[serde(skip_serializing_if = "Option::is_none")]
     pub allow_deleting_browser_history: Option<bool>, // Chrome/Edge only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disable_sync: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restrict_signin: Option<SigninRestriction>, // Chrome/Edge only
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocked_urls: Vec<String>, // Safari only
+
+    /// Name of the policy entry each item came from, keyed by extension ID,
+    /// blocked URL or the setting's field name above (see
+    /// `config::policy_sources`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub sources: BTreeMap<String, String>,
 }
 
 impl BrowserState {
@@ -110,6 +424,29 @@ impl BrowserState {
             disable_private_browsing: None,
             disable_guest_mode: None,
             allow_deleting_browser_history: None,
+            disable_sync: None,
+            restrict_signin: None,
+            blocked_urls: Vec::new(),
+            sources: BTreeMap::new(),
+        }
+    }
+
+    /// Applied value of a privacy setting for `browser`
+    ///
+    /// For the sign-in restriction this is whether sign-in is restricted at
+    /// all (disabled or limited to a pattern).
+    pub fn privacy_setting(&self, setting: PrivacySetting, browser: Browser) -> Option<bool> {
+        match (setting, browser) {
+            (PrivacySetting::PrivateMode, Browser::Firefox | Browser::Safari) => self.disable_private_browsing,
+            (PrivacySetting::PrivateMode, Browser::Edge) => self.disable_inprivate,
+            (PrivacySetting::PrivateMode, _) => self.disable_incognito,
+            (PrivacySetting::GuestMode, _) => self.disable_guest_mode,
+            (PrivacySetting::HistoryDeletion, _) => self.allow_deleting_browser_history,
+            (PrivacySetting::Sync, _) => self.disable_sync,
+            (PrivacySetting::Signin, _) => self
+                .restrict_signin
+                .as_ref()
+                .map(|r| *r != SigninRestriction::Restricted(false)),
         }
     }
 
@@ -121,6 +458,9 @@ impl BrowserState {
             && self.disable_private_browsing.is_none()
             && self.disable_guest_mode.is_none()
             && self.allow_deleting_browser_history.is_none()
+            && self.disable_sync.is_none()
+            && self.restrict_signin.is_none()
+            && self.blocked_urls.is_empty()
     }
 }
 
@@ -130,8 +470,36 @@ impl Default for BrowserState {
     }
 }
 
+#[cfg(test)]
+thread_local! {
+    static TEST_STATE_DIR: std::cell::RefCell<Option<PathBuf>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Keep the state file, policy cache and inbox in `dir` on the current thread
+/// until the returned guard is dropped
+#[cfg(test)]
+pub(crate) fn use_test_state_dir(dir: &std::path::Path) -> TestStateDir {
+    TEST_STATE_DIR.with(|d| *d.borrow_mut() = Some(dir.to_path_buf()));
+    TestStateDir
+}
+
+#[cfg(test)]
+pub(crate) struct TestStateDir;
+
+#[cfg(test)]
+impl Drop for TestStateDir {
+    fn drop(&mut self) {
+        TEST_STATE_DIR.with(|d| *d.borrow_mut() = None);
+    }
+}
+
 /// Get the platform-specific state file path
 pub fn get_state_path() -> Result<PathBuf> {
+    #[cfg(test)]
+    if let Some(dir) = TEST_STATE_DIR.with(|d| d.borrow().clone()) {
+        return Ok(dir.join("state.json"));
+    }
+
     #[cfg(target_os = "linux")]
     {
         // Try system location first, fall back to user location
@@ -176,28 +544,73 @@ pub fn get_state_path() -> Result<PathBuf> {
 }
 
 /// Load state from the state file
+///
+/// A file from an older version is migrated and rewritten in place, keeping
+/// a backup of the original next to it. One from a version we can't migrate
+/// (e.g. written by a newer release) is backed up and treated as no state.
 pub fn load_state() -> Result<Option<State>> {
     let state_path = get_state_path()?;
+    let writer = writer();
 
-    if !state_path.exists() {
+    let Some(content) = writer
+        .read_file(&state_path)
+        .with_context(|| format!("Failed to read state file: {}", state_path.display()))?
+    else {
         return Ok(None);
-    }
+    };
 
-    let content = std::fs::read_to_string(&state_path)
-        .with_context(|| format!("Failed to read state file: {}", state_path.display()))?;
-
-    let state: State = serde_json::from_str(&content)
+    let mut document: serde_json::Value = serde_json::from_slice(&content)
         .with_context(|| format!("Failed to parse state file: {}", state_path.display()))?;
 
-    // Validate state version
-    if state.version != STATE_VERSION {
-        eprintln!(
-            "Warning: State file version mismatch (expected {}, got {}). Treating as new state.",
-            STATE_VERSION, state.version
-        );
-        return Ok(None);
+    let backup = |version: &str| -> Result<PathBuf> {
+        let backup_path = migrate::backup_path(&state_path, version);
+        writer
+            .write_file(&backup_path, &content, 0o644)
+            .with_context(|| format!("Failed to back up state file to {}", backup_path.display()))?;
+        Ok(backup_path)
+    };
+
+    match migrate::upgrade(&mut document, STATE_VERSION, STATE_MIGRATIONS)
+        .with_context(|| format!("Failed to upgrade state file: {}", state_path.display()))?
+    {
+        Upgrade::Current => {}
+        Upgrade::Migrated { from } => {
+            // Without write access (e.g. `status` run as a regular user) the
+            // migrated state is still used, just not saved
+            let saved = backup(&from).and_then(|backup_path| {
+                let migrated = serde_json::to_vec_pretty(&document).context("Failed to serialize state")?;
+                writer
+                    .write_file(&state_path, &migrated, 0o644)
+                    .with_context(|| format!("Failed to write state file: {}", state_path.display()))?;
+                Ok(backup_path)
+            });
+            match saved {
+                Ok(backup_path) => eprintln!(
+                    "Upgraded state file from version {} to {} (original kept at {})",
+                    from,
+                    STATE_VERSION,
+                    backup_path.display()
+                ),
+                Err(e) => eprintln!("Warning: Couldn't save upgraded state file: {:#}", e),
+            }
+        }
+        Upgrade::Unsupported { version } => {
+            let kept = match backup(&version) {
+                Ok(backup_path) => format!("the file is kept at {}", backup_path.display()),
+                Err(e) => format!("{:#}", e),
+            };
+            eprintln!(
+                "Warning: State file version {} can't be read by this version (expected {}); \
+                 treating as new state ({})",
+                version, STATE_VERSION, kept
+            );
+            return Ok(None);
+        }
     }
 
+    let state: State = serde_json::from_value(document)
+        .with_context(|| format!("Failed to parse state file: {}", state_path.display()))?;
+
     Ok(Some(state))
 }
 
@@ -205,56 +618,90 @@ pub fn load_state() -> Result<Option<State>> {
 pub fn save_state(state: &State) -> Result<()> {
     let state_path = get_state_path()?;
 
-    // Ensure parent directory exists
-    if let Some(parent) = state_path.parent() {
-        std::fs::create_dir_all(parent).with_context(|| {
-            format!(
-                "Failed to create state directory: {}",
-                parent.display()
-            )
-        })?;
-    }
-
     // Serialize state to JSON
     let content = serde_json::to_string_pretty(state)
         .context("Failed to serialize state")?;
 
-    // Write atomically
-    crate::platform::common::atomic_write(&state_path, content.as_bytes())
+    // Write atomically, creating the state directory. World-readable
+    // (0o644 on Unix) so the User UI, running as a regular user, can read it
+    writer()
+        .write_file(&state_path, content.as_bytes(), 0o644)
         .with_context(|| format!("Failed to write state file: {}", state_path.display()))?;
 
-    // Set world-readable permissions (0o644 on Unix)
-    // This allows User UI (running as regular user) to read the state
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let mut perms = std::fs::metadata(&state_path)
-            .context("Failed to get state file metadata")?
-            .permissions();
-        perms.set_mode(0o644); // rw-r--r-- (world-readable)
-        std::fs::set_permissions(&state_path, perms)
-            .context("Failed to set state file permissions")?;
-    }
-
     Ok(())
 }
 
-/// Delete the state file
+/// Delete the state file (and the cached policy that goes with it)
 pub fn delete_state() -> Result<()> {
     let state_path = get_state_path()?;
 
-    if state_path.exists() {
-        std::fs::remove_file(&state_path)
-            .with_context(|| format!("Failed to delete state file: {}", state_path.display()))?;
-    }
+    let writer = writer();
+    writer
+        .remove_file(&state_path)
+        .with_context(|| format!("Failed to delete state file: {}", state_path.display()))?;
+
+    let cache_path = get_policy_cache_path()?;
+    writer
+        .remove_file(&cache_path)
+        .with_context(|| format!("Failed to delete policy cache: {}", cache_path.display()))?;
+
+    Ok(())
+}
+
+/// Get the path of the cached copy of the last applied policy YAML
+///
+/// Lives next to the state file so the agent can re-apply without network.
+pub fn get_policy_cache_path() -> Result<PathBuf> {
+    Ok(get_state_path()?.with_file_name("last-policy.yaml"))
+}
+
+/// Cache the policy YAML that was just applied
+pub fn save_policy_cache(content: &str) -> Result<()> {
+    let cache_path = get_policy_cache_path()?;
+
+    // Only the agent needs this; the access token is never part of it, but
+    // private policy repos shouldn't be readable by everyone either
+    writer()
+        .write_file(&cache_path, content.as_bytes(), 0o600)
+        .with_context(|| format!("Failed to write policy cache: {}", cache_path.display()))?;
 
     Ok(())
 }
 
+/// Load the cached policy YAML, if any
+pub fn load_policy_cache() -> Result<Option<String>> {
+    let cache_path = get_policy_cache_path()?;
+
+    let content = writer()
+        .read_file(&cache_path)
+        .with_context(|| format!("Failed to read policy cache: {}", cache_path.display()))?;
+
+    content
+        .map(String::from_utf8)
+        .transpose()
+        .with_context(|| format!("Policy cache isn't valid UTF-8: {}", cache_path.display()))
+}
+
 /// Compute hash of configuration for change detection
+///
+/// Every field of the config is covered, including when and where entries
+/// apply, but not how the YAML is laid out: reordering policies or the
+/// extensions in them only changes the hash if it changes which entry wins
+/// a setting.
 pub fn compute_config_hash(config: &Config) -> Result<String> {
-    // Serialize config to stable JSON representation
-    let json = serde_json::to_string(config)
+    // Going through serde_json::Value sorts map keys (extension settings are
+    // a HashMap), so the JSON is stable across runs. The browser configs
+    // capture what policy order decides.
+    let browsers = (
+        to_browser_configs(config),
+        [Browser::Chromium, Browser::Vivaldi, Browser::Opera].map(|b| chromium_based_config(config, b)),
+        safari_config(config),
+    );
+    let normalized = serde_json::json!({
+        "config": normalized_config(config)?,
+        "browsers": serde_json::to_value(browsers).context("Failed to serialize config for hashing")?,
+    });
+    let json = serde_json::to_string(&normalized)
         .context("Failed to serialize config for hashing")?;
 
     // Compute SHA-256 hash
@@ -266,6 +713,32 @@ pub fn compute_config_hash(config: &Config) -> Result<String> {
     Ok(format!("sha256:{}", hex::encode(&result)))
 }
 
+/// Hash recorded in the state file for `config` applied on `host`
+///
+/// The agent, `apply` and the FFI all record and compare this, so each can
+/// tell whether a policy the others applied is current. Entries for other
+/// machines are left out; expired and out-of-schedule entries are kept, so
+/// the hash doesn't change with the time of day (callers check
+/// `schedule_changes_since` for that).
+pub fn applied_config_hash(config: &Config, host: &HostContext) -> Result<String> {
+    compute_config_hash(&config.for_host(host))
+}
+
+/// `config` as JSON with its policies, and the extensions in each, sorted
+fn normalized_config(config: &Config) -> Result<serde_json::Value> {
+    let mut value = serde_json::to_value(config).context("Failed to serialize config for hashing")?;
+    if let Some(policies) = value.get_mut("policies").and_then(serde_json::Value::as_array_mut) {
+        for policy in policies.iter_mut() {
+            let extensions = policy.get_mut("extensions").and_then(serde_json::Value::as_array_mut);
+            if let Some(extensions) = extensions {
+                extensions.sort_by_cached_key(|extension| extension.to_string());
+            }
+        }
+        policies.sort_by_cached_key(|policy| policy.to_string());
+    }
+    Ok(value)
+}
+
 /// Create a new state from config
 pub fn create_state(config: &Config, applied_policies: AppliedPolicies) -> Result<State> {
     Ok(State {
@@ -276,9 +749,24 @@ pub fn create_state(config: &Config, applied_policies: AppliedPolicies) -> Resul
         machine_id: Uuid::new_v4().to_string(),
         last_checked: None,
         etag: None,
+        last_modified: None,
+        content_hash: None,
+        change_detection: None,
+        failed_apply: None,
+        poll_failures: None,
+        pending_approval: None,
+        unmanaged_browsers: Vec::new(),
+        blocked_executables: Vec::new(),
     })
 }
 
+/// Hash of a downloaded policy file, byte for byte
+pub fn compute_content_hash(content: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(content.as_bytes());
+    format!("sha256:{}", hex::encode(&hasher.finalize()))
+}
+
 // Helper module for hex encoding
 mod hex {
     pub fn encode(bytes: &[u8]) -> String {
@@ -300,18 +788,29 @@ mod tests {
 
     fn make_test_config() -> Config {
         Config {
+            include: Vec::new(),
+            unmanaged_browsers: None,
+            conflict_policy: None,
             policies: vec![PolicyEntry {
                 name: "Test Policy".to_string(),
                 browsers: vec![Browser::Chrome],
                 disable_private_mode: Some(true),
                 disable_guest_mode: None,
                 allow_deleting_browser_history: None,
+                disable_sync: None,
+                restrict_signin: None,
+                blocked_urls: Vec::new(),
                 extensions: vec![ExtensionEntry {
                     name: "Test".to_string(),
                     id: BrowserIdMap::Single("test123".to_string()),
                     force_installed: Some(true),
                     settings: HashMap::new(),
                 }],
+                expires_at: None,
+                enabled: true,
+                active_from: None,
+                active_until: None,
+                host_match: None,
             }],
         }
     }
@@ -324,6 +823,10 @@ mod tests {
             disable_private_browsing: None,
             disable_guest_mode: Some(false),
             allow_deleting_browser_history: None,
+            disable_sync: None,
+            restrict_signin: None,
+            blocked_urls: Vec::new(),
+            sources: Default::default(),
         }
     }
 
@@ -332,6 +835,12 @@ mod tests {
             chrome: Some(make_test_browser_state()),
             firefox: None,
             edge: None,
+            chromium: None,
+            vivaldi: None,
+            opera: None,
+            safari: None,
+            unverified: Vec::new(),
+            conflicts: Vec::new(),
         }
     }
 
@@ -383,6 +892,16 @@ mod tests {
         assert!(policies.edge.is_none());
     }
 
+    #[test]
+    fn applied_policies_uncovered_lists_installed_browsers_without_policies() {
+        let mut policies = AppliedPolicies::default();
+        policies.set(Browser::Chrome, BrowserState::new());
+
+        let installed = [Browser::Chrome, Browser::Firefox, Browser::Safari];
+        assert_eq!(policies.uncovered(&installed), [Browser::Firefox, Browser::Safari]);
+        assert!(policies.uncovered(&[Browser::Chrome]).is_empty());
+    }
+
     // Config Hashing Tests
 
     #[test]
@@ -420,16 +939,75 @@ mod tests {
         assert_ne!(hash1, hash2);
     }
 
+    #[test]
+    fn compute_config_hash_ignores_policy_and_extension_order() {
+        let mut config1 = make_test_config();
+        config1.policies[0].extensions.push(ExtensionEntry {
+            name: "Second".to_string(),
+            id: BrowserIdMap::Single("second456".to_string()),
+            force_installed: Some(true),
+            settings: HashMap::from([
+                ("a".to_string(), serde_json::json!(1)),
+                ("b".to_string(), serde_json::json!(2)),
+                ("c".to_string(), serde_json::json!(3)),
+            ]),
+        });
+        config1.policies.push(PolicyEntry {
+            name: "Privacy".to_string(),
+            browsers: vec![Browser::Edge],
+            disable_private_mode: Some(true),
+            disable_guest_mode: None,
+            allow_deleting_browser_history: None,
+            disable_sync: None,
+            restrict_signin: None,
+            blocked_urls: Vec::new(),
+            extensions: vec![],
+            expires_at: None,
+            enabled: true,
+            active_from: None,
+            active_until: None,
+            host_match: None,
+        });
+
+        let mut config2 = config1.clone();
+        config2.policies.reverse();
+        config2.policies[1].extensions.reverse();
+
+        assert_eq!(
+            compute_config_hash(&config1).unwrap(),
+            compute_config_hash(&config2).unwrap()
+        );
+    }
+
+    #[test]
+    fn compute_content_hash_is_byte_for_byte() {
+        let hash = compute_content_hash("policies: []\n");
+        assert!(hash.starts_with("sha256:"));
+        assert_eq!(hash, compute_content_hash("policies: []\n"));
+        assert_ne!(hash, compute_content_hash("policies: [] \n"));
+    }
+
     #[test]
     fn compute_config_hash_handles_empty_config() {
         let config = Config {
+            include: Vec::new(),
+            unmanaged_browsers: None,
+            conflict_policy: None,
             policies: vec![PolicyEntry {
                 name: "Empty Policy".to_string(),
                 browsers: vec![Browser::Chrome],
                 disable_private_mode: None,
                 disable_guest_mode: None,
                 allow_deleting_browser_history: None,
+                disable_sync: None,
+                restrict_signin: None,
+                blocked_urls: Vec::new(),
                 extensions: vec![],
+                expires_at: None,
+                enabled: true,
+                active_from: None,
+                active_until: None,
+                host_match: None,
             }],
         };
 
@@ -541,6 +1119,10 @@ mod tests {
             disable_private_browsing: None,
             disable_guest_mode: None,
             allow_deleting_browser_history: None,
+            disable_sync: None,
+            restrict_signin: None,
+            blocked_urls: Vec::new(),
+            sources: Default::default(),
         };
 
         let json = serde_json::to_string(&state).unwrap();
@@ -587,13 +1169,24 @@ mod tests {
     #[test]
     fn state_with_all_browsers_roundtrips_correctly() {
         let config = Config {
+            include: Vec::new(),
+            unmanaged_browsers: None,
+            conflict_policy: None,
             policies: vec![PolicyEntry {
                 name: "Multi-browser Policy".to_string(),
                 browsers: vec![Browser::Chrome, Browser::Firefox, Browser::Edge],
                 disable_private_mode: Some(true),
                 disable_guest_mode: Some(false),
                 allow_deleting_browser_history: None,
+                disable_sync: None,
+                restrict_signin: None,
+                blocked_urls: Vec::new(),
                 extensions: vec![],
+                expires_at: None,
+                enabled: true,
+                active_from: None,
+                active_until: None,
+                host_match: None,
             }],
         };
 
@@ -605,6 +1198,10 @@ mod tests {
                 disable_private_browsing: None,
                 disable_guest_mode: None,
                 allow_deleting_browser_history: None,
+                disable_sync: None,
+                restrict_signin: None,
+                blocked_urls: Vec::new(),
+                sources: Default::default(),
             }),
             firefox: Some(BrowserState {
                 extensions: vec![],
@@ -613,6 +1210,10 @@ mod tests {
                 disable_private_browsing: Some(true),
                 disable_guest_mode: None,
                 allow_deleting_browser_history: None,
+                disable_sync: None,
+                restrict_signin: None,
+                blocked_urls: Vec::new(),
+                sources: Default::default(),
             }),
             edge: Some(BrowserState {
                 extensions: vec![],
@@ -621,7 +1222,17 @@ mod tests {
                 disable_private_browsing: None,
                 disable_guest_mode: Some(false),
                 allow_deleting_browser_history: None,
+                disable_sync: None,
+                restrict_signin: None,
+                blocked_urls: Vec::new(),
+                sources: Default::default(),
             }),
+            chromium: None,
+            vivaldi: None,
+            opera: None,
+            safari: None,
+            unverified: Vec::new(),
+            conflicts: Vec::new(),
         };
 
         let state = create_state(&config, policies).unwrap();
@@ -631,4 +1242,82 @@ mod tests {
         assert_eq!(loaded.version, state.version);
         assert_eq!(loaded.config_hash, state.config_hash);
     }
+
+    #[test]
+    fn test_state_persistence_through_writer() {
+        use crate::platform::writer::{use_test_writer, RecordingWriter, WriterOp};
+        use std::rc::Rc;
+
+        let writer = Rc::new(RecordingWriter::default());
+        let _writer = use_test_writer(writer.clone());
+        let _state = use_test_state_dir(std::path::Path::new("/nonexistent/state"));
+
+        assert!(load_state().unwrap().is_none());
+
+        let state = State::new_agent();
+        save_state(&state).unwrap();
+        save_policy_cache("policies: []\n").unwrap();
+
+        assert_eq!(load_state().unwrap().unwrap().machine_id, state.machine_id);
+        assert_eq!(load_policy_cache().unwrap().as_deref(), Some("policies: []\n"));
+
+        delete_state().unwrap();
+        assert!(load_state().unwrap().is_none());
+        assert!(load_policy_cache().unwrap().is_none());
+
+        let state_path = get_state_path().unwrap();
+        let cache_path = get_policy_cache_path().unwrap();
+        assert_eq!(
+            writer.operations(),
+            [
+                WriterOp::Write { path: state_path.clone(), mode: 0o644 },
+                WriterOp::Write { path: cache_path.clone(), mode: 0o600 },
+                WriterOp::Remove { path: state_path },
+                WriterOp::Remove { path: cache_path },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_state_migrates_old_versions_with_backup() {
+        use crate::platform::writer::{use_test_writer, PlatformWriter, RecordingWriter};
+        use std::rc::Rc;
+
+        let writer = Rc::new(RecordingWriter::default());
+        let _writer = use_test_writer(writer.clone());
+        let _state = use_test_state_dir(std::path::Path::new("/nonexistent/state"));
+        let state_path = get_state_path().unwrap();
+        let original = r#"{
+            "version": "1.0",
+            "config_hash": "sha256:abc",
+            "last_updated": "2025-01-01T00:00:00Z",
+            "applied_policies": {"chrome": {"extensions": []}}
+        }"#;
+        writer.write_file(&state_path, original.as_bytes(), 0o644).unwrap();
+
+        let state = load_state().unwrap().unwrap();
+        assert_eq!(state.version, STATE_VERSION);
+        assert_eq!(state.config_hash, "sha256:abc");
+        assert!(state.applied_policies.chrome.is_some());
+
+        // The id is saved, not generated again on the next load
+        assert_eq!(load_state().unwrap().unwrap().machine_id, state.machine_id);
+        assert_eq!(writer.file(state_path.with_file_name("state.json.v1.0.bak")).as_deref(), Some(original));
+    }
+
+    #[test]
+    fn test_load_state_keeps_unsupported_versions() {
+        use crate::platform::writer::{use_test_writer, PlatformWriter, RecordingWriter};
+        use std::rc::Rc;
+
+        let writer = Rc::new(RecordingWriter::default());
+        let _writer = use_test_writer(writer.clone());
+        let _state = use_test_state_dir(std::path::Path::new("/nonexistent/state"));
+        let state_path = get_state_path().unwrap();
+        let newer = r#"{"version": "9.0", "config_hash": "sha256:abc"}"#;
+        writer.write_file(&state_path, newer.as_bytes(), 0o644).unwrap();
+
+        assert!(load_state().unwrap().is_none());
+        assert_eq!(writer.file(state_path.with_file_name("state.json.v9.0.bak")).as_deref(), Some(newer));
+    }
 }
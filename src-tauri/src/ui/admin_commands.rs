@@ -1,36 +1,112 @@
 use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Runtime};
 
+use crate::agent;
+use crate::agent::helper::{HelperRequest, HelperResponse};
+use crate::browser::Browser;
 use crate::core;
 use crate::config;
+use crate::inbox;
+use crate::state;
+
+use super::extension_search;
+
+/// Event emitted while policies are being applied or removed
+pub const APPLY_PROGRESS_EVENT: &str = "apply-progress";
+
+/// Payload of `APPLY_PROGRESS_EVENT`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApplyProgress {
+    /// One of "loading", "applying", "removing", "done" or "failed"
+    pub stage: String,
+    pub message: String,
+}
+
+fn emit_progress<R: Runtime>(app: &AppHandle<R>, stage: &str, message: impl Into<String>) {
+    let progress = ApplyProgress {
+        stage: stage.to_string(),
+        message: message.into(),
+    };
+    if let Err(e) = app.emit(APPLY_PROGRESS_EVENT, progress) {
+        tracing::warn!("Failed to emit apply progress: {}", e);
+    }
+}
 
 /// Apply policies from configuration file
-/// Requires admin privileges (checked by caller)
+/// Requires admin privileges unless `dry_run` is set
 #[tauri::command]
-pub async fn apply_policies(config_path: String) -> Result<core::apply::ApplyResult, String> {
-    // Verify admin privileges
-    if !core::privileges::is_admin() {
-        return Err("This operation requires administrator privileges".to_string());
-    }
+pub async fn apply_policies<R: Runtime>(
+    app: AppHandle<R>,
+    config_path: String,
+    dry_run: bool,
+) -> Result<core::apply::ApplyResult, String> {
+    core::privileges::check_privileges(core::privileges::PrivilegeCheck::admin_or_dry_run(), dry_run)
+        .map_err(|e| format!("{:#}", e))?;
 
-    let path = std::path::PathBuf::from(config_path);
+    emit_progress(&app, "loading", format!("Loading {}", config_path));
+    let path = std::path::PathBuf::from(&config_path);
     let config = config::load_config(&path)
-        .map_err(|e| format!("Failed to load config: {}", e))?;
+        .map_err(|e| format!("Failed to load config: {:#}", e))
+        .inspect_err(|e| emit_progress(&app, "failed", e.clone()))?
+        .for_host(&agent::current_host());
 
-    core::apply::apply_policies_from_config(&config, false)
-        .map_err(|e| format!("Failed to apply policies: {}", e))
+    emit_progress(
+        &app,
+        "applying",
+        if dry_run { "Computing changes (dry run)" } else { "Writing browser policies" },
+    );
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        core::apply::apply_policies_from_config(&config, dry_run)
+    })
+    .await
+    .map_err(|e| format!("Apply task failed: {}", e))
+    .and_then(|r| r.map_err(|e| format!("Failed to apply policies: {:#}", e)));
+
+    match &result {
+        Ok(r) if !r.changed => emit_progress(&app, "done", "Policies already up to date"),
+        Ok(_) if dry_run => emit_progress(&app, "done", "Dry run complete, nothing was changed"),
+        Ok(_) => emit_progress(&app, "done", "Policies applied"),
+        Err(e) => emit_progress(&app, "failed", e.clone()),
+    }
+
+    result
 }
 
 /// Remove all applied policies
-/// Requires admin privileges (checked by caller)
+/// Requires admin privileges, or an account the agent's helper lets in
 #[tauri::command]
-pub async fn remove_policies() -> Result<core::apply::RemovalResult, String> {
-    // Verify admin privileges
-    if !core::privileges::is_admin() {
-        return Err("This operation requires administrator privileges".to_string());
+pub async fn remove_policies<R: Runtime>(app: AppHandle<R>) -> Result<core::apply::RemovalResult, String> {
+    let admin = core::privileges::is_admin();
+
+    emit_progress(&app, "removing", "Removing browser policies");
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        if admin {
+            return core::apply::remove_all_policies(false)
+                .map_err(|e| format!("Failed to remove policies: {:#}", e));
+        }
+        match agent::helper::call(&HelperRequest::Remove) {
+            Ok(Some(HelperResponse::Removed { result })) => Ok(result),
+            Ok(Some(HelperResponse::AwaitingSecondApproval { requested_by })) => Err(format!(
+                "Removal requested by {}; another parent must also remove all policies from their \
+                 account within 10 minutes",
+                requested_by
+            )),
+            Ok(Some(HelperResponse::Error { message })) => Err(format!("Failed to remove policies: {}", message)),
+            Ok(Some(other)) => Err(format!("Unexpected response from the agent: {:?}", other)),
+            Ok(None) => Err("This operation requires administrator privileges".to_string()),
+            Err(e) => Err(format!("{:#}", e)),
+        }
+    })
+    .await
+    .map_err(|e| format!("Removal task failed: {}", e))
+    .and_then(|r| r);
+
+    match &result {
+        Ok(_) => emit_progress(&app, "done", "Policies removed"),
+        Err(e) => emit_progress(&app, "failed", e.clone()),
     }
 
-    core::apply::remove_all_policies(false)
-        .map_err(|e| format!("Failed to remove policies: {}", e))
+    result
 }
 
 /// Preview policy removal (what would be removed)
@@ -47,28 +123,105 @@ pub async fn validate_config(config_path: String) -> Result<ValidationResult, St
     let path = std::path::PathBuf::from(config_path);
 
     match config::load_config(&path) {
-        Ok(config) => {
-            match config::validate_config(&config) {
-                Ok(_) => Ok(ValidationResult {
-                    valid: true,
-                    errors: vec![],
-                    warnings: vec![],
-                }),
-                Err(e) => Ok(ValidationResult {
-                    valid: false,
-                    errors: vec![format!("{}", e)],
-                    warnings: vec![],
-                }),
-            }
-        }
+        Ok(config) => Ok(validation_result(&config)),
+        Err(e) => Ok(ValidationResult {
+            valid: false,
+            errors: vec![format!("Failed to load config: {:#}", e)],
+            warnings: vec![],
+        }),
+    }
+}
+
+/// Read a policy YAML file for editing
+#[tauri::command]
+pub async fn load_policy_yaml(config_path: String) -> Result<String, String> {
+    std::fs::read_to_string(&config_path)
+        .map_err(|e| format!("Failed to read {}: {}", config_path, e))
+}
+
+/// Validate policy YAML from the editor (no file involved)
+#[tauri::command]
+pub async fn validate_policy_yaml(config_yaml: String) -> Result<ValidationResult, String> {
+    match serde_yaml::from_str::<config::Config>(&config_yaml) {
+        Ok(config) => Ok(validation_result(&config)),
         Err(e) => Ok(ValidationResult {
             valid: false,
-            errors: vec![format!("Failed to load config: {}", e)],
+            errors: vec![format!("Invalid YAML: {}", e)],
             warnings: vec![],
         }),
     }
 }
 
+/// Preview what applying the edited policy YAML would change
+#[tauri::command]
+pub async fn preview_policy_yaml(config_yaml: String) -> Result<core::diff::PolicyDiff, String> {
+    let config = config::Config::from_yaml_str(&config_yaml)
+        .map_err(|e| format!("Invalid config: {:#}", e))?
+        .for_host(&agent::current_host());
+
+    let current_state = state::load_state()
+        .map_err(|e| format!("Failed to load state: {}", e))?;
+
+    core::diff::generate_diff_with_conflicts(&config, current_state.as_ref())
+        .map_err(|e| format!("Failed to check for conflicting policies: {:#}", e))
+}
+
+/// Look up extensions to add to a policy
+///
+/// Firefox searches addons.mozilla.org. Chrome and Edge have no public
+/// search API, so the query must be a store URL or extension ID. Safari
+/// extensions can't be managed at all.
+#[tauri::command]
+pub async fn search_extensions(
+    browser: Browser,
+    query: String,
+) -> Result<Vec<extension_search::ExtensionSearchResult>, String> {
+    match browser {
+        Browser::Firefox => extension_search::search_amo(&query)
+            .await
+            .map_err(|e| format!("Extension search failed: {:#}", e)),
+        Browser::Chrome | Browser::Edge | Browser::Chromium | Browser::Vivaldi | Browser::Opera => {
+            let id = extension_search::chromium_extension_id(&query).ok_or_else(|| {
+                format!(
+                    "{:?} extensions can't be searched by name. Paste the store URL or the 32-character extension ID.",
+                    browser
+                )
+            })?;
+            Ok(vec![extension_search::ExtensionSearchResult {
+                id,
+                name: String::new(),
+                summary: None,
+                url: None,
+            }])
+        }
+        Browser::Safari => Err("Safari extensions can't be managed by policy".to_string()),
+    }
+}
+
+fn validation_result(config: &config::Config) -> ValidationResult {
+    match config::validate_config(config) {
+        Ok(_) => {
+            let mut warnings = config::extension_setting_conflicts(config);
+            warnings.extend(
+                config
+                    .expired_policies(chrono::Utc::now())
+                    .iter()
+                    .map(|p| format!("Policy '{}' has expired and will be ignored", p.name)),
+            );
+            ValidationResult {
+                valid: true,
+                errors: vec![],
+                warnings,
+            }
+        }
+        Err(e) => ValidationResult {
+            valid: false,
+            errors: vec![format!("{:#}", e)],
+            warnings: vec![],
+        },
+    }
+}
+
 /// Save configuration to file
 /// Requires admin privileges for system-wide configs
 #[tauri::command]
@@ -85,9 +238,9 @@ pub async fn save_config(config_path: String, config_yaml: String) -> Result<(),
         return Err("Writing to system directories requires administrator privileges".to_string());
     }
 
-    // Validate the YAML first
-    let _config: config::Config = serde_yaml::from_str(&config_yaml)
-        .map_err(|e| format!("Invalid YAML: {}", e))?;
+    // Validate first so the editor can't save a config the agent would reject
+    config::Config::from_yaml_str(&config_yaml)
+        .map_err(|e| format!("Invalid config: {:#}", e))?;
 
     // Write to file
     std::fs::write(&path, config_yaml)
@@ -131,6 +284,39 @@ policies:
 "#.to_string())
 }
 
+/// Get the agent notification inbox
+#[tauri::command]
+pub async fn get_inbox() -> Result<inbox::Inbox, String> {
+    inbox::load_inbox()
+        .map_err(|e| format!("Failed to load inbox: {}", e))
+}
+
+/// Mark notifications as read (all of them if no id is given)
+/// Requires admin privileges
+#[tauri::command]
+pub async fn mark_inbox_read(id: Option<u64>) -> Result<inbox::Inbox, String> {
+    if !core::privileges::is_admin() {
+        return Err("This operation requires administrator privileges".to_string());
+    }
+
+    let mut inbox = inbox::load_inbox()
+        .map_err(|e| format!("Failed to load inbox: {}", e))?;
+
+    match id {
+        Some(id) => {
+            if !inbox.mark_read(id) {
+                return Err(format!("No notification with id {}", id));
+            }
+        }
+        None => inbox.mark_all_read(),
+    }
+
+    inbox::save_inbox(&inbox)
+        .map_err(|e| format!("Failed to save inbox: {}", e))?;
+
+    Ok(inbox)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ValidationResult {
     pub valid: bool,
@@ -0,0 +1,201 @@
+//! Running a one-shot command as administrator from the unprivileged UI
+//!
+//! Rather than elevating the whole UI, the current executable is started
+//! again with administrator rights to run a single CLI command, using the
+//! platform's own prompt: UAC (ShellExecute "runas") on Windows, polkit
+//! (`pkexec`) on Linux and an authorization dialog (`osascript`) on macOS.
+
+use anyhow::{Context, Result};
+
+/// How an elevated command ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElevatedRun {
+    /// The command ran and exited successfully
+    Succeeded,
+    /// The command ran but failed, with whatever it printed (if available)
+    Failed(String),
+    /// The user dismissed the prompt or didn't authenticate
+    Cancelled,
+}
+
+/// Run the current executable with `args` as administrator and wait for it
+///
+/// Without a prompt if the UI is already running as administrator.
+pub fn run_elevated(args: &[&str]) -> Result<ElevatedRun> {
+    let exe = std::env::current_exe().context("Failed to locate the family-policy executable")?;
+
+    if crate::core::is_admin() {
+        let output = std::process::Command::new(&exe)
+            .args(args)
+            .output()
+            .with_context(|| format!("Failed to run {}", exe.display()))?;
+        return Ok(if output.status.success() {
+            ElevatedRun::Succeeded
+        } else {
+            ElevatedRun::Failed(command_output(&output))
+        });
+    }
+
+    platform_run_elevated(&exe, args)
+}
+
+#[cfg(target_os = "linux")]
+fn platform_run_elevated(exe: &std::path::Path, args: &[&str]) -> Result<ElevatedRun> {
+    let output = std::process::Command::new("pkexec")
+        .arg(exe)
+        .args(args)
+        .output()
+        .context("Failed to run pkexec (is polkit installed?)")?;
+
+    match output.status.code() {
+        Some(0) => Ok(ElevatedRun::Succeeded),
+        // 126: the dialog was dismissed, 127: not authorized
+        Some(126) | Some(127) => Ok(ElevatedRun::Cancelled),
+        _ => Ok(ElevatedRun::Failed(command_output(&output))),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn platform_run_elevated(exe: &std::path::Path, args: &[&str]) -> Result<ElevatedRun> {
+    let exe = exe.to_string_lossy();
+    let mut words = vec![exe.as_ref()];
+    words.extend_from_slice(args);
+
+    let output = std::process::Command::new("osascript")
+        .arg("-e")
+        .arg(admin_script(&words))
+        .output()
+        .context("Failed to run osascript")?;
+
+    if output.status.success() {
+        Ok(ElevatedRun::Succeeded)
+    } else if String::from_utf8_lossy(&output.stderr).contains("(-128)") {
+        // "User canceled."
+        Ok(ElevatedRun::Cancelled)
+    } else {
+        Ok(ElevatedRun::Failed(command_output(&output)))
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn platform_run_elevated(exe: &std::path::Path, args: &[&str]) -> Result<ElevatedRun> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Foundation::{CloseHandle, GetLastError, ERROR_CANCELLED};
+    use windows_sys::Win32::System::Threading::{GetExitCodeProcess, WaitForSingleObject, INFINITE};
+    use windows_sys::Win32::UI::Shell::{
+        ShellExecuteExW, SEE_MASK_NOASYNC, SEE_MASK_NOCLOSEPROCESS, SHELLEXECUTEINFOW,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::SW_HIDE;
+
+    fn wide(s: &std::ffi::OsStr) -> Vec<u16> {
+        s.encode_wide().chain(std::iter::once(0)).collect()
+    }
+
+    let verb = wide("runas".as_ref());
+    let file = wide(exe.as_os_str());
+    let parameters = wide(windows_parameters(args).as_ref());
+
+    unsafe {
+        let mut info: SHELLEXECUTEINFOW = std::mem::zeroed();
+        info.cbSize = std::mem::size_of::<SHELLEXECUTEINFOW>() as u32;
+        info.fMask = SEE_MASK_NOCLOSEPROCESS | SEE_MASK_NOASYNC;
+        info.lpVerb = verb.as_ptr();
+        info.lpFile = file.as_ptr();
+        info.lpParameters = parameters.as_ptr();
+        info.nShow = SW_HIDE;
+
+        if ShellExecuteExW(&mut info) == 0 {
+            let error = GetLastError();
+            if error == ERROR_CANCELLED {
+                return Ok(ElevatedRun::Cancelled);
+            }
+            anyhow::bail!("Failed to start elevated process (error {})", error);
+        }
+        if info.hProcess.is_null() {
+            anyhow::bail!("Elevated process handle was not returned");
+        }
+
+        WaitForSingleObject(info.hProcess, INFINITE);
+        let mut code = 1u32;
+        let got_code = GetExitCodeProcess(info.hProcess, &mut code);
+        CloseHandle(info.hProcess);
+
+        if got_code == 0 {
+            anyhow::bail!("Failed to read the elevated process's exit code");
+        }
+        if code == 0 {
+            Ok(ElevatedRun::Succeeded)
+        } else {
+            // Output isn't available through ShellExecute
+            Ok(ElevatedRun::Failed(format!(
+                "exited with code {}; see the agent log for details",
+                code
+            )))
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn platform_run_elevated(_exe: &std::path::Path, _args: &[&str]) -> Result<ElevatedRun> {
+    anyhow::bail!("Elevation is not supported on this platform")
+}
+
+/// What a finished command printed, for showing alongside an error
+fn command_output(output: &std::process::Output) -> String {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let text = if stderr.trim().is_empty() { stdout } else { stderr };
+    text.trim().to_string()
+}
+
+/// An AppleScript running `words` as a shell command with administrator privileges
+#[cfg(any(target_os = "macos", test))]
+fn admin_script(words: &[&str]) -> String {
+    let command: Vec<String> = words
+        .iter()
+        .map(|w| format!("'{}'", w.replace('\'', r"'\''")))
+        .collect();
+    let command = command.join(" ").replace('\\', r"\\").replace('"', "\\\"");
+    format!("do shell script \"{}\" with administrator privileges", command)
+}
+
+/// Join arguments into a Windows command line, quoting those with spaces
+#[cfg(any(target_os = "windows", test))]
+fn windows_parameters(args: &[&str]) -> String {
+    args.iter()
+        .map(|a| {
+            if a.is_empty() || a.contains([' ', '\t', '"']) {
+                format!("\"{}\"", a.replace('"', "\\\""))
+            } else {
+                a.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admin_script_quotes_for_the_shell_and_applescript() {
+        assert_eq!(
+            admin_script(&["/Applications/Family Policy.app/family-policy", "check-now"]),
+            r#"do shell script "'/Applications/Family Policy.app/family-policy' 'check-now'" with administrator privileges"#
+        );
+        assert_eq!(
+            admin_script(&["it's \"quoted\""]),
+            r#"do shell script "'it'\\''s \"quoted\"'" with administrator privileges"#
+        );
+    }
+
+    #[test]
+    fn windows_parameters_quote_arguments_with_spaces() {
+        assert_eq!(windows_parameters(&["check-now"]), "check-now");
+        assert_eq!(
+            windows_parameters(&["--config", r"C:\Family Policy\policy.yaml"]),
+            r#"--config "C:\Family Policy\policy.yaml""#
+        );
+    }
+}
@@ -0,0 +1,380 @@
+//! Extension lookup for the admin policy editor and `config validate --online`
+//!
+//! Firefox add-ons are searched through the public addons.mozilla.org API.
+//! The Chrome Web Store (and Edge Add-ons) have no public search API, so for
+//! Chromium browsers the editor accepts a store URL or a bare extension ID.
+//!
+//! Looking up a known ID works for all three stores: AMO and Edge Add-ons
+//! return the listing as JSON, and the Chrome Web Store's update service
+//! reports the latest version (the name comes from the store page).
+
+use anyhow::{Context, Result};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::browser::Browser;
+
+const AMO_SEARCH_URL: &str = "https://addons.mozilla.org/api/v5/addons/search/";
+const AMO_ADDON_URL: &str = "https://addons.mozilla.org/api/v5/addons/addon/";
+const EDGE_PRODUCT_URL: &str = "https://microsoftedge.microsoft.com/addons/getproductdetailsbycrxid/";
+const CHROME_DETAIL_URL: &str = "https://chromewebstore.google.com/detail/";
+
+/// Chrome version reported to the update service (it only answers for
+/// extensions compatible with the given version)
+const CHROME_PRODUCT_VERSION: &str = "140.0";
+
+/// Maximum number of search results returned to the UI
+const MAX_RESULTS: usize = 10;
+
+/// An extension the admin can add to a policy
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtensionSearchResult {
+    pub id: String,
+    pub name: String,
+    pub summary: Option<String>,
+    pub url: Option<String>,
+}
+
+/// An extension as published in its browser's store
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoreListing {
+    /// Name shown in the store, if it could be read
+    pub name: Option<String>,
+    pub version: String,
+}
+
+/// HTTP client for the extension stores
+pub fn store_client() -> Result<Client> {
+    Client::builder()
+        .user_agent(format!("family-policy/{}", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(15))
+        .https_only(true)
+        .build()
+        .context("Failed to create HTTP client")
+}
+
+/// Search addons.mozilla.org for Firefox extensions
+pub async fn search_amo(query: &str) -> Result<Vec<ExtensionSearchResult>> {
+    let client = store_client()?;
+
+    let page_size = MAX_RESULTS.to_string();
+    let response = client
+        .get(AMO_SEARCH_URL)
+        .query(&[
+            ("q", query),
+            ("app", "firefox"),
+            ("type", "extension"),
+            ("page_size", page_size.as_str()),
+        ])
+        .send()
+        .await
+        .context("Failed to connect to addons.mozilla.org")?;
+
+    if !response.status().is_success() {
+        anyhow::bail!("addons.mozilla.org returned unexpected status: {}", response.status());
+    }
+
+    let body = response.text().await
+        .context("Failed to read response body")?;
+    let json: serde_json::Value = serde_json::from_str(&body)
+        .context("Failed to parse addons.mozilla.org response")?;
+
+    Ok(parse_amo_results(&json))
+}
+
+/// Extract results from an AMO search response
+///
+/// Names and summaries are either plain strings or maps of locale to string
+/// depending on the request; prefer en-US, then any locale.
+fn parse_amo_results(json: &serde_json::Value) -> Vec<ExtensionSearchResult> {
+    let Some(results) = json.get("results").and_then(|r| r.as_array()) else {
+        return Vec::new();
+    };
+
+    results
+        .iter()
+        .filter_map(|addon| {
+            let id = addon.get("guid")?.as_str()?.to_string();
+            let name = localized(addon.get("name")?)?;
+            Some(ExtensionSearchResult {
+                id,
+                name,
+                summary: addon.get("summary").and_then(localized),
+                url: addon.get("url").and_then(|u| u.as_str()).map(String::from),
+            })
+        })
+        .take(MAX_RESULTS)
+        .collect()
+}
+
+fn localized(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Object(map) => map
+            .get("en-US")
+            .or_else(|| map.values().next())
+            .and_then(|v| v.as_str())
+            .map(String::from),
+        _ => None,
+    }
+}
+
+/// Look up an extension ID in `browser`'s store, returning `None` if the
+/// store doesn't have it
+pub async fn lookup_extension(client: &Client, browser: Browser, id: &str) -> Result<Option<StoreListing>> {
+    match browser {
+        Browser::Firefox => lookup_amo(client, id).await,
+        Browser::Edge => lookup_edge(client, id).await,
+        // These install extensions from the Chrome Web Store
+        Browser::Chrome | Browser::Chromium | Browser::Vivaldi | Browser::Opera => lookup_chrome(client, id).await,
+        Browser::Safari => anyhow::bail!("Safari extensions can't be managed"),
+    }
+}
+
+/// GET `url`, returning `None` for 404 (and the empty 204 Edge Add-ons
+/// answers for unknown IDs)
+async fn get_text(client: &Client, url: &str, store: &str) -> Result<Option<String>> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .with_context(|| format!("Failed to connect to {}", store))?;
+
+    match response.status() {
+        StatusCode::NOT_FOUND | StatusCode::NO_CONTENT => return Ok(None),
+        status if !status.is_success() => {
+            anyhow::bail!("{} returned unexpected status: {}", store, status)
+        }
+        _ => {}
+    }
+
+    let body = response.text().await.context("Failed to read response body")?;
+    Ok((!body.trim().is_empty()).then_some(body))
+}
+
+async fn lookup_amo(client: &Client, id: &str) -> Result<Option<StoreListing>> {
+    let url = format!("{}{}/", AMO_ADDON_URL, id);
+    let Some(body) = get_text(client, &url, "addons.mozilla.org").await? else {
+        return Ok(None);
+    };
+    let json: serde_json::Value = serde_json::from_str(&body)
+        .context("Failed to parse addons.mozilla.org response")?;
+    Ok(parse_amo_addon(&json))
+}
+
+fn parse_amo_addon(json: &serde_json::Value) -> Option<StoreListing> {
+    let version = json.pointer("/current_version/version")?.as_str()?.to_string();
+    Some(StoreListing {
+        name: json.get("name").and_then(localized),
+        version,
+    })
+}
+
+async fn lookup_edge(client: &Client, id: &str) -> Result<Option<StoreListing>> {
+    let url = format!("{}{}", EDGE_PRODUCT_URL, id);
+    let Some(body) = get_text(client, &url, "Edge Add-ons").await? else {
+        return Ok(None);
+    };
+    let json: serde_json::Value =
+        serde_json::from_str(&body).context("Failed to parse Edge Add-ons response")?;
+    Ok(parse_edge_product(&json))
+}
+
+fn parse_edge_product(json: &serde_json::Value) -> Option<StoreListing> {
+    let version = json.get("version")?.as_str()?.to_string();
+    Some(StoreListing {
+        name: json.get("name").and_then(|n| n.as_str()).map(String::from),
+        version,
+    })
+}
+
+async fn lookup_chrome(client: &Client, id: &str) -> Result<Option<StoreListing>> {
+    let query = format!("id={}&installsource=ondemand&uc", id);
+    let response = client
+        .get(crate::config::DEFAULT_CHROME_UPDATE_URL)
+        .query(&[
+            ("response", "updatecheck"),
+            ("acceptformat", "crx3"),
+            ("prodversion", CHROME_PRODUCT_VERSION),
+            ("x", query.as_str()),
+        ])
+        .send()
+        .await
+        .context("Failed to connect to the Chrome Web Store")?
+        .error_for_status()
+        .context("Chrome Web Store update check failed")?;
+    let body = response.text().await.context("Failed to read response body")?;
+
+    let Some(version) = parse_chrome_update_check(&body) else {
+        return Ok(None);
+    };
+
+    // The update service doesn't know names; the store page does
+    let name = match get_text(client, &format!("{}{}", CHROME_DETAIL_URL, id), "the Chrome Web Store").await {
+        Ok(page) => page.as_deref().and_then(parse_chrome_store_title),
+        Err(e) => {
+            tracing::debug!("Failed to read Chrome Web Store page for {}: {:#}", id, e);
+            None
+        }
+    };
+
+    Ok(Some(StoreListing { name, version }))
+}
+
+/// The version in an update check response, or `None` if the store doesn't
+/// have the extension
+///
+/// `<updatecheck ... status="ok" version="2025.1.1"/>`; unknown IDs answer
+/// with `status="noupdate"` or `status="error-unknownApplication"`.
+fn parse_chrome_update_check(xml: &str) -> Option<String> {
+    let start = xml.find("<updatecheck")?;
+    let tag = &xml[start..start + xml[start..].find('>')?];
+    if xml_attribute(tag, "status")? != "ok" {
+        return None;
+    }
+    xml_attribute(tag, "version").map(String::from)
+}
+
+fn xml_attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let needle = format!(" {}=\"", name);
+    let start = tag.find(&needle)? + needle.len();
+    let len = tag[start..].find('"')?;
+    Some(&tag[start..start + len])
+}
+
+/// The extension name from a store page's `og:title`
+fn parse_chrome_store_title(html: &str) -> Option<String> {
+    let start = html.find("<meta property=\"og:title\"")?;
+    let tag = &html[start..start + html[start..].find('>')?];
+    let title = xml_attribute(tag, "content")?;
+    let title = title.strip_suffix(" - Chrome Web Store").unwrap_or(title);
+    Some(title.replace("&amp;", "&").replace("&#39;", "'").replace("&quot;", "\""))
+}
+
+/// Extract a Chrome/Edge extension ID from a store URL or a bare ID
+///
+/// Store URLs end in the ID, e.g.
+/// `https://chromewebstore.google.com/detail/ublock-origin-lite/ddkjiahejlhfcafbddmgiahcphecmpfh`.
+pub fn chromium_extension_id(input: &str) -> Option<String> {
+    input
+        .trim()
+        .split(['/', '?', '#'])
+        .find(|segment| crate::config::is_chromium_extension_id(segment))
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chromium_extension_id_from_bare_id() {
+        assert_eq!(
+            chromium_extension_id(" ddkjiahejlhfcafbddmgiahcphecmpfh "),
+            Some("ddkjiahejlhfcafbddmgiahcphecmpfh".to_string())
+        );
+    }
+
+    #[test]
+    fn chromium_extension_id_from_store_urls() {
+        let expected = Some("ddkjiahejlhfcafbddmgiahcphecmpfh".to_string());
+        assert_eq!(
+            chromium_extension_id(
+                "https://chromewebstore.google.com/detail/ublock-origin-lite/ddkjiahejlhfcafbddmgiahcphecmpfh?hl=en"
+            ),
+            expected
+        );
+        assert_eq!(
+            chromium_extension_id(
+                "https://microsoftedge.microsoft.com/addons/detail/ddkjiahejlhfcafbddmgiahcphecmpfh"
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn chromium_extension_id_rejects_search_terms() {
+        assert_eq!(chromium_extension_id("ublock origin"), None);
+        assert_eq!(chromium_extension_id("DDKJIAHEJLHFCAFBDDMGIAHCPHECMPFH"), None);
+    }
+
+    #[test]
+    fn chromium_extension_id_rejects_letters_after_p_and_digits() {
+        // A typo ('z') and a pasted hex hash are the right length but not IDs
+        assert_eq!(chromium_extension_id("ddkjiahejlhfcafbddmgiahcphecmpfz"), None);
+        assert_eq!(chromium_extension_id("3f2504e04f8911d39a0c0305e82c3301"), None);
+    }
+
+    #[test]
+    fn parse_amo_results_handles_localized_fields() {
+        let json = serde_json::json!({
+            "results": [
+                {
+                    "guid": "uBOLite@raymondhill.net",
+                    "name": {"en-US": "uBlock Origin Lite", "de": "uBlock Origin Lite"},
+                    "summary": {"en-US": "A lightweight content blocker"},
+                    "url": "https://addons.mozilla.org/firefox/addon/ublock-origin-lite/"
+                },
+                {
+                    "guid": "plain@example.com",
+                    "name": "Plain Name"
+                },
+                {
+                    "name": "Missing guid"
+                }
+            ]
+        });
+
+        let results = parse_amo_results(&json);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].id, "uBOLite@raymondhill.net");
+        assert_eq!(results[0].name, "uBlock Origin Lite");
+        assert_eq!(results[0].summary.as_deref(), Some("A lightweight content blocker"));
+        assert_eq!(results[1].name, "Plain Name");
+        assert_eq!(results[1].summary, None);
+    }
+
+    #[test]
+    fn parse_amo_results_without_results_is_empty() {
+        assert!(parse_amo_results(&serde_json::json!({"detail": "error"})).is_empty());
+    }
+
+    #[test]
+    fn parse_store_listings() {
+        let amo = serde_json::json!({
+            "name": {"en-US": "uBlock Origin Lite"},
+            "current_version": {"version": "2025.1.1"}
+        });
+        assert_eq!(
+            parse_amo_addon(&amo),
+            Some(StoreListing {
+                name: Some("uBlock Origin Lite".to_string()),
+                version: "2025.1.1".to_string(),
+            })
+        );
+
+        let edge = serde_json::json!({"name": "uBlock Origin Lite", "version": "2025.1.1"});
+        assert_eq!(parse_edge_product(&edge), parse_amo_addon(&amo));
+        assert_eq!(parse_edge_product(&serde_json::json!({})), None);
+    }
+
+    #[test]
+    fn parse_chrome_update_check_response() {
+        let ok = r#"<?xml version="1.0" encoding="UTF-8"?><gupdate protocol="2.0" server="prod">
+<app appid="ddkjiahejlhfcafbddmgiahcphecmpfh" status="ok">
+<updatecheck codebase="https://clients2.googleusercontent.com/crx/x.crx" fp="1.abc" hash_sha256="abc" protected="0" size="100" status="ok" version="2025.1.1"/>
+</app></gupdate>"#;
+        assert_eq!(parse_chrome_update_check(ok).as_deref(), Some("2025.1.1"));
+
+        let unknown = r#"<gupdate protocol="2.0"><app appid="aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa" status="error-unknownApplication"><updatecheck status="noupdate"/></app></gupdate>"#;
+        assert_eq!(parse_chrome_update_check(unknown), None);
+    }
+
+    #[test]
+    fn parse_chrome_store_page_title() {
+        let html = r#"<head><meta property="og:title" content="Tom &amp; Jerry&#39;s Helper - Chrome Web Store"><meta property="og:type" content="website"></head>"#;
+        assert_eq!(parse_chrome_store_title(html).as_deref(), Some("Tom & Jerry's Helper"));
+        assert_eq!(parse_chrome_store_title("<head></head>"), None);
+    }
+}
@@ -7,10 +7,14 @@ use tauri::{
 pub mod admin;
 pub mod admin_commands;
 mod config_bridge;
+mod elevation;
+pub mod extension_search;
+pub mod onboarding_commands;
 pub mod user;
 pub mod user_commands;
 
 use crate::agent::config::AgentConfig;
+use crate::agent::helper::{self, HelperRequest, HelperResponse};
 
 #[tauri::command]
 async fn get_agent_config() -> Result<AgentConfig, String> {
@@ -19,12 +23,40 @@ async fn get_agent_config() -> Result<AgentConfig, String> {
 
 #[tauri::command]
 async fn save_agent_config(config: AgentConfig) -> Result<(), String> {
-    config_bridge::save_config(&config).map_err(|e| e.to_string())
+    if config_bridge::is_admin() {
+        return config_bridge::save_config(&config).map_err(|e| e.to_string());
+    }
+
+    // Without admin, the agent's helper may write it for us
+    let request = HelperRequest::WriteConfig { config: Box::new(config) };
+    match tauri::async_runtime::spawn_blocking(move || helper::call(&request))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("{:#}", e))?
+    {
+        Some(HelperResponse::ConfigSaved) => Ok(()),
+        Some(HelperResponse::AwaitingSecondApproval { requested_by }) => Err(format!(
+            "Security settings change requested by {}; another parent must make the same change \
+             from their account within 10 minutes",
+            requested_by
+        )),
+        Some(HelperResponse::Error { message }) => Err(message),
+        Some(other) => Err(format!("Unexpected response from the agent: {:?}", other)),
+        None => Err("Saving the agent configuration requires administrator privileges".to_string()),
+    }
 }
 
+/// Whether settings can be changed: running as admin, or allowed to by the
+/// agent's helper
 #[tauri::command]
 async fn check_admin_privileges() -> Result<bool, String> {
-    Ok(config_bridge::is_admin())
+    if config_bridge::is_admin() {
+        return Ok(true);
+    }
+    let status = tauri::async_runtime::spawn_blocking(|| helper::call(&HelperRequest::Status))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(matches!(status, Ok(Some(HelperResponse::Ready))))
 }
 
 fn show_settings_window<R: Runtime>(app: &AppHandle<R>) {
@@ -85,13 +117,26 @@ pub fn run() -> anyhow::Result<()> {
             user_commands::preview_apply,
             user_commands::check_admin,
             user_commands::request_elevation,
+            user_commands::get_translations,
             // Admin commands (require admin privileges)
             admin_commands::apply_policies,
             admin_commands::remove_policies,
             admin_commands::preview_removal,
             admin_commands::validate_config,
+            admin_commands::load_policy_yaml,
+            admin_commands::validate_policy_yaml,
+            admin_commands::preview_policy_yaml,
+            admin_commands::search_extensions,
             admin_commands::save_config,
-            admin_commands::get_default_config
+            admin_commands::get_default_config,
+            admin_commands::get_inbox,
+            admin_commands::mark_inbox_read,
+            // First-run setup
+            onboarding_commands::onboarding_status,
+            onboarding_commands::list_presets,
+            onboarding_commands::get_preset,
+            onboarding_commands::apply_preset,
+            onboarding_commands::install_agent_service
         ])
         .run(tauri::generate_context!())
         .map_err(|e| anyhow::anyhow!("Failed to run UI: {}", e))?;
@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+
+use crate::agent;
+use crate::browser::{is_browser_available, Browser};
+use crate::commands;
+use crate::config;
+use crate::core;
+use crate::state;
+
+/// Whether a supported browser is installed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectedBrowser {
+    pub browser: Browser,
+    pub installed: bool,
+}
+
+/// What the first-run setup needs to know about this machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OnboardingStatus {
+    /// Nothing has been set up yet (no agent configuration, no policies)
+    pub needed: bool,
+    pub is_admin: bool,
+    pub agent_configured: bool,
+    pub policies_applied: bool,
+    pub browsers: Vec<DetectedBrowser>,
+}
+
+/// A preset offered by the setup wizard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresetInfo {
+    pub name: String,
+    pub description: String,
+}
+
+/// Check whether this machine still needs setting up (no admin required)
+#[tauri::command]
+pub async fn onboarding_status() -> Result<OnboardingStatus, String> {
+    let agent_configured = agent::get_agent_config_path()
+        .map(|path| path.exists())
+        .unwrap_or(false);
+    let policies_applied = state::load_state().ok().flatten().is_some();
+
+    let browsers = Browser::ALL
+        .into_iter()
+        .map(|browser| DetectedBrowser {
+            browser,
+            installed: is_browser_available(browser),
+        })
+        .collect();
+
+    Ok(OnboardingStatus {
+        needed: !agent_configured && !policies_applied,
+        is_admin: core::privileges::is_admin(),
+        agent_configured,
+        policies_applied,
+        browsers,
+    })
+}
+
+/// List the presets bundled in the binary
+#[tauri::command]
+pub async fn list_presets() -> Result<Vec<PresetInfo>, String> {
+    Ok(config::PRESETS
+        .iter()
+        .map(|p| PresetInfo {
+            name: p.name.to_string(),
+            description: p.description.to_string(),
+        })
+        .collect())
+}
+
+/// Get a preset's YAML
+#[tauri::command]
+pub async fn get_preset(name: String) -> Result<String, String> {
+    config::preset(&name)
+        .map(|p| p.content.to_string())
+        .ok_or_else(|| format!("Unknown preset '{}'", name))
+}
+
+/// Apply a preset as this machine's local policy
+/// Requires admin privileges
+#[tauri::command]
+pub async fn apply_preset(name: String) -> Result<core::apply::ApplyResult, String> {
+    if !core::privileges::is_admin() {
+        return Err("Applying policies requires administrator privileges".to_string());
+    }
+
+    let preset = config::preset(&name).ok_or_else(|| format!("Unknown preset '{}'", name))?;
+    let config = config::Config::from_yaml_str(preset.content)
+        .map_err(|e| format!("Invalid preset: {:#}", e))?
+        .for_host(&agent::current_host());
+
+    tauri::async_runtime::spawn_blocking(move || core::apply::apply_policies_from_config(&config, false))
+        .await
+        .map_err(|e| format!("Apply task failed: {}", e))?
+        .map_err(|e| format!("Failed to apply policies: {:#}", e))
+}
+
+/// Install the agent service and start it
+/// Requires admin privileges and a saved agent configuration
+#[tauri::command]
+pub async fn install_agent_service() -> Result<(), String> {
+    if !core::privileges::is_admin() {
+        return Err("Installing the service requires administrator privileges".to_string());
+    }
+    let path = agent::get_agent_config_path().map_err(|e| format!("{:#}", e))?;
+    agent::AgentConfig::load(&path).map_err(|e| format!("Agent configuration is not ready: {:#}", e))?;
+
+    tauri::async_runtime::spawn_blocking(|| -> anyhow::Result<()> {
+        commands::agent::install_service(false)?;
+        // The LaunchDaemon is started when it is installed
+        #[cfg(not(target_os = "macos"))]
+        commands::agent::start(false, &[], false)?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("Install task failed: {}", e))?
+    .map_err(|e| format!("Failed to install the service: {:#}", e))
+}
@@ -1,9 +1,16 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
+use crate::agent;
+use crate::browser::{is_browser_available, Browser};
 use crate::core;
 use crate::state;
 use crate::config;
+use crate::i18n;
+use std::collections::HashMap;
+
+use super::elevation::{self, ElevatedRun};
+use crate::agent::helper::{HelperRequest, HelperResponse};
 
 /// State information for User UI
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +25,8 @@ pub struct StateInfo {
     pub privacy_settings_count: BrowserCounts,
     /// Hash of current configuration
     pub config_hash: String,
+    /// Names of installed browsers no policy is applied to
+    pub uncovered_browsers: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -25,6 +34,10 @@ pub struct BrowserCounts {
     pub chrome: usize,
     pub firefox: usize,
     pub edge: usize,
+    pub chromium: usize,
+    pub vivaldi: usize,
+    pub opera: usize,
+    pub safari: usize,
 }
 
 /// Configuration summary for User UI
@@ -74,13 +87,41 @@ pub async fn read_state() -> Result<Option<StateInfo>, String> {
         extensions_count.edge = edge.extensions.len();
         privacy_count.edge = count_privacy_settings_edge(edge);
     }
+    // These record the same settings as Chrome
+    if let Some(ref chromium) = state.applied_policies.chromium {
+        extensions_count.chromium = chromium.extensions.len();
+        privacy_count.chromium = count_privacy_settings_chrome(chromium);
+    }
+    if let Some(ref vivaldi) = state.applied_policies.vivaldi {
+        extensions_count.vivaldi = vivaldi.extensions.len();
+        privacy_count.vivaldi = count_privacy_settings_chrome(vivaldi);
+    }
+    if let Some(ref opera) = state.applied_policies.opera {
+        extensions_count.opera = opera.extensions.len();
+        privacy_count.opera = count_privacy_settings_chrome(opera);
+    }
+    if let Some(ref safari) = state.applied_policies.safari {
+        privacy_count.safari = count_privacy_settings_safari(safari);
+    }
 
     let policies_applied = extensions_count.chrome > 0
         || extensions_count.firefox > 0
         || extensions_count.edge > 0
+        || extensions_count.chromium > 0
+        || extensions_count.vivaldi > 0
+        || extensions_count.opera > 0
         || privacy_count.chrome > 0
         || privacy_count.firefox > 0
-        || privacy_count.edge > 0;
+        || privacy_count.edge > 0
+        || privacy_count.chromium > 0
+        || privacy_count.vivaldi > 0
+        || privacy_count.opera > 0
+        || privacy_count.safari > 0;
+
+    let installed: Vec<Browser> = Browser::ALL
+        .into_iter()
+        .filter(|b| is_browser_available(*b))
+        .collect();
 
     Ok(Some(StateInfo {
         policies_applied,
@@ -88,6 +129,12 @@ pub async fn read_state() -> Result<Option<StateInfo>, String> {
         extensions_count,
         privacy_settings_count: privacy_count,
         config_hash: state.config_hash,
+        uncovered_browsers: state
+            .applied_policies
+            .uncovered(&installed)
+            .iter()
+            .map(|b| b.name().to_string())
+            .collect(),
     }))
 }
 
@@ -126,14 +173,26 @@ pub async fn read_config_summary(config_path: String) -> Result<ConfigSummary, S
 pub async fn preview_apply(config_path: String) -> Result<core::diff::PolicyDiff, String> {
     let path = std::path::PathBuf::from(config_path);
     let config = config::load_config(&path)
-        .map_err(|e| format!("Failed to load config: {}", e))?;
+        .map_err(|e| format!("Failed to load config: {}", e))?
+        .for_host(&agent::current_host());
 
     let current_state = state::load_state()
         .map_err(|e| format!("Failed to load state: {}", e))?;
 
-    let diff = core::diff::generate_diff(&config, current_state.as_ref());
+    core::diff::generate_diff_with_conflicts(&config, current_state.as_ref())
+        .map_err(|e| format!("Failed to check for conflicting policies: {:#}", e))
+}
 
-    Ok(diff)
+/// Translated UI messages for `locale` (default: the system locale)
+#[tauri::command]
+pub async fn get_translations(locale: Option<String>) -> Result<HashMap<String, String>, String> {
+    let locale = locale
+        .as_deref()
+        .and_then(i18n::normalize_locale)
+        .or_else(i18n::system_locale)
+        .unwrap_or_else(|| i18n::DEFAULT_LOCALE.to_string());
+    let localizer = i18n::Localizer::new(&locale, i18n::locales_dir().as_deref());
+    Ok(localizer.messages().clone())
 }
 
 /// Check if current process has admin privileges
@@ -142,44 +201,61 @@ pub async fn check_admin() -> Result<bool, String> {
     Ok(core::privileges::is_admin())
 }
 
-/// Request elevation (platform-specific)
-/// Returns true if elevation was successful or already elevated
+/// Apply the agent's policy now, as administrator
+///
+/// Asks the agent's helper if it's running and lets this user in;
+/// otherwise runs `family-policy check-now` through the platform's
+/// elevation prompt (UAC, polkit or the macOS authorization dialog) and
+/// waits for it.
 #[tauri::command]
 pub async fn request_elevation() -> Result<ElevationResult, String> {
-    if core::privileges::is_admin() {
+    let configured = agent::get_agent_config_path()
+        .map(|path| path.exists())
+        .unwrap_or(false);
+    if !configured {
         return Ok(ElevationResult {
-            success: true,
-            error: None,
+            success: false,
+            error: Some("The agent isn't set up yet. Use Setup to configure it first.".to_string()),
         });
     }
 
-    // On Unix, we can't actually elevate from within the process
-    // The user needs to restart with sudo
-    #[cfg(unix)]
-    {
-        Ok(ElevationResult {
-            success: false,
-            error: Some("Please restart the application with 'sudo' to apply policies.".to_string()),
-        })
+    let helper = tauri::async_runtime::spawn_blocking(|| agent::helper::call(&HelperRequest::Apply))
+        .await
+        .map_err(|e| format!("Helper task failed: {}", e))?
+        .map_err(|e| format!("{:#}", e))?;
+    match helper {
+        Some(HelperResponse::Applied { .. }) => {
+            return Ok(ElevationResult {
+                success: true,
+                error: None,
+            });
+        }
+        Some(HelperResponse::Error { message }) => {
+            tracing::info!("Helper refused to apply, elevating instead: {}", message);
+        }
+        Some(other) => return Err(format!("Unexpected response from the agent: {:?}", other)),
+        None => {}
     }
 
-    // On Windows, we could potentially re-launch with elevation
-    // For now, just return an error message
-    #[cfg(windows)]
-    {
-        Ok(ElevationResult {
-            success: false,
-            error: Some("Please restart the application as Administrator to apply policies.".to_string()),
-        })
-    }
+    let run = tauri::async_runtime::spawn_blocking(|| elevation::run_elevated(&["check-now"]))
+        .await
+        .map_err(|e| format!("Elevation task failed: {}", e))?
+        .map_err(|e| format!("{:#}", e))?;
 
-    #[cfg(not(any(unix, windows)))]
-    {
-        Ok(ElevationResult {
+    Ok(match run {
+        ElevatedRun::Succeeded => ElevationResult {
+            success: true,
+            error: None,
+        },
+        ElevatedRun::Failed(output) => ElevationResult {
             success: false,
-            error: Some("Platform not supported for elevation".to_string()),
-        })
-    }
+            error: Some(format!("Applying the policy failed: {}", output)),
+        },
+        ElevatedRun::Cancelled => ElevationResult {
+            success: false,
+            error: Some("Administrator access was not granted".to_string()),
+        },
+    })
 }
 
 // Helper functions
@@ -195,6 +271,12 @@ fn count_privacy_settings_chrome(state: &state::BrowserState) -> usize {
     if state.allow_deleting_browser_history.is_some() {
         count += 1;
     }
+    if state.disable_sync.is_some() {
+        count += 1;
+    }
+    if state.restrict_signin.is_some() {
+        count += 1;
+    }
     count
 }
 
@@ -203,6 +285,9 @@ fn count_privacy_settings_firefox(state: &state::BrowserState) -> usize {
     if state.disable_private_browsing.is_some() {
         count += 1;
     }
+    if state.disable_sync.is_some() {
+        count += 1;
+    }
     count
 }
 
@@ -217,9 +302,20 @@ fn count_privacy_settings_edge(state: &state::BrowserState) -> usize {
     if state.allow_deleting_browser_history.is_some() {
         count += 1;
     }
+    if state.disable_sync.is_some() {
+        count += 1;
+    }
+    if state.restrict_signin.is_some() {
+        count += 1;
+    }
     count
 }
 
+/// Private browsing plus one for each blocked website
+fn count_privacy_settings_safari(state: &state::BrowserState) -> usize {
+    usize::from(state.disable_private_browsing.is_some()) + state.blocked_urls.len()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -234,6 +330,10 @@ mod tests {
             disable_private_browsing: None,
             disable_guest_mode: Some(false),
             allow_deleting_browser_history: None,
+            disable_sync: None,
+            restrict_signin: None,
+            blocked_urls: Vec::new(),
+            sources: Default::default(),
         };
         assert_eq!(count_privacy_settings_chrome(&state), 2);
     }
@@ -247,6 +347,10 @@ mod tests {
             disable_private_browsing: Some(true),
             disable_guest_mode: None,
             allow_deleting_browser_history: None,
+            disable_sync: None,
+            restrict_signin: None,
+            blocked_urls: Vec::new(),
+            sources: Default::default(),
         };
         assert_eq!(count_privacy_settings_firefox(&state), 1);
     }
@@ -260,6 +364,10 @@ mod tests {
             disable_private_browsing: None,
             disable_guest_mode: Some(true),
             allow_deleting_browser_history: None,
+            disable_sync: None,
+            restrict_signin: None,
+            blocked_urls: Vec::new(),
+            sources: Default::default(),
         };
         assert_eq!(count_privacy_settings_edge(&state), 2);
     }
@@ -270,5 +378,9 @@ mod tests {
         assert_eq!(counts.chrome, 0);
         assert_eq!(counts.firefox, 0);
         assert_eq!(counts.edge, 0);
+        assert_eq!(counts.chromium, 0);
+        assert_eq!(counts.vivaldi, 0);
+        assert_eq!(counts.opera, 0);
+        assert_eq!(counts.safari, 0);
     }
 }
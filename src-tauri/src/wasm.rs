@@ -0,0 +1,114 @@
+//! WebAssembly bindings for config validation and diff preview
+//!
+//! Lets the admin editor (and a static page in the policy repo) validate
+//! policy YAML and preview the resulting changes client-side, using exactly
+//! the rules the agent enforces. Build with:
+//!
+//! ```bash
+//! wasm-pack build --target web -- --features wasm
+//! ```
+//!
+//! Results are returned as JSON strings so callers only need `JSON.parse`.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::config::{extension_setting_conflicts, Config};
+use crate::core::diff::{generate_diff, PolicyDiff};
+use crate::state::State;
+
+/// Outcome of validating a policy YAML document
+#[derive(Debug, Serialize)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub error: Option<String>,
+    pub warnings: Vec<String>,
+    pub policy_count: usize,
+}
+
+/// Validate a policy YAML document
+pub fn validate_yaml(yaml: &str) -> ValidationReport {
+    match Config::from_yaml_str(yaml) {
+        Ok(config) => ValidationReport {
+            valid: true,
+            error: None,
+            warnings: extension_setting_conflicts(&config),
+            policy_count: config.policies.len(),
+        },
+        Err(e) => ValidationReport {
+            valid: false,
+            error: Some(format!("{:#}", e)),
+            warnings: vec![],
+            policy_count: 0,
+        },
+    }
+}
+
+/// Diff a policy YAML document against a previously applied state
+///
+/// `state_json` is the contents of the agent's state file; when absent the
+/// diff is computed as if nothing had been applied yet.
+pub fn diff_yaml(yaml: &str, state_json: Option<&str>) -> Result<PolicyDiff> {
+    let config = Config::from_yaml_str(yaml)?;
+    let state = state_json
+        .map(|json| serde_json::from_str::<State>(json).context("Failed to parse state JSON"))
+        .transpose()?;
+
+    Ok(generate_diff(&config, state.as_ref()))
+}
+
+/// Validate a policy YAML document, returning a `ValidationReport` as JSON
+#[wasm_bindgen(js_name = validateConfig)]
+pub fn validate_config(yaml: &str) -> String {
+    serde_json::to_string(&validate_yaml(yaml)).unwrap_or_default()
+}
+
+/// Preview the changes a policy YAML document would make, returning a
+/// `PolicyDiff` as JSON
+#[wasm_bindgen(js_name = previewDiff)]
+pub fn preview_diff(yaml: &str, state_json: Option<String>) -> Result<String, JsError> {
+    let diff = diff_yaml(yaml, state_json.as_deref()).map_err(|e| JsError::new(&format!("{:#}", e)))?;
+    serde_json::to_string(&diff).map_err(|e| JsError::new(&e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID_YAML: &str = r#"
+policies:
+  - name: Block private browsing
+    browsers: [chrome, firefox]
+    disable_private_mode: true
+"#;
+
+    #[test]
+    fn test_validate_yaml_accepts_valid_config() {
+        let report = validate_yaml(VALID_YAML);
+        assert!(report.valid);
+        assert!(report.error.is_none());
+        assert_eq!(report.policy_count, 1);
+    }
+
+    #[test]
+    fn test_validate_yaml_reports_error() {
+        let report = validate_yaml("policies: []");
+        assert!(!report.valid);
+        assert!(report.error.unwrap().contains("at least one policy"));
+    }
+
+    #[test]
+    fn test_diff_yaml_without_state() {
+        let diff = diff_yaml(VALID_YAML, None).unwrap();
+        assert!(diff.chrome.is_some());
+        assert!(diff.firefox.is_some());
+        assert!(diff.edge.is_none());
+        assert!(!diff.chrome.unwrap().privacy_settings.is_empty());
+    }
+
+    #[test]
+    fn test_diff_yaml_rejects_bad_state() {
+        assert!(diff_yaml(VALID_YAML, Some("not json")).is_err());
+    }
+}
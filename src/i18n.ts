@@ -0,0 +1,27 @@
+// Translated UI text
+//
+// Messages come from the same bundles the agent uses (locales/*.ftl, plus
+// any extra ones installed next to the agent configuration), fetched once
+// for the webview's language. Until they arrive, or for an unknown id, the
+// id itself is shown.
+
+import { ref } from "vue";
+import { invoke } from "@tauri-apps/api/core";
+
+const messages = ref<Record<string, string>>({});
+
+export async function loadTranslations(locale: string = navigator.language): Promise<void> {
+  try {
+    messages.value = await invoke<Record<string, string>>("get_translations", { locale });
+  } catch (error) {
+    console.error("Failed to load translations:", error);
+  }
+}
+
+/** The message `id` with its `{ $name }` placeholders filled in from `args` */
+export function t(id: string, args: Record<string, string | number> = {}): string {
+  const pattern = messages.value[id] ?? id;
+  return pattern.replace(/\{\s*\$([\w-]+)\s*\}/g, (placeholder, name) =>
+    name in args ? String(args[name]) : placeholder
+  );
+}